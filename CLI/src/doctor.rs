@@ -0,0 +1,95 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use ninja::common::types::{CheckStatus, DoctorReport};
+use owo_colors::OwoColorize;
+
+/// Output formats for `shurikenctl doctor`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DoctorFormat {
+    /// One colorized line per check
+    #[default]
+    Text,
+    /// `{ checks: [{ name, status, detail }], ok }`
+    Json,
+}
+
+/// Renders a doctor report in the requested format.
+pub fn render_doctor(report: &DoctorReport, format: DoctorFormat) -> Result<String> {
+    match format {
+        DoctorFormat::Json => Ok(serde_json::to_string_pretty(report)? + "\n"),
+        DoctorFormat::Text => {
+            let mut out = String::new();
+            for check in &report.checks {
+                let label = match check.status {
+                    CheckStatus::Pass => "PASS".green().to_string(),
+                    CheckStatus::Warn => "WARN".yellow().to_string(),
+                    CheckStatus::Fail => "FAIL".red().to_string(),
+                };
+                out.push_str(&format!("{}  {}: {}\n", label, check.name, check.detail));
+            }
+
+            let failed = report
+                .checks
+                .iter()
+                .filter(|c| c.status == CheckStatus::Fail)
+                .count();
+            if report.ok {
+                out.push_str(&format!("\n{}\n", "All checks passed".green()));
+            } else {
+                out.push_str(&format!(
+                    "\n{}\n",
+                    format!("{} check(s) failed", failed).red()
+                ));
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ninja::common::types::DoctorCheck;
+    use serde_json::{Value, json};
+
+    fn sample() -> DoctorReport {
+        DoctorReport::new(vec![
+            DoctorCheck {
+                name: "shurikens directory".into(),
+                status: CheckStatus::Pass,
+                detail: "/srv/ninja/shurikens".into(),
+            },
+            DoctorCheck {
+                name: "apache: start script".into(),
+                status: CheckStatus::Fail,
+                detail: "Compiled script not found".into(),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_json_report_shape() {
+        let parsed: Value =
+            serde_json::from_str(&render_doctor(&sample(), DoctorFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(
+            parsed,
+            json!({
+                "checks": [
+                    { "name": "shurikens directory", "status": "pass", "detail": "/srv/ninja/shurikens" },
+                    { "name": "apache: start script", "status": "fail", "detail": "Compiled script not found" },
+                ],
+                "ok": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_text_report_lists_every_check() {
+        let text = render_doctor(&sample(), DoctorFormat::Text).unwrap();
+
+        assert!(text.contains("shurikens directory: /srv/ninja/shurikens"));
+        assert!(text.contains("apache: start script: Compiled script not found"));
+        assert!(text.contains("1 check(s) failed"));
+    }
+}
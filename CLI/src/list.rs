@@ -0,0 +1,217 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use ninja::common::types::{ShurikenState, StateChange};
+use owo_colors::OwoColorize;
+use serde_json::{Value, json};
+use std::{future::Future, io::Write, time::Duration};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Clears the screen and moves the cursor home, so each frame replaces the last.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Output formats for `shurikenctl list`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// Aligned, colorized table
+    #[default]
+    Table,
+    /// JSON array of `{ name, state }` objects
+    Json,
+    /// YAML list of `{ name, state }` entries
+    Yaml,
+}
+
+/// The table's name for a state, after its variant like in JSON and YAML.
+fn state_label(state: &ShurikenState) -> String {
+    match state {
+        ShurikenState::Running => "running".into(),
+        ShurikenState::Idle => "idle".into(),
+        ShurikenState::Paused => "paused".into(),
+        ShurikenState::Error(e) => format!("error: {}", e),
+        ShurikenState::Crashed(code) => format!("crashed (exit {})", code),
+    }
+}
+
+/// Renders shuriken states in the requested format, sorted by name.
+pub fn render_list(
+    mut shurikens: Vec<(String, ShurikenState)>,
+    format: ListFormat,
+) -> Result<String> {
+    shurikens.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let entries = || -> Value {
+        shurikens
+            .iter()
+            .map(|(name, state)| json!({ "name": name, "state": state }))
+            .collect()
+    };
+
+    match format {
+        ListFormat::Json => Ok(serde_json::to_string_pretty(&entries())? + "\n"),
+        ListFormat::Yaml => Ok(serde_yaml::to_string(&entries())?),
+        ListFormat::Table => {
+            let width = shurikens
+                .iter()
+                .map(|(name, _)| name.len())
+                .chain(["NAME".len()])
+                .max()
+                .unwrap_or_default();
+
+            let mut out = format!("{:<width$}  {}\n", "NAME".bold(), "STATE".bold());
+            for (name, state) in &shurikens {
+                // Pad before colorizing so escape codes don't skew the columns
+                let label = state_label(state);
+                let label = match state {
+                    ShurikenState::Running => label.green().to_string(),
+                    ShurikenState::Idle => label.red().to_string(),
+                    ShurikenState::Paused => label.cyan().to_string(),
+                    ShurikenState::Error(_) => label.yellow().to_string(),
+                    ShurikenState::Crashed(_) => label.magenta().to_string(),
+                };
+                out.push_str(&format!("{:<width$}  {}\n", name, label));
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Waits for the next broadcast state change, or forever without a receiver.
+async fn next_change(
+    changes: &mut Option<broadcast::Receiver<StateChange>>,
+) -> Result<StateChange, RecvError> {
+    match changes {
+        Some(changes) => changes.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Draws the state table until `cancel` completes, redrawing it as states change.
+///
+/// A frame is drawn right away, then after every broadcast state change and
+/// every `interval`. Polling also catches changes made by other processes,
+/// which the broadcast never sees, and takes over alone if the broadcast is
+/// missing or closes. On a terminal each frame replaces the previous one;
+/// otherwise frames are appended so piped output stays readable.
+///
+/// # Returns
+/// - `Ok(frames)` with how many frames were drawn once cancelled
+/// - `Err` if taking a snapshot or writing fails
+pub async fn watch_list<F, Fut>(
+    mut snapshot: F,
+    mut changes: Option<broadcast::Receiver<StateChange>>,
+    interval: Duration,
+    tty: bool,
+    out: &mut impl Write,
+    cancel: impl Future<Output = ()>,
+) -> Result<usize>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Vec<(String, ShurikenState)>>>,
+{
+    tokio::pin!(cancel);
+    let mut frames = 0;
+    loop {
+        let table = render_list(snapshot().await?, ListFormat::Table)?;
+        if tty {
+            write!(out, "{}", CLEAR_SCREEN)?;
+        } else if frames > 0 {
+            writeln!(out)?;
+        }
+        write!(out, "{}", table)?;
+        out.flush()?;
+        frames += 1;
+
+        let closed = tokio::select! {
+            _ = &mut cancel => return Ok(frames),
+            _ = tokio::time::sleep(interval) => false,
+            change = next_change(&mut changes) => matches!(change, Err(RecvError::Closed)),
+        };
+        if closed {
+            changes = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<(String, ShurikenState)> {
+        vec![
+            ("redis".into(), ShurikenState::Idle),
+            ("apache".into(), ShurikenState::Running),
+        ]
+    }
+
+    #[test]
+    fn test_json_lists_states_by_name() {
+        let parsed: Value =
+            serde_json::from_str(&render_list(sample(), ListFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(
+            parsed,
+            json!([
+                { "name": "apache", "state": "Running" },
+                { "name": "redis", "state": "Idle" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_yaml_matches_json() {
+        let yaml: Value =
+            serde_yaml::from_str(&render_list(sample(), ListFormat::Yaml).unwrap()).unwrap();
+        let json: Value =
+            serde_json::from_str(&render_list(sample(), ListFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(yaml, json);
+    }
+
+    #[tokio::test]
+    async fn test_watch_renders_snapshot_until_cancelled() {
+        let mut out = Vec::new();
+        let frames = watch_list(
+            || async { Ok(sample()) },
+            None,
+            Duration::from_secs(60),
+            false,
+            &mut out,
+            std::future::ready(()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(frames, 1);
+        let text = String::from_utf8(out).unwrap();
+        assert!(!text.contains(CLEAR_SCREEN));
+        assert_eq!(text, render_list(sample(), ListFormat::Table).unwrap());
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let table = render_list(sample(), ListFormat::Table).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("NAME") && lines[0].contains("STATE"));
+        assert!(lines[1].starts_with("apache  "));
+        assert!(lines[2].starts_with("redis   "));
+        assert!(lines[1].contains("running"));
+        assert!(lines[2].contains("idle"));
+    }
+
+    #[test]
+    fn test_crashed_shows_exit_code() {
+        let crashed = vec![("worker".to_string(), ShurikenState::Crashed(3))];
+
+        let table = render_list(crashed.clone(), ListFormat::Table).unwrap();
+        assert!(table.contains("crashed (exit 3)"));
+
+        let parsed: Value =
+            serde_json::from_str(&render_list(crashed, ListFormat::Json).unwrap()).unwrap();
+        assert_eq!(
+            parsed,
+            json!([{ "name": "worker", "state": { "Crashed": 3 } }])
+        );
+    }
+}
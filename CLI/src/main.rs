@@ -1,5 +1,5 @@
 use ::log::info;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Args, Parser, Subcommand};
 use clap_verbosity_flag::Verbosity;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -7,11 +7,17 @@ use ninja::{
     VERSION,
     common::{
         config::{ShurikenReference, get_shuriken_info},
+        error::error_code,
+        logs::{LogFollower, filter_log, log_record, parse_duration},
+        self_update::UpdateOutcome,
         traits::Reporter,
-        types::{ArmoryMetadata, ShurikenState},
+        types::{
+            ArmoryMetadata, BulkMode, FieldValue, ForgeFilter, ForgeOptions, InstallOptions,
+            LogFilter, ShurikenState, StartOverrides,
+        },
     },
     manager::ShurikenManager,
-    shuriken::{Shuriken, ShurikenConfig, ShurikenMetadata},
+    shuriken::{LIFECYCLE_FUNCTIONS, Shuriken, ShurikenConfig, ShurikenMetadata, StartPlan},
 };
 use ninja_http::server;
 use ninja_mcp::server as mcpserver;
@@ -19,10 +25,11 @@ use owo_colors::OwoColorize;
 use std::{
     env,
     fs::{File, create_dir_all},
-    io::Write,
+    io::{IsTerminal, Write},
     path::PathBuf,
     process::exit,
     sync::Arc,
+    time::Duration,
 };
 use tokio::{fs, sync::Mutex};
 
@@ -32,8 +39,27 @@ use log::setup_logger;
 mod repl;
 use repl::repl_mode;
 
+mod list;
+use list::{ListFormat, render_list, watch_list};
+
+mod doctor;
+use doctor::{DoctorFormat, render_doctor};
+
 mod prompts;
-use prompts::{collect_forge_metadata, collect_new_shuriken_input};
+use prompts::{
+    collect_forge_metadata, collect_new_shuriken_input, confirm_removal, confirm_stale_lock_removal,
+};
+
+mod remove;
+use remove::needs_confirmation;
+
+mod run;
+use run::{ScriptSource, read_script};
+
+/// Lines `logs --follow` shows before following, unless `-n` says otherwise.
+const FOLLOW_BACKLOG: usize = 10;
+/// How often `logs --follow` looks for new lines.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
 
 struct CliReporter {
     bar: ProgressBar,
@@ -90,16 +116,32 @@ enum Commands {
     Start(StartArgs),
     /// Stop a shuriken
     Stop(StopArgs),
+    /// Stop a shuriken and start it again
+    Restart(RestartArgs),
+    /// Freeze a running shuriken's process without killing it (Unix only)
+    Pause(PauseArgs),
+    /// Continue a paused shuriken
+    Resume(PauseArgs),
     /// Run a script using the Ninja Runtime
     Run(RunArgs),
+    /// Check that a lifecycle script defines start and stop, without calling them
+    ValidateScript(ValidateScriptArgs),
     /// List shuriken services with their statuses
-    List,
+    List(ListArgs),
     /// Generate a new shuriken with specified manifest
     New,
     /// Configure a shuriken
     Configure(ConfigureArgs),
+    /// Save and apply options for several shurikens from one TOML or JSON file
+    Apply(ApplyArgs),
+    /// Show options that differ from a shuriken's saved options.toml
+    Diff(DiffArgs),
+    /// Read or change a single option of a shuriken
+    Config(ConfigArgs),
     /// Lockpick a shuriken (remove the .lck file, dangerous/use with caution)
     Lockpick(LockpickArgs),
+    /// Adopt a process started outside ninja as a running shuriken
+    Adopt(AdoptArgs),
     /// Start up the HTTP API with a specified port (optional but recommended).
     Api(ApiArgs),
     /// Install a shuriken
@@ -108,26 +150,164 @@ enum Commands {
     Forge(ForgeArgs),
     /// Remove a shuriken (uninstall it completely)
     Remove(RemoveArgs),
+    /// Show how much disk space shurikens use, largest first
+    Du(DuArgs),
+    /// Check the installation and every shuriken for problems
+    Doctor(DoctorArgs),
+    /// Copy an installed shuriken under a new name
+    Clone(CloneArgs),
+    /// Print the manifest of a shuriken as the manager parsed it
+    ExportManifest(ExportManifestArgs),
+    /// Show a shuriken's logs, optionally filtered
+    Logs(LogsArgs),
     /// Manage registries and get shuriken information
     Registry(RegistryArgs),
+    /// Update shurikenctl to the latest release published by a registry
+    SelfUpdate,
 }
 
 #[derive(Args)]
 pub struct ApiArgs {
     /// The port for the HTTP api to use
     pub port: u16,
+    /// Bearer token required on routes that change anything (default: $NINJA_API_TOKEN)
+    #[arg(long)]
+    pub token: Option<String>,
 }
 
 #[derive(Args)]
 pub struct StartArgs {
-    /// The name of the shuriken to start
+    /// The name(s) of the shuriken(s) to start
+    #[arg(required = true)]
+    pub shurikens: Vec<String>,
+    /// Seconds to wait for the shuriken to become healthy before failing
+    #[arg(short = 't', long)]
+    pub timeout: Option<u64>,
+    /// Print what would be executed instead of starting anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Ask before removing a stale lockfile (only when attached to a terminal)
+    #[arg(short = 'i', long)]
+    pub interactive: bool,
+    /// Start with one of the profiles declared in the manifest
+    #[arg(short, long)]
+    pub profile: Option<String>,
+    /// Set an environment variable for the started processes (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = StartOverrides::parse_env)]
+    pub env: Vec<(String, String)>,
+    #[command(flatten)]
+    pub bulk: BulkArgs,
+}
+
+#[derive(Args)]
+pub struct RestartArgs {
+    /// The name of the shuriken to restart
     pub shuriken: String,
 }
 
 #[derive(Args)]
 pub struct StopArgs {
-    /// The name of the shuriken to stop
-    pub shuriken: String,
+    /// The name(s) of the shuriken(s) to stop
+    #[arg(required = true)]
+    pub shurikens: Vec<String>,
+    #[command(flatten)]
+    pub bulk: BulkArgs,
+}
+
+#[derive(Args)]
+pub struct BulkArgs {
+    /// Run up to N operations at once (default when no shuriken has dependencies)
+    #[arg(long, value_name = "N", conflicts_with = "sequential")]
+    pub parallel: Option<usize>,
+    /// Run one at a time in dependency order (default when dependencies exist)
+    #[arg(long)]
+    pub sequential: bool,
+}
+
+impl BulkArgs {
+    async fn mode(&self, manager: &ShurikenManager, names: &[String]) -> BulkMode {
+        match (self.parallel, self.sequential) {
+            (Some(n), _) => BulkMode::Parallel(n),
+            (None, true) => BulkMode::Sequential,
+            (None, false) => manager.default_bulk_mode(names).await,
+        }
+    }
+}
+
+/// Prints the outcome of a bulk start/stop and returns whether all succeeded.
+fn report_bulk(verb: &str, results: Vec<(String, Result<()>)>) -> bool {
+    let mut all_ok = true;
+    for (name, result) in results {
+        match result {
+            Ok(_) => println!("{} {}", verb, name.green()),
+            Err(e) => {
+                all_ok = false;
+                eprintln!("{}", format!("Failed on '{}': {}", name, e).red());
+            }
+        }
+    }
+    all_ok
+}
+
+/// Prints a dry-run start plan in a human readable form.
+fn print_start_plan(name: &str, plan: &StartPlan) {
+    let show = |path: &Option<PathBuf>| {
+        path.as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "-".into())
+    };
+
+    println!("{} ({})", name.bold(), plan.shuriken_type);
+    println!("  cwd:       {}", plan.cwd.display());
+    println!("  script:    {}", show(&plan.script_path));
+    println!("  compiled:  {}", show(&plan.compiled_path));
+    println!("  function:  {}", plan.function.as_deref().unwrap_or("-"));
+    if let Some(args) = &plan.args {
+        println!("  args:      {}", args);
+    }
+    for (key, value) in &plan.env {
+        println!("  env:       {}={}", key, value);
+    }
+    println!("  lockfile:  {}", plan.lockfile.display());
+    if !plan.checked_ports.is_empty() {
+        println!("  ports:     {:?}", plan.checked_ports);
+    }
+    for problem in &plan.problems {
+        println!("  {} {}", "problem:".yellow(), problem);
+    }
+    println!();
+}
+
+/// Prints log lines of `shuriken`, as JSON records when `json` is set.
+fn print_log_lines(shuriken: &str, lines: Vec<String>, json: bool) -> serde_json::Result<()> {
+    let now = chrono::Utc::now();
+    for line in lines {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string(&log_record(shuriken, &line, now))?
+            );
+        } else {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
 }
 
 #[derive(Args)]
@@ -136,30 +316,64 @@ pub struct ConfigureArgs {
     pub shuriken: String,
 }
 
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// A file with one table of options per shuriken
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The name of the shuriken
+    pub shuriken: String,
+}
+
+#[derive(Args)]
+pub struct PauseArgs {
+    /// The name of the shuriken
+    pub shuriken: String,
+}
+
 #[derive(Args)]
 pub struct LockpickArgs {
     /// The name of the shuriken to lockpick
     pub shuriken: String,
 }
 
+#[derive(Args)]
+pub struct AdoptArgs {
+    /// The name of the shuriken the process belongs to
+    pub shuriken: String,
+    /// The PID of the already running process
+    pub pid: u32,
+}
+
 #[derive(Args)]
 pub struct RunArgs {
-    /// The path of the file or snippet of script to run
+    /// The path of the file or snippet of script to run, or `-` to read it from stdin
     #[arg(name = "file/script")]
     pub file_script: Option<String>,
 }
 
 #[derive(Args)]
-pub struct ListArgs {
-    /// Show all shurikens and their statuses
-    #[arg(short = 'f', long)]
-    pub full: bool,
+pub struct ValidateScriptArgs {
+    /// The lifecycle script to check
+    pub path: PathBuf,
 }
 
 #[derive(Args)]
 pub struct InstallArgs {
     /// The name of the shuriken to install (can be a path, a registry reference or a url)
     pub name: String,
+    /// Don't run the package's postinstall script (for offline/air-gapped setups)
+    #[arg(long)]
+    pub no_postinstall: bool,
+    /// Reject the package unless it is signed with this Ed25519 public key (64 hex chars)
+    #[arg(long, value_name = "PUBKEY")]
+    pub verify: Option<PathBuf>,
+    /// Reinstall over an existing shuriken (stops it and keeps its options)
+    #[arg(long)]
+    pub replace: bool,
 }
 
 #[derive(Args)]
@@ -169,15 +383,95 @@ pub struct ForgeArgs {
     /// optional path to something like forge-options.json to skip inputs (CI friendly)
     #[arg(short = 'c', long)]
     pub options: Option<PathBuf>,
+    /// optional TOML manifest with the package metadata, instead of prompts
+    #[arg(long, value_name = "PATH", conflicts_with = "options")]
+    pub manifest: Option<PathBuf>,
     /// optional output path
     #[arg(short = 'o', long)]
     pub output: Option<PathBuf>,
+    /// Only pack files matching this glob (repeatable)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Leave out files matching this glob, on top of .ninjaignore (repeatable)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// Sign the package with this Ed25519 secret key (64 hex chars)
+    #[arg(long, value_name = "KEYFILE")]
+    pub sign: Option<PathBuf>,
 }
 
 #[derive(Args)]
 pub struct RemoveArgs {
-    /// The name of the shuriken to remove/uninstall, it's the same thing
+    /// The name(s) of the shuriken(s) to remove; `*` and `?` match several, e.g. 'test-*'
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    pub shurikens: Vec<String>,
+    /// Remove every installed shuriken
+    #[arg(long)]
+    pub all: bool,
+    /// Don't ask before removing several shurikens
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Output format
+    #[arg(short = 'f', long, value_enum, default_value_t = ListFormat::Table)]
+    pub format: ListFormat,
+    /// Keep redrawing the table as states change, until Ctrl-C
+    #[arg(short, long, visible_alias = "watch-states", conflicts_with = "format")]
+    pub watch: bool,
+    /// Seconds between redraws in watch mode
+    #[arg(long, default_value_t = 2, requires = "watch")]
+    pub interval: u64,
+}
+
+#[derive(Args)]
+pub struct CloneArgs {
+    /// The name of the shuriken to copy
+    pub source: String,
+    /// The name for the copy
+    pub destination: String,
+}
+
+#[derive(Args)]
+pub struct ExportManifestArgs {
+    /// The name of the shuriken whose manifest to print
+    pub shuriken: String,
+}
+
+#[derive(Args)]
+pub struct LogsArgs {
+    /// The name of the shuriken whose logs to show
     pub shuriken: String,
+    /// Only show lines matching this regex
+    #[arg(short, long)]
+    pub grep: Option<String>,
+    /// Only show lines logged within this long ago (e.g. 30s, 10m, 2h, 1d)
+    #[arg(short, long)]
+    pub since: Option<String>,
+    /// Only show the last N matching lines
+    #[arg(short = 'n', long)]
+    pub lines: Option<usize>,
+    /// Keep printing lines as they are written to the log file, until Ctrl-C
+    #[arg(short, long)]
+    pub follow: bool,
+    /// Print each line as a JSON object with its shuriken and timestamp
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// Output format; exits non-zero when a check fails either way
+    #[arg(short, long, value_enum, default_value_t = DoctorFormat::Text)]
+    pub output: DoctorFormat,
+}
+
+#[derive(Args)]
+pub struct DuArgs {
+    /// Only report this shuriken
+    pub shuriken: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -188,6 +482,56 @@ pub enum RegistrySubcommands {
     Install(RegistryInstallArgs),
 }
 
+#[derive(Subcommand)]
+pub enum ConfigSubcommands {
+    /// Print the value of an option
+    Get {
+        /// The name of the shuriken
+        shuriken: String,
+        /// The option to read
+        key: String,
+    },
+    /// Set an option and save it (types are inferred like in the DSL)
+    Set {
+        /// The name of the shuriken
+        shuriken: String,
+        /// The option to set
+        key: String,
+        /// The new value, e.g. 8080, 1.5, true or "quoted text"
+        value: String,
+    },
+    /// Remove an option and save the rest
+    Unset {
+        /// The name of the shuriken
+        shuriken: String,
+        /// The option to remove
+        key: String,
+    },
+    /// Write a shuriken's options and generated config to a .tar.gz to share
+    Export {
+        /// The name of the shuriken
+        shuriken: String,
+        /// Where to write the bundle
+        output: PathBuf,
+    },
+    /// Apply a bundle made by `config export` to an installed shuriken
+    Import {
+        /// The name of the shuriken
+        shuriken: String,
+        /// The bundle to apply
+        bundle: PathBuf,
+        /// Render the config from this shuriken's template instead of copying the bundled one
+        #[arg(long)]
+        regenerate: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub subcommand: ConfigSubcommands,
+}
+
 #[derive(Args)]
 pub struct RegistryArgs {
     #[command(subcommand)]
@@ -204,6 +548,15 @@ pub struct RegistryGetArgs {
 pub struct RegistryInstallArgs {
     /// The shuriken reference in format "registry:shuriken"
     pub reference: String,
+    /// Don't run the package's postinstall script (for offline/air-gapped setups)
+    #[arg(long)]
+    pub no_postinstall: bool,
+    /// Reject the package unless it is signed with this Ed25519 public key (64 hex chars)
+    #[arg(long, value_name = "PUBKEY")]
+    pub verify: Option<PathBuf>,
+    /// Reinstall over an existing shuriken (stops it and keeps its options)
+    #[arg(long)]
+    pub replace: bool,
 }
 
 #[tokio::main]
@@ -231,74 +584,220 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args.command {
         Some(Commands::Start(shuriken_args)) => {
-            let shuriken_name = shuriken_args.shuriken;
+            let timeout = shuriken_args.timeout.map(Duration::from_secs);
+            let profile = shuriken_args.profile.as_deref();
 
-            println!("Starting shuriken {}...\n", shuriken_name);
-            // Use the actual name from manifest, not service-name
-            match manager.start(shuriken_name.as_str()).await {
-                Ok(_) => println!("\nStarted shuriken '{}'", shuriken_name.green()),
-                Err(e) => eprintln!(
+            if profile.is_some() && shuriken_args.shurikens.len() > 1 {
+                eprintln!(
                     "{}",
-                    format!("Failed to start shuriken '{}': {}", shuriken_name, e).red()
-                ),
+                    "--profile can only be used with a single shuriken".red()
+                );
+                exit(1);
+            }
+            if !shuriken_args.env.is_empty() && shuriken_args.shurikens.len() > 1 {
+                eprintln!("{}", "--env can only be used with a single shuriken".red());
+                exit(1);
+            }
+
+            if shuriken_args.dry_run {
+                let overrides = StartOverrides {
+                    profile: shuriken_args.profile.clone(),
+                    timeout,
+                    env: shuriken_args.env.into_iter().collect(),
+                };
+                let mut ok = true;
+                for name in &shuriken_args.shurikens {
+                    let plan = manager.dry_start_with_overrides(name, &overrides).await?;
+                    ok &= plan.problems.is_empty();
+                    print_start_plan(name, &plan);
+                }
+                if !ok {
+                    exit(1);
+                }
+            } else if let [shuriken_name] = shuriken_args.shurikens.as_slice() {
+                if shuriken_args.interactive
+                    && std::io::stdin().is_terminal()
+                    && let Some(pid) = manager.stale_lock(shuriken_name).await
+                {
+                    if !confirm_stale_lock_removal(shuriken_name, pid)? {
+                        println!("Left the lockfile of '{}' in place", shuriken_name);
+                        exit(1);
+                    }
+                    manager.clear_stale_lock(shuriken_name).await?;
+                }
+
+                println!("Starting shuriken {}...\n", shuriken_name);
+                let overrides = StartOverrides {
+                    profile: shuriken_args.profile.clone(),
+                    timeout,
+                    env: shuriken_args.env.into_iter().collect(),
+                };
+                // Use the actual name from manifest, not service-name
+                match manager
+                    .start_with_overrides(shuriken_name.as_str(), &overrides)
+                    .await
+                {
+                    Ok(_) => println!("\nStarted shuriken '{}'", shuriken_name.green()),
+                    Err(e) => {
+                        eprintln!(
+                            "{}",
+                            format!("Failed to start shuriken '{}': {}", shuriken_name, e).red()
+                        );
+                        exit(1);
+                    }
+                }
+            } else {
+                let names = shuriken_args.shurikens;
+                let mode = shuriken_args.bulk.mode(&manager, &names).await;
+                println!("Starting {} shurikens ({:?})...\n", names.len(), mode);
+                let results = manager.start_many(&names, mode, timeout).await?;
+                if !report_bulk("Started", results) {
+                    exit(1);
+                }
+            }
+        }
+        Some(Commands::Config(args)) => match args.subcommand {
+            ConfigSubcommands::Get { shuriken, key } => {
+                match manager.get_option(&shuriken, &key).await? {
+                    Some(value) => println!("{}", value.render()),
+                    None => {
+                        eprintln!(
+                            "{}",
+                            format!("'{}' has no option '{}'", shuriken, key).red()
+                        );
+                        exit(1);
+                    }
+                }
+            }
+            ConfigSubcommands::Set {
+                shuriken,
+                key,
+                value,
+            } => {
+                let value = FieldValue::from(value.as_str());
+                manager.set_option(&shuriken, &key, value.clone()).await?;
+                println!("Set {} = {} for {}", key, value.render(), shuriken.green());
+            }
+            ConfigSubcommands::Unset { shuriken, key } => {
+                if manager.unset_option(&shuriken, &key).await? {
+                    println!("Removed {} from {}", key, shuriken.green());
+                } else {
+                    println!("'{}' has no option '{}'", shuriken, key);
+                }
+            }
+            ConfigSubcommands::Export { shuriken, output } => {
+                manager.export_config_bundle(&shuriken, &output).await?;
+                println!(
+                    "Exported config of {} to {}",
+                    shuriken.green(),
+                    output.display()
+                );
+            }
+            ConfigSubcommands::Import {
+                shuriken,
+                bundle,
+                regenerate,
+            } => {
+                manager
+                    .import_config_bundle(&shuriken, &bundle, regenerate)
+                    .await?;
+                println!(
+                    "Imported config from {} into {}",
+                    bundle.display(),
+                    shuriken.green()
+                );
+            }
+        },
+        Some(Commands::Restart(args)) => {
+            println!("Restarting shuriken {}...\n", args.shuriken);
+            match manager.restart(&args.shuriken).await {
+                Ok(_) => println!("\nRestarted shuriken '{}'", args.shuriken.green()),
+                Err(e) => {
+                    eprintln!(
+                        "{}",
+                        format!("Failed to restart shuriken '{}': {}", args.shuriken, e).red()
+                    );
+                    exit(1);
+                }
             }
         }
         Some(Commands::Stop(shuriken_args)) => {
-            let shuriken_name = shuriken_args.shuriken;
-
-            println!("Stopping shuriken {}...\n", shuriken_name);
-            // Use the actual name from manifest, not service-name
-            match manager.stop(shuriken_name.as_str()).await {
-                Ok(_) => println!("\nStopped shuriken '{}'", shuriken_name.red()),
-                Err(e) => eprintln!(
-                    "{}",
-                    format!("Failed to stop shuriken '{}': {}", shuriken_name, e).red()
-                ),
+            if let [shuriken_name] = shuriken_args.shurikens.as_slice() {
+                println!("Stopping shuriken {}...\n", shuriken_name);
+                // Use the actual name from manifest, not service-name
+                match manager.stop(shuriken_name.as_str()).await {
+                    Ok(_) => println!("\nStopped shuriken '{}'", shuriken_name.red()),
+                    Err(e) => eprintln!(
+                        "{}",
+                        format!("Failed to stop shuriken '{}': {}", shuriken_name, e).red()
+                    ),
+                }
+            } else {
+                let names = shuriken_args.shurikens;
+                let mode = shuriken_args.bulk.mode(&manager, &names).await;
+                println!("Stopping {} shurikens ({:?})...\n", names.len(), mode);
+                let results = manager.stop_many(&names, mode).await?;
+                if !report_bulk("Stopped", results) {
+                    exit(1);
+                }
             }
         }
-        Some(Commands::List) => {
+        Some(Commands::List(args)) if args.watch => {
+            let snapshot = || async {
+                // Pick up changes made by other processes too
+                manager.refresh().await?;
+                Ok::<_, anyhow::Error>(manager.list(true).await?.left().unwrap_or_default())
+            };
+            let ctrl_c = async {
+                let _ = tokio::signal::ctrl_c().await;
+            };
+            watch_list(
+                snapshot,
+                Some(manager.subscribe_states()),
+                Duration::from_secs(args.interval.max(1)),
+                std::io::stdout().is_terminal(),
+                &mut std::io::stdout(),
+                ctrl_c,
+            )
+            .await?;
+        }
+        Some(Commands::List(args)) => {
             let partial_shurikens = manager.list(true).await?.left();
             if let Some(shurikens) = partial_shurikens {
-                println!("{}", "Shurikens:\n".blue().bold());
-                for (name, state) in shurikens {
-                    if state == ShurikenState::Running {
-                        println!("{} {}", name, "running".green());
-                    } else {
-                        println!("{} {}", name, "stopped".red());
-                    }
+                if args.format == ListFormat::Table {
+                    println!("{}", "Shurikens:\n".blue().bold());
+                }
+                print!("{}", render_list(shurikens, args.format)?);
+                if args.format == ListFormat::Table {
+                    println!(); // for styling purposes
                 }
             } else {
                 eprintln!("Failed to list shurikens, None returned.")
             }
-
-            println!(); // for styling purposes
         }
         Some(Commands::Run(script_args)) => {
             let file_arg = script_args.file_script.ok_or("path argument is empty")?;
-            let content = file_arg.as_str();
-            let path = PathBuf::from(content);
-            if path.exists() {
-                match manager
-                    .engine
-                    .lock()
-                    .await
-                    .execute_file(&path, None, Some(manager.clone()))
-                    .await
-                {
-                    Ok(_) => exit(0),
-                    Err(e) => eprintln!("Error: {}", e),
+            let engine = manager.engine.lock().await;
+            let result = match ScriptSource::parse(&file_arg) {
+                ScriptSource::File(path) => {
+                    engine
+                        .execute_file(&path, None, Some(manager.clone()))
+                        .await
                 }
-            } else {
-                match manager
-                    .engine
-                    .lock()
-                    .await
-                    .execute(content, Some(&manager.root_path), Some(manager.clone()))
-                    .await
-                {
-                    Ok(_) => exit(0),
-                    Err(e) => eprintln!("Error: {}", e),
+                ScriptSource::Inline(content) => {
+                    engine
+                        .execute(&content, Some(&manager.root_path), Some(manager.clone()))
+                        .await
+                }
+                // Runs like a file would, from the current directory
+                ScriptSource::Stdin => {
+                    let script = read_script(std::io::stdin().lock())?;
+                    engine.execute(&script, None, Some(manager.clone())).await
                 }
+            };
+            match result {
+                Ok(_) => exit(0),
+                Err(e) => eprintln!("Error: {}", e),
             }
         }
         Some(Commands::New) => {
@@ -319,6 +818,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     check_ports: input.check_ports,
                     shuriken_type: input.shuriken_type,
                     ports: input.ports,
+                    start_timeout: None,
+                    idle_timeout: None,
+                    post_stop: None,
+                    depends_on: None,
+                    profiles: None,
+                    restart: None,
+                    log_buffer: None,
+                    start_delay: None,
+                    wait_for: None,
                 },
                 config: input.config_path.map(|path| ShurikenConfig {
                     config_path: path,
@@ -331,7 +839,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 dirty: Arc::new(Mutex::new(false)),
             };
 
-            create_dir_all(format!("shurikens/{}/.ninja", shuriken_name)).unwrap_or_else(|_| {
+            let ninja_dir = PathBuf::from("shurikens")
+                .join(&shuriken_name)
+                .join(".ninja");
+            create_dir_all(&ninja_dir).unwrap_or_else(|_| {
                 eprintln!(
                     "Failed to create directory for shuriken '{}'",
                     shuriken_name
@@ -339,15 +850,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 exit(1);
             });
 
-            env::set_current_dir(format!("shurikens/{}/.ninja", shuriken_name))?;
-
             if let Some(opts) = input.options {
                 let serialized_options = toml::ser::to_string_pretty(&opts)?;
-                fs::write("config.tmpl", "").await?;
-                fs::write("options.toml", serialized_options).await?;
+                fs::write(ninja_dir.join("config.tmpl"), "").await?;
+                fs::write(ninja_dir.join("options.toml"), serialized_options).await?;
             }
 
-            let manifest_path = PathBuf::from("manifest.toml");
+            let manifest_path = ninja_dir.join("manifest.toml");
             let mut file = File::create(&manifest_path).unwrap_or_else(|_| {
                 eprintln!(
                     "Failed to create manifest file for shuriken '{}'",
@@ -357,12 +866,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             });
 
             if let Some(script_path) = &input.script_path {
+                let script_path = ninja_dir.join(script_path);
                 if let Some(parent) = script_path.parent() {
                     fs::create_dir_all(parent).await?;
                 }
 
                 fs::write(
-                        script_path,
+                        &script_path,
                         "function start()\n\t-- Start procedure goes here\nend\n\nfunction stop()\n\t-- Stop procedure goes here\nend",
                     )
                     .await?;
@@ -384,53 +894,293 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 exit(1);
             });
 
-            env::set_current_dir(&manager.root_path)?;
-
             println!("Manifest for '{}' generated successfully!", shuriken_name);
         }
         Some(Commands::Configure(args)) => {
             info!("Configuring shuriken {}", args.shuriken);
             manager.configure_shuriken(&args.shuriken).await?;
         }
+        Some(Commands::Apply(args)) => {
+            let results = manager.bulk_configure_from_file(&args.file).await?;
+            if results.is_empty() {
+                println!("No installed shurikens in {}", args.file.display());
+            }
+            if !report_bulk("Configured", results) {
+                exit(1);
+            }
+        }
+        Some(Commands::Diff(args)) => {
+            let changes = manager.diff_config(&args.shuriken).await?;
+            if changes.is_empty() {
+                println!("No unsaved changes for {}", args.shuriken.bold());
+            }
+            for (key, old, new) in changes {
+                match (old, new) {
+                    (None, Some(new)) => {
+                        println!("{}", format!("+ {} = {}", key, new.render()).green())
+                    }
+                    (Some(old), None) => {
+                        println!("{}", format!("- {} = {}", key, old.render()).red())
+                    }
+                    (Some(old), Some(new)) => println!(
+                        "{}",
+                        format!("~ {} = {} -> {}", key, old.render(), new.render()).yellow()
+                    ),
+                    (None, None) => {}
+                }
+            }
+        }
+        Some(Commands::Pause(args)) => {
+            manager.pause(&args.shuriken).await?;
+            println!("Paused {}", args.shuriken.bold());
+        }
+        Some(Commands::Resume(args)) => {
+            manager.resume(&args.shuriken).await?;
+            println!("Resumed {}", args.shuriken.bold());
+        }
         Some(Commands::Lockpick(args)) => {
             info!("Lockpicking shuriken {}", args.shuriken);
             manager.lockpick(&args.shuriken).await?;
         }
+        Some(Commands::Adopt(args)) => {
+            manager.adopt(&args.shuriken, args.pid).await?;
+            println!(
+                "Adopted PID {} as shuriken '{}'",
+                args.pid,
+                args.shuriken.green()
+            );
+        }
 
         Some(Commands::Api(args)) => {
             info!("Starting API endpoint with port {}", args.port);
-            server(args.port).await?;
+            server(args.port, args.token).await?;
         }
         Some(Commands::Install(args)) => {
             let reporter = CliReporter {
                 bar: ProgressBar::hidden(),
             };
             info!("Installing a shuriken");
-            manager.install(&args.name, reporter).await?;
+            let options = InstallOptions {
+                run_postinstall: !args.no_postinstall,
+                verify_key: args.verify,
+                replace: args.replace,
+            };
+            manager
+                .install_with_options(&args.name, reporter, &options)
+                .await?;
         }
         Some(Commands::Forge(args)) => {
             use serde_json::from_str;
             use tokio::fs;
 
-            if let Some(config_path) = args.options {
+            let options = ForgeOptions {
+                filter: ForgeFilter {
+                    include: args.include,
+                    exclude: args.exclude,
+                },
+                sign_key: args.sign,
+            };
+
+            let metadata = if let Some(config_path) = args.options {
                 // --- Load metadata from config file ---
                 let serialized_metadata = fs::read_to_string(&config_path).await?;
-                let metadata: ArmoryMetadata = from_str(&serialized_metadata)?;
+                from_str::<ArmoryMetadata>(&serialized_metadata)?
+            } else if let Some(manifest_path) = args.manifest {
+                let manifest = fs::read_to_string(&manifest_path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+                ArmoryMetadata::from_manifest(&manifest)
+                    .map_err(anyhow::Error::msg)
+                    .with_context(|| format!("Can't forge from {}", manifest_path.display()))?
+            } else {
+                collect_forge_metadata()?
+            };
 
-                println!("{}", "Creating shuriken...".bold());
+            println!("{}", "Creating shuriken...".bold());
 
-                // No need to manually create "blacksmith" here,
-                // `forge` already ensures the directory exists.
-                manager.forge(metadata, args.path, args.output).await?;
+            // No need to manually create "blacksmith" here,
+            // `forge` already ensures the directory exists.
+            let forged = manager
+                .forge_with_options(metadata, args.path, args.output, &options)
+                .await?;
+            println!(
+                "Packed {} files ({} bytes)",
+                forged.report.file_count.to_string().bold(),
+                forged.report.total_size
+            );
+            if forged.report.link_count > 0 {
+                println!(
+                    "{} symlinks packed, installing needs allow_symlinks",
+                    forged.report.link_count
+                );
+            }
+            for skipped in &forged.report.skipped {
+                println!("{} {}", "Skipped".yellow(), skipped.display());
+            }
+            println!(
+                "Wrote {} ({} bytes)\nsha256: {}",
+                forged.path.display().to_string().bold(),
+                forged.size,
+                forged.checksum
+            );
+        }
+        Some(Commands::Remove(args)) => {
+            let names = if args.all {
+                manager.list(false).await?.right().unwrap_or_default()
             } else {
-                let metadata = collect_forge_metadata()?;
+                manager.matching_names(&args.shurikens).await?
+            };
+            if names.is_empty() {
+                println!("No shurikens match, nothing to remove");
+                return Ok(());
+            }
+
+            if needs_confirmation(&args.shurikens, args.all, args.yes) {
+                if !std::io::stdin().is_terminal() {
+                    eprintln!(
+                        "{}",
+                        "Refusing to remove several shurikens without a terminal to confirm; pass --yes"
+                            .red()
+                    );
+                    exit(1);
+                }
+                if !confirm_removal(&names)? {
+                    println!("Nothing removed");
+                    return Ok(());
+                }
+            }
 
-                println!("{}", "Creating shuriken...".bold());
-                manager.forge(metadata, args.path, args.output).await?;
+            let results = manager.remove_many(&names).await?;
+            if !report_bulk("Removed", results) {
+                exit(1);
             }
         }
-        Some(Commands::Remove(args)) => {
-            manager.remove(&args.shuriken).await?;
+        Some(Commands::Clone(args)) => {
+            manager
+                .clone_shuriken(&args.source, &args.destination)
+                .await?;
+            println!(
+                "Cloned {} into {}",
+                args.source.bold(),
+                args.destination.green()
+            );
+        }
+        Some(Commands::ExportManifest(args)) => {
+            let shuriken = manager
+                .get(ninja::utils::normalize_shuriken_name(&args.shuriken))
+                .await?;
+            print!("{}", shuriken.to_manifest()?);
+        }
+        Some(Commands::Logs(args)) => {
+            let shuriken = ninja::utils::normalize_shuriken_name(&args.shuriken);
+            let filter = LogFilter {
+                grep: args.grep.clone(),
+                since: args.since.as_deref().map(parse_duration).transpose()?,
+                // Like tail -f, start with the last few lines
+                lines: args.lines.or(args.follow.then_some(FOLLOW_BACKLOG)),
+            };
+
+            // Start following first so nothing written while reading is lost
+            let follower = if args.follow {
+                Some(LogFollower::new(&manager.log_path(&shuriken).await?).await)
+            } else {
+                None
+            };
+
+            let lines = if filter.grep.is_none()
+                && filter.since.is_none()
+                && let Some(n) = filter.lines
+            {
+                match manager.tail_logs(&shuriken, n).await {
+                    // Captured output has no file to tail
+                    Err(e) if error_code(&e) == Some("no_log_file") => {
+                        manager.read_logs(&shuriken, &filter).await?
+                    }
+                    result => result?,
+                }
+            } else {
+                manager.read_logs(&shuriken, &filter).await?
+            };
+            print_log_lines(&shuriken, lines, args.json)?;
+
+            if let Some(mut follower) = follower {
+                let grep = LogFilter {
+                    grep: args.grep,
+                    ..Default::default()
+                };
+                loop {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => break,
+                        _ = tokio::time::sleep(FOLLOW_INTERVAL) => {}
+                    }
+                    let appended = follower.poll().await?;
+                    if appended.is_empty() {
+                        continue;
+                    }
+                    let lines = filter_log(&appended.join("\n"), &grep, chrono::Utc::now(), None)?;
+                    print_log_lines(&shuriken, lines, args.json)?;
+                }
+            }
+        }
+        Some(Commands::ValidateScript(args)) => {
+            let missing = manager
+                .engine
+                .lock()
+                .await
+                .missing_functions(&args.path, LIFECYCLE_FUNCTIONS)
+                .await?;
+            if missing.is_empty() {
+                println!(
+                    "{} defines {}",
+                    args.path.display().to_string().bold(),
+                    LIFECYCLE_FUNCTIONS.join(", ").green()
+                );
+            } else {
+                eprintln!(
+                    "{} is missing: {}",
+                    args.path.display().to_string().bold(),
+                    missing.join(", ").red()
+                );
+                exit(1);
+            }
+        }
+        Some(Commands::Doctor(args)) => {
+            let report = manager.doctor().await;
+            print!("{}", render_doctor(&report, args.output)?);
+            if !report.ok {
+                exit(1);
+            }
+        }
+        Some(Commands::Du(args)) => {
+            let mut usage: Vec<(String, u64)> = match args.shuriken {
+                Some(name) => vec![(name.clone(), manager.disk_usage(&name).await?)],
+                None => manager.disk_usage_all().await?.into_iter().collect(),
+            };
+            usage.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+            for (name, bytes) in &usage {
+                println!("{:>10}  {}", format_size(*bytes), name);
+            }
+            if usage.len() > 1 {
+                let total: u64 = usage.iter().map(|(_, bytes)| bytes).sum();
+                println!("{:>10}  {}", format_size(total).bold(), "total".bold());
+            }
+        }
+        Some(Commands::SelfUpdate) => {
+            let exe = env::current_exe()?;
+            println!("Checking for a newer shurikenctl...");
+            match manager.self_update(env!("CARGO_PKG_VERSION"), &exe).await {
+                Ok(UpdateOutcome::UpToDate { version }) => {
+                    println!("shurikenctl {} is up to date", version)
+                }
+                Ok(UpdateOutcome::Updated { from, to }) => {
+                    println!("Updated shurikenctl from {} to {}", from, to.green())
+                }
+                Err(e) => {
+                    eprintln!("{}", format!("Self-update failed: {}", e).red());
+                    exit(1);
+                }
+            }
         }
         Some(Commands::Registry(registry_args)) => {
             let config = manager.config.read().await;
@@ -462,7 +1212,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                     let reporter = CliReporter::new();
 
-                    manager.install_from_registry(&reference, reporter).await?;
+                    let options = InstallOptions {
+                        run_postinstall: !install_args.no_postinstall,
+                        verify_key: install_args.verify,
+                        replace: install_args.replace,
+                    };
+                    manager
+                        .install_from_registry(&reference, reporter, &options)
+                        .await?;
                 }
             }
         }
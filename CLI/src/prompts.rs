@@ -184,6 +184,27 @@ pub(crate) fn collect_new_shuriken_input() -> Result<NewShurikenInput, dialoguer
     })
 }
 
+pub(crate) fn confirm_stale_lock_removal(name: &str, pid: u32) -> Result<bool, dialoguer::Error> {
+    let theme = ColorfulTheme::default();
+    prompt_confirm(
+        &theme,
+        &format!(
+            "'{}' left a lockfile behind but PID {} is gone. Remove it and start?",
+            name, pid
+        ),
+        true,
+    )
+}
+
+pub(crate) fn confirm_removal(names: &[String]) -> Result<bool, dialoguer::Error> {
+    let theme = ColorfulTheme::default();
+    println!("This deletes {} shuriken(s):", names.len());
+    for name in names {
+        println!("  {}", name);
+    }
+    prompt_confirm(&theme, "Remove them?", false)
+}
+
 pub(crate) fn collect_forge_metadata() -> Result<ArmoryMetadata, dialoguer::Error> {
     let theme = ColorfulTheme::default();
 
@@ -0,0 +1,36 @@
+use ninja::utils::is_name_pattern;
+
+/// Whether `shurikenctl remove` has to ask before deleting.
+///
+/// Removing one shuriken by its exact name works as it always has. `--all`,
+/// wildcards and several names ask first, unless `--yes` is given.
+pub fn needs_confirmation(patterns: &[String], all: bool, yes: bool) -> bool {
+    if yes {
+        return false;
+    }
+    all || patterns.len() > 1 || patterns.iter().any(|p| is_name_pattern(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_bulk_removal_asks_first() {
+        assert!(needs_confirmation(&[], true, false));
+        assert!(needs_confirmation(&names(&["test-*"]), false, false));
+        assert!(needs_confirmation(&names(&["api", "db"]), false, false));
+        assert!(!needs_confirmation(&names(&["api"]), false, false));
+    }
+
+    #[test]
+    fn test_yes_skips_confirmation() {
+        assert!(!needs_confirmation(&[], true, true));
+        assert!(!needs_confirmation(&names(&["test-*"]), false, true));
+        assert!(!needs_confirmation(&names(&["api", "db"]), false, true));
+    }
+}
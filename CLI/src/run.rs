@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use std::{io::Read, path::PathBuf};
+
+/// Where `shurikenctl run` takes its script from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScriptSource {
+    /// `-`: the whole script is read from stdin
+    Stdin,
+    /// An existing file
+    File(PathBuf),
+    /// Anything else is run as a snippet
+    Inline(String),
+}
+
+impl ScriptSource {
+    /// Picks the source for `run`'s argument. `-` always means stdin, even
+    /// if a file by that name exists.
+    pub fn parse(arg: &str) -> Self {
+        if arg == "-" {
+            return ScriptSource::Stdin;
+        }
+        let path = PathBuf::from(arg);
+        if path.exists() {
+            ScriptSource::File(path)
+        } else {
+            ScriptSource::Inline(arg.to_string())
+        }
+    }
+}
+
+/// Reads a whole script, as piped into `shurikenctl run -`.
+pub fn read_script(mut reader: impl Read) -> Result<String> {
+    let mut script = String::new();
+    reader
+        .read_to_string(&mut script)
+        .context("Failed to read script from stdin")?;
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ninja::scripting::NinjaEngine;
+
+    #[test]
+    fn test_dash_means_stdin() {
+        assert_eq!(ScriptSource::parse("-"), ScriptSource::Stdin);
+        assert_eq!(
+            ScriptSource::parse("print('hi')"),
+            ScriptSource::Inline("print('hi')".to_string())
+        );
+        assert_eq!(
+            ScriptSource::parse("Cargo.toml"),
+            ScriptSource::File(PathBuf::from("Cargo.toml"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_piped_script_runs() {
+        let piped: &[u8] = b"local greeting = 'piped'\nerror(greeting .. ' script ran')\n";
+        let script = read_script(piped).unwrap();
+
+        // The script's own error proves it got executed
+        let engine = NinjaEngine::new().await.unwrap();
+        let err = engine.execute(&script, None, None).await.unwrap_err();
+        assert!(err.to_string().contains("piped script ran"));
+
+        assert!(engine.execute("local ok = 1 + 1", None, None).await.is_ok());
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+use tempfile::tempdir;
+
+/// Runs `shurikenctl` with `HOME` pointed at `home`, so it manages `home/.ninja`.
+fn shurikenctl(home: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_shurikenctl"));
+    cmd.env("HOME", home).env("USERPROFILE", home);
+    cmd
+}
+
+#[test]
+fn test_doctor_json_exits_non_zero_on_failure() {
+    let home = tempdir().unwrap();
+    let ninja_dir = home.path().join(".ninja/shurikens/broken/.ninja");
+    fs::create_dir_all(&ninja_dir).unwrap();
+    // The manifest points at a compiled script that was never written.
+    fs::write(
+        ninja_dir.join("manifest.toml"),
+        "[shuriken]\nname = \"broken\"\nid = \"broken\"\nversion = \"1.0.0\"\ntype = \"daemon\"\nscript-path = \"broken.ns\"\n",
+    )
+    .unwrap();
+
+    let output = shurikenctl(home.path())
+        .args(["doctor", "--output", "json"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(output.status.code(), Some(1));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["ok"], false);
+    assert!(
+        report["checks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|c| c["status"] == "fail")
+    );
+}
+
+#[test]
+fn test_run_reads_script_from_stdin() {
+    let home = tempdir().unwrap();
+    let mut child = shurikenctl(home.path())
+        .args(["run", "-"])
+        .current_dir(home.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"local greeting = 'piped'\nfs.write('piped.txt', greeting .. ' script ran')\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // Piped scripts run from the current directory, like a file would
+    assert_eq!(
+        fs::read_to_string(home.path().join("piped.txt")).unwrap(),
+        "piped script ran"
+    );
+}
@@ -94,7 +94,7 @@ static inline int ninja_check_and_clear_error(const char *context) {
 /*
  * NINJA_CHECK(expr)
  *
- * Evaluates expr (expected to return 0 on success).
+ * Evaluates expr (expected to return 0, i.e. NINJA_STATUS_OK, on success).
  * If it fails (non-zero), jumps to `error:` label.
  *
  * Example:
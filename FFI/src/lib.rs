@@ -1,9 +1,15 @@
-use anyhow::Result;
-use ninja::common::{structs::NoopReporter, types::ArmoryMetadata};
+use anyhow::{Context, Result};
+use ninja::common::{
+    error::ManagerError,
+    structs::NoopReporter,
+    types::{ArmoryMetadata, FieldValue},
+};
 use ninja::manager::ShurikenManager;
 use once_cell::sync::Lazy;
 use serde::Serialize;
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     ffi::{CStr, CString},
     os::raw::{c_char, c_int, c_void},
     path::PathBuf,
@@ -13,6 +19,8 @@ use std::{
 use tokio::{
     fs,
     runtime::{Builder, Runtime},
+    sync::broadcast::error::RecvError,
+    task::JoinHandle,
 };
 
 // ========================
@@ -37,44 +45,113 @@ pub struct NinjaManagerOpaque {
 
 struct ManagerBox(pub Box<ShurikenManager>);
 
+// ========================
+// State subscriptions
+// ========================
+
+/// Dispatch tasks for state subscriptions, keyed by manager pointer address.
+static STATE_SUBSCRIPTIONS: Lazy<Mutex<HashMap<usize, JoinHandle<()>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Aborts a dispatch task and waits until it has finished, so its callback
+/// is guaranteed not to run afterwards.
+fn cancel(task: JoinHandle<()>) {
+    task.abort();
+    // From the callback itself (a runtime thread) we cannot block; the abort
+    // still takes effect as soon as the callback returns.
+    if tokio::runtime::Handle::try_current().is_err() {
+        let _ = RUNTIME.block_on(task);
+    }
+}
+
+fn unsubscribe(mgr: *mut NinjaManagerOpaque) -> bool {
+    let task = STATE_SUBSCRIPTIONS.lock().unwrap().remove(&(mgr as usize));
+    match task {
+        Some(task) => {
+            cancel(task);
+            true
+        }
+        None => false,
+    }
+}
+
 // ========================
 // Last error tracking
 // ========================
-static LAST_ERROR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+thread_local! {
+    /// Like `errno`: each thread only sees the errors of its own calls.
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
 
 fn set_last_error(msg: String) {
-    let mut lock = LAST_ERROR.lock().unwrap();
-    *lock = Some(msg);
+    LAST_ERROR.with_borrow_mut(|last| *last = Some(msg));
+}
+
+// ========================
+// Status codes
+// ========================
+
+/// Result of a synchronous FFI call.
+///
+/// `NINJA_STATUS_OK` is 0, so `if (rc != 0)` still detects failure. On any
+/// other value the message is in `out_err` (when given) and the last error.
+/// Errors are mapped to the closest status:
+/// - `NULL_ARG`: a required pointer or string was null or not UTF-8
+/// - `NOT_FOUND`: no shuriken with that name is installed
+/// - `IO`: reading or writing a file or directory failed
+/// - `PARSE`: JSON or TOML passed in or read from disk was malformed
+/// - `INTERNAL`: anything else, e.g. a script error or a shuriken that is
+///   already running
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NinjaStatus {
+    Ok = 0,
+    NullArg = 1,
+    NotFound = 2,
+    Io = 3,
+    Parse = 4,
+    Internal = 5,
+}
+
+/// Picks the status for an error from the first cause that tells it apart.
+fn status_of(err: &anyhow::Error) -> NinjaStatus {
+    for cause in err.chain() {
+        if let Some(ManagerError::NotFound(_)) = cause.downcast_ref::<ManagerError>() {
+            return NinjaStatus::NotFound;
+        }
+        if cause.is::<std::io::Error>() {
+            return NinjaStatus::Io;
+        }
+        if cause.is::<serde_json::Error>() || cause.is::<toml::de::Error>() {
+            return NinjaStatus::Parse;
+        }
+    }
+    NinjaStatus::Internal
 }
 
 // ========================
 // Error helpers
 // ========================
 
-/// Clears the last error message.
+/// Clears the calling thread's last error message.
 ///
 /// # Safety
 /// Safe to call at any time. Does not dereference any pointers.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn ninja_clear_last_error() {
-    let mut lock = LAST_ERROR.lock().unwrap();
-    *lock = None;
+    LAST_ERROR.with_borrow_mut(|last| *last = None);
 }
 
-/// Returns 1 if there is an error, 0 otherwise.
+/// Returns 1 if the calling thread has an error, 0 otherwise.
 ///
 /// # Safety
 /// Safe to call at any time. Does not dereference any pointers.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn ninja_has_error() -> c_int {
-    if LAST_ERROR.lock().unwrap().is_some() {
-        1
-    } else {
-        0
-    }
+    LAST_ERROR.with_borrow(|last| c_int::from(last.is_some()))
 }
 
-/// Writes the last error into a buffer.
+/// Writes the calling thread's last error into a buffer.
 ///
 /// Returns:
 /// - Number of bytes written (excluding null terminator)
@@ -91,7 +168,7 @@ pub unsafe extern "C" fn ninja_get_last_error_buf(
     if buffer.is_null() || buffer_size == 0 {
         return -1;
     }
-    match &*LAST_ERROR.lock().unwrap() {
+    LAST_ERROR.with_borrow(|last| match last {
         Some(s) => {
             let bytes = s.as_bytes();
             if bytes.len() + 1 > buffer_size {
@@ -104,21 +181,22 @@ pub unsafe extern "C" fn ninja_get_last_error_buf(
             bytes.len() as c_int
         }
         None => 0,
-    }
+    })
 }
 
-/// Returns the last error string (caller must free via `ninja_string_free`).
+/// Returns the calling thread's last error string (caller must free via
+/// `ninja_string_free`).
 ///
 /// # Safety
 /// The returned string must be freed with `ninja_string_free`.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn ninja_last_error() -> *mut c_char {
-    match &*LAST_ERROR.lock().unwrap() {
+    LAST_ERROR.with_borrow(|last| match last {
         Some(s) => CString::new(s.as_str())
             .ok()
             .map_or(ptr::null_mut(), |c| c.into_raw()),
         None => ptr::null_mut(),
-    }
+    })
 }
 
 /// Frees a string returned by the library.
@@ -143,18 +221,53 @@ fn str_from_c(ptr: *const c_char) -> Option<String> {
     unsafe { CStr::from_ptr(ptr).to_str().ok().map(|s| s.to_string()) }
 }
 
-unsafe fn mgr_from_ptr<'a>(mgr: *mut NinjaManagerOpaque) -> Option<&'a mut ShurikenManager> {
+/// Borrows the manager behind an opaque pointer.
+///
+/// Only a shared reference is handed out: the manager's state lives behind
+/// `Arc`s and locks, so the same pointer can be used from several threads.
+unsafe fn mgr_from_ptr<'a>(mgr: *mut NinjaManagerOpaque) -> Option<&'a ShurikenManager> {
     if mgr.is_null() {
         return None;
     }
-    let b = unsafe { &mut *(mgr as *mut ManagerBox) };
-    Some(b.0.as_mut())
+    let b = unsafe { &*(mgr as *const ManagerBox) };
+    Some(b.0.as_ref())
 }
 
 fn path_from_c(ptr: *const c_char) -> Option<PathBuf> {
     str_from_c(ptr).map(PathBuf::from)
 }
 
+/// Hands an async function's JSON result to its completion callback.
+///
+/// The string is borrowed: it is only valid until `cb` returns and is freed
+/// here afterwards, so callers must copy it if they need it later and must
+/// not pass it to `ninja_string_free`.
+fn complete(
+    cb: Option<extern "C" fn(*mut c_void, *const c_char)>,
+    userdata: usize,
+    json: &serde_json::Value,
+) {
+    let Some(cb) = cb else {
+        return;
+    };
+    // serde_json escapes NUL, so this can't fail
+    let json = CString::new(json.to_string()).unwrap_or_default();
+    cb(userdata as *mut c_void, json.as_ptr());
+}
+
+/// Fails an async function before any work was scheduled.
+///
+/// Sets the last error and hands `{"error": msg}` to `cb` right away, so a
+/// caller waiting on the callback isn't left hanging.
+fn complete_with_error(
+    cb: Option<extern "C" fn(*mut c_void, *const c_char)>,
+    userdata: usize,
+    msg: &str,
+) {
+    set_last_error(msg.to_string());
+    complete(cb, userdata, &serde_json::json!({ "error": msg }));
+}
+
 #[allow(dead_code)]
 unsafe fn json_result_or_error<T: Serialize>(
     res: Result<T>,
@@ -192,6 +305,8 @@ macro_rules! ffi_sync {
         #[unsafe(no_mangle)]
         /// Synchronous FFI function.
         ///
+        /// Returns `NINJA_STATUS_OK`, or the status for what went wrong.
+        ///
         /// # Safety
         /// `mgr` must be a valid pointer to a NinjaManagerOpaque.
         /// `name` must be valid C string.
@@ -200,7 +315,7 @@ macro_rules! ffi_sync {
             mgr: *mut NinjaManagerOpaque,
             name: *const c_char,
             out_err: *mut *mut c_char,
-        ) -> i32 {
+        ) -> NinjaStatus {
             let manager = if let Some(mgr) = unsafe { mgr_from_ptr(mgr) } {
                 mgr
             } else {
@@ -209,7 +324,7 @@ macro_rules! ffi_sync {
                         *out_err = CString::new("Manager pointer was null").unwrap().into_raw()
                     };
                 }
-                return -1;
+                return NinjaStatus::NullArg;
             };
 
             let name = if let Some(s) = str_from_c(name) {
@@ -218,11 +333,11 @@ macro_rules! ffi_sync {
                 if !out_err.is_null() {
                     unsafe { *out_err = CString::new("Name pointer was null").unwrap().into_raw() };
                 }
-                return -1;
+                return NinjaStatus::NullArg;
             };
 
             match $action(manager, &name) {
-                Ok(_) => 0,
+                Ok(_) => NinjaStatus::Ok,
                 Err(e) => {
                     let msg = format!(
                         "Operation '{}' failed for '{}': {}",
@@ -234,7 +349,7 @@ macro_rules! ffi_sync {
                     if !out_err.is_null() {
                         unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
                     }
-                    -1
+                    status_of(&e)
                 }
             }
         }
@@ -246,6 +361,10 @@ macro_rules! ffi_async {
         #[unsafe(no_mangle)]
         /// Asynchronous FFI function.
         ///
+        /// `cb` receives `{"ok":true}` or `{"error":...}` once the operation
+        /// finishes. The string is only valid during the call; copy it to keep
+        /// it and don't free it.
+        ///
         /// # Safety
         /// `mgr` must be valid.
         /// `name` must be valid C string.
@@ -267,21 +386,20 @@ macro_rules! ffi_async {
             };
 
             let userdata_ptr = userdata as usize;
-            RUNTIME.spawn(async move {
-                let res = $action(&mut manager.clone(), &name);
-                let json = match res {
-                    Ok(_) => "{\"ok\":true}".to_string(),
-                    Err(e) => format!(
-                        "{{\"error\":\"Operation '{}' failed for '{}': {}\"}}",
-                        stringify!($fn_name),
-                        name,
-                        e
-                    ),
+            // The actions block on the runtime, which a worker thread can't do
+            RUNTIME.spawn_blocking(move || {
+                let json = match $action(&manager, &name) {
+                    Ok(_) => serde_json::json!({ "ok": true }),
+                    Err(e) => serde_json::json!({
+                        "error": format!(
+                            "Operation '{}' failed for '{}': {}",
+                            stringify!($fn_name),
+                            name,
+                            e
+                        )
+                    }),
                 };
-                if let Some(cb_fn) = cb {
-                    let userdata_ptr = userdata_ptr as *mut c_void;
-                    cb_fn(userdata_ptr, CString::new(json).unwrap().into_raw());
-                }
+                complete(cb, userdata_ptr, &json);
             });
         }
     };
@@ -316,6 +434,56 @@ pub unsafe extern "C" fn ninja_manager_new(out_err: *mut *mut c_char) -> *mut Ni
     }
 }
 
+/// Creates a new Ninja manager rooted at a custom data directory.
+///
+/// The directory is created if it doesn't exist yet.
+/// Returns a pointer to the manager, or null on failure.
+/// `out_err` receives descriptive error message on failure.
+///
+/// # Safety
+/// `root_path` must be a valid C string. `out_err` can be null.
+/// Caller must free the manager with `ninja_manager_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ninja_manager_new_with_root(
+    root_path: *const c_char,
+    out_err: *mut *mut c_char,
+) -> *mut NinjaManagerOpaque {
+    let root = match path_from_c(root_path) {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => {
+            let msg = "Root path was null or empty".to_string();
+            set_last_error(msg.clone());
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
+            }
+            return ptr::null_mut();
+        }
+    };
+
+    let res = if root.exists() && !root.is_dir() {
+        Err(anyhow::Error::msg(format!(
+            "'{}' is not a directory",
+            root.display()
+        )))
+    } else {
+        RUNTIME.block_on(async { ShurikenManager::with_root(root).await })
+    };
+
+    match res {
+        Ok(manager) => {
+            Box::into_raw(Box::new(ManagerBox(Box::new(manager)))) as *mut NinjaManagerOpaque
+        }
+        Err(e) => {
+            let msg = format!("Failed to create manager: {}", e);
+            set_last_error(msg.clone());
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
 /// Frees a Ninja manager pointer.
 ///
 /// # Safety
@@ -323,42 +491,168 @@ pub unsafe extern "C" fn ninja_manager_new(out_err: *mut *mut c_char) -> *mut Ni
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn ninja_manager_free(mgr: *mut NinjaManagerOpaque) {
     if !mgr.is_null() {
+        unsubscribe(mgr);
         let _ = unsafe { Box::from_raw(mgr as *mut ManagerBox) };
     }
 }
 
+// ========================
+// State change notifications
+// ========================
+
+/// Registers `cb` to be called whenever a shuriken changes state.
+///
+/// The callback runs on the library's runtime thread with `userdata`,
+/// the shuriken name and its new state as JSON (e.g. `"Running"`).
+///
+/// When a refresh finds the shuriken gone from disk, the state is the
+/// pseudo-state `"Removed"`. It is not a shuriken state and no further
+/// changes are reported for that name unless it is installed again.
+///
+/// Both strings are only valid for the duration of the call and must not
+/// be freed. Subscribing again replaces the previous callback, waiting for
+/// it to finish first.
+///
+/// Returns 0 on success, -1 if `mgr` or `cb` is null.
+///
+/// # Safety
+/// `mgr` must be valid. `userdata` must stay valid until
+/// `ninja_unsubscribe_state_changes` or `ninja_manager_free` is called.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ninja_subscribe_state_changes(
+    mgr: *mut NinjaManagerOpaque,
+    cb: Option<extern "C" fn(*mut c_void, *const c_char, *const c_char)>,
+    userdata: *mut c_void,
+) -> c_int {
+    let Some(manager) = (unsafe { mgr_from_ptr(mgr) }) else {
+        set_last_error("Manager pointer was null".to_string());
+        return -1;
+    };
+    let Some(cb) = cb else {
+        set_last_error("Callback was null".to_string());
+        return -1;
+    };
+
+    let mut events = manager.subscribe_states();
+    let userdata_ptr = userdata as usize;
+    let task = RUNTIME.spawn(async move {
+        loop {
+            let change = match events.recv().await {
+                Ok(change) => change,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let Ok(name) = CString::new(change.name) else {
+                continue;
+            };
+            let state = if change.removed {
+                "\"Removed\"".to_string()
+            } else {
+                serde_json::to_string(&change.state).unwrap_or_default()
+            };
+            let Ok(state) = CString::new(state) else {
+                continue;
+            };
+            cb(userdata_ptr as *mut c_void, name.as_ptr(), state.as_ptr());
+        }
+    });
+
+    let previous = STATE_SUBSCRIPTIONS
+        .lock()
+        .unwrap()
+        .insert(mgr as usize, task);
+    if let Some(previous) = previous {
+        cancel(previous);
+    }
+    0
+}
+
+/// Stops delivering state changes for `mgr`.
+///
+/// Blocks until any callback already in progress has returned, so once this
+/// returns the callback will not be invoked again and `userdata` may be freed.
+/// Must not be called from inside the callback.
+///
+/// Returns 0 if a subscription was removed, -1 if there was none.
+///
+/// # Safety
+/// Safe to call with any pointer; it is only used as a lookup key.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ninja_unsubscribe_state_changes(mgr: *mut NinjaManagerOpaque) -> c_int {
+    if unsubscribe(mgr) { 0 } else { -1 }
+}
+
 // ========================
 // Sync Shuriken operations
 // ========================
-ffi_sync!(ninja_start_shuriken_sync, |m: &mut ShurikenManager, n| {
+ffi_sync!(ninja_start_shuriken_sync, |m: &ShurikenManager, n| {
     RUNTIME.block_on(async { m.start(n).await })
 });
-ffi_sync!(ninja_stop_shuriken_sync, |m: &mut ShurikenManager, n| {
+ffi_sync!(ninja_stop_shuriken_sync, |m: &ShurikenManager, n| {
     RUNTIME.block_on(async { m.stop(n).await })
 });
-ffi_sync!(ninja_refresh_shuriken_sync, |m: &mut ShurikenManager, _| {
+ffi_sync!(ninja_refresh_shuriken_sync, |m: &ShurikenManager, _| {
     RUNTIME.block_on(async { m.refresh().await })
 });
-ffi_sync!(ninja_remove_shuriken_sync, |m: &mut ShurikenManager, n| {
+ffi_sync!(ninja_remove_shuriken_sync, |m: &ShurikenManager, n| {
     RUNTIME.block_on(async { m.remove(n).await })
 });
+ffi_sync!(ninja_configure_shuriken_sync, |m: &ShurikenManager, n| {
+    RUNTIME.block_on(async { m.configure_shuriken(n).await })
+});
 
 // ========================
 // Async Shuriken operations
 // ========================
-ffi_async!(ninja_start_shuriken_async, |m: &mut ShurikenManager, n| {
+ffi_async!(ninja_start_shuriken_async, |m: &ShurikenManager, n| {
     RUNTIME.block_on(async { m.start(n).await })
 });
-ffi_async!(ninja_stop_shuriken_async, |m: &mut ShurikenManager, n| {
+ffi_async!(ninja_stop_shuriken_async, |m: &ShurikenManager, n| {
     RUNTIME.block_on(async { m.stop(n).await })
 });
-ffi_async!(
-    ninja_refresh_shuriken_async,
-    |m: &mut ShurikenManager, _| RUNTIME.block_on(async { m.refresh().await })
-);
-ffi_async!(ninja_remove_shuriken_async, |m: &mut ShurikenManager, n| {
+ffi_async!(ninja_refresh_shuriken_async, |m: &ShurikenManager, _| {
+    RUNTIME.block_on(async { m.refresh().await })
+});
+ffi_async!(ninja_remove_shuriken_async, |m: &ShurikenManager, n| {
     RUNTIME.block_on(async { m.remove(n).await })
 });
+ffi_async!(ninja_configure_shuriken_async, |m: &ShurikenManager, n| {
+    RUNTIME.block_on(async { m.configure_shuriken(n).await })
+});
+
+#[unsafe(no_mangle)]
+/// Check whether a shuriken is running.
+///
+/// Returns 1 if it is running, 0 if not (or it isn't installed), and -1 on error.
+///
+/// # Safety
+/// `mgr` must be valid. `name` must be a valid C string. `out_err` can be null.
+pub unsafe extern "C" fn ninja_is_running_sync(
+    mgr: *mut NinjaManagerOpaque,
+    name: *const c_char,
+    out_err: *mut *mut c_char,
+) -> i32 {
+    let manager = match unsafe { mgr_from_ptr(mgr) } {
+        Some(m) => m,
+        None => {
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new("Manager was null").unwrap().into_raw() };
+            }
+            return -1;
+        }
+    };
+    let name = match str_from_c(name) {
+        Some(s) => s,
+        None => {
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new("Name was null").unwrap().into_raw() };
+            }
+            return -1;
+        }
+    };
+
+    i32::from(RUNTIME.block_on(manager.is_running(&name)))
+}
 
 // ========================
 // Forge / Install / Write options
@@ -367,6 +661,8 @@ ffi_async!(ninja_remove_shuriken_async, |m: &mut ShurikenManager, n| {
 #[unsafe(no_mangle)]
 /// Forge a shuriken from metadata JSON and source path and optionally output dir.
 ///
+/// Returns `NINJA_STATUS_OK`, or the status for what went wrong.
+///
 /// # Safety
 /// `mgr` must be valid, `meta_json` and `src_path` must be valid C strings.
 /// `out_err` can be null.
@@ -376,14 +672,14 @@ pub unsafe extern "C" fn ninja_forge_shuriken_sync(
     src_path: *const c_char,
     output_dir: *const c_char,
     out_err: *mut *mut c_char,
-) -> i32 {
+) -> NinjaStatus {
     let manager = match unsafe { mgr_from_ptr(mgr) } {
         Some(m) => m,
         None => {
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("Manager was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let meta_str = match str_from_c(meta_json) {
@@ -392,7 +688,7 @@ pub unsafe extern "C" fn ninja_forge_shuriken_sync(
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("Metadata JSON was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let src = match path_from_c(src_path) {
@@ -401,7 +697,7 @@ pub unsafe extern "C" fn ninja_forge_shuriken_sync(
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("Source path was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let meta: ArmoryMetadata = match serde_json::from_str(&meta_str) {
@@ -414,7 +710,7 @@ pub unsafe extern "C" fn ninja_forge_shuriken_sync(
                         .into_raw();
                 }
             }
-            return -1;
+            return NinjaStatus::Parse;
         }
     };
     let output_dir_opt: Option<PathBuf> = if output_dir.is_null() {
@@ -425,35 +721,87 @@ pub unsafe extern "C" fn ninja_forge_shuriken_sync(
         }))
     };
     match RUNTIME.block_on(manager.forge(meta, src, output_dir_opt)) {
-        Ok(_) => 0,
+        Ok(_) => NinjaStatus::Ok,
         Err(e) => {
             let msg = format!("Forge failed: {}", e);
             set_last_error(msg.clone());
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
             }
-            -1
+            status_of(&e)
         }
     }
 }
 
+#[unsafe(no_mangle)]
+/// Forge a shuriken in the background and report where the package landed.
+///
+/// `cb` receives `{"ok":true,"path":...,"size":...,"checksum":...}` on success
+/// or `{"error":...}` on failure. The string is only valid during the call;
+/// copy it to keep it and don't free it. If `mgr`, `meta_json` or `src_path`
+/// is null, `cb` is called before returning and the last error is set.
+///
+/// # Safety
+/// `mgr` must be valid, `meta_json` and `src_path` must be valid C strings.
+/// `output_dir` and `cb` can be null. `userdata` is passed to callback as-is.
+pub unsafe extern "C" fn ninja_forge_shuriken_async(
+    mgr: *mut NinjaManagerOpaque,
+    meta_json: *const c_char,
+    src_path: *const c_char,
+    output_dir: *const c_char,
+    cb: Option<extern "C" fn(*mut c_void, *const c_char)>,
+    userdata: *mut c_void,
+) {
+    let userdata_ptr = userdata as usize;
+    let manager = match unsafe { mgr_from_ptr(mgr) } {
+        Some(m) => m.clone(),
+        None => return complete_with_error(cb, userdata_ptr, "Manager pointer was null"),
+    };
+    let (Some(meta_str), Some(src)) = (str_from_c(meta_json), path_from_c(src_path)) else {
+        return complete_with_error(cb, userdata_ptr, "Metadata or source path was null");
+    };
+    let output_dir_opt = path_from_c(output_dir);
+
+    RUNTIME.spawn(async move {
+        let result = match serde_json::from_str::<ArmoryMetadata>(&meta_str) {
+            Ok(meta) => manager
+                .forge(meta, src, output_dir_opt)
+                .await
+                .map_err(|e| format!("Forge failed: {}", e)),
+            Err(e) => Err(format!("Invalid metadata JSON: {}", e)),
+        };
+        let json = match result {
+            Ok(forged) => serde_json::json!({
+                "ok": true,
+                "path": forged.path,
+                "size": forged.size,
+                "checksum": forged.checksum,
+            }),
+            Err(msg) => serde_json::json!({ "error": msg }),
+        };
+        complete(cb, userdata_ptr, &json);
+    });
+}
+
 #[unsafe(no_mangle)]
 /// Install a shuriken from a path.
 ///
+/// Returns `NINJA_STATUS_OK`, or the status for what went wrong.
+///
 /// # Safety
 /// `mgr` must be valid. `name_ptr` must be a valid C string. `out_err` can be null.
 pub unsafe extern "C" fn ninja_install_shuriken_sync(
     mgr: *mut NinjaManagerOpaque,
     name_ptr: *const c_char,
     out_err: *mut *mut c_char,
-) -> i32 {
+) -> NinjaStatus {
     let path = match str_from_c(name_ptr) {
         Some(p) => p,
         None => {
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("String was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let manager = match unsafe { mgr_from_ptr(mgr) } {
@@ -462,19 +810,19 @@ pub unsafe extern "C" fn ninja_install_shuriken_sync(
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("Manager was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let reporter = NoopReporter {};
     match RUNTIME.block_on(manager.install(&path, reporter)) {
-        Ok(_) => 0,
+        Ok(_) => NinjaStatus::Ok,
         Err(e) => {
             let msg = format!("Install failed: {}", e);
             set_last_error(msg.clone());
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
             }
-            -1
+            status_of(&e)
         }
     }
 }
@@ -482,6 +830,8 @@ pub unsafe extern "C" fn ninja_install_shuriken_sync(
 #[unsafe(no_mangle)]
 /// Write TOML options to a shuriken.
 ///
+/// Returns `NINJA_STATUS_OK`, or the status for what went wrong.
+///
 /// # Safety
 /// `mgr` must be valid. `name` and `toml_str` must be valid C strings. `out_err` can be null.
 pub unsafe extern "C" fn ninja_write_options_toml_sync(
@@ -489,14 +839,14 @@ pub unsafe extern "C" fn ninja_write_options_toml_sync(
     name: *const c_char,
     toml_str: *const c_char,
     out_err: *mut *mut c_char,
-) -> i32 {
+) -> NinjaStatus {
     let manager = match unsafe { mgr_from_ptr(mgr) } {
         Some(m) => m,
         None => {
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("Manager was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let name = match str_from_c(name) {
@@ -505,7 +855,7 @@ pub unsafe extern "C" fn ninja_write_options_toml_sync(
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("Name was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let toml_str = match str_from_c(toml_str) {
@@ -514,7 +864,7 @@ pub unsafe extern "C" fn ninja_write_options_toml_sync(
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new("TOML string was null").unwrap().into_raw() };
             }
-            return -1;
+            return NinjaStatus::NullArg;
         }
     };
     let path = manager
@@ -531,14 +881,541 @@ pub unsafe extern "C" fn ninja_write_options_toml_sync(
         Ok::<(), anyhow::Error>(())
     });
     match res {
-        Ok(_) => 0,
+        Ok(_) => NinjaStatus::Ok,
         Err(e) => {
             let msg = format!("Write TOML failed: {}", e);
             set_last_error(msg.clone());
             if !out_err.is_null() {
                 unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
             }
-            -1
+            status_of(&e)
+        }
+    }
+}
+
+// Parses `options_json` and saves it as the shuriken's options
+async fn save_config_json(manager: &ShurikenManager, name: &str, options_json: &str) -> Result<()> {
+    let options: HashMap<String, FieldValue> =
+        serde_json::from_str(options_json).context("Invalid options JSON")?;
+    manager.save_shuriken_config(name, options).await
+}
+
+#[unsafe(no_mangle)]
+/// Save a shuriken's options from a JSON object of field values.
+///
+/// The options are checked against the shuriken's schema and written to its
+/// `options.toml`; call `ninja_configure_shuriken_sync` to apply them.
+///
+/// Returns `NINJA_STATUS_OK`, or the status for what went wrong.
+///
+/// # Safety
+/// `mgr` must be valid. `name` and `options_json` must be valid C strings.
+/// `out_err` can be null.
+pub unsafe extern "C" fn ninja_save_config_sync(
+    mgr: *mut NinjaManagerOpaque,
+    name: *const c_char,
+    options_json: *const c_char,
+    out_err: *mut *mut c_char,
+) -> NinjaStatus {
+    let manager = match unsafe { mgr_from_ptr(mgr) } {
+        Some(m) => m,
+        None => {
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new("Manager was null").unwrap().into_raw() };
+            }
+            return NinjaStatus::NullArg;
+        }
+    };
+    let name = match str_from_c(name) {
+        Some(s) => s,
+        None => {
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new("Name was null").unwrap().into_raw() };
+            }
+            return NinjaStatus::NullArg;
+        }
+    };
+    let options_json = match str_from_c(options_json) {
+        Some(s) => s,
+        None => {
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new("Options JSON was null").unwrap().into_raw() };
+            }
+            return NinjaStatus::NullArg;
+        }
+    };
+    match RUNTIME.block_on(save_config_json(manager, &name, &options_json)) {
+        Ok(_) => NinjaStatus::Ok,
+        Err(e) => {
+            let msg = format!("Save config failed: {:#}", e);
+            set_last_error(msg.clone());
+            if !out_err.is_null() {
+                unsafe { *out_err = CString::new(msg).unwrap().into_raw() };
+            }
+            status_of(&e)
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+/// Save a shuriken's options in the background.
+///
+/// `cb` receives `{"ok":true}` or `{"error":...}` once the options are saved.
+/// The string is only valid during the call; copy it to keep it and don't
+/// free it. If `mgr`, `name` or `options_json` is null, `cb` is called before
+/// returning and the last error is set.
+///
+/// # Safety
+/// `mgr` must be valid. `name` and `options_json` must be valid C strings.
+/// `cb` can be null. `userdata` is passed to callback as-is.
+pub unsafe extern "C" fn ninja_save_config_async(
+    mgr: *mut NinjaManagerOpaque,
+    name: *const c_char,
+    options_json: *const c_char,
+    cb: Option<extern "C" fn(*mut c_void, *const c_char)>,
+    userdata: *mut c_void,
+) {
+    let userdata_ptr = userdata as usize;
+    let manager = match unsafe { mgr_from_ptr(mgr) } {
+        Some(m) => m.clone(),
+        None => return complete_with_error(cb, userdata_ptr, "Manager pointer was null"),
+    };
+    let (Some(name), Some(options_json)) = (str_from_c(name), str_from_c(options_json)) else {
+        return complete_with_error(cb, userdata_ptr, "Name or options JSON was null");
+    };
+
+    RUNTIME.spawn(async move {
+        let json = match save_config_json(&manager, &name, &options_json).await {
+            Ok(_) => serde_json::json!({ "ok": true }),
+            Err(e) => serde_json::json!({ "error": format!("Save config failed: {:#}", e) }),
+        };
+        complete(cb, userdata_ptr, &json);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manager_new_with_root_starts_empty() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("data");
+        let root_c = CString::new(root.to_str().unwrap()).unwrap();
+        let mut err: *mut c_char = ptr::null_mut();
+
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), &mut err) };
+        assert!(!mgr.is_null());
+        assert!(err.is_null());
+        assert!(root.join("shurikens").is_dir());
+
+        let manager = unsafe { mgr_from_ptr(mgr) }.unwrap();
+        let names = RUNTIME
+            .block_on(manager.list(false))
+            .unwrap()
+            .right()
+            .unwrap();
+        assert!(names.is_empty());
+
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    #[test]
+    fn test_manager_new_with_root_rejects_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("not-a-dir");
+        std::fs::write(&file, "").unwrap();
+        let file_c = CString::new(file.to_str().unwrap()).unwrap();
+        let mut err: *mut c_char = ptr::null_mut();
+
+        let mgr = unsafe { ninja_manager_new_with_root(file_c.as_ptr(), &mut err) };
+        assert!(mgr.is_null());
+        assert!(!err.is_null());
+        unsafe { ninja_string_free(err) };
+    }
+
+    extern "C" fn record_state(userdata: *mut c_void, name: *const c_char, state: *const c_char) {
+        let seen = unsafe { &*(userdata as *const Mutex<Vec<(String, String)>>) };
+        let name = str_from_c(name).unwrap();
+        let state = str_from_c(state).unwrap();
+        seen.lock().unwrap().push((name, state));
+    }
+
+    /// Writes a daemon shuriken with a no-op script under `root` and returns
+    /// its directory. `extra` is appended to the manifest.
+    fn write_daemon_shuriken(root: &std::path::Path, name: &str, extra: &str) -> PathBuf {
+        let shuriken_dir = root.join("shurikens").join(name);
+        let ninja_dir = shuriken_dir.join(".ninja");
+        std::fs::create_dir_all(&ninja_dir).unwrap();
+        std::fs::write(
+            ninja_dir.join("manifest.toml"),
+            format!(
+                "[shuriken]\nname = \"{name}\"\nid = \"{name}\"\nversion = \"1.0.0\"\ntype = \"daemon\"\nscript-path = \"{name}.ns\"\n{extra}"
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            ninja_dir.join(format!("{name}.ns")),
+            "function start() end\nfunction stop() end\n",
+        )
+        .unwrap();
+        shuriken_dir
+    }
+
+    #[test]
+    fn test_state_callback_fires_on_start() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "echo", "");
+
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+
+        let seen: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+        let rc = unsafe {
+            ninja_subscribe_state_changes(mgr, Some(record_state), &seen as *const _ as *mut c_void)
+        };
+        assert_eq!(rc, 0);
+
+        let name = CString::new("echo").unwrap();
+        let rc = unsafe { ninja_start_shuriken_sync(mgr, name.as_ptr(), ptr::null_mut()) };
+        assert_eq!(rc, NinjaStatus::Ok);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while seen.lock().unwrap().is_empty() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        assert_eq!(unsafe { ninja_unsubscribe_state_changes(mgr) }, 0);
+        assert_eq!(
+            seen.lock().unwrap().first(),
+            Some(&("echo".to_string(), "\"Running\"".to_string()))
+        );
+
+        // Once unsubscribed, later changes must not reach the callback.
+        let delivered = seen.lock().unwrap().len();
+        let rc = unsafe { ninja_stop_shuriken_sync(mgr, name.as_ptr(), ptr::null_mut()) };
+        assert_eq!(rc, NinjaStatus::Ok);
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert_eq!(seen.lock().unwrap().len(), delivered);
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    #[test]
+    fn test_state_callback_reports_removed_shuriken() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "echo", "");
+
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+
+        let seen: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+        let rc = unsafe {
+            ninja_subscribe_state_changes(mgr, Some(record_state), &seen as *const _ as *mut c_void)
+        };
+        assert_eq!(rc, 0);
+
+        std::fs::remove_dir_all(&shuriken_dir).unwrap();
+        let manager = unsafe { mgr_from_ptr(mgr) }.unwrap();
+        RUNTIME.block_on(manager.refresh()).unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while seen.lock().unwrap().is_empty() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        assert_eq!(unsafe { ninja_unsubscribe_state_changes(mgr) }, 0);
+        assert_eq!(
+            seen.lock().unwrap().as_slice(),
+            &[("echo".to_string(), "\"Removed\"".to_string())]
+        );
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    extern "C" fn record_result(userdata: *mut c_void, json: *const c_char) {
+        let (seen, done) =
+            unsafe { &*(userdata as *const (Mutex<Vec<String>>, std::sync::Condvar)) };
+        // Borrowed: copy it before returning
+        seen.lock().unwrap().push(str_from_c(json).unwrap());
+        done.notify_all();
+    }
+
+    #[test]
+    fn test_async_callback_gets_borrowed_json() {
+        let dir = tempdir().unwrap();
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+
+        let state: (Mutex<Vec<String>>, std::sync::Condvar) =
+            (Mutex::new(Vec::new()), std::sync::Condvar::new());
+        let userdata = &state as *const _ as *mut c_void;
+        let empty = CString::new("").unwrap();
+        let ghost = CString::new("gh\"ost").unwrap();
+        unsafe {
+            ninja_refresh_shuriken_async(mgr, empty.as_ptr(), Some(record_result), userdata);
+            ninja_start_shuriken_async(mgr, ghost.as_ptr(), Some(record_result), userdata);
+        }
+
+        let (seen, done) = &state;
+        let seen = done
+            .wait_timeout_while(
+                seen.lock().unwrap(),
+                std::time::Duration::from_secs(5),
+                |seen| seen.len() < 2,
+            )
+            .unwrap()
+            .0;
+        let mut results: Vec<serde_json::Value> = seen
+            .iter()
+            .map(|json| serde_json::from_str(json).unwrap())
+            .collect();
+        results.sort_by_key(|result| result.get("ok").is_none());
+        assert_eq!(results[0], serde_json::json!({ "ok": true }));
+        // Quotes in the message stay valid JSON
+        assert!(
+            results[1]["error"]
+                .as_str()
+                .unwrap()
+                .contains("failed for 'gh\"ost'")
+        );
+        drop(seen);
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    #[test]
+    fn test_async_calls_report_null_arguments() {
+        let dir = tempdir().unwrap();
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+
+        let state: (Mutex<Vec<String>>, std::sync::Condvar) =
+            (Mutex::new(Vec::new()), std::sync::Condvar::new());
+        let userdata = &state as *const _ as *mut c_void;
+        let src = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let name = CString::new("web").unwrap();
+        unsafe {
+            ninja_forge_shuriken_async(
+                ptr::null_mut(),
+                ptr::null(),
+                src.as_ptr(),
+                ptr::null(),
+                Some(record_result),
+                userdata,
+            );
+            ninja_forge_shuriken_async(
+                mgr,
+                ptr::null(),
+                src.as_ptr(),
+                ptr::null(),
+                Some(record_result),
+                userdata,
+            );
+            ninja_save_config_async(
+                mgr,
+                name.as_ptr(),
+                ptr::null(),
+                Some(record_result),
+                userdata,
+            );
+        }
+
+        // Delivered before returning, nothing is left to wait for
+        let errors: Vec<String> = state
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|json| {
+                let result: serde_json::Value = serde_json::from_str(json).unwrap();
+                result["error"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "Manager pointer was null",
+                "Metadata or source path was null",
+                "Name or options JSON was null",
+            ]
+        );
+        let last = unsafe { ninja_last_error() };
+        assert_eq!(
+            str_from_c(last).as_deref(),
+            Some("Name or options JSON was null")
+        );
+        unsafe {
+            ninja_string_free(last);
+            ninja_manager_free(mgr);
+        }
+    }
+
+    #[test]
+    fn test_sync_calls_report_status() {
+        let dir = tempdir().unwrap();
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+        let name = CString::new("ghost").unwrap();
+
+        let rc =
+            unsafe { ninja_start_shuriken_sync(ptr::null_mut(), name.as_ptr(), ptr::null_mut()) };
+        assert_eq!(rc, NinjaStatus::NullArg);
+        let rc = unsafe { ninja_start_shuriken_sync(mgr, ptr::null(), ptr::null_mut()) };
+        assert_eq!(rc, NinjaStatus::NullArg);
+
+        let mut err: *mut c_char = ptr::null_mut();
+        let rc = unsafe { ninja_start_shuriken_sync(mgr, name.as_ptr(), &mut err) };
+        assert_eq!(rc, NinjaStatus::NotFound);
+        assert!(str_from_c(err).unwrap().contains("ghost"));
+        unsafe { ninja_string_free(err) };
+
+        let meta = CString::new("{").unwrap();
+        let src = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let rc = unsafe {
+            ninja_forge_shuriken_sync(
+                mgr,
+                meta.as_ptr(),
+                src.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(rc, NinjaStatus::Parse);
+
+        // A file where the shuriken's directory should be
+        std::fs::write(dir.path().join("shurikens").join("blocked"), "").unwrap();
+        let blocked = CString::new("blocked").unwrap();
+        let toml = CString::new("port = 80").unwrap();
+        let rc = unsafe {
+            ninja_write_options_toml_sync(mgr, blocked.as_ptr(), toml.as_ptr(), ptr::null_mut())
+        };
+        assert_eq!(rc, NinjaStatus::Io);
+
+        assert_eq!(
+            status_of(&anyhow::anyhow!("script blew up")),
+            NinjaStatus::Internal
+        );
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    #[test]
+    fn test_save_config_then_configure() {
+        let dir = tempdir().unwrap();
+        let ninja_dir = write_daemon_shuriken(
+            dir.path(),
+            "web",
+            "\n[config]\nconfig-path = \"web.conf\"\n",
+        )
+        .join(".ninja");
+        std::fs::write(ninja_dir.join("config.tmpl"), "port={{ port }}\n").unwrap();
+
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+        let name = CString::new("web").unwrap();
+
+        let bad = CString::new("not json").unwrap();
+        let mut err: *mut c_char = ptr::null_mut();
+        let rc = unsafe { ninja_save_config_sync(mgr, name.as_ptr(), bad.as_ptr(), &mut err) };
+        assert_eq!(rc, NinjaStatus::Parse);
+        assert!(str_from_c(err).unwrap().contains("Invalid options JSON"));
+        unsafe { ninja_string_free(err) };
+
+        let options = CString::new(r#"{"port": 8080}"#).unwrap();
+        let rc = unsafe {
+            ninja_save_config_sync(mgr, name.as_ptr(), options.as_ptr(), ptr::null_mut())
+        };
+        assert_eq!(rc, NinjaStatus::Ok);
+        assert!(ninja_dir.join("options.toml").is_file());
+
+        let rc = unsafe { ninja_configure_shuriken_sync(mgr, name.as_ptr(), ptr::null_mut()) };
+        assert_eq!(rc, NinjaStatus::Ok);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("shurikens/web/web.conf")).unwrap(),
+            "port=8080\n"
+        );
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    #[test]
+    fn test_last_error_is_per_thread() {
+        let dir = tempdir().unwrap();
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+
+        // Raw pointers aren't Send, so pass the address between threads
+        let addr = mgr as usize;
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+        let workers: Vec<_> = ["alpha", "beta"]
+            .into_iter()
+            .map(|name| {
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let mgr = addr as *mut NinjaManagerOpaque;
+                    let name_c = CString::new(name).unwrap();
+                    let rc =
+                        unsafe { ninja_start_shuriken_sync(mgr, name_c.as_ptr(), ptr::null_mut()) };
+                    assert_eq!(rc, NinjaStatus::NotFound);
+
+                    // Both errors are set before either is read back
+                    barrier.wait();
+                    let err = unsafe { ninja_last_error() };
+                    let seen = str_from_c(err).unwrap();
+                    unsafe { ninja_string_free(err) };
+                    (name, seen)
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            let (name, seen) = worker.join().unwrap();
+            assert!(seen.contains(&format!("failed for '{}'", name)), "{}", seen);
+        }
+        assert_eq!(unsafe { ninja_has_error() }, 0);
+        unsafe { ninja_manager_free(mgr) };
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_manager_is_send_sync() {
+        assert_send_sync::<ShurikenManager>();
+    }
+
+    #[test]
+    fn test_manager_shared_across_threads() {
+        let dir = tempdir().unwrap();
+        let root_c = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mgr = unsafe { ninja_manager_new_with_root(root_c.as_ptr(), ptr::null_mut()) };
+        assert!(!mgr.is_null());
+
+        // Raw pointers aren't Send, so pass the address between threads
+        let addr = mgr as usize;
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let mgr = addr as *mut NinjaManagerOpaque;
+                    let name = CString::new("").unwrap();
+                    for _ in 0..10 {
+                        let rc = unsafe {
+                            ninja_refresh_shuriken_sync(mgr, name.as_ptr(), ptr::null_mut())
+                        };
+                        assert_eq!(rc, NinjaStatus::Ok);
+                        let manager = unsafe { mgr_from_ptr(mgr) }.unwrap();
+                        assert!(RUNTIME.block_on(manager.list(false)).is_ok());
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
         }
+        unsafe { ninja_manager_free(mgr) };
     }
 }
@@ -23,6 +23,7 @@ import {
 import { useShuriken } from "@/hooks/use-shuriken";
 import { invoke } from "@tauri-apps/api/core";
 import LocalProjectsSidebar from "../ui/projects-pane";
+import { formatState } from "@/lib/utils";
 
 export default function Dashboard({
   gridView,
@@ -250,7 +251,7 @@ export default function Dashboard({
                       {service?.metadata?.name}
                     </TableCell>
                     <TableCell className="text-center">
-                      {service.state.toString()}
+                      {formatState(service.state)}
                     </TableCell>
                     <TableCell className="text-center">
                       {service?.metadata?.type === "daemon"
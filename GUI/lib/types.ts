@@ -1,4 +1,9 @@
-export type ShurikenState = "Running" | "Idle" | { Error: string };
+export type ShurikenState =
+  | "Running"
+  | "Idle"
+  | "Paused"
+  | { Error: string }
+  | { Crashed: number };
 
 export type Shuriken = {
   metadata: ShurikenMetadata;
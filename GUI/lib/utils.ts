@@ -1,5 +1,6 @@
 import { clsx, type ClassValue } from "clsx";
 import { twMerge } from "tailwind-merge";
+import { ShurikenState } from "@/lib/types";
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
@@ -7,4 +8,11 @@ export function cn(...inputs: ClassValue[]) {
 
 export function capitalizeFirstLetter(str: string) {
   return str.charAt(0).toUpperCase() + str.slice(1);
-}
\ No newline at end of file
+}
+
+export function formatState(state: ShurikenState) {
+  if (typeof state === "string") return state;
+  if ("Error" in state) return `Error: ${state.Error}`;
+  // -1 means the exit code isn't known
+  return state.Crashed === -1 ? "Crashed" : `Crashed (exit ${state.Crashed})`;
+}
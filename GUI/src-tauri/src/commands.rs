@@ -9,9 +9,9 @@ use ninja::{
     scripting::dsl::DslEngine,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, io::Read, path::PathBuf};
+use std::{collections::HashMap, io::Read, path::PathBuf, pin::pin, time::Duration};
 use tauri::State;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_opener::OpenerExt;
 use tokio::{fs, sync::Mutex};
 
@@ -287,6 +287,57 @@ pub async fn install_shuriken(
     Ok(())
 }
 
+/// Payload of the `tool-progress` event emitted while a tool runs.
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolProgress {
+    shuriken: String,
+    tool: String,
+    elapsed_secs: u64,
+    done: bool,
+}
+
+/// How often `tool-progress` is emitted while a tool is still running.
+const TOOL_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[tauri::command]
+pub async fn run_shuriken_tool(
+    app: AppHandle,
+    manager: State<'_, Mutex<ShurikenManager>>,
+    name: String,
+    tool: String,
+) -> Result<String, String> {
+    info!("Running tool {} of shuriken {}", tool, name);
+    // Don't hold the lock while the script runs, other commands would wait on it
+    let manager = manager.lock().await.clone();
+
+    let started = std::time::Instant::now();
+    let progress = |done: bool| {
+        let payload = ToolProgress {
+            shuriken: name.clone(),
+            tool: tool.clone(),
+            elapsed_secs: started.elapsed().as_secs(),
+            done,
+        };
+        if let Err(e) = app.emit("tool-progress", payload) {
+            debug!("Failed to emit tool progress: {}", e);
+        }
+    };
+
+    let mut run = pin!(manager.run_tool(&name, &tool));
+    let result = loop {
+        progress(false);
+        if let Ok(result) = tokio::time::timeout(TOOL_PROGRESS_INTERVAL, &mut run).await {
+            break result;
+        }
+    };
+    progress(true);
+
+    result.map_err(|e| {
+        error!("Tool {} of shuriken {} failed: {}", tool, name, e);
+        e.to_string()
+    })
+}
+
 #[tauri::command]
 pub async fn remove_shuriken(
     manager: State<'_, Mutex<ShurikenManager>>,
@@ -193,6 +193,7 @@ pub fn run() {
             config_exists,
             registry_get_all_registries,
             remove_shuriken,
+            run_shuriken_tool,
             open_devtools,
             read_logs,
             registry_get_registry_by_shuriken,
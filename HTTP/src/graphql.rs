@@ -1,28 +1,126 @@
-use async_graphql::{Context, EmptySubscription, Object, Result, Schema};
-use ninja::common::types::ShurikenState;
-use ninja::manager::ShurikenManager;
-use std::collections::HashMap;
+use async_graphql::{
+    Context, EmptySubscription, Enum, ErrorExtensions, Json, Object, Result, Schema,
+};
+use ninja::common::{
+    error::error_code,
+    types::{FieldValue, ShurikenInfo, ShurikenState},
+};
+use ninja::{manager::ShurikenManager, utils::normalize_shuriken_name};
+use std::{collections::HashMap, sync::Arc};
+
+// Converts a manager error, exposing its code as the `code` extension
+fn manager_error(e: anyhow::Error) -> async_graphql::Error {
+    let error = async_graphql::Error::new(e.to_string());
+    match error_code(&e) {
+        Some(code) => error.extend_with(|_, extensions| extensions.set("code", code)),
+        None => error,
+    }
+}
+
+/// [`ShurikenState`] without its payload, so it can be a GraphQL enum.
+///
+/// The error message or exit code is exposed separately as `stateDetail`.
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+#[graphql(name = "ShurikenState")]
+pub enum StateKind {
+    Running,
+    Idle,
+    Paused,
+    Error,
+    Crashed,
+}
+
+impl From<&ShurikenState> for StateKind {
+    fn from(state: &ShurikenState) -> Self {
+        match state {
+            ShurikenState::Running => StateKind::Running,
+            ShurikenState::Idle => StateKind::Idle,
+            ShurikenState::Paused => StateKind::Paused,
+            ShurikenState::Error(_) => StateKind::Error,
+            ShurikenState::Crashed(_) => StateKind::Crashed,
+        }
+    }
+}
+
+/// A shuriken as seen through GraphQL, backed by [`ShurikenInfo`].
+pub struct ShurikenObject(ShurikenInfo);
+
+#[Object(name = "Shuriken")]
+impl ShurikenObject {
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn version(&self) -> &str {
+        &self.0.version
+    }
+
+    #[graphql(name = "type")]
+    async fn shuriken_type(&self) -> &str {
+        &self.0.shuriken_type
+    }
+
+    async fn state(&self) -> StateKind {
+        StateKind::from(&self.0.state)
+    }
+
+    /// The error message of an `ERROR` state or the exit code of a `CRASHED` one
+    async fn state_detail(&self) -> Option<String> {
+        match &self.0.state {
+            ShurikenState::Error(message) => Some(message.clone()),
+            ShurikenState::Crashed(code) => Some(code.to_string()),
+            _ => None,
+        }
+    }
+
+    /// PID from the lockfile, while running or paused
+    async fn pid(&self) -> Option<u32> {
+        self.0.pid
+    }
+
+    /// RFC 3339 start time from the lockfile, while running or paused
+    async fn start_time(&self) -> Option<String> {
+        self.0.start_time.map(|time| time.to_rfc3339())
+    }
+}
+
+async fn shuriken_object(manager: &ShurikenManager, name: &str) -> Result<ShurikenObject> {
+    let info = manager.info(name).await.map_err(manager_error)?;
+    Ok(ShurikenObject(info))
+}
 
 pub struct QueryRoot;
 
 #[Object]
 impl QueryRoot {
-    async fn list_shurikens(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
-        let manager = ctx.data::<ShurikenManager>()?;
-        let result = manager
+    /// Every installed shuriken, optionally only those in `state`
+    async fn shurikens(
+        &self,
+        ctx: &Context<'_>,
+        state: Option<StateKind>,
+    ) -> Result<Vec<ShurikenObject>> {
+        let manager = ctx.data::<Arc<ShurikenManager>>()?;
+        let mut names = manager
             .list(false)
             .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(result.right().unwrap_or_default())
+            .map_err(manager_error)?
+            .right()
+            .unwrap_or_default();
+        names.sort();
+
+        let mut shurikens = Vec::with_capacity(names.len());
+        for name in names {
+            let shuriken = shuriken_object(manager, &name).await?;
+            if state.is_none_or(|state| StateKind::from(&shuriken.0.state) == state) {
+                shurikens.push(shuriken);
+            }
+        }
+        Ok(shurikens)
     }
 
-    async fn shuriken_states(&self, ctx: &Context<'_>) -> Result<HashMap<String, ShurikenState>> {
-        let manager = ctx.data::<ShurikenManager>()?;
-        let result = manager
-            .list(true)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(result.left().unwrap_or_default().into_iter().collect())
+    async fn shuriken(&self, ctx: &Context<'_>, name: String) -> Result<ShurikenObject> {
+        let manager = ctx.data::<Arc<ShurikenManager>>()?;
+        shuriken_object(manager, &name).await
     }
 }
 
@@ -30,28 +128,43 @@ pub struct MutationRoot;
 
 #[Object]
 impl MutationRoot {
-    async fn start_shuriken(&self, ctx: &Context<'_>, name: String) -> Result<String> {
-        let manager = ctx.data::<ShurikenManager>()?;
-        manager
-            .start(&name)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(format!("Shuriken '{}' started", name))
+    async fn start(&self, ctx: &Context<'_>, name: String) -> Result<ShurikenObject> {
+        let manager = ctx.data::<Arc<ShurikenManager>>()?;
+        manager.start(&name).await.map_err(manager_error)?;
+        shuriken_object(manager, &name).await
+    }
+
+    async fn stop(&self, ctx: &Context<'_>, name: String) -> Result<ShurikenObject> {
+        let manager = ctx.data::<Arc<ShurikenManager>>()?;
+        manager.stop(&name).await.map_err(manager_error)?;
+        shuriken_object(manager, &name).await
     }
 
-    async fn stop_shuriken(&self, ctx: &Context<'_>, name: String) -> Result<String> {
-        let manager = ctx.data::<ShurikenManager>()?;
+    /// Saves `options` over the shuriken's saved ones, keeping any not
+    /// given, then regenerates its config file
+    async fn configure(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        options: Option<Json<HashMap<String, FieldValue>>>,
+    ) -> Result<ShurikenObject> {
+        let manager = ctx.data::<Arc<ShurikenManager>>()?;
+        match options {
+            Some(Json(options)) => manager.patch_config(&name, options).await,
+            None => manager.get(normalize_shuriken_name(&name)).await.map(drop),
+        }
+        .map_err(manager_error)?;
         manager
-            .stop(&name)
+            .configure_shuriken(&name)
             .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(format!("Shuriken '{}' stopped", name))
+            .map_err(manager_error)?;
+        shuriken_object(manager, &name).await
     }
 }
 
 pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
-pub fn build_schema(manager: ShurikenManager) -> AppSchema {
+pub fn build_schema(manager: Arc<ShurikenManager>) -> AppSchema {
     Schema::build(QueryRoot, MutationRoot, EmptySubscription)
         .data(manager)
         .finish()
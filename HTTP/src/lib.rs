@@ -1,14 +1,35 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    routing::get,
+    extract::{DefaultBodyLimit, FromRequest, Multipart, Path, Query, Request, State},
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
 };
-use ninja::manager::ShurikenManager;
-use serde::Serialize;
-use std::{collections::HashMap, sync::Arc};
+use axum_server::{Handle, tls_rustls::RustlsConfig};
+use futures_util::{Stream, StreamExt};
+use log::{info, warn};
+use ninja::{
+    common::{
+        error::ManagerError,
+        logs::parse_duration,
+        metrics::OPENMETRICS_CONTENT_TYPE,
+        structs::NoopReporter,
+        types::{FieldValue, InstallOptions, LogFilter, OptionError, ShurikenState},
+    },
+    manager::{HISTORY_CAPACITY, ShurikenManager},
+    utils::normalize_shuriken_name,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap, convert::Infallible, net::TcpListener as StdTcpListener, path::PathBuf,
+    sync::Arc,
+};
+use tokio::sync::{broadcast::error::RecvError, watch};
 
 pub mod graphql;
 
@@ -20,12 +41,41 @@ where
     success: bool,
     data: Option<T>,
     error: Option<String>,
+    /// Machine-readable counterpart of `error`, e.g. `"not_found"`
+    code: Option<String>,
 }
 
 // Shared state for Axum
 #[derive(Clone)]
 struct AppState {
     manager: Arc<ShurikenManager>,
+    shutdown: Shutdown,
+    schema: graphql::AppSchema,
+}
+
+/// Tells a server started by [`serve`] to stop.
+///
+/// `POST /api/stop` triggers the handle given to [`router`]; embedders can
+/// keep a clone and call [`Shutdown::trigger`] themselves.
+#[derive(Clone, Debug)]
+pub struct Shutdown(Arc<watch::Sender<bool>>);
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+}
+
+impl Shutdown {
+    /// Stops the server: it finishes in-flight requests, then [`serve`] returns.
+    pub fn trigger(&self) {
+        self.0.send_replace(true);
+    }
+
+    // Resolves for every waiter, including ones that start waiting afterwards
+    async fn triggered(&self) {
+        let _ = self.0.subscribe().wait_for(|stopped| *stopped).await;
+    }
 }
 
 fn ok_response<T>(data: Option<T>) -> Response
@@ -38,6 +88,7 @@ where
             success: true,
             data,
             error: None,
+            code: None,
         }),
     )
         .into_response()
@@ -50,6 +101,33 @@ fn err_response(status: StatusCode, message: String) -> Response {
             success: false,
             data: None,
             error: Some(message),
+            code: None,
+        }),
+    )
+        .into_response()
+}
+
+// Error response for a manager call, with its code and a matching status
+fn manager_err_response(e: anyhow::Error) -> Response {
+    let Some(error) = e.downcast_ref::<ManagerError>() else {
+        return err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    };
+    let status = match error {
+        ManagerError::NotFound(_) | ManagerError::NoLogFile(_) => StatusCode::NOT_FOUND,
+        ManagerError::AlreadyRunning(_)
+        | ManagerError::NotRunning(_)
+        | ManagerError::NotPaused(_)
+        | ManagerError::AlreadyInstalled(_)
+        | ManagerError::IllegalTransition(..) => StatusCode::CONFLICT,
+    };
+
+    (
+        status,
+        Json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some(error.to_string()),
+            code: Some(error.code().to_string()),
         }),
     )
         .into_response()
@@ -59,7 +137,7 @@ fn err_response(status: StatusCode, message: String) -> Response {
 async fn start_shuriken(Path(name): Path<String>, State(state): State<AppState>) -> Response {
     match state.manager.start(&name).await {
         Ok(()) => ok_response::<()>(None),
-        Err(e) => err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        Err(e) => manager_err_response(e),
     }
 }
 
@@ -67,28 +145,166 @@ async fn start_shuriken(Path(name): Path<String>, State(state): State<AppState>)
 async fn stop_shuriken(Path(name): Path<String>, State(state): State<AppState>) -> Response {
     match state.manager.stop(&name).await {
         Ok(()) => ok_response::<()>(None),
-        Err(e) => err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        Err(e) => manager_err_response(e),
     }
 }
 
-// List shuriken states
-async fn list_shuriken_states(State(state): State<AppState>) -> Response {
-    match state.manager.list(true).await {
-        Ok(either) => {
-            if let Some(left) = either.left() {
-                let mut formatted = HashMap::new();
-                for (name, value) in left.iter().cloned() {
-                    formatted.insert(name, value);
-                }
+// Handler to stop and start a shuriken again
+async fn restart_shuriken(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    match state.manager.restart(&name).await {
+        Ok(()) => ok_response::<()>(None),
+        Err(e) => manager_err_response(e),
+    }
+}
 
-                ok_response(Some(formatted))
-            } else {
-                err_response(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "No shurikens found.".to_string(),
-                )
+// Reset an on-demand shuriken's idle timer
+async fn touch_shuriken(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    match state.manager.touch(&name).await {
+        Ok(()) => ok_response::<()>(None),
+        Err(e) => manager_err_response(e),
+    }
+}
+
+/// Largest `.shuriken` package `POST /api/shurikens/install` accepts as an upload.
+pub const MAX_UPLOAD_SIZE: usize = 512 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct InstallRequest {
+    /// Path of a `.shuriken` package on the server's filesystem
+    path: PathBuf,
+    /// Run the package's postinstall script (default: true)
+    #[serde(default = "default_true")]
+    run_postinstall: bool,
+    /// Reinstall over an existing shuriken of the same name
+    #[serde(default)]
+    replace: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Reads a multipart flag field; only an explicit yes turns it on
+fn form_flag(value: &str) -> bool {
+    matches!(value.trim(), "true" | "1" | "on" | "yes")
+}
+
+// Install a shuriken from a JSON `{ "path": ... }` body or a multipart
+// upload whose `file` field is the package itself. Both take `replace` and
+// `run_postinstall`; uploads only run the postinstall script when asked to
+async fn install_shuriken(State(state): State<AppState>, request: Request) -> Response {
+    let multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let result = if multipart {
+        let mut form = match Multipart::from_request(request, &state).await {
+            Ok(form) => form,
+            Err(e) => return err_response(StatusCode::BAD_REQUEST, e.body_text()),
+        };
+        let mut package = None;
+        let mut options = InstallOptions {
+            run_postinstall: false,
+            ..InstallOptions::default()
+        };
+        loop {
+            let field = match form.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                Err(e) => return err_response(StatusCode::BAD_REQUEST, e.body_text()),
+            };
+            match field.name().map(str::to_owned).as_deref() {
+                Some("file") => match field.bytes().await {
+                    Ok(bytes) => package = Some(bytes),
+                    Err(e) => return err_response(StatusCode::BAD_REQUEST, e.body_text()),
+                },
+                Some(name @ ("replace" | "run_postinstall")) => {
+                    let flag = match field.text().await {
+                        Ok(text) => form_flag(&text),
+                        Err(e) => return err_response(StatusCode::BAD_REQUEST, e.body_text()),
+                    };
+                    if name == "replace" {
+                        options.replace = flag;
+                    } else {
+                        options.run_postinstall = flag;
+                    }
+                }
+                _ => continue,
             }
         }
+        let Some(package) = package else {
+            return err_response(
+                StatusCode::BAD_REQUEST,
+                "Missing the 'file' field with the .shuriken package".to_string(),
+            );
+        };
+        state
+            .manager
+            .install_bytes(&package, NoopReporter {}, &options)
+            .await
+    } else {
+        let Json(install) = match Json::<InstallRequest>::from_request(request, &state).await {
+            Ok(install) => install,
+            Err(e) => return err_response(e.status(), e.body_text()),
+        };
+        let options = InstallOptions {
+            run_postinstall: install.run_postinstall,
+            replace: install.replace,
+            ..InstallOptions::default()
+        };
+        state
+            .manager
+            .install_file(&install.path, Arc::new(NoopReporter {}), &options)
+            .await
+    };
+
+    match result {
+        Ok(()) => ok_response::<()>(None),
+        Err(e) => manager_err_response(e),
+    }
+}
+
+#[derive(Serialize)]
+struct RunningStatus {
+    running: bool,
+}
+
+// Check whether a single shuriken is running
+async fn is_running(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    if let Err(e) = state.manager.get(normalize_shuriken_name(&name)).await {
+        return manager_err_response(e);
+    }
+
+    let running = state.manager.is_running(&name).await;
+    ok_response(Some(RunningStatus { running }))
+}
+
+// Report a single shuriken's state
+async fn shuriken_status(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    match state.manager.status(&name).await {
+        Ok(status) => ok_response(Some(status)),
+        Err(e) => manager_err_response(e),
+    }
+}
+
+// Describe a single shuriken, with its PID while it runs
+async fn shuriken_info(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    match state.manager.info(&name).await {
+        Ok(info) => ok_response(Some(info)),
+        Err(e) => manager_err_response(e),
+    }
+}
+
+// List shuriken states, reusing the manager's serialized snapshot
+async fn list_shuriken_states(State(state): State<AppState>) -> Response {
+    match state.manager.list_states_json().await {
+        Ok(states) => (
+            [(header::CONTENT_TYPE, "application/json")],
+            format!(r#"{{"success":true,"data":{states},"error":null,"code":null}}"#),
+        )
+            .into_response(),
         Err(e) => err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
     }
 }
@@ -110,24 +326,462 @@ async fn list_shurikens(State(state): State<AppState>) -> Response {
     }
 }
 
-// Stop the API
-async fn stop_api() -> StatusCode {
-    std::process::exit(0);
+// List the maintenance tools a shuriken declares
+async fn list_tools(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    match state.manager.tools(&name).await {
+        Ok(tools) => ok_response(Some(tools)),
+        Err(e) => manager_err_response(e),
+    }
 }
 
-// Main server function
-pub async fn server(port: u16) -> Result<()> {
-    let manager = Arc::new(ShurikenManager::new().await?);
+// Describe how a shuriken's options should be entered
+async fn options_schema(Path(name): Path<String>, State(state): State<AppState>) -> Response {
+    if let Err(e) = state.manager.get(normalize_shuriken_name(&name)).await {
+        return manager_err_response(e);
+    }
+
+    match state.manager.options_schema(&name).await {
+        Ok(schema) => ok_response(Some(schema)),
+        Err(e) => err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+// Check option values against a shuriken's schema without saving them
+async fn validate_options(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    Json(options): Json<HashMap<String, FieldValue>>,
+) -> Response {
+    if let Err(e) = state.manager.get(normalize_shuriken_name(&name)).await {
+        return manager_err_response(e);
+    }
+
+    match state.manager.validate_options(&name, &options).await {
+        Ok(()) => ok_response(Some(Vec::<OptionError>::new())),
+        Err(errors) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ApiResponse {
+                success: false,
+                error: Some(format!("{} invalid option(s)", errors.len())),
+                data: Some(errors),
+                code: None,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    grep: Option<String>,
+    since: Option<String>,
+    lines: Option<usize>,
+}
 
+// Read a shuriken's logs, filtered by pattern and time window
+async fn read_logs(
+    Path(name): Path<String>,
+    Query(query): Query<LogsQuery>,
+    State(state): State<AppState>,
+) -> Response {
+    if let Err(e) = state.manager.get(normalize_shuriken_name(&name)).await {
+        return manager_err_response(e);
+    }
+
+    let since = match query.since.as_deref().map(parse_duration).transpose() {
+        Ok(since) => since,
+        Err(e) => return err_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let filter = LogFilter {
+        grep: query.grep,
+        since,
+        lines: query.lines,
+    };
+
+    match state.manager.read_logs(&name, &filter).await {
+        Ok(lines) => ok_response(Some(lines)),
+        Err(e) => err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+}
+
+// Recent lifecycle events of a shuriken, oldest first
+async fn history(
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>,
+) -> Response {
+    if let Err(e) = state.manager.get(normalize_shuriken_name(&name)).await {
+        return manager_err_response(e);
+    }
+
+    let limit = query.limit.unwrap_or(HISTORY_CAPACITY);
+    ok_response(Some(state.manager.history(&name, limit)))
+}
+
+// Run one of a shuriken's maintenance tools
+async fn run_tool(
+    Path((name, tool)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Response {
+    let tools = match state.manager.tools(&name).await {
+        Ok(tools) => tools,
+        Err(e) => return manager_err_response(e),
+    };
+    if !tools.iter().any(|t| t.name == tool) {
+        return err_response(
+            StatusCode::NOT_FOUND,
+            format!("Shuriken '{}' has no tool named '{}'", name, tool),
+        );
+    }
+
+    match state.manager.run_tool_captured(&name, &tool).await {
+        Ok(output) => ok_response(Some(output)),
+        Err(e) => err_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+// Push every state change as a server-sent event until the client leaves.
+// The receiver lives in the stream, so it is dropped along with the
+// connection; keep-alives make sure a silent disconnect is noticed.
+async fn state_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.manager.subscribe_states();
+    let events = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(change) => Event::default().json_data(&change).ok()?,
+            // Tell the client to refetch the full list instead of guessing
+            Err(RecvError::Lagged(missed)) => {
+                Event::default().event("lagged").data(missed.to_string())
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), receiver))
+    });
+
+    // Open streams would otherwise hold a graceful shutdown up forever
+    let shutdown = state.shutdown.clone();
+    Sse::new(events.take_until(async move { shutdown.triggered().await }))
+        .keep_alive(KeepAlive::default())
+}
+
+// Execute a GraphQL query or mutation; errors are reported in the body
+async fn graphql_handler(
+    State(state): State<AppState>,
+    Json(request): Json<async_graphql::Request>,
+) -> Response {
+    Json(state.schema.execute(request).await).into_response()
+}
+
+// In-browser IDE for the GraphQL endpoint
+async fn graphiql() -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        async_graphql::http::GraphiQLSource::build()
+            .endpoint("/graphql")
+            .finish(),
+    )
+        .into_response()
+}
+
+// Per-shuriken metrics for Prometheus-compatible scrapers
+async fn metrics(State(state): State<AppState>) -> Response {
+    (
+        [(header::CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)],
+        state.manager.export_openmetrics_per_shuriken().await,
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct StopApiQuery {
+    /// Stop every running shuriken before the server goes down
+    #[serde(default)]
+    shurikens: bool,
+}
+
+// Stop the API once this response is sent
+async fn stop_api(Query(query): Query<StopApiQuery>, State(state): State<AppState>) -> Response {
+    tokio::spawn(async move {
+        if query.shurikens {
+            stop_running(&state.manager).await;
+        }
+        state.shutdown.trigger();
+    });
+    ok_response::<()>(None)
+}
+
+// Stop every running (or paused) shuriken, in dependency order if they have any
+async fn stop_running(manager: &ShurikenManager) {
+    let Ok(states) = manager.list(true).await else {
+        return;
+    };
+    let running: Vec<String> = states
+        .left()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, state)| matches!(state, ShurikenState::Running | ShurikenState::Paused))
+        .map(|(name, _)| name)
+        .collect();
+
+    let mode = manager.default_bulk_mode(&running).await;
+    match manager.stop_many(&running, mode).await {
+        Ok(results) => {
+            for (name, result) in results {
+                if let Err(e) = result {
+                    warn!("Failed to stop '{}' on shutdown: {}", name, e);
+                }
+            }
+        }
+        Err(e) => warn!("Failed to stop shurikens on shutdown: {}", e),
+    }
+}
+
+/// Environment variable naming the PEM certificate (chain) to serve HTTPS with.
+pub const TLS_CERT_ENV: &str = "NINJA_TLS_CERT";
+/// Environment variable naming the PEM private key for [`TLS_CERT_ENV`].
+pub const TLS_KEY_ENV: &str = "NINJA_TLS_KEY";
+
+/// Paths to the certificate and private key the server uses for HTTPS.
+#[derive(Debug, Clone)]
+pub struct TlsPaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsPaths {
+    /// Reads the paths from `NINJA_TLS_CERT` and `NINJA_TLS_KEY`.
+    ///
+    /// # Returns
+    /// - `Ok(Some(paths))` if both are set
+    /// - `Ok(None)` if neither is set, meaning plain HTTP
+    /// - `Err` if only one of them is set
+    pub fn from_env() -> Result<Option<Self>> {
+        let cert = std::env::var_os(TLS_CERT_ENV).filter(|v| !v.is_empty());
+        let key = std::env::var_os(TLS_KEY_ENV).filter(|v| !v.is_empty());
+        match (cert, key) {
+            (Some(cert), Some(key)) => Ok(Some(Self {
+                cert: cert.into(),
+                key: key.into(),
+            })),
+            (None, None) => Ok(None),
+            (Some(_), None) => Err(anyhow::anyhow!(
+                "{} is set but {} isn't; both are needed for HTTPS",
+                TLS_CERT_ENV,
+                TLS_KEY_ENV
+            )),
+            (None, Some(_)) => Err(anyhow::anyhow!(
+                "{} is set but {} isn't; both are needed for HTTPS",
+                TLS_KEY_ENV,
+                TLS_CERT_ENV
+            )),
+        }
+    }
+}
+
+/// Environment variable holding the bearer token mutating routes require.
+pub const API_TOKEN_ENV: &str = "NINJA_API_TOKEN";
+
+/// Compares without stopping at the first difference, so response times
+/// don't reveal how much of a guessed token was right.
+fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// Refuses requests that change anything when a browser sent them from
+// another site. Browsers attach `Origin` to cross-site POSTs, including
+// multipart forms that need no preflight; clients like curl send none
+async fn require_same_origin(request: Request, next: Next) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let headers = request.headers();
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        let origin_host = origin
+            .to_str()
+            .ok()
+            .and_then(|origin| origin.split_once("://"))
+            .map(|(_, host)| host.trim_end_matches('/'));
+        let host = headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok());
+        if origin_host.is_none() || origin_host != host {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(ApiResponse::<()> {
+                    success: false,
+                    data: None,
+                    error: Some("Cross-origin requests may not change anything".to_string()),
+                    code: Some("forbidden_origin".to_string()),
+                }),
+            )
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+// Rejects requests that change anything unless they carry the API token
+async fn require_token(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if tokens_match(given.trim().as_bytes(), token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                error: Some(match given {
+                    Some(_) => "Invalid API token".to_string(),
+                    None => "Missing API token".to_string(),
+                }),
+                code: Some("unauthorized".to_string()),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Builds the REST API routes around `manager`.
+///
+/// `POST /api/stop` triggers `shutdown`; pass the same handle to [`serve`].
+/// Routes that change something refuse requests whose `Origin` is another
+/// site, so a web page can't drive the API through the user's browser. With
+/// a `token`, they also require an `Authorization: Bearer <token>` header;
+/// reads stay open.
+pub fn router(manager: Arc<ShurikenManager>, shutdown: Shutdown, token: Option<String>) -> Router {
     let app = Router::new()
-        .route("/api/shurikens/start/{shuriken}", get(start_shuriken))
-        .route("/api/shurikens/stop/{shuriken}", get(stop_shuriken))
+        .route("/api/shurikens/start/{shuriken}", post(start_shuriken))
+        .route("/api/shurikens/stop/{shuriken}", post(stop_shuriken))
+        .route("/api/shurikens/restart/{shuriken}", post(restart_shuriken))
+        .route("/api/shurikens/running/{shuriken}", get(is_running))
+        .route("/api/shurikens/status/{shuriken}", get(shuriken_status))
+        .route("/api/shurikens/info/{shuriken}", get(shuriken_info))
+        .route("/api/shurikens/touch/{shuriken}", post(touch_shuriken))
+        .route(
+            "/api/shurikens/install",
+            post(install_shuriken).layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE)),
+        )
         .route("/api/shurikens/list", get(list_shurikens))
         .route("/api/shurikens/list/states", get(list_shuriken_states))
-        .route("/api/stop", get(stop_api))
-        .with_state(AppState { manager });
+        .route("/api/shurikens/events", get(state_events))
+        .route("/api/shurikens/tools/{shuriken}", get(list_tools))
+        .route("/api/shurikens/tools/{shuriken}/{tool}", post(run_tool))
+        .route("/api/shurikens/schema/{shuriken}", get(options_schema))
+        .route("/api/shurikens/logs/{shuriken}", get(read_logs))
+        .route("/api/shurikens/history/{shuriken}", get(history))
+        .route("/api/shurikens/validate/{shuriken}", post(validate_options))
+        .route("/api/metrics", get(metrics))
+        .route("/api/stop", post(stop_api))
+        .route("/graphql", post(graphql_handler))
+        .route("/graphiql", get(graphiql))
+        .with_state(AppState {
+            schema: graphql::build_schema(manager.clone()),
+            manager,
+            shutdown,
+        })
+        .layer(middleware::from_fn(require_same_origin));
+
+    match token {
+        Some(token) => app.layer(middleware::from_fn_with_state(
+            Arc::<str>::from(token),
+            require_token,
+        )),
+        None => app,
+    }
+}
+
+/// Serves `app` on `listener`, over HTTPS when `tls` is given.
+///
+/// The certificate and key are loaded before accepting connections, so a
+/// missing or malformed file fails right away instead of on the first request.
+/// Returns once `shutdown` is triggered and in-flight requests are answered.
+pub async fn serve(
+    app: Router,
+    listener: StdTcpListener,
+    tls: Option<TlsPaths>,
+    shutdown: Shutdown,
+) -> Result<()> {
+    let Some(tls) = tls else {
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move { shutdown.triggered().await })
+            .await?;
+        return Ok(());
+    };
 
-    let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
-    axum::serve(listener, app).await?;
+    let config = RustlsConfig::from_pem_file(&tls.cert, &tls.key)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to load TLS certificate {} and key {}",
+                tls.cert.display(),
+                tls.key.display()
+            )
+        })?;
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown.triggered().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+    axum_server::from_tcp_rustls(listener, config)
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await?;
     Ok(())
 }
+
+// Main server function; returns once the API is stopped.
+// Without an explicit `token`, `NINJA_API_TOKEN` is used if set.
+pub async fn server(port: u16, token: Option<String>) -> Result<()> {
+    let tls = TlsPaths::from_env()?;
+    let token = token
+        .or_else(|| std::env::var(API_TOKEN_ENV).ok())
+        .filter(|token| !token.is_empty());
+    if token.is_some() {
+        info!("Mutating API routes require a bearer token");
+    }
+    let manager = Arc::new(ShurikenManager::new().await?);
+
+    let listener = StdTcpListener::bind(("127.0.0.1", port))?;
+    let shutdown = Shutdown::default();
+    serve(
+        router(manager, shutdown.clone(), token),
+        listener,
+        tls,
+        shutdown,
+    )
+    .await
+}
@@ -0,0 +1,562 @@
+//! JSON-RPC batch support in front of the rmcp stdio transport.
+//!
+//! rmcp only understands one message per line, so batch arrays are split
+//! into individual requests before they reach the server, and the matching
+//! responses are collected and written back as a single array in request
+//! order. Notifications inside a batch never get a response element.
+//! Requests the server hasn't answered within the batch timeout are answered
+//! with an error instead, so one stuck call can't hold back the whole batch.
+//! Elements that aren't requests, notifications or responses are answered
+//! with an "Invalid Request" error right away.
+
+use serde_json::{Value, json};
+use std::{io, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::Mutex,
+    time::{Instant, sleep_until},
+};
+
+/// How long a batch waits for its responses before the missing ones fail.
+pub(crate) const BATCH_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// JSON-RPC error code (implementation-defined range) for timed out requests.
+const TIMED_OUT: i64 = -32000;
+
+/// JSON-RPC error code for elements that aren't a valid request.
+const INVALID_REQUEST: i64 = -32600;
+
+fn invalid_request(id: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": INVALID_REQUEST, "message": "Invalid Request" },
+    })
+}
+
+/// What a message sent by the client is, as far as batching cares.
+enum Message {
+    /// A call the server answers, with its id
+    Request(Value),
+    /// A notification or a response to a server request, nothing comes back
+    Forward,
+    /// Not a JSON-RPC message, answered with the id it had, if any
+    Invalid(Value),
+}
+
+impl Message {
+    fn classify(message: &Value) -> Self {
+        let Some(object) = message.as_object() else {
+            return Message::Invalid(Value::Null);
+        };
+        let id = object.get("id").cloned();
+        match (object.get("method"), id) {
+            (Some(Value::String(_)), Some(id)) => Message::Request(id),
+            (Some(Value::String(_)), None) => Message::Forward,
+            (None, Some(_)) if object.contains_key("result") || object.contains_key("error") => {
+                Message::Forward
+            }
+            (_, id) => Message::Invalid(id.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// A batch (or lone request) whose responses are still being collected.
+struct PendingBatch {
+    ids: Vec<Value>,
+    responses: Vec<Option<Value>>,
+    deadline: Instant,
+    /// A request sent on its own, its response is passed on as is
+    single: bool,
+    /// Already answered with timeout errors, late responses are dropped
+    expired: bool,
+}
+
+impl PendingBatch {
+    /// Creates a batch from `(id, response)` pairs, where elements that
+    /// are answered up front (invalid ones) already carry their response.
+    fn new(entries: Vec<(Value, Option<Value>)>, timeout: Duration) -> Self {
+        let (ids, responses) = entries.into_iter().unzip();
+        Self {
+            ids,
+            responses,
+            deadline: Instant::now() + timeout,
+            single: false,
+            expired: false,
+        }
+    }
+
+    fn single(id: Value, timeout: Duration) -> Self {
+        Self {
+            single: true,
+            ..Self::new(vec![(id, None)], timeout)
+        }
+    }
+
+    /// Stores `response` if it answers one of this batch's outstanding requests.
+    fn accept(&mut self, id: &Value, response: &Value) -> bool {
+        let slot = self
+            .ids
+            .iter()
+            .zip(&self.responses)
+            .position(|(pending, answered)| pending == id && answered.is_none());
+
+        match slot {
+            Some(slot) => {
+                self.responses[slot] = Some(response.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.responses.iter().all(Option::is_some)
+    }
+
+    /// Returns the responses in request order, with an error in place of
+    /// every request that never got one.
+    fn responses(&self) -> Vec<Value> {
+        self.ids
+            .iter()
+            .zip(&self.responses)
+            .map(|(id, response)| {
+                response.clone().unwrap_or_else(|| {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": TIMED_OUT, "message": "Request timed out" },
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+type Pending = Arc<Mutex<Vec<PendingBatch>>>;
+
+/// Relays messages between a client and the rmcp server, handling batches.
+///
+/// # Arguments
+/// - `input`: Where client messages are read from (usually stdin)
+/// - `output`: Where responses are written to (usually stdout)
+/// - `server_out`: The server's outgoing message stream
+/// - `server_in`: The server's incoming message stream
+/// - `timeout`: How long a batch waits for its responses, see [`BATCH_TIMEOUT`]
+pub(crate) async fn relay<I, O, SR, SW>(
+    input: I,
+    output: O,
+    server_out: SR,
+    server_in: SW,
+    timeout: Duration,
+) -> io::Result<()>
+where
+    I: AsyncRead + Unpin,
+    O: AsyncWrite + Unpin,
+    SR: AsyncRead + Unpin,
+    SW: AsyncWrite + Unpin,
+{
+    let pending: Pending = Arc::default();
+    let output = Arc::new(Mutex::new(output));
+
+    let forward = async {
+        tokio::try_join!(
+            forward_requests(input, server_in, pending.clone(), output.clone(), timeout),
+            forward_responses(server_out, output.clone(), pending.clone()),
+        )
+    };
+
+    // Expiring never finishes on its own, it only stops early on a write error
+    tokio::select! {
+        result = forward => result.map(|_| ()),
+        result = expire_batches(output.clone(), pending.clone(), timeout) => result,
+    }
+}
+
+async fn forward_requests<I, O, SW>(
+    input: I,
+    mut server_in: SW,
+    pending: Pending,
+    output: Arc<Mutex<O>>,
+    timeout: Duration,
+) -> io::Result<()>
+where
+    I: AsyncRead + Unpin,
+    O: AsyncWrite + Unpin,
+    SW: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(input).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        match serde_json::from_str::<Value>(&line) {
+            Ok(Value::Array(messages)) if messages.is_empty() => {
+                write_line(&output, &invalid_request(Value::Null).to_string()).await?;
+            }
+            Ok(Value::Array(messages)) => {
+                let mut entries = Vec::new();
+                let mut forwarded = Vec::new();
+                for message in messages {
+                    match Message::classify(&message) {
+                        Message::Request(id) => {
+                            entries.push((id, None));
+                            forwarded.push(message);
+                        }
+                        Message::Forward => forwarded.push(message),
+                        Message::Invalid(id) => {
+                            entries.push((id.clone(), Some(invalid_request(id))))
+                        }
+                    }
+                }
+
+                // Register before forwarding so no response can outrun its batch
+                let batch = PendingBatch::new(entries, timeout);
+                if batch.is_complete() {
+                    if !batch.ids.is_empty() {
+                        let responses = Value::Array(batch.responses());
+                        write_line(&output, &responses.to_string()).await?;
+                    }
+                } else {
+                    pending.lock().await.push(batch);
+                }
+
+                for message in forwarded {
+                    server_in.write_all(message.to_string().as_bytes()).await?;
+                    server_in.write_all(b"\n").await?;
+                }
+            }
+            parsed => {
+                // Lone requests queue up too, so a batch reusing their id
+                // can't take their response
+                if let Ok(message) = parsed
+                    && let Message::Request(id) = Message::classify(&message)
+                {
+                    pending.lock().await.push(PendingBatch::single(id, timeout));
+                }
+                server_in.write_all(line.as_bytes()).await?;
+                server_in.write_all(b"\n").await?;
+            }
+        }
+        server_in.flush().await?;
+    }
+
+    server_in.shutdown().await
+}
+
+async fn forward_responses<O, SR>(
+    server_out: SR,
+    output: Arc<Mutex<O>>,
+    pending: Pending,
+) -> io::Result<()>
+where
+    O: AsyncWrite + Unpin,
+    SR: AsyncRead + Unpin,
+{
+    let mut lines = BufReader::new(server_out).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if let Ok(message) = serde_json::from_str::<Value>(&line)
+            && message.get("method").is_none()
+            && let Some(id) = message.get("id")
+        {
+            // Batches are queued oldest first, so a reused id goes to the
+            // request that has waited longest for it
+            let mut batches = pending.lock().await;
+            if let Some(index) = batches
+                .iter_mut()
+                .position(|batch| batch.accept(id, &message))
+            {
+                if batches[index].is_complete() {
+                    let batch = batches.remove(index);
+                    drop(batches);
+                    if batch.single {
+                        write_line(&output, &line).await?;
+                    } else if !batch.expired {
+                        let responses = Value::Array(batch.responses());
+                        write_line(&output, &responses.to_string()).await?;
+                    }
+                }
+                continue;
+            }
+        }
+
+        write_line(&output, &line).await?;
+    }
+
+    Ok(())
+}
+
+/// Answers batches that outlived their deadline, filling in errors for the
+/// requests the server never answered.
+async fn expire_batches<O>(
+    output: Arc<Mutex<O>>,
+    pending: Pending,
+    timeout: Duration,
+) -> io::Result<()>
+where
+    O: AsyncWrite + Unpin,
+{
+    loop {
+        // A batch registered while sleeping has a later deadline than the
+        // wake-up, so it is picked up in time by the next round
+        let next = pending
+            .lock()
+            .await
+            .iter()
+            .map(|batch| batch.deadline)
+            .min();
+        sleep_until(next.unwrap_or_else(|| Instant::now() + timeout)).await;
+
+        let mut answers = Vec::new();
+        {
+            let mut batches = pending.lock().await;
+            let now = Instant::now();
+            // An expired batch stays queued for another timeout, so a late
+            // response is dropped instead of answering a newer batch
+            batches.retain_mut(|batch| {
+                if batch.deadline > now {
+                    return true;
+                }
+                if batch.single || batch.expired {
+                    return false;
+                }
+                answers.push(Value::Array(batch.responses()));
+                batch.expired = true;
+                batch.deadline = now + timeout;
+                true
+            });
+        }
+        for responses in answers {
+            write_line(&output, &responses.to_string()).await?;
+        }
+    }
+}
+
+async fn write_line<O>(output: &Mutex<O>, line: &str) -> io::Result<()>
+where
+    O: AsyncWrite + Unpin,
+{
+    let mut output = output.lock().await;
+    output.write_all(line.as_bytes()).await?;
+    output.write_all(b"\n").await?;
+    output.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, duplex, split};
+
+    /// Runs `input` through the relay against a fake server that answers
+    /// every request in reverse order once its input closes. Requests with
+    /// an id in `ignored` never get an answer.
+    async fn run_relay(input: &str, ignored: &[Value], timeout: Duration) -> Vec<Value> {
+        let (relay_side, server_side) = duplex(4096);
+        let (server_out, server_in) = split(relay_side);
+        let (mut client_out, relay_out) = duplex(4096);
+
+        let ignored = ignored.to_vec();
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = split(server_side);
+            let mut lines = BufReader::new(reader).lines();
+            let mut ids = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let message: Value = serde_json::from_str(&line).unwrap();
+                if message.get("method").is_some()
+                    && let Some(id) = message.get("id")
+                {
+                    ids.push(id.clone());
+                }
+            }
+            for id in ids.into_iter().rev().filter(|id| !ignored.contains(id)) {
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": {} });
+                writer
+                    .write_all(format!("{response}\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+            // Stay up long enough for unanswered batches to time out
+            if !ignored.is_empty() {
+                tokio::time::sleep(timeout * 2).await;
+            }
+            writer.shutdown().await.unwrap();
+        });
+
+        relay(input.as_bytes(), relay_out, server_out, server_in, timeout)
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        let mut raw = String::new();
+        client_out.read_to_string(&mut raw).await.unwrap();
+        raw.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_batch_responses_keep_request_order() {
+        let input = concat!(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"tools/list"},"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"},"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"list_shurikens"}}]"#,
+            "\n"
+        );
+
+        let messages = run_relay(input, &[], BATCH_TIMEOUT).await;
+        assert_eq!(messages.len(), 1);
+
+        let responses = messages[0].as_array().expect("batch response array");
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["id"], json!(1));
+        assert_eq!(responses[1]["id"], json!(2));
+    }
+
+    #[tokio::test]
+    async fn test_single_and_empty_batch_messages() {
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":7,"method":"tools/list"}"#,
+            "\n[]\n"
+        );
+
+        let messages = run_relay(input, &[], BATCH_TIMEOUT).await;
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().any(|m| m["error"]["code"] == json!(-32600)));
+        assert!(messages.iter().any(|m| m["id"] == json!(7)));
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_batch_requests_time_out() {
+        let input = concat!(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"tools/list"},"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"stuck"}}]"#,
+            "\n"
+        );
+
+        let messages = run_relay(input, &[json!(2)], Duration::from_millis(50)).await;
+        assert_eq!(messages.len(), 1);
+
+        let responses = messages[0].as_array().expect("batch response array");
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"], json!({}));
+        assert_eq!(responses[1]["id"], json!(2));
+        assert_eq!(responses[1]["error"]["code"], json!(TIMED_OUT));
+    }
+
+    #[tokio::test]
+    async fn test_batch_waits_only_for_requests() {
+        // A response to a server request is forwarded but never waited on
+        let input = concat!(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"tools/list"},"#,
+            r#"{"jsonrpc":"2.0","id":9,"result":{}}]"#,
+            "\n"
+        );
+
+        let started = Instant::now();
+        let messages = run_relay(input, &[], BATCH_TIMEOUT).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(messages.len(), 1);
+
+        let responses = messages[0].as_array().expect("batch response array");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["id"], json!(1));
+        assert_eq!(responses[0]["result"], json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_batch_elements_are_answered_in_place() {
+        let input = concat!(
+            r#"[5,{"jsonrpc":"2.0","id":1,"method":"tools/list"},{"jsonrpc":"2.0","id":3}]"#,
+            "\n",
+            r#"[1,"two"]"#,
+            "\n"
+        );
+
+        let messages = run_relay(input, &[], BATCH_TIMEOUT).await;
+        assert_eq!(messages.len(), 2);
+
+        // Batches with nothing to forward are answered before the server replies
+        let invalid = messages[0].as_array().expect("batch response array");
+        assert_eq!(invalid.len(), 2);
+        assert!(
+            invalid
+                .iter()
+                .all(|r| r["error"]["code"] == json!(INVALID_REQUEST))
+        );
+        assert!(invalid.iter().all(|r| r["id"] == Value::Null));
+
+        let mixed = messages[1].as_array().expect("batch response array");
+        assert_eq!(mixed.len(), 3);
+        assert_eq!(mixed[0]["error"]["code"], json!(INVALID_REQUEST));
+        assert_eq!(mixed[1]["result"], json!({}));
+        assert_eq!(mixed[2]["id"], json!(3));
+        assert_eq!(mixed[2]["error"]["code"], json!(INVALID_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_reused_ids_go_to_the_oldest_waiting_request() {
+        let (mut client_in, relay_in) = duplex(4096);
+        let (relay_out, client_out) = duplex(4096);
+        let (relay_side, server_side) = duplex(4096);
+        let (server_out, server_in) = split(relay_side);
+        let relay = tokio::spawn(relay(
+            relay_in,
+            relay_out,
+            server_out,
+            server_in,
+            Duration::from_millis(200),
+        ));
+        let (server_reader, mut server_writer) = split(server_side);
+        let mut server_lines = BufReader::new(server_reader).lines();
+        let mut client_lines = BufReader::new(client_out).lines();
+        let mut next_answer = async || -> Value {
+            serde_json::from_str(&client_lines.next_line().await.unwrap().unwrap()).unwrap()
+        };
+
+        // A lone request and a batch share id 1, each gets its own answer
+        client_in
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"single\"}\n")
+            .await
+            .unwrap();
+        client_in
+            .write_all(b"[{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"batched\"}]\n")
+            .await
+            .unwrap();
+        server_lines.next_line().await.unwrap();
+        server_lines.next_line().await.unwrap();
+        for answer in ["single", "batched"] {
+            let response = json!({ "jsonrpc": "2.0", "id": 1, "result": answer });
+            server_writer
+                .write_all(format!("{response}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+        assert_eq!(next_answer().await["result"], json!("single"));
+        assert_eq!(next_answer().await[0]["result"], json!("batched"));
+
+        // A late answer to a timed out batch doesn't complete a newer one
+        client_in
+            .write_all(b"[{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"slow\"}]\n")
+            .await
+            .unwrap();
+        server_lines.next_line().await.unwrap();
+        assert_eq!(next_answer().await[0]["error"]["code"], json!(TIMED_OUT));
+        client_in
+            .write_all(b"[{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"fast\"}]\n")
+            .await
+            .unwrap();
+        server_lines.next_line().await.unwrap();
+        for answer in ["slow", "fast"] {
+            let response = json!({ "jsonrpc": "2.0", "id": 2, "result": answer });
+            server_writer
+                .write_all(format!("{response}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+        assert_eq!(next_answer().await[0]["result"], json!("fast"));
+
+        drop(client_in);
+        server_writer.shutdown().await.unwrap();
+        relay.await.unwrap().unwrap();
+        assert!(client_lines.next_line().await.unwrap().is_none());
+    }
+}
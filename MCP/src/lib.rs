@@ -1,11 +1,24 @@
 use log::info;
 use rmcp::{ErrorData, ServiceExt};
-use tokio::io::{stdin, stdout};
+use tokio::io::{duplex, split, stdin, stdout};
 
+mod batch;
 mod tools;
 
 pub async fn server() -> Result<(), ErrorData> {
-    let transport = (stdin(), stdout());
+    // rmcp talks to one end of an in-memory pipe; the batch relay sits
+    // between it and stdio so JSON-RPC batch arrays are understood.
+    let (relay_side, server_side) = duplex(64 * 1024);
+    let (server_out, server_in) = split(relay_side);
+    let relay = tokio::spawn(batch::relay(
+        stdin(),
+        stdout(),
+        server_out,
+        server_in,
+        batch::BATCH_TIMEOUT,
+    ));
+
+    let transport = split(server_side);
 
     let service = tools::Manager::new();
 
@@ -23,5 +36,10 @@ pub async fn server() -> Result<(), ErrorData> {
         .waiting()
         .await
         .map_err(|e| ErrorData::new(rmcp::model::ErrorCode(-2), e.to_string(), None))?;
+
+    relay
+        .await
+        .map_err(|e| ErrorData::new(rmcp::model::ErrorCode(-3), e.to_string(), None))?
+        .map_err(|e| ErrorData::new(rmcp::model::ErrorCode(-3), e.to_string(), None))?;
     Ok(())
 }
@@ -1,10 +1,18 @@
-use ninja::manager::ShurikenManager;
+use ninja::{common::types::LogFilter, manager::ShurikenManager, utils::normalize_shuriken_name};
 use rmcp::{
     ErrorData as McpError,
+    RoleServer,
     ServerHandler,
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
+    handler::server::{
+        router::{prompt::PromptRouter, tool::ToolRouter},
+        wrapper::Parameters,
+    },
     model::*, // <-- brings in CallToolResult, Content, ServerCapabilities, ServerInfo, Resource, RawResource, etc.
+    prompt,
+    prompt_handler,
+    prompt_router,
     schemars,
+    service::RequestContext,
     tool,
     tool_handler,
     tool_router,
@@ -34,12 +42,17 @@ pub struct ScriptRequest {
     pub script: String,
 }
 
+/// How many trailing log lines the health prompt includes.
+const HEALTH_LOG_LINES: usize = 20;
+
 #[derive(Clone)]
 pub struct Manager {
     manager: ShurikenManager,
     #[allow(dead_code)]
     // <-- if you don't directly use this field, the compiler will warn about it. This attribute suppresses that warning.
     tool_router: ToolRouter<Self>,
+    #[allow(dead_code)]
+    prompt_router: PromptRouter<Self>,
 }
 
 #[tool_router(router = tool_router)]
@@ -55,6 +68,7 @@ impl Manager {
         Self {
             manager,
             tool_router: Self::tool_router(),
+            prompt_router: Self::prompt_router(),
         }
     }
 
@@ -100,12 +114,7 @@ impl Manager {
     ) -> Result<CallToolResult, McpError> {
         let _ = &self
             .manager
-            .stop(name.as_str())
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-        let _ = &self
-            .manager
-            .start(name.as_str())
+            .restart(name.as_str())
             .await
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
         Ok(CallToolResult::success(vec![Content::text(
@@ -199,17 +208,127 @@ impl Manager {
     }
 }
 
+#[prompt_router]
+impl Manager {
+    #[prompt(
+        name = "shuriken_health",
+        description = "Summarize a shuriken's state, manifest and recent logs so an operator can diagnose it"
+    )]
+    pub async fn shuriken_health(
+        &self,
+        Parameters(ShurikenRequest { name }): Parameters<ShurikenRequest>,
+    ) -> Result<Vec<PromptMessage>, McpError> {
+        let name = normalize_shuriken_name(&name);
+        let shuriken = self
+            .manager
+            .get(name.clone())
+            .await
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let state = shuriken.state.lock().await.clone();
+        let meta = &shuriken.metadata;
+
+        let ports = meta
+            .ports
+            .as_ref()
+            .map(|ports| {
+                ports
+                    .iter()
+                    .map(u16::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_else(|| "none".to_string());
+
+        let logs = if shuriken.logs.is_none() && shuriken.metadata.log_buffer.is_none() {
+            "(no log file configured)".to_string()
+        } else {
+            let filter = LogFilter {
+                lines: Some(HEALTH_LOG_LINES),
+                ..Default::default()
+            };
+            match self.manager.read_logs(&name, &filter).await {
+                Ok(lines) => lines.join("\n"),
+                Err(e) => format!("(could not read logs: {:#})", e),
+            }
+        };
+
+        let summary = format!(
+            "You are helping an operator diagnose the shuriken '{}'.\n\n\
+             State: {:?}\n\
+             Manifest: id = {}, version = {}, type = {}, ports = {}\n\n\
+             Last {} log lines:\n```\n{}\n```\n\n\
+             Explain whether the shuriken looks healthy, point out likely causes of any problems \
+             and suggest concrete next steps.",
+            meta.name,
+            state,
+            meta.id,
+            meta.version,
+            meta.shuriken_type,
+            ports,
+            HEALTH_LOG_LINES,
+            logs
+        );
+
+        Ok(vec![PromptMessage::new_text(
+            PromptMessageRole::User,
+            summary,
+        )])
+    }
+}
+
 #[tool_handler]
+#[prompt_handler]
 impl ServerHandler for Manager {
     fn get_info(&self) -> ServerInfo {
-        let capabilities = ServerCapabilities::builder().enable_tools().build();
+        let capabilities = ServerCapabilities::builder()
+            .enable_tools()
+            .enable_prompts()
+            .build();
 
         ServerInfo::new(capabilities)
             .with_instructions(r#"This server provides resources and mostly tools
                 for managing shurikens (arbitrary units of other dev software e.g Apache)
                 which are: start_shuriken, stop_shuriken, restart_shuriken, shuriken_status and provides tools 
                 to execute ninjascript (Luau with a few built-in libraries) and Ninja DSL (a domain-specific language for managing shurikens and interacting with them).
+                The shuriken_health prompt summarizes a shuriken's state and recent logs for diagnosis.
                 The cheatsheet for the ninjascript can be found as a resource."#)
             .with_protocol_version(ProtocolVersion::LATEST)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shuriken_health_includes_the_log_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let ninja_dir = dir.path().join("shurikens/demo/.ninja");
+        fs::create_dir_all(&ninja_dir).unwrap();
+        fs::write(
+            ninja_dir.join("manifest.toml"),
+            "[shuriken]\nname = \"demo\"\nid = \"demo\"\nversion = \"1.0.0\"\ntype = \"daemon\"\n\n[logs]\nlog-path = \"demo.log\"\n",
+        )
+        .unwrap();
+        let log = (1..=30).map(|n| format!("line {n}\n")).collect::<String>();
+        fs::write(dir.path().join("shurikens/demo/demo.log"), log).unwrap();
+
+        let manager = Manager {
+            manager: ShurikenManager::with_root(dir.path()).await.unwrap(),
+            tool_router: Manager::tool_router(),
+            prompt_router: Manager::prompt_router(),
+        };
+        // Names are matched the way the manager normalizes them
+        let request = ShurikenRequest {
+            name: "Demo".into(),
+        };
+        let messages = manager.shuriken_health(Parameters(request)).await.unwrap();
+
+        let PromptMessageContent::Text { text } = &messages[0].content else {
+            panic!("expected a text prompt");
+        };
+        assert!(text.contains("line 11\n"));
+        assert!(text.contains("line 30\n"));
+        assert!(!text.contains("line 10\n"));
+    }
+}
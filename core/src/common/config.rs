@@ -38,6 +38,19 @@ pub struct NinjaConfig {
     pub registries: HashMap<String, String>,
     pub check_updates: bool,
     pub dev_mode: bool,
+    /// Let installed archives contain symlinks and hard links (off by default)
+    #[serde(default)]
+    pub allow_symlinks: bool,
+    /// Registry `shurikenctl self-update` takes releases from (default: the official `ninja`)
+    #[serde(default = "default_update_registry")]
+    pub update_registry: String,
+    /// Public key, as hex, that `shurikenctl` releases must be signed with
+    #[serde(default)]
+    pub update_key: Option<String>,
+}
+
+fn default_update_registry() -> String {
+    "ninja".to_string()
 }
 
 impl NinjaConfig {
@@ -50,6 +63,9 @@ impl NinjaConfig {
             )]),
             check_updates: true,
             dev_mode: false,
+            allow_symlinks: false,
+            update_registry: default_update_registry(),
+            update_key: None,
         }
     }
 
@@ -74,6 +90,10 @@ impl NinjaConfig {
         self.dev_mode = dev_mode;
     }
 
+    pub fn set_allow_symlinks(&mut self, allow: bool) {
+        self.allow_symlinks = allow;
+    }
+
     pub fn remove_registry(&mut self, registry: &str) {
         self.registries.remove(registry);
     }
@@ -1,3 +1,5 @@
+use crate::common::types::ShurikenState;
+
 /// Error types specific to Shuriken operations.
 ///
 /// Provides detailed error information for various failure scenarios
@@ -49,3 +51,76 @@ impl From<std::io::Error> for ShurikenError {
         ShurikenError::IoError(err)
     }
 }
+
+/// Errors from [`ShurikenManager`](crate::manager::ShurikenManager) that
+/// callers may want to tell apart.
+///
+/// Manager methods return these wrapped in `anyhow::Error`; use
+/// [`error_code`] or `downcast_ref::<ManagerError>()` to recover them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManagerError {
+    /// No Shuriken with this name is installed
+    NotFound(String),
+    /// The Shuriken is already running (or paused)
+    AlreadyRunning(String),
+    /// The Shuriken isn't running
+    NotRunning(String),
+    /// The Shuriken isn't paused
+    NotPaused(String),
+    /// A Shuriken with this name is already installed
+    AlreadyInstalled(String),
+    /// The Shuriken has no `[logs]` section, so there's no log file to read
+    NoLogFile(String),
+    /// The Shuriken's current state can't move into the requested one
+    IllegalTransition(String, ShurikenState, ShurikenState),
+}
+
+impl ManagerError {
+    /// Stable, machine-readable code for this error, e.g. `"not_found"`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ManagerError::NotFound(_) => "not_found",
+            ManagerError::AlreadyRunning(_) => "already_running",
+            ManagerError::NotRunning(_) => "not_running",
+            ManagerError::NotPaused(_) => "not_paused",
+            ManagerError::AlreadyInstalled(_) => "already_installed",
+            ManagerError::NoLogFile(_) => "no_log_file",
+            ManagerError::IllegalTransition(..) => "illegal_transition",
+        }
+    }
+}
+
+impl std::fmt::Display for ManagerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManagerError::NotFound(name) => write!(f, "No such shuriken: {}", name),
+            ManagerError::AlreadyRunning(name) => {
+                write!(f, "Shuriken '{}' is already running", name)
+            }
+            ManagerError::NotRunning(name) => write!(f, "Shuriken '{}' is not running", name),
+            ManagerError::NotPaused(name) => write!(f, "Shuriken '{}' is not paused", name),
+            ManagerError::AlreadyInstalled(name) => write!(
+                f,
+                "Shuriken '{}' is already installed (use replace to reinstall it)",
+                name
+            ),
+            ManagerError::NoLogFile(name) => write!(
+                f,
+                "Shuriken '{}' has no log file (add a [logs] section with a log-path)",
+                name
+            ),
+            ManagerError::IllegalTransition(name, from, to) => write!(
+                f,
+                "Shuriken '{}' can't go from {:?} to {:?}",
+                name, from, to
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ManagerError {}
+
+/// Returns the [`ManagerError`] code behind `err`, if it carries one.
+pub fn error_code(err: &anyhow::Error) -> Option<&'static str> {
+    err.downcast_ref::<ManagerError>().map(ManagerError::code)
+}
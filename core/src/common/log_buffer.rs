@@ -0,0 +1,177 @@
+//! In-memory output of Shurikens that don't write a log file.
+//!
+//! A Shuriken opts in with `log-buffer = <lines>` in its manifest. Processes
+//! its start script spawns then have their stdout and stderr piped into a
+//! ring buffer keyed by the Shuriken's directory, so frontends can show
+//! recent output. Memory is bounded by the line count and by
+//! [`MAX_LINE_BYTES`] per line.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Longest line kept; the rest of a longer line is dropped.
+pub const MAX_LINE_BYTES: usize = 4096;
+
+/// The last lines written by a Shuriken's processes.
+#[derive(Debug)]
+pub struct LogBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    /// Creates an empty buffer holding at most `capacity` lines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity.min(1024)),
+            capacity,
+        }
+    }
+
+    /// Appends a line, dropping the oldest one once full.
+    pub fn push(&mut self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// The buffered lines, oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.iter().cloned().collect()
+    }
+
+    fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.lines.len() > capacity {
+            self.lines.pop_front();
+        }
+    }
+}
+
+type SharedBuffer = Arc<Mutex<LogBuffer>>;
+
+static BUFFERS: LazyLock<Mutex<HashMap<PathBuf, SharedBuffer>>> = LazyLock::new(Default::default);
+
+fn key(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+/// Enables capturing for the Shuriken in `dir`, keeping `capacity` lines.
+///
+/// Lines from an earlier run are kept, trimmed to the new capacity.
+pub fn enable(dir: &Path, capacity: usize) {
+    let mut buffers = BUFFERS.lock().unwrap_or_else(PoisonError::into_inner);
+    buffers
+        .entry(key(dir))
+        .and_modify(|buffer| {
+            buffer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .resize(capacity)
+        })
+        .or_insert_with(|| Arc::new(Mutex::new(LogBuffer::new(capacity))));
+}
+
+/// The buffer output of processes run in `dir` goes to, if capturing is enabled.
+pub fn buffer_for(dir: &Path) -> Option<SharedBuffer> {
+    BUFFERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key(dir))
+        .cloned()
+}
+
+/// The lines captured for the Shuriken in `dir`, oldest first.
+///
+/// # Returns
+/// - `Some(lines)` if capturing is enabled for it
+/// - `None` otherwise
+pub fn captured_lines(dir: &Path) -> Option<Vec<String>> {
+    let buffer = buffer_for(dir)?;
+    let lines = buffer
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .lines();
+    Some(lines)
+}
+
+/// Copies `reader` into `buffer` line by line until it closes.
+///
+/// Reads in fixed chunks so a line without a newline can't grow past
+/// [`MAX_LINE_BYTES`]. Invalid UTF-8 is converted lossily.
+pub async fn pump(mut reader: impl AsyncRead + Unpin, buffer: SharedBuffer) {
+    let mut chunk = [0u8; 8192];
+    let mut line: Vec<u8> = Vec::new();
+    let mut truncated = false;
+
+    let flush = |line: &mut Vec<u8>, truncated: &mut bool| {
+        let text = String::from_utf8_lossy(line)
+            .trim_end_matches('\r')
+            .to_string();
+        buffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(text);
+        line.clear();
+        *truncated = false;
+    };
+
+    loop {
+        let read = match reader.read(&mut chunk).await {
+            Ok(read) if read > 0 => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            // A broken pipe or any other error means the writer is gone
+            Ok(_) | Err(_) => break,
+        };
+        for &byte in &chunk[..read] {
+            if byte == b'\n' {
+                flush(&mut line, &mut truncated);
+            } else if line.len() < MAX_LINE_BYTES {
+                line.push(byte);
+            } else {
+                truncated = true;
+            }
+        }
+    }
+    if !line.is_empty() || truncated {
+        flush(&mut line, &mut truncated);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_keeps_last_lines() {
+        let mut buffer = LogBuffer::new(2);
+        for line in ["one", "two", "three"] {
+            buffer.push(line.to_string());
+        }
+        assert_eq!(buffer.lines(), vec!["two", "three"]);
+    }
+
+    #[tokio::test]
+    async fn test_pump_splits_and_bounds_lines() {
+        let buffer = Arc::new(Mutex::new(LogBuffer::new(10)));
+        let long = "x".repeat(MAX_LINE_BYTES + 100);
+        let input = format!("first\r\n{long}\nlast");
+
+        pump(input.as_bytes(), buffer.clone()).await;
+
+        let lines = buffer.lock().unwrap().lines();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "first");
+        assert_eq!(lines[1].len(), MAX_LINE_BYTES);
+        assert_eq!(lines[2], "last");
+    }
+}
@@ -0,0 +1,448 @@
+//! Filtering of Shuriken log files.
+//!
+//! Logs are plain text, so filtering by time relies on a timestamp at the
+//! start of each line. Lines without one (stack traces, wrapped messages)
+//! belong to the closest timestamped line above them.
+
+use crate::common::types::{LogFilter, LogRecord};
+use anyhow::{Error, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use std::{
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+/// How much [`tail_file`] reads per step, going backwards from the end.
+const TAIL_CHUNK: u64 = 8192;
+
+/// Timestamp layouts without a timezone, read as local time.
+///
+/// Covers the common ISO-ish forms and the format ninja's own logger uses.
+const NAIVE_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%d/%m/%Y %H:%M:%S",
+];
+
+/// Parses a duration like `30s`, `10m`, `2h`, `1d` or `1h30m`.
+///
+/// A bare number is read as seconds.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(Error::msg("Duration is empty"));
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => {
+                return Err(Error::msg(format!(
+                    "Unknown unit '{}' in duration '{}' (use s, m, h or d)",
+                    c, text
+                )));
+            }
+        };
+        let value: u64 = digits.parse().map_err(|_| {
+            Error::msg(format!(
+                "Missing number before '{}' in duration '{}'",
+                c, text
+            ))
+        })?;
+        total = total.saturating_add(value.saturating_mul(unit));
+        digits.clear();
+    }
+
+    if !digits.is_empty() {
+        let seconds: u64 = digits
+            .parse()
+            .map_err(|e| Error::msg(format!("Invalid duration '{}': {}", text, e)))?;
+        total = total.saturating_add(seconds);
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Reads the timestamp a log line starts with, if any.
+///
+/// Accepts RFC 3339 and the layouts in [`NAIVE_FORMATS`], optionally
+/// wrapped in square brackets like `[2024-05-01 12:00:00]`.
+pub fn leading_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let trimmed = line.trim_start().trim_start_matches('[');
+    let mut tokens = trimmed.split(|c: char| c.is_whitespace() || c == ']');
+    let first = tokens.next().filter(|t| !t.is_empty())?;
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(first) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+
+    // Date and time are often separated by a space
+    let joined = format!("{} {}", first, tokens.next().unwrap_or_default());
+    NAIVE_FORMATS.iter().find_map(|format| {
+        let naive = NaiveDateTime::parse_from_str(&joined, format)
+            .or_else(|_| NaiveDateTime::parse_from_str(first, format))
+            .ok()?;
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|local| local.with_timezone(&Utc))
+    })
+}
+
+/// Wraps a log line in a [`LogRecord`].
+///
+/// The record carries the line's [`leading_timestamp`] when it has one and
+/// `ingested` (usually the time it was read) otherwise.
+pub fn log_record(shuriken: &str, line: &str, ingested: DateTime<Utc>) -> LogRecord {
+    LogRecord {
+        shuriken: shuriken.to_string(),
+        timestamp: leading_timestamp(line).unwrap_or(ingested),
+        line: line.to_string(),
+    }
+}
+
+/// Applies `filter` to the contents of a log file.
+///
+/// # Arguments
+/// - `content`: The whole log file
+/// - `filter`: Which lines to keep
+/// - `now`: The time `filter.since` counts back from
+/// - `modified`: When the file was last written; decides `since` for logs
+///   that carry no timestamps at all (all lines or none)
+///
+/// # Returns
+/// - `Ok(lines)` with the matching lines, oldest first
+/// - `Err` if `filter.grep` is not a valid regex
+pub fn filter_log(
+    content: &str,
+    filter: &LogFilter,
+    now: DateTime<Utc>,
+    modified: Option<DateTime<Utc>>,
+) -> Result<Vec<String>> {
+    let pattern = filter
+        .grep
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| Error::msg(format!("Invalid grep pattern: {}", e)))?;
+    let cutoff = filter
+        .since
+        .and_then(|since| chrono::Duration::from_std(since).ok())
+        .map(|since| now - since);
+
+    let timestamped = cutoff.is_some() && content.lines().any(|l| leading_timestamp(l).is_some());
+    let untimed_is_recent = match (cutoff, modified) {
+        (Some(cutoff), Some(modified)) => modified >= cutoff,
+        _ => true,
+    };
+
+    let mut current: Option<DateTime<Utc>> = None;
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if let Some(cutoff) = cutoff {
+            let recent = if timestamped {
+                if let Some(timestamp) = leading_timestamp(line) {
+                    current = Some(timestamp);
+                }
+                // Lines above the first timestamp are older than it
+                current.is_some_and(|timestamp| timestamp >= cutoff)
+            } else {
+                untimed_is_recent
+            };
+            if !recent {
+                continue;
+            }
+        }
+
+        if pattern
+            .as_ref()
+            .is_some_and(|pattern| !pattern.is_match(line))
+        {
+            continue;
+        }
+
+        lines.push(line.to_string());
+    }
+
+    if let Some(limit) = filter.lines {
+        let start = lines.len().saturating_sub(limit);
+        lines.drain(..start);
+    }
+
+    Ok(lines)
+}
+
+/// Reads the last `lines` lines of the file at `path`, oldest first.
+///
+/// Reads backwards from the end in [`TAIL_CHUNK`] steps, so a large log
+/// costs about as much as the lines asked for. Invalid UTF-8 is converted
+/// lossily.
+pub async fn tail_file(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    if lines == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut file = fs::File::open(path).await?;
+    let mut pos = file.metadata().await?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    // One newline more than lines wanted means the first one is complete
+    while pos > 0 && tail.iter().filter(|&&b| b == b'\n').count() <= lines {
+        let step = pos.min(TAIL_CHUNK);
+        pos -= step;
+        file.seek(SeekFrom::Start(pos)).await?;
+        let mut chunk = vec![0u8; step as usize];
+        file.read_exact(&mut chunk).await?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+    }
+
+    let text = String::from_utf8_lossy(&tail);
+    let mut all: Vec<&str> = text.lines().collect();
+    if pos > 0 && !all.is_empty() {
+        // Cut off somewhere inside a line
+        all.remove(0);
+    }
+    let start = all.len().saturating_sub(lines);
+    Ok(all[start..].iter().map(|line| line.to_string()).collect())
+}
+
+/// Picks up lines appended to a log file, like `tail -f`.
+#[derive(Debug)]
+pub struct LogFollower {
+    path: PathBuf,
+    offset: u64,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    /// Follows `path` from its current end; a missing file is followed from its start.
+    pub async fn new(path: &Path) -> Self {
+        let offset = fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+        Self {
+            path: path.to_path_buf(),
+            offset,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Returns the complete lines written since the last call.
+    ///
+    /// A file that got shorter was truncated or rotated and is read again
+    /// from the start. A missing file yields nothing until it reappears.
+    pub async fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut file = match fs::File::open(&self.path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let len = file.metadata().await?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if len == self.offset {
+            return Ok(Vec::new());
+        }
+
+        file.seek(SeekFrom::Start(self.offset)).await?;
+        let mut appended = Vec::new();
+        file.take(len - self.offset)
+            .read_to_end(&mut appended)
+            .await?;
+        self.offset += appended.len() as u64;
+        self.partial.extend_from_slice(&appended);
+
+        // Keep an unfinished last line for the next poll
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "\
+2024-05-01T10:00:00Z INFO booting
+2024-05-01T10:05:00Z ERROR disk full
+  at write_block
+2024-05-01T10:20:00Z INFO recovered
+2024-05-01T10:25:00Z ERROR disk full again
+";
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::from_secs(172_800));
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn test_leading_timestamp_formats() {
+        let expected = at("2024-05-01T10:00:00Z");
+        assert_eq!(
+            leading_timestamp("2024-05-01T10:00:00Z hello"),
+            Some(expected)
+        );
+        assert_eq!(
+            leading_timestamp("2024-05-01T12:00:00+02:00 hello"),
+            Some(expected)
+        );
+        assert!(leading_timestamp("[2024-05-01 10:00:00] [INFO] hello").is_some());
+        assert!(leading_timestamp("[01/05/2024 10:00:00] [INFO] hello").is_some());
+        assert_eq!(leading_timestamp("  at write_block"), None);
+        assert_eq!(leading_timestamp(""), None);
+    }
+
+    #[test]
+    fn test_log_record_json() {
+        let ingested = at("2024-05-01T11:00:00Z");
+        for line in FIXTURE.lines() {
+            let json = serde_json::to_string(&log_record("web", line, ingested)).unwrap();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(value["shuriken"], "web");
+            assert_eq!(value["line"], line);
+            let timestamp = value["timestamp"].as_str().unwrap();
+            let expected = leading_timestamp(line).unwrap_or(ingested);
+            assert_eq!(at(timestamp), expected);
+        }
+
+        let record = log_record("web", "  at write_block", ingested);
+        assert_eq!(record.timestamp, ingested);
+        let record = log_record("web", "2024-05-01T10:05:00Z ERROR disk full", ingested);
+        assert_eq!(record.timestamp, at("2024-05-01T10:05:00Z"));
+    }
+
+    #[test]
+    fn test_filter_by_pattern() {
+        let filter = LogFilter {
+            grep: Some("ERROR".to_string()),
+            ..Default::default()
+        };
+        let lines = filter_log(FIXTURE, &filter, Utc::now(), None).unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                "2024-05-01T10:05:00Z ERROR disk full",
+                "2024-05-01T10:25:00Z ERROR disk full again",
+            ]
+        );
+
+        let bad = LogFilter {
+            grep: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(filter_log(FIXTURE, &bad, Utc::now(), None).is_err());
+    }
+
+    #[test]
+    fn test_filter_by_time_window() {
+        let now = at("2024-05-01T10:30:00Z");
+        let filter = LogFilter {
+            since: Some(Duration::from_secs(26 * 60)),
+            ..Default::default()
+        };
+        let lines = filter_log(FIXTURE, &filter, now, None).unwrap();
+        // The continuation line belongs to the 10:05 entry
+        assert_eq!(
+            lines,
+            vec![
+                "2024-05-01T10:05:00Z ERROR disk full",
+                "  at write_block",
+                "2024-05-01T10:20:00Z INFO recovered",
+                "2024-05-01T10:25:00Z ERROR disk full again",
+            ]
+        );
+
+        let filter = LogFilter {
+            grep: Some("ERROR".to_string()),
+            since: Some(Duration::from_secs(10 * 60)),
+            lines: None,
+        };
+        let lines = filter_log(FIXTURE, &filter, now, None).unwrap();
+        assert_eq!(lines, vec!["2024-05-01T10:25:00Z ERROR disk full again"]);
+    }
+
+    #[test]
+    fn test_filter_untimed_log_uses_modification_time() {
+        let content = "one\ntwo\nthree\n";
+        let now = at("2024-05-01T10:30:00Z");
+        let filter = LogFilter {
+            since: Some(Duration::from_secs(60)),
+            lines: Some(2),
+            ..Default::default()
+        };
+
+        let stale = filter_log(content, &filter, now, Some(at("2024-05-01T09:00:00Z"))).unwrap();
+        assert!(stale.is_empty());
+
+        let fresh = filter_log(content, &filter, now, Some(at("2024-05-01T10:29:30Z"))).unwrap();
+        assert_eq!(fresh, vec!["two", "three"]);
+    }
+
+    #[tokio::test]
+    async fn test_tail_file_reads_last_lines_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        let content: String = (0..5000).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(&path, content).unwrap();
+
+        let tail = tail_file(&path, 3).await.unwrap();
+        assert_eq!(tail, vec!["line 4997", "line 4998", "line 4999"]);
+        assert_eq!(tail_file(&path, 10_000).await.unwrap().len(), 5000);
+        assert!(tail_file(&path, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_follower_returns_complete_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "old\n").unwrap();
+        let mut follower = LogFollower::new(&path).await;
+        assert!(follower.poll().await.unwrap().is_empty());
+
+        std::fs::write(&path, "old\nnew\nhal").unwrap();
+        assert_eq!(follower.poll().await.unwrap(), vec!["new"]);
+        std::fs::write(&path, "old\nnew\nhalf done\n").unwrap();
+        assert_eq!(follower.poll().await.unwrap(), vec!["half done"]);
+
+        // Rotated: read again from the start
+        std::fs::write(&path, "fresh\n").unwrap();
+        assert_eq!(follower.poll().await.unwrap(), vec!["fresh"]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Per-Shuriken metrics in the OpenMetrics text format.
+//!
+//! Every Shuriken gets one sample in each family, labelled with its name:
+//!
+//! - `ninja_shuriken_up`: 1 while running, 0 otherwise
+//! - `ninja_shuriken_uptime_seconds`: time since the current run started
+//! - `ninja_shuriken_restarts_total`: starts after the first one
+
+use std::fmt::Write;
+
+/// Content type to serve [`render_openmetrics`] output with.
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// What's known about one Shuriken at the time of a scrape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShurikenSample {
+    pub name: String,
+    pub up: bool,
+    pub uptime_seconds: u64,
+    pub restarts: u64,
+}
+
+/// Escapes a label value: backslashes, double quotes and newlines.
+pub fn escape_label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A metric family: name, type, help text and how to read it off a sample.
+type Family = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&ShurikenSample) -> u64,
+);
+
+/// Renders `samples` as an OpenMetrics exposition, ending in `# EOF`.
+pub fn render_openmetrics(samples: &[ShurikenSample]) -> String {
+    let families: [Family; 3] = [
+        (
+            "ninja_shuriken_up",
+            "gauge",
+            "Whether the shuriken is running.",
+            |sample| u64::from(sample.up),
+        ),
+        (
+            "ninja_shuriken_uptime_seconds",
+            "gauge",
+            "Seconds since the shuriken was started, 0 when it isn't running.",
+            |sample| sample.uptime_seconds,
+        ),
+        (
+            "ninja_shuriken_restarts",
+            "counter",
+            "Times the shuriken was restarted by its restart policy.",
+            |sample| sample.restarts,
+        ),
+    ];
+
+    let mut out = String::new();
+    for (family, kind, help, value) in families {
+        // Counter samples carry a `_total` suffix the family name doesn't
+        let metric = if kind == "counter" {
+            format!("{family}_total")
+        } else {
+            family.to_string()
+        };
+
+        let _ = writeln!(out, "# TYPE {family} {kind}");
+        let _ = writeln!(out, "# HELP {family} {help}");
+        for sample in samples {
+            let _ = writeln!(
+                out,
+                "{metric}{{name=\"{}\"}} {}",
+                escape_label(&sample.name),
+                value(sample)
+            );
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("web"), "web");
+        assert_eq!(escape_label(r#"a"b"#), r#"a\"b"#);
+        assert_eq!(escape_label(r"C:\ninja"), r"C:\\ninja");
+        assert_eq!(escape_label("two\nlines"), r"two\nlines");
+    }
+
+    #[test]
+    fn test_render_openmetrics() {
+        let samples = [ShurikenSample {
+            name: "we\"b".to_string(),
+            up: true,
+            uptime_seconds: 42,
+            restarts: 2,
+        }];
+        let text = render_openmetrics(&samples);
+
+        assert!(text.contains("# TYPE ninja_shuriken_up gauge\n"));
+        assert!(text.contains("ninja_shuriken_up{name=\"we\\\"b\"} 1\n"));
+        assert!(text.contains("ninja_shuriken_uptime_seconds{name=\"we\\\"b\"} 42\n"));
+        assert!(text.contains("# TYPE ninja_shuriken_restarts counter\n"));
+        assert!(text.contains("ninja_shuriken_restarts_total{name=\"we\\\"b\"} 2\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
@@ -4,12 +4,22 @@
 //!
 //! - [`config`]: Global Ninja configuration including registries and settings
 //! - [`types`]: Core types like `ShurikenState`, `FieldValue`, and `PlatformPath`
-//! - [`error`]: Error types for Shuriken-specific failures
+//! - [`error`]: Error types for Shuriken and manager failures, with stable error codes
 //! - [`registry`]: Registry support for discovering and managing Shurikens
+//! - [`logs`]: Filtering Shuriken log files by pattern and time
+//! - [`log_buffer`]: Recent output of Shurikens without a log file
+//! - [`metrics`]: Per-Shuriken metrics in the OpenMetrics text format
+//! - [`signing`]: Optional Ed25519 signatures for `.shuriken` packages
+//! - [`self_update`]: Replacing the `shurikenctl` binary with a newer release
 
 pub mod config;
 pub mod error;
+pub mod log_buffer;
+pub mod logs;
+pub mod metrics;
 pub mod registry;
+pub mod self_update;
+pub mod signing;
 pub mod structs;
 pub mod traits;
 pub mod types;
@@ -13,6 +13,30 @@ pub struct Registry {
     pub name: String,
     pub description: Option<String>,
     pub shurikens: Vec<ArmoryItem>,
+    /// Latest `shurikenctl` release, for registries that publish the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cli: Option<CliRelease>,
+}
+
+/// A `shurikenctl` release announced by a registry, see [`self_update`](crate::common::self_update).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CliRelease {
+    pub version: String,
+    /// Binaries keyed by target triple, e.g. `x86_64-unknown-linux-gnu`
+    pub binaries: HashMap<String, ReleaseBinary>,
+}
+
+/// One downloadable build of a [`CliRelease`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseBinary {
+    /// Absolute URL, or relative to the registry file
+    pub url: String,
+    /// SHA-256 of the binary as lowercase hex
+    pub sha256: String,
+    /// Ed25519 signature as hex over the release's name, version and that
+    /// SHA-256, see [`signing::release_message`](crate::common::signing::release_message)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -185,6 +209,33 @@ impl RegistrySources {
         }
     }
 
+    /// The newest `shurikenctl` release any registry announces.
+    ///
+    /// Relative binary URLs are resolved against their registry's URL.
+    pub async fn latest_cli_release(&self) -> Option<CliRelease> {
+        let mut latest: Option<CliRelease> = None;
+        for (name, registry) in self.fetch_all().await {
+            let Some(mut release) = registry.cli else {
+                continue;
+            };
+            let Some(registry_url) = self.registries.get(&name) else {
+                continue;
+            };
+            for binary in release.binaries.values_mut() {
+                if let Ok(url) = resolve_shuriken_url(registry_url, &binary.url) {
+                    binary.url = url;
+                }
+            }
+            if latest
+                .as_ref()
+                .is_none_or(|current| is_newer_version(&release.version, &current.version))
+            {
+                latest = Some(release);
+            }
+        }
+        latest
+    }
+
     fn registry_url(&self, registry_name: &str) -> Result<&str> {
         self.registries
             .get(registry_name)
@@ -193,6 +244,31 @@ impl RegistrySources {
     }
 }
 
+/// Whether version `candidate` is newer than `current`.
+///
+/// Compares the dot-separated numbers of each, ignoring a leading `v` and
+/// anything after a `-` or `+`, so `1.10.0` is newer than `1.9.2`.
+pub fn is_newer_version(candidate: &str, current: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+
+    let (candidate, current) = (parts(candidate), parts(current));
+    let len = candidate.len().max(current.len());
+    let at = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| at(&candidate, i).cmp(&at(&current, i)))
+        .find(|ordering| ordering.is_ne())
+        .is_some_and(|ordering| ordering.is_gt())
+}
+
 pub fn is_absolute_url(url: &str) -> bool {
     url.starts_with("http://") || url.starts_with("https://")
 }
@@ -0,0 +1,244 @@
+//! Updating the `shurikenctl` binary from a registry.
+//!
+//! A registry can announce the latest CLI release next to its shurikens:
+//!
+//! ```yaml
+//! cli:
+//!   version: 1.16.0
+//!   binaries:
+//!     x86_64-unknown-linux-gnu:
+//!       url: releases/shurikenctl-x86_64-unknown-linux-gnu
+//!       sha256: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+//!       signature: 5b1d...e80f
+//! ```
+//!
+//! The binary for this platform's target triple is downloaded next to the
+//! executable, checked against its checksum and its Ed25519 signature from
+//! a pinned key, and renamed over it, so an interrupted update never leaves
+//! a half-written binary behind and a compromised registry can't push one.
+//! The signature covers `shurikenctl`, the announced version and the
+//! checksum (see [`signing::release_message`]), so an older signed binary
+//! can't be passed off as a newer release either.
+
+use crate::common::{
+    registry::{CliRelease, RegistrySources, ReleaseBinary, is_newer_version},
+    signing,
+};
+use anyhow::{Context, Error, Result};
+use ed25519_dalek::VerifyingKey;
+use futures_util::StreamExt;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+use tempfile::NamedTempFile;
+
+/// What [`self_update`] did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum UpdateOutcome {
+    /// No registry announces a newer release
+    UpToDate { version: String },
+    /// The executable was replaced
+    Updated { from: String, to: String },
+}
+
+/// The target triple release binaries for this build are published under.
+///
+/// Uses the triples `rustc` builds the official releases with, e.g.
+/// `x86_64-unknown-linux-gnu` or `aarch64-apple-darwin`.
+pub fn target_triple() -> String {
+    let arch = std::env::consts::ARCH;
+    let rest = match std::env::consts::OS {
+        "linux" if cfg!(target_env = "musl") => "unknown-linux-musl",
+        "linux" => "unknown-linux-gnu",
+        "macos" => "apple-darwin",
+        "windows" if cfg!(target_env = "gnu") => "pc-windows-gnu",
+        "windows" => "pc-windows-msvc",
+        "freebsd" => "unknown-freebsd",
+        other => other,
+    };
+    format!("{arch}-{rest}")
+}
+
+/// Creates the file an update is downloaded into, next to `exe`.
+///
+/// Being in the same directory lets the final rename be atomic. Fails with
+/// a clear error when the directory can't be written to, before anything
+/// is downloaded.
+fn staging_file(exe: &Path) -> Result<NamedTempFile> {
+    let dir = exe
+        .parent()
+        .with_context(|| format!("'{}' has no parent directory", exe.display()))?;
+    let readonly = std::fs::metadata(exe)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(false);
+
+    match tempfile::Builder::new()
+        .prefix(".shurikenctl-update")
+        .tempfile_in(dir)
+    {
+        Ok(file) if !readonly => Ok(file),
+        Ok(_) => Err(read_only_error(exe)),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+            ) =>
+        {
+            Err(read_only_error(exe))
+        }
+        Err(e) => Err(Error::new(e).context(format!(
+            "Failed to create a temporary file in {}",
+            dir.display()
+        ))),
+    }
+}
+
+fn read_only_error(exe: &Path) -> Error {
+    Error::msg(format!(
+        "Can't replace {}: its location is read-only. If a package manager \
+         installed it, update it there, or rerun with rights to write there",
+        exe.display()
+    ))
+}
+
+/// Name release signatures are made for.
+pub const RELEASE_NAME: &str = "shurikenctl";
+
+/// Downloads `binary` of release `version` into `file` and checks it
+/// against its checksum and its signature from `key`.
+pub async fn download_verified(
+    binary: &ReleaseBinary,
+    version: &str,
+    file: &mut NamedTempFile,
+    key: &VerifyingKey,
+) -> Result<()> {
+    info!("Downloading {}", binary.url);
+    let response = reqwest::get(&binary.url)
+        .await
+        .with_context(|| format!("Failed to download {}", binary.url))?;
+    if !response.status().is_success() {
+        return Err(Error::msg(format!(
+            "Failed to download {}: HTTP {}",
+            binary.url,
+            response.status()
+        )));
+    }
+
+    let mut hasher = Sha256::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.with_context(|| format!("Failed to download {}", binary.url))?;
+        hasher.update(&chunk);
+        file.write_all(&chunk)?;
+    }
+    file.flush()?;
+
+    let digest = hasher.finalize();
+    let checksum = signing::to_hex(&digest);
+    if !checksum.eq_ignore_ascii_case(binary.sha256.trim()) {
+        return Err(Error::msg(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            binary.url, binary.sha256, checksum
+        )));
+    }
+
+    let signature = binary
+        .signature
+        .as_deref()
+        .with_context(|| format!("{} is not signed", binary.url))?;
+    let message = signing::release_message(RELEASE_NAME, version, &digest);
+    signing::verify_digest(signature, &message, key)
+        .with_context(|| format!("Refusing {}", binary.url))
+}
+
+/// Moves a verified download over `exe`.
+///
+/// On Windows a running executable can't be overwritten, but it can be
+/// renamed, so the old one is moved aside to `<exe>.old` first.
+pub fn replace_executable(file: NamedTempFile, exe: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old)
+            .with_context(|| format!("Failed to move {} aside", exe.display()))?;
+    }
+
+    let result = file
+        .persist(exe)
+        .map(drop)
+        .with_context(|| format!("Failed to replace {}", exe.display()));
+    #[cfg(windows)]
+    if result.is_err() {
+        let _ = std::fs::rename(exe.with_extension("old"), exe);
+    }
+    result
+}
+
+/// Updates the executable at `exe` to the newest release the registries announce.
+///
+/// # Arguments
+/// - `sources`: The registries to ask
+/// - `current_version`: The version `exe` is at
+/// - `exe`: The executable to replace, usually [`std::env::current_exe`]
+/// - `key`: The public key releases must be signed with
+///
+/// # Returns
+/// - `Ok(UpdateOutcome)` telling whether anything changed
+/// - `Err` if no registry announces a release, there's no build for this
+///   platform, the location is read-only, or the download, checksum or
+///   signature check fails
+pub async fn self_update(
+    sources: &RegistrySources,
+    current_version: &str,
+    exe: &Path,
+    key: &VerifyingKey,
+) -> Result<UpdateOutcome> {
+    let release: CliRelease = sources
+        .latest_cli_release()
+        .await
+        .context("No configured registry publishes shurikenctl releases")?;
+    if !is_newer_version(&release.version, current_version) {
+        return Ok(UpdateOutcome::UpToDate {
+            version: current_version.to_string(),
+        });
+    }
+
+    let triple = target_triple();
+    let binary = release.binaries.get(&triple).with_context(|| {
+        let mut available: Vec<_> = release.binaries.keys().cloned().collect();
+        available.sort();
+        format!(
+            "shurikenctl {} has no build for {} (available: {})",
+            release.version,
+            triple,
+            available.join(", ")
+        )
+    })?;
+
+    let mut file = staging_file(exe)?;
+    download_verified(binary, &release.version, &mut file, key).await?;
+    replace_executable(file, exe)?;
+    info!(
+        "Updated {} from {} to {}",
+        exe.display(),
+        current_version,
+        release.version
+    );
+
+    Ok(UpdateOutcome::Updated {
+        from: current_version.to_string(),
+        to: release.version,
+    })
+}
@@ -0,0 +1,168 @@
+//! Optional Ed25519 signatures for `.shuriken` packages.
+//!
+//! A signed package carries an extra section after the SHA-256 checksum:
+//!
+//! - `SIGNATURE_MAGIC` (4 bytes): "NSIG"
+//! - key id (8 bytes): first bytes of SHA-256(public key)
+//! - signature (64 bytes): Ed25519 over SHA-256 of everything before the section
+//!
+//! Installers that don't know about the section stop reading after the
+//! checksum, so signed packages stay installable everywhere.
+//!
+//! Keys are stored as 64 hex characters (or the 32 raw bytes): the secret
+//! seed for signing and the public key for verifying.
+//!
+//! `shurikenctl` releases are signed the same way, with the signature over
+//! the binary's SHA-256 published as hex next to its checksum.
+
+use anyhow::{Context, Error, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Marks the start of a package's signature section.
+pub const SIGNATURE_MAGIC: &[u8; 4] = b"NSIG";
+
+/// Length of a key id in bytes.
+pub const KEY_ID_LEN: usize = 8;
+
+/// Total length of a signature section.
+pub const SIGNATURE_SECTION_LEN: usize = SIGNATURE_MAGIC.len() + KEY_ID_LEN + 64;
+
+/// Encodes bytes as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return Err(Error::msg(
+            "Key is neither 32 raw bytes nor 64 hex characters",
+        ));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&text[i..i + 2], 16)
+                .map_err(|e| Error::msg(format!("Invalid hex in key: {}", e)))
+        })
+        .collect()
+}
+
+/// Parses a public verifying key written as 64 hex characters.
+pub fn parse_verifying_key(text: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = from_hex(text.trim())?
+        .try_into()
+        .map_err(|_| Error::msg("Public key is not 32 bytes long"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| Error::msg(format!("Invalid public key: {}", e)))
+}
+
+/// Reads a 32-byte key stored as hex text or raw bytes.
+fn read_key_bytes(path: &Path) -> Result<[u8; 32]> {
+    let raw =
+        std::fs::read(path).with_context(|| format!("Failed to read key {}", path.display()))?;
+
+    let bytes = if raw.len() == 32 {
+        raw
+    } else {
+        from_hex(String::from_utf8_lossy(&raw).trim())?
+    };
+
+    bytes
+        .try_into()
+        .map_err(|_| Error::msg(format!("Key {} is not 32 bytes long", path.display())))
+}
+
+/// Loads a secret signing key from `path`.
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    Ok(SigningKey::from_bytes(&read_key_bytes(path)?))
+}
+
+/// Loads a public verifying key from `path`.
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&read_key_bytes(path)?)
+        .map_err(|e| Error::msg(format!("Invalid public key {}: {}", path.display(), e)))
+}
+
+/// Short identifier for a public key, stored in signed packages.
+pub fn key_id(key: &VerifyingKey) -> [u8; KEY_ID_LEN] {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut id = [0u8; KEY_ID_LEN];
+    id.copy_from_slice(&digest[..KEY_ID_LEN]);
+    id
+}
+
+/// Builds the signature section for a package whose signed bytes hash to `digest`.
+pub fn signature_section(key: &SigningKey, digest: &[u8]) -> Vec<u8> {
+    let mut section = Vec::with_capacity(SIGNATURE_SECTION_LEN);
+    section.extend_from_slice(SIGNATURE_MAGIC);
+    section.extend_from_slice(&key_id(&key.verifying_key()));
+    section.extend_from_slice(&key.sign(digest).to_bytes());
+    section
+}
+
+/// The bytes a release binary's signature covers.
+///
+/// Besides the binary's SHA-256 `digest` they name what it is and which
+/// version, so an old signed binary can't be announced again as a newer
+/// release.
+pub fn release_message(name: &str, version: &str, digest: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(name.len() + version.len() + digest.len() + 2);
+    message.extend_from_slice(name.as_bytes());
+    message.push(0);
+    message.extend_from_slice(version.as_bytes());
+    message.push(0);
+    message.extend_from_slice(digest);
+    message
+}
+
+/// Signs a SHA-256 `digest`, or a [`release_message`], as 128 hex characters.
+pub fn sign_digest(key: &SigningKey, digest: &[u8]) -> String {
+    to_hex(&key.sign(digest).to_bytes())
+}
+
+/// Checks a hex signature made by [`sign_digest`] against `key`.
+///
+/// # Returns
+/// - `Ok(())` if `signature` is `key`'s signature over `digest`
+/// - `Err` if it's malformed or made by another key or over other bytes
+pub fn verify_digest(signature: &str, digest: &[u8], key: &VerifyingKey) -> Result<()> {
+    let signature = Signature::from_slice(&from_hex(signature.trim())?)
+        .map_err(|e| Error::msg(format!("Malformed signature: {}", e)))?;
+    key.verify(digest, &signature)
+        .map_err(|_| Error::msg("Signature verification failed"))
+}
+
+/// Checks a package's trailing bytes against `key`.
+///
+/// # Arguments
+/// - `trailer`: Everything after the package checksum
+/// - `digest`: SHA-256 of everything before the signature section
+/// - `key`: The public key the package must be signed with
+///
+/// # Returns
+/// - `Ok(())` if the package carries a valid signature from `key`
+/// - `Err` if it's unsigned, signed by another key, or the signature is invalid
+pub fn verify_section(trailer: &[u8], digest: &[u8], key: &VerifyingKey) -> Result<()> {
+    if trailer.is_empty() {
+        return Err(Error::msg("Package is not signed"));
+    }
+    if trailer.len() != SIGNATURE_SECTION_LEN || !trailer.starts_with(SIGNATURE_MAGIC) {
+        return Err(Error::msg("Package has a malformed signature section"));
+    }
+
+    let (key_id_bytes, signature) = trailer[SIGNATURE_MAGIC.len()..].split_at(KEY_ID_LEN);
+    if key_id_bytes != key_id(key) {
+        return Err(Error::msg(format!(
+            "Package is signed by key {}, expected {}",
+            to_hex(key_id_bytes),
+            to_hex(&key_id(key))
+        )));
+    }
+
+    let signature = Signature::from_slice(signature)
+        .map_err(|e| Error::msg(format!("Malformed package signature: {}", e)))?;
+    key.verify(digest, &signature).map_err(|_| {
+        Error::msg("Package signature verification failed (package was tampered with)")
+    })
+}
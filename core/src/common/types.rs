@@ -1,20 +1,60 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 use toml::{Value, map::Map};
 
 /// Represents the runtime state of a Shuriken.
 ///
 /// - `Running`: The Shuriken's process is actively running
 /// - `Idle`: The Shuriken is stopped or has never been started
+/// - `Paused`: The Shuriken's process is frozen and can be resumed
 /// - `Error(String)`: The Shuriken encountered an error with the provided message
+/// - `Crashed(i32)`: The Shuriken's process exited without being stopped
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ShurikenState {
     /// Actively running
     Running,
     /// Stopped or not started
     Idle,
+    /// Process frozen by [`ShurikenManager::pause`](crate::manager::ShurikenManager::pause)
+    Paused,
     /// Error state with error message
     Error(String),
+    /// The process exited on its own with this exit code, -1 if it isn't known
+    Crashed(i32),
+}
+
+impl ShurikenState {
+    /// Whether a Shuriken may move from this state into `next`.
+    ///
+    /// Staying in the same state is always allowed. A process has to be
+    /// running before it can be paused or crash, and only a paused one can
+    /// be resumed:
+    ///
+    /// - `Idle` → `Running`, `Error`
+    /// - `Running` → `Idle`, `Paused`, `Error`, `Crashed`
+    /// - `Paused` → `Running`, `Idle`, `Error`, `Crashed`
+    /// - `Error`, `Crashed` → `Running`, `Idle`, `Error`, `Crashed`
+    ///
+    /// There are no `Starting`/`Stopping` states. Starts and stops of a
+    /// Shuriken already run one at a time under its operation lock in
+    /// [`ShurikenManager`](crate::manager::ShurikenManager), so nothing can
+    /// observe or race a half-finished start. Adding them would also change
+    /// the states every client (CLI, GUI, FFI, HTTP) already understands.
+    pub fn can_transition_to(&self, next: &ShurikenState) -> bool {
+        use ShurikenState::*;
+
+        if self == next {
+            return true;
+        }
+        match (self, next) {
+            (Idle, Running | Error(_)) => true,
+            (Idle, _) => false,
+            (Running | Paused, _) => true,
+            (Error(_) | Crashed(_), Paused) => false,
+            (Error(_) | Crashed(_), _) => true,
+        }
+    }
 }
 
 impl Default for ShurikenState {
@@ -24,6 +64,366 @@ impl Default for ShurikenState {
     }
 }
 
+/// A Shuriken moving into a new state, as broadcast by the manager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChange {
+    /// Name of the Shuriken that changed
+    pub name: String,
+    /// The state it moved into, or its last known state if it was removed
+    pub state: ShurikenState,
+    /// Whether the Shuriken disappeared from disk and is no longer managed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+}
+
+/// A snapshot of one Shuriken, as returned by
+/// [`ShurikenManager::info`](crate::manager::ShurikenManager::info).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShurikenInfo {
+    /// Name the Shuriken is registered under
+    pub name: String,
+    /// Version from the manifest
+    pub version: String,
+    /// Type from the manifest, e.g. `"daemon"`
+    #[serde(rename = "type")]
+    pub shuriken_type: String,
+    /// Current state
+    pub state: ShurikenState,
+    /// PID recorded in the lockfile, if it is running and recorded one
+    pub pid: Option<u32>,
+    /// When it was started, if it is running and the lockfile records it
+    pub start_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// What happened in a [`LifecycleEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    /// The Shuriken was started
+    Started,
+    /// The Shuriken was stopped
+    Stopped,
+    /// The Shuriken's process was frozen
+    Paused,
+    /// A paused Shuriken was continued
+    Resumed,
+    /// Starting failed or the Shuriken entered an error state
+    Failed,
+}
+
+impl LifecycleEventKind {
+    /// The event a state transition corresponds to.
+    pub fn from_transition(old: &ShurikenState, new: &ShurikenState) -> Self {
+        match (old, new) {
+            (ShurikenState::Paused, ShurikenState::Running) => LifecycleEventKind::Resumed,
+            (_, ShurikenState::Running) => LifecycleEventKind::Started,
+            (_, ShurikenState::Idle) => LifecycleEventKind::Stopped,
+            (_, ShurikenState::Paused) => LifecycleEventKind::Paused,
+            (_, ShurikenState::Error(_) | ShurikenState::Crashed(_)) => LifecycleEventKind::Failed,
+        }
+    }
+}
+
+/// One entry in a Shuriken's lifecycle history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    /// When it happened
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// What happened
+    pub event: LifecycleEventKind,
+    /// Extra context, such as the error a start failed with
+    pub detail: Option<String>,
+}
+
+/// How bulk operations like `start_many` schedule their work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkMode {
+    /// Run up to the given number of operations at once
+    Parallel(usize),
+    /// Run one at a time, respecting `depends-on` order
+    Sequential,
+}
+
+/// Per-start settings for [`ShurikenManager::start_with_overrides`](crate::manager::ShurikenManager::start_with_overrides).
+///
+/// They only apply to this start and to the restarts its
+/// [`RestartPolicy`](crate::shuriken::RestartPolicy) makes; nothing is saved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartOverrides {
+    /// The profile to start with, `None` for the base definition
+    pub profile: Option<String>,
+    /// Health-check deadline, `None` for the manifest's `start-timeout`
+    pub timeout: Option<Duration>,
+    /// Variables set for processes the start script spawns, winning over
+    /// the ones the script passes itself
+    pub env: HashMap<String, String>,
+}
+
+impl StartOverrides {
+    /// Parses a `KEY=VALUE` environment override.
+    ///
+    /// The value may be empty and may contain `=`; the key may not be empty
+    /// and may not contain whitespace.
+    pub fn parse_env(assignment: &str) -> Result<(String, String), String> {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", assignment))?;
+        if key.is_empty() {
+            return Err(format!("missing variable name in '{}'", assignment));
+        }
+        if key.contains(|c: char| c.is_whitespace() || c == '\0') {
+            return Err(format!("invalid variable name '{}'", key));
+        }
+        if value.contains('\0') {
+            return Err(format!("value of '{}' contains a NUL byte", key));
+        }
+        Ok((key.to_string(), value.to_string()))
+    }
+}
+
+/// Extra glob patterns deciding which files `forge` packs.
+///
+/// Patterns are gitignore-style and relative to the Shuriken directory. They
+/// apply on top of the directory's `.ninjaignore`. When `include` is non-empty
+/// only matching files (and the `.ninja` folder) are packed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeFilter {
+    /// Only pack files matching one of these patterns
+    pub include: Vec<String>,
+    /// Never pack files matching one of these patterns
+    pub exclude: Vec<String>,
+}
+
+/// Knobs for [`ShurikenManager::forge_with_options`](crate::manager::ShurikenManager::forge_with_options).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeOptions {
+    /// Which files to pack, on top of `.ninjaignore`
+    pub filter: ForgeFilter,
+    /// Ed25519 secret key to sign the package with, see [`signing`](crate::common::signing)
+    pub sign_key: Option<PathBuf>,
+}
+
+/// What went into a forged `.shuriken` archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeReport {
+    /// Number of files packed
+    pub file_count: usize,
+    /// Total uncompressed size of the packed files, in bytes
+    pub total_size: u64,
+    /// Number of symlinks packed (installing them needs `allow_symlinks`)
+    #[serde(default)]
+    pub link_count: usize,
+    /// Paths left out because they can't be packed, relative to the source
+    #[serde(default)]
+    pub skipped: Vec<PathBuf>,
+}
+
+/// A `.shuriken` package written by [`ShurikenManager::forge`](crate::manager::ShurikenManager::forge).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeOutput {
+    /// Where the package was written
+    pub path: PathBuf,
+    /// Size of the package file, in bytes
+    pub size: u64,
+    /// SHA-256 of the whole package file as lowercase hex, as `sha256sum` prints it
+    pub checksum: String,
+    /// What went into the archive
+    pub report: ForgeReport,
+}
+
+/// Which lines [`ShurikenManager::read_logs`](crate::manager::ShurikenManager::read_logs) returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFilter {
+    /// Only keep lines matching this regex
+    pub grep: Option<String>,
+    /// Only keep lines logged within this long ago
+    pub since: Option<Duration>,
+    /// Only keep the last this many lines left after filtering
+    pub lines: Option<usize>,
+}
+
+/// One log line as a structured record, for `shurikenctl logs --json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// The Shuriken the line came from
+    pub shuriken: String,
+    /// The line's own timestamp, or when it was read if it has none
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// The line as written, without its line ending
+    pub line: String,
+}
+
+/// Outcome of one [`DoctorCheck`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// Nothing to report
+    Pass,
+    /// Worth a look, but doesn't fail the report
+    Warn,
+    /// Broken; fails the report
+    Fail,
+}
+
+/// One finding of [`ShurikenManager::doctor`](crate::manager::ShurikenManager::doctor).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoctorCheck {
+    /// What was checked, e.g. `apache: start script`
+    pub name: String,
+    pub status: CheckStatus,
+    /// What was found, in a form fit for a human
+    pub detail: String,
+}
+
+/// All checks [`ShurikenManager::doctor`](crate::manager::ShurikenManager::doctor) ran.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+    /// `false` if any check failed
+    pub ok: bool,
+}
+
+impl DoctorReport {
+    /// Builds a report, deriving `ok` from the checks.
+    pub fn new(checks: Vec<DoctorCheck>) -> Self {
+        let ok = checks.iter().all(|c| c.status != CheckStatus::Fail);
+        Self { checks, ok }
+    }
+}
+
+/// How an option should be entered, as declared in a Shuriken's `schema.toml`.
+///
+/// Lets frontends render a fitting control (slider, dropdown, toggle)
+/// instead of a plain text box:
+///
+/// ```toml
+/// [port]
+/// type = "number"
+/// min = 1
+/// max = 65535
+///
+/// [mode]
+/// type = "choice"
+/// choices = ["dev", "prod"]
+///
+/// [host]
+/// type = "text"
+/// pattern = "[a-z0-9.-]+"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum InputType {
+    /// Free-form text, optionally required to fully match `pattern`
+    Text {
+        default: Option<String>,
+        description: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+    },
+    /// An integer, optionally bounded
+    Number {
+        min: Option<i64>,
+        max: Option<i64>,
+        step: Option<i64>,
+        default: Option<i64>,
+        description: Option<String>,
+    },
+    /// An on/off switch
+    Bool {
+        default: Option<bool>,
+        description: Option<String>,
+    },
+    /// One value out of a fixed list
+    Choice {
+        choices: Vec<String>,
+        default: Option<String>,
+        description: Option<String>,
+    },
+}
+
+impl InputType {
+    /// Checks `value` against this input type.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the value has the right type and is in range
+    /// - `Err(message)` describing the first problem found
+    pub fn check(&self, value: &FieldValue) -> Result<(), String> {
+        match (self, value) {
+            (InputType::Text { pattern, .. }, FieldValue::String(text)) => {
+                if let Some(pattern) = pattern {
+                    let anchored = Regex::new(&format!("^(?:{})$", pattern))
+                        .map_err(|e| format!("schema pattern is invalid: {}", e))?;
+                    if !anchored.is_match(text) {
+                        return Err(format!("must match the pattern '{}'", pattern));
+                    }
+                }
+                Ok(())
+            }
+            (InputType::Number { min, max, step, .. }, FieldValue::Number(n)) => {
+                if let Some(min) = min
+                    && n < min
+                {
+                    return Err(format!("must be at least {}", min));
+                }
+                if let Some(max) = max
+                    && n > max
+                {
+                    return Err(format!("must be at most {}", max));
+                }
+                if let Some(step) = step
+                    && *step != 0
+                    && (n - min.unwrap_or(0)) % step != 0
+                {
+                    return Err(format!(
+                        "must be {} plus a multiple of {}",
+                        min.unwrap_or(0),
+                        step
+                    ));
+                }
+                Ok(())
+            }
+            (InputType::Bool { .. }, FieldValue::Bool(_)) => Ok(()),
+            (InputType::Choice { choices, .. }, FieldValue::String(choice)) => {
+                if choices.contains(choice) {
+                    Ok(())
+                } else {
+                    Err(format!("must be one of: {}", choices.join(", ")))
+                }
+            }
+            (expected, _) => Err(format!("must be {}", expected.kind())),
+        }
+    }
+
+    /// Describes the kind of value this input type accepts, for error messages.
+    fn kind(&self) -> &'static str {
+        match self {
+            InputType::Text { .. } => "text",
+            InputType::Number { .. } => "a whole number",
+            InputType::Bool { .. } => "true or false",
+            InputType::Choice { .. } => "one of the listed choices",
+        }
+    }
+}
+
+/// An option value that doesn't fit the Shuriken's schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionError {
+    /// The offending option, empty when the problem isn't tied to one option
+    pub option: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl std::fmt::Display for OptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.option.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.option, self.message)
+        }
+    }
+}
+
 /// Platform-aware path that can be different for Windows and Unix systems.
 ///
 /// Allows specifying platform-specific paths in configuration files.
@@ -57,17 +457,56 @@ impl PlatformPath {
     }
 }
 
+/// How a [`FieldValue`] is written into a generated config.
+///
+/// Templates pick one per placeholder with a directive, e.g.
+/// `{{ enabled:on_off }}`; placeholders without one use [`RenderFormat::Raw`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderFormat {
+    /// The same text as [`FieldValue::render`]
+    #[default]
+    Raw,
+    /// Strings in double quotes with `\` and `"` escaped; other values raw
+    Quoted,
+    /// Booleans as `on`/`off`; other values raw
+    BoolOnOff,
+    /// Booleans as `yes`/`no`; other values raw
+    BoolYesNo,
+    /// Booleans as `1`/`0`; other values raw
+    BoolNumeric,
+}
+
+impl RenderFormat {
+    /// Every directive name, in the order they're listed in errors.
+    pub const DIRECTIVES: &'static [&'static str] =
+        &["raw", "quoted", "on_off", "yes_no", "numeric"];
+
+    /// Looks up the format named by a template directive such as `on_off`.
+    pub fn from_directive(directive: &str) -> Option<Self> {
+        match directive {
+            "raw" => Some(RenderFormat::Raw),
+            "quoted" => Some(RenderFormat::Quoted),
+            "on_off" => Some(RenderFormat::BoolOnOff),
+            "yes_no" => Some(RenderFormat::BoolYesNo),
+            "numeric" => Some(RenderFormat::BoolNumeric),
+            _ => None,
+        }
+    }
+}
+
 /// A flexible value type used for configuration options.
 ///
-/// Supports strings, integers, booleans, maps, and arrays.
+/// Supports strings, integers, floats, booleans, maps, and arrays.
 /// Provides methods for accessing and converting values.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum FieldValue {
     /// A string value
     String(String),
     /// An integer value
     Number(i64),
+    /// A floating-point value; whole numbers stay `Number`
+    Float(f64),
     /// A boolean value
     Bool(bool),
     /// A map of field values (for nested configuration)
@@ -100,7 +539,8 @@ impl FieldValue {
 
     /// Renders the value as a string for template substitution.
     ///
-    /// - Strings and numbers are converted directly
+    /// - Strings and numbers are converted directly; whole floats keep
+    ///   their `.0` so they don't read back as integers
     /// - Booleans become "true" or "false"
     /// - Arrays are debug-formatted
     /// - Maps become "[object map]"
@@ -108,12 +548,31 @@ impl FieldValue {
         match self {
             FieldValue::String(s) => s.clone(),
             FieldValue::Number(n) => n.to_string(),
+            FieldValue::Float(f) if f.is_finite() && f.fract() == 0.0 => format!("{:.1}", f),
+            FieldValue::Float(f) => f.to_string(),
             FieldValue::Bool(b) => b.to_string(),
             FieldValue::Array(a) => format!("{:#?}", a).to_string(),
             FieldValue::Map(_) => "[object map]".to_string(),
         }
     }
 
+    /// Renders the value the way `format` asks for.
+    ///
+    /// Formats only change the values they are about (booleans for the
+    /// `Bool*` formats, strings for `Quoted`); everything else is rendered
+    /// as by [`FieldValue::render`].
+    pub fn render_for(&self, format: RenderFormat) -> String {
+        match (self, format) {
+            (FieldValue::String(s), RenderFormat::Quoted) => {
+                format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            (FieldValue::Bool(b), RenderFormat::BoolOnOff) => if *b { "on" } else { "off" }.into(),
+            (FieldValue::Bool(b), RenderFormat::BoolYesNo) => if *b { "yes" } else { "no" }.into(),
+            (FieldValue::Bool(b), RenderFormat::BoolNumeric) => if *b { "1" } else { "0" }.into(),
+            _ => self.render(),
+        }
+    }
+
     /// Attempts to extract a string value.
     ///
     /// # Returns
@@ -140,6 +599,19 @@ impl FieldValue {
         }
     }
 
+    /// Attempts to extract a floating-point value.
+    ///
+    /// # Returns
+    /// - `Some(f64)` if this is a `Float` or `Number` variant
+    /// - `None` otherwise
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            FieldValue::Float(f) => Some(*f),
+            FieldValue::Number(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
     /// Attempts to extract a boolean value.
     ///
     /// # Returns
@@ -157,12 +629,21 @@ impl FieldValue {
 // ---------------- From impls ----------------
 
 impl From<&str> for FieldValue {
+    /// Infers a typed value from raw text.
+    ///
+    /// Quoted text (single or double) is always a string, `true`/`false`
+    /// become booleans, integers become numbers and decimals like `1.5`
+    /// become floats, as do `1.0` and `2e3`. Version-like text such as `3.10`
+    /// or `1.2.3` is kept as a string, as is anything else.
     fn from(val: &str) -> Self {
         let val = val.trim();
 
-        // Try number
-        if let Ok(n) = val.parse::<i64>() {
-            return FieldValue::Number(n);
+        // Try string with quotes
+        if val.len() >= 2
+            && ((val.starts_with('"') && val.ends_with('"'))
+                || (val.starts_with('\'') && val.ends_with('\'')))
+        {
+            return FieldValue::String(val[1..val.len() - 1].to_string());
         }
 
         // Try bool
@@ -173,9 +654,17 @@ impl From<&str> for FieldValue {
             return FieldValue::Bool(false);
         }
 
-        // Try string with quotes
-        if val.starts_with('"') && val.ends_with('"') && val.len() >= 2 {
-            return FieldValue::String(val[1..val.len() - 1].to_string());
+        // Try number
+        if let Ok(n) = val.parse::<i64>() {
+            return FieldValue::Number(n);
+        }
+
+        // Try float, deciding on the text itself so words like `inf` and
+        // versions like `3.10` stay strings
+        if is_decimal_literal(val)
+            && let Ok(f) = val.parse::<f64>()
+        {
+            return FieldValue::Float(f);
         }
 
         // Fallback
@@ -183,6 +672,34 @@ impl From<&str> for FieldValue {
     }
 }
 
+/// Whether `val` is written as a decimal: digits with a fractional part
+/// and/or an exponent (`1.0`, `-2.5`, `2e3`, `1.5E-2`).
+///
+/// A fraction of two or more digits ending in `0`, like `3.10`, reads as a
+/// version rather than a number and is rejected.
+fn is_decimal_literal(val: &str) -> bool {
+    let unsigned = val.strip_prefix(['-', '+']).unwrap_or(val);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole) || (fraction.is_none() && exponent.is_none()) {
+        return false;
+    }
+    if let Some(fraction) = fraction
+        && (!is_digits(fraction) || (fraction.len() > 1 && fraction.ends_with('0')))
+    {
+        return false;
+    }
+    exponent.is_none_or(|exponent| is_digits(exponent.strip_prefix(['-', '+']).unwrap_or(exponent)))
+}
+
 impl From<String> for FieldValue {
     fn from(val: String) -> Self {
         FieldValue::from(val.as_str())
@@ -212,7 +729,7 @@ impl From<toml::Value> for FieldValue {
 
                 FieldValue::Array(arr)
             }
-            toml::Value::Float(f) => FieldValue::String(f.to_string()),
+            toml::Value::Float(f) => FieldValue::Float(f),
             toml::Value::Datetime(dt) => FieldValue::String(dt.to_string()),
         }
     }
@@ -223,6 +740,7 @@ impl From<FieldValue> for toml::Value {
         match f {
             FieldValue::String(s) => Value::String(s.clone()),
             FieldValue::Number(n) => Value::Integer(n),
+            FieldValue::Float(f) => Value::Float(f),
             FieldValue::Bool(b) => Value::Boolean(b),
             FieldValue::Array(a) => {
                 let mut arr: Vec<Value> = Vec::new();
@@ -274,6 +792,45 @@ pub struct ArmoryMetadata {
     pub platform: String,
 }
 
+impl ArmoryMetadata {
+    /// Reads metadata from a TOML manifest, for forging without prompts.
+    ///
+    /// The manifest holds the fields at the top level:
+    ///
+    /// ```toml
+    /// id = "nginx"
+    /// name = "Nginx"
+    /// version = "1.27.0"
+    /// platform = "linux-x86_64"
+    /// authors = ["Jane Doe"]
+    /// ```
+    pub fn from_manifest(manifest: &str) -> Result<Self, String> {
+        toml::from_str(manifest).map_err(|e| format!("Malformed forge manifest: {}", e))
+    }
+}
+
+/// Knobs for [`ShurikenManager::install_with_options`](crate::manager::ShurikenManager::install_with_options).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallOptions {
+    /// Run the package's postinstall script after unpacking (default: true)
+    pub run_postinstall: bool,
+    /// Only accept packages signed with this Ed25519 public key
+    pub verify_key: Option<PathBuf>,
+    /// Reinstall over an existing Shuriken of the same name instead of failing.
+    /// It is stopped first and keeps its `options.toml`.
+    pub replace: bool,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            run_postinstall: true,
+            verify_key: None,
+            replace: false,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum LocalInstallStages {
     Validating,
@@ -1,43 +1,354 @@
 use crate::{
     common::{
         config::{NinjaConfig, ShurikenReference},
+        error::ManagerError,
+        log_buffer,
+        logs::{filter_log, tail_file},
+        metrics::{ShurikenSample, render_openmetrics},
         registry::{Registry, RegistrySources, download_shuriken},
+        self_update::{self, UpdateOutcome},
+        signing,
         traits::Reporter,
-        types::{ArmoryMetadata, FieldValue, InstallStage, ShurikenState},
+        types::{
+            ArmoryMetadata, BulkMode, CheckStatus, DoctorCheck, DoctorReport, FieldValue,
+            ForgeOptions, ForgeOutput, InputType, InstallOptions, InstallStage, LifecycleEvent,
+            LifecycleEventKind, LogFilter, OptionError, ShurikenInfo, ShurikenState,
+            StartOverrides, StateChange,
+        },
     },
     scripting::{NinjaEngine, dsl::DslEngine},
-    shuriken::{Shuriken, ShurikenConfig},
+    shuriken::{RestartPolicy, Shuriken, ShurikenConfig, StartPlan, ToolInfo, ToolOutput},
     utils::{
-        create_tar_gz_bytes, load_shurikens, normalize_path, normalize_shuriken_name, parse_path,
+        ArchiveFormat, ConfigBundle, copy_dir_all, create_tar_gz_bytes, dir_size, is_name_pattern,
+        load_shuriken, load_shurikens, name_matches, normalize_path, normalize_shuriken_name,
+        parse_path, process_alive, process_start_time, read_bulk_options, replace_files,
+        resume_process, retry_with_backoff, suspend_process, take_exit_code, unpack_archive_safely,
+        watch_exit_code,
     },
 };
 use anyhow::{Context, Error, Result};
 use ciborium::{from_reader, ser::into_writer};
 use dirs_next as dirs;
 use either::Either::{self, Left, Right};
-use flate2::read::GzDecoder;
-use futures_util::future::join_all;
+use futures_util::{StreamExt, future::join_all, stream};
 use log::{debug, info, warn};
 use sha2::{Digest, Sha256};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     env, io,
     marker::Send,
     path::{Path, PathBuf},
     str,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
 use tokio::{
     fs::{self, File},
     io::{AsyncReadExt, AsyncWriteExt},
-    sync::{Mutex, RwLock},
+    sync::{Mutex, OwnedMutexGuard, RwLock, broadcast},
 };
 
 const MAGIC_BYTES: &[u8; 6] = b"HSRZEG";
 
+/// How many operations `BulkMode::Parallel` runs at once unless told otherwise.
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// Attempts at creating the manager's directories before giving up.
+const DIR_CREATE_ATTEMPTS: u32 = 5;
+/// Delay before the first retry; doubled after every failed attempt.
+const DIR_CREATE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How often a started Shuriken's process is checked for having exited.
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// A thin wrapper around a spawned process. We keep it simple: the
 /// ManagedProcess owns a `tokio::process::Child` and provides async helpers.
 
+/// How many unread state changes a slow subscriber can fall behind by.
+const STATE_EVENT_CAPACITY: usize = 64;
+
+/// How many lifecycle events are kept per Shuriken.
+pub const HISTORY_CAPACITY: usize = 100;
+
+/// In-process callback run on every state transition with `(name, old, new)`.
+pub type StateListener = dyn Fn(&str, ShurikenState, ShurikenState) + Send + Sync;
+
+/// Broadcasts Shuriken state changes to anyone subscribed.
+///
+/// Sending never blocks and is a no-op when nobody is listening. An optional
+/// [`StateListener`] is called synchronously for each change as well. The
+/// last [`HISTORY_CAPACITY`] lifecycle events of each Shuriken are kept in memory.
+#[derive(Clone)]
+pub struct StateEvents {
+    sender: broadcast::Sender<StateChange>,
+    listener: Arc<std::sync::RwLock<Option<Arc<StateListener>>>>,
+    history: Arc<std::sync::Mutex<HashMap<String, VecDeque<LifecycleEvent>>>>,
+    restarts: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+}
+
+impl Default for StateEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(STATE_EVENT_CAPACITY).0,
+            listener: Arc::default(),
+            history: Arc::default(),
+            restarts: Arc::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for StateEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let has_listener = self
+            .listener
+            .read()
+            .map(|listener| listener.is_some())
+            .unwrap_or(false);
+        f.debug_struct("StateEvents")
+            .field("subscribers", &self.sender.receiver_count())
+            .field("has_listener", &has_listener)
+            .finish()
+    }
+}
+
+impl StateEvents {
+    /// Returns a receiver for all state changes from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<StateChange> {
+        self.sender.subscribe()
+    }
+
+    /// Number of receivers currently subscribed.
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Replaces the in-process listener.
+    pub fn set_listener(&self, listener: Arc<StateListener>) {
+        if let Ok(mut slot) = self.listener.write() {
+            *slot = Some(listener);
+        }
+    }
+
+    /// Appends an event to `name`'s history, dropping the oldest when full.
+    pub fn record(&self, name: &str, event: LifecycleEventKind, detail: Option<String>) {
+        let mut history = self
+            .history
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let events = history.entry(name.to_string()).or_default();
+        if events.len() == HISTORY_CAPACITY {
+            events.pop_front();
+        }
+        events.push_back(LifecycleEvent {
+            timestamp: chrono::Utc::now(),
+            event,
+            detail,
+        });
+    }
+
+    /// Counts a restart of `name` made by its [`RestartPolicy`].
+    pub fn record_restart(&self, name: &str) {
+        *self
+            .restarts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .entry(name.to_string())
+            .or_default() += 1;
+    }
+
+    /// How often `name` was restarted by its [`RestartPolicy`].
+    ///
+    /// Starting it again by hand doesn't count. Unlike the history this is
+    /// never trimmed, but it only counts restarts made by this process.
+    pub fn restarts(&self, name: &str) -> u64 {
+        self.restarts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(name)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns up to `limit` of `name`'s most recent events, oldest first.
+    pub fn history(&self, name: &str, limit: usize) -> Vec<LifecycleEvent> {
+        let history = self
+            .history
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let Some(events) = history.get(name) else {
+            return Vec::new();
+        };
+        events
+            .iter()
+            .skip(events.len().saturating_sub(limit))
+            .cloned()
+            .collect()
+    }
+
+    fn send(&self, name: &str, old_state: ShurikenState, new_state: ShurikenState) {
+        let detail = match &new_state {
+            ShurikenState::Error(message) => Some(message.clone()),
+            ShurikenState::Crashed(code) => Some(format!("exited with code {}", code)),
+            _ => None,
+        };
+        self.record(
+            name,
+            LifecycleEventKind::from_transition(&old_state, &new_state),
+            detail,
+        );
+
+        // Clone the listener out so it can replace itself without deadlocking
+        let listener = self.listener.read().ok().and_then(|slot| slot.clone());
+        if let Some(listener) = listener {
+            listener(name, old_state, new_state.clone());
+        }
+
+        let _ = self.sender.send(StateChange {
+            name: name.to_string(),
+            state: new_state,
+            removed: false,
+        });
+    }
+
+    /// Forgets `name`'s history and tells subscribers it is gone.
+    ///
+    /// The listener isn't called, as there is no new state to hand it.
+    fn send_removed(&self, name: &str, last_state: ShurikenState) {
+        self.history
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .remove(name);
+        self.restarts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .remove(name);
+
+        let _ = self.sender.send(StateChange {
+            name: name.to_string(),
+            state: last_state,
+            removed: true,
+        });
+    }
+}
+
+/// Per-Shuriken locks that serialize lifecycle operations.
+///
+/// Starting, stopping, pausing or resuming the same Shuriken waits for any
+/// operation already in flight, so two callers can't both spawn it. Different
+/// Shurikens don't contend.
+#[derive(Clone, Debug, Default)]
+pub struct OperationLocks {
+    locks: Arc<std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl OperationLocks {
+    /// Waits for and takes the lock for `name`, held until the guard drops.
+    pub async fn acquire(&self, name: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self
+                .locks
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            locks.entry(name.to_string()).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// Last activity of running on-demand Shurikens.
+///
+/// A Shuriken with an `idle-timeout` is armed when it starts and stopped once
+/// nothing has touched it for that long. Each arming gets a new generation so
+/// a watcher left over from an earlier run can tell it's stale.
+#[derive(Clone, Debug, Default)]
+pub struct IdleTimers {
+    timers: Arc<std::sync::Mutex<HashMap<String, (Instant, u64)>>>,
+    generations: Arc<AtomicU64>,
+}
+
+impl IdleTimers {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Instant, u64)>> {
+        self.timers
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Starts the idle timer for `name` and returns its generation.
+    fn arm(&self, name: &str) -> u64 {
+        let generation = self.generations.fetch_add(1, Ordering::Relaxed);
+        self.lock()
+            .insert(name.to_string(), (Instant::now(), generation));
+        generation
+    }
+
+    fn disarm(&self, name: &str) {
+        self.lock().remove(name);
+    }
+
+    /// Resets the idle timer for `name`.
+    ///
+    /// # Returns
+    /// - `true` if `name` has a running idle timer
+    /// - `false` if it isn't on-demand or isn't running
+    pub fn touch(&self, name: &str) -> bool {
+        match self.lock().get_mut(name) {
+            Some((last_activity, _)) => {
+                *last_activity = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// When `name` becomes idle, or `None` once `generation` is no longer armed.
+    fn deadline(&self, name: &str, generation: u64, timeout: Duration) -> Option<Instant> {
+        self.lock()
+            .get(name)
+            .filter(|(_, armed)| *armed == generation)
+            .map(|(last_activity, _)| *last_activity + timeout)
+    }
+}
+
+/// Serialized snapshot of every Shuriken's state, shared by repeated lists.
+///
+/// Dropped whenever a state changes or the set of Shurikens does. Each drop
+/// bumps the generation, so a snapshot built from states read before the
+/// drop is never stored.
+#[derive(Clone, Debug, Default)]
+pub struct StatesCache {
+    snapshot: Arc<std::sync::Mutex<Option<Arc<str>>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl StatesCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Arc<str>>> {
+        self.snapshot
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn get(&self) -> Option<Arc<str>> {
+        self.lock().clone()
+    }
+
+    /// Stores `snapshot` unless the cache was invalidated since `generation`.
+    fn store(&self, generation: u64, snapshot: Arc<str>) {
+        let mut cached = self.lock();
+        if self.generation.load(Ordering::Acquire) == generation {
+            *cached = Some(snapshot);
+        }
+    }
+
+    fn invalidate(&self) {
+        let mut cached = self.lock();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        *cached = None;
+    }
+}
+
 /// The main orchestrator for managing Shurikens and their lifecycle.
 ///
 /// `ShurikenManager` handles all operations related to Shuriken services,
@@ -49,12 +360,20 @@ const MAGIC_BYTES: &[u8; 6] = b"HSRZEG";
 /// - `engine`: Lua scripting engine for executing Shuriken scripts
 /// - `shurikens`: Cached map of loaded Shurikens by name
 /// - `config`: Global Ninja configuration including registries
+/// - `state_events`: Broadcast of Shuriken state changes
+/// - `operation_locks`: Serializes lifecycle operations per Shuriken
+/// - `idle_timers`: Last activity of on-demand Shurikens
+/// - `states_cache`: Serialized states reused by [`ShurikenManager::list_states_json`]
 #[derive(Clone, Debug)]
 pub struct ShurikenManager {
     pub root_path: PathBuf,
     pub engine: Arc<Mutex<NinjaEngine>>,
     pub shurikens: Arc<RwLock<HashMap<String, Shuriken>>>,
     pub config: Arc<RwLock<crate::common::config::NinjaConfig>>,
+    pub state_events: StateEvents,
+    pub operation_locks: OperationLocks,
+    pub idle_timers: IdleTimers,
+    pub states_cache: StatesCache,
 }
 
 impl ShurikenManager {
@@ -74,18 +393,70 @@ impl ShurikenManager {
             .ok_or_else(|| Error::msg("Could not find home directory"))?
             .join(".ninja");
 
-        fs::create_dir_all(&exe_dir).await?;
+        Self::with_root(exe_dir).await
+    }
 
-        let shurikens_dir = exe_dir.join("shurikens");
-        let projects_dir = exe_dir.join("projects");
+    /// Creates a `ShurikenManager` rooted at a custom data directory.
+    ///
+    /// Behaves like [`ShurikenManager::new`] but uses `root_path` instead of
+    /// `~/.ninja`, which lets embedders and tests keep an isolated data dir.
+    /// A relative `root_path` is resolved against the current directory
+    /// once, here; nothing later depends on the process-wide cwd, which
+    /// concurrent starts couldn't share safely.
+    ///
+    /// # Arguments
+    /// - `root_path`: Directory to store shurikens, projects and config in
+    ///
+    /// # Returns
+    /// - `Ok(ShurikenManager)` on success
+    /// - `Err` if the directory cannot be created or initialization fails
+    pub async fn with_root(root_path: impl Into<PathBuf>) -> Result<Self> {
+        Self::with_root_creating(root_path, fs::create_dir_all).await
+    }
 
-        // Create shurikens directory if it doesn't exist
-        if !shurikens_dir.exists() {
-            fs::create_dir(&shurikens_dir).await?;
-        }
+    /// Creates a manager like [`ShurikenManager::with_root`], making its
+    /// directories with `create_dir`, so tests can make it fail.
+    #[cfg(feature = "testing")]
+    pub async fn with_root_using<F, Fut>(
+        root_path: impl Into<PathBuf>,
+        create_dir: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(PathBuf) -> Fut,
+        Fut: Future<Output = io::Result<()>>,
+    {
+        Self::with_root_creating(root_path, create_dir).await
+    }
 
-        if !projects_dir.exists() {
-            fs::create_dir(&projects_dir).await?;
+    async fn with_root_creating<F, Fut>(
+        root_path: impl Into<PathBuf>,
+        mut create_dir: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(PathBuf) -> Fut,
+        Fut: Future<Output = io::Result<()>>,
+    {
+        let exe_dir = root_path.into();
+        let exe_dir = std::path::absolute(&exe_dir)
+            .with_context(|| format!("Failed to resolve data directory {}", exe_dir.display()))?;
+
+        // Slow or network filesystems can fail transiently (e.g. while mounting)
+        for dir in [
+            exe_dir.clone(),
+            exe_dir.join("shurikens"),
+            exe_dir.join("projects"),
+        ] {
+            retry_with_backoff(DIR_CREATE_ATTEMPTS, DIR_CREATE_BACKOFF, || {
+                create_dir(dir.clone())
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to create {} after {} attempts",
+                    dir.display(),
+                    DIR_CREATE_ATTEMPTS
+                )
+            })?;
         }
 
         let shurikens = load_shurikens(&exe_dir).await?;
@@ -110,17 +481,78 @@ impl ShurikenManager {
             engine: Arc::new(Mutex::new(engine)),
             shurikens: Arc::new(RwLock::new(shurikens)),
             config,
+            state_events: StateEvents::default(),
+            operation_locks: OperationLocks::default(),
+            idle_timers: IdleTimers::default(),
+            states_cache: StatesCache::default(),
         })
     }
 
     /// Updates the state of a Shuriken (internal helper).
     ///
+    /// This is the only place a Shuriken's state changes. The transition is
+    /// checked against the state the Shuriken is in right now, read under its
+    /// state lock, not against whatever the caller saw earlier. Staying in
+    /// the same state changes nothing, so nothing is broadcast.
+    ///
     /// # Arguments
+    /// - `name`: The name the Shuriken is registered under
     /// - `shuriken`: The Shuriken instance to update
     /// - `new_state`: The new state to set
-    async fn update_state(&self, shuriken: Shuriken, new_state: ShurikenState) {
+    ///
+    /// # Returns
+    /// - `Ok(())` once the state is set and broadcast
+    /// - `Err(ManagerError::IllegalTransition)` if the current state can't
+    ///   move into `new_state`; the state is left untouched
+    async fn update_state(
+        &self,
+        name: &str,
+        shuriken: &Shuriken,
+        new_state: ShurikenState,
+    ) -> Result<(), ManagerError> {
         let mut state_lock = shuriken.state.lock().await;
-        *state_lock = new_state;
+        let old_state = state_lock.clone();
+        if !old_state.can_transition_to(&new_state) {
+            warn!(
+                "Rejected illegal state transition of '{}': {:?} -> {:?}",
+                name, old_state, new_state
+            );
+            return Err(ManagerError::IllegalTransition(
+                name.to_string(),
+                old_state,
+                new_state,
+            ));
+        }
+        if old_state == new_state {
+            return Ok(());
+        }
+
+        *state_lock = new_state.clone();
+        drop(state_lock);
+        self.states_cache.invalidate();
+        self.state_events.send(name, old_state, new_state);
+        Ok(())
+    }
+
+    /// Sets a callback run in-process on every state transition.
+    ///
+    /// The callback receives `(name, old, new)`. Only one listener is kept;
+    /// setting a new one replaces the previous. For several consumers use
+    /// [`ShurikenManager::subscribe_states`] instead.
+    pub fn set_state_listener(
+        &self,
+        listener: impl Fn(&str, ShurikenState, ShurikenState) + Send + Sync + 'static,
+    ) {
+        self.state_events.set_listener(Arc::new(listener));
+    }
+
+    /// Subscribes to Shuriken state changes.
+    ///
+    /// Every successful start/stop is delivered as a [`StateChange`], as is
+    /// every Shuriken a [`refresh`](ShurikenManager::refresh) finds gone from
+    /// disk, with `removed` set.
+    pub fn subscribe_states(&self) -> broadcast::Receiver<StateChange> {
+        self.state_events.subscribe()
     }
 
     /// Starts a Shuriken by name.
@@ -135,19 +567,174 @@ impl ShurikenManager {
     /// - `Ok(())` if startup completed successfully
     /// - `Err` if Shuriken not found, script execution fails, or startup errors occur
     pub async fn start(&self, name: &str) -> Result<()> {
+        self.start_with_timeout(name, None).await
+    }
+
+    /// Resolves what starting a Shuriken would run, without running it.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    ///
+    /// # Returns
+    /// - `Ok(StartPlan)` with resolved paths and any problems found
+    /// - `Err` if the Shuriken doesn't exist
+    pub async fn dry_start(&self, name: &str) -> Result<StartPlan> {
+        self.dry_start_profile(name, None).await
+    }
+
+    /// Resolves what starting a Shuriken with `profile` would run, without running it.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `profile`: The profile to start with, or `None` for the base definition
+    ///
+    /// # Returns
+    /// - `Ok(StartPlan)` with resolved paths and any problems found
+    /// - `Err` if the Shuriken or profile doesn't exist
+    pub async fn dry_start_profile(&self, name: &str, profile: Option<&str>) -> Result<StartPlan> {
+        let overrides = StartOverrides {
+            profile: profile.map(String::from),
+            ..Default::default()
+        };
+        self.dry_start_with_overrides(name, &overrides).await
+    }
+
+    /// Resolves what [`ShurikenManager::start_with_overrides`] would run, without running it.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `overrides`: The profile and environment the start would use
+    ///
+    /// # Returns
+    /// - `Ok(StartPlan)` with resolved paths, environment and any problems found
+    /// - `Err` if the Shuriken or profile doesn't exist
+    pub async fn dry_start_with_overrides(
+        &self,
+        name: &str,
+        overrides: &StartOverrides,
+    ) -> Result<StartPlan> {
+        let normalized_name = normalize_shuriken_name(name);
+        let mut shuriken = self.get(normalized_name.clone()).await?;
+        if let Some(profile) = &overrides.profile {
+            shuriken = shuriken.with_profile(profile)?;
+        }
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+        shuriken.dry_start_with_env(&shuriken_dir, &overrides.env)
+    }
+
+    /// Starts a Shuriken using one of the profiles its manifest declares.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to start
+    /// - `profile`: The profile whose start definition to use
+    ///
+    /// # Returns
+    /// - `Ok(())` if the Shuriken started
+    /// - `Err` if the profile doesn't exist or startup fails
+    pub async fn start_profile(&self, name: &str, profile: &str) -> Result<()> {
+        self.start_profile_with_timeout(name, Some(profile), None)
+            .await
+    }
+
+    /// Starts a Shuriken and waits for it to become healthy.
+    ///
+    /// The wait uses `timeout` if given, otherwise the manifest's `start-timeout`.
+    /// With neither set, this returns as soon as the startup script finishes.
+    /// If the Shuriken isn't healthy in time it is stopped again.
+    ///
+    /// Concurrent starts of the same Shuriken run one at a time, so only the
+    /// first spawns it and the rest fail with [`ManagerError::AlreadyRunning`].
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to start
+    /// - `timeout`: Optional override for the health-check deadline
+    ///
+    /// # Returns
+    /// - `Ok(())` if the Shuriken started (and became healthy, when waiting)
+    /// - `Err` if startup fails or the health check times out
+    pub async fn start_with_timeout(&self, name: &str, timeout: Option<Duration>) -> Result<()> {
+        self.start_profile_with_timeout(name, None, timeout).await
+    }
+
+    /// Starts a Shuriken with an optional profile and waits for it to become healthy.
+    ///
+    /// Behaves like [`ShurikenManager::start_with_timeout`], except that a
+    /// given `profile` replaces the manifest's base start definition.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to start
+    /// - `profile`: The profile to start with, or `None` for the base definition
+    /// - `timeout`: Optional override for the health-check deadline
+    ///
+    /// # Returns
+    /// - `Ok(())` if the Shuriken started (and became healthy, when waiting)
+    /// - `Err` if the profile doesn't exist, startup fails or the health check times out
+    pub async fn start_profile_with_timeout(
+        &self,
+        name: &str,
+        profile: Option<&str>,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        let overrides = StartOverrides {
+            profile: profile.map(String::from),
+            timeout,
+            ..Default::default()
+        };
+        self.start_with_overrides(name, &overrides).await
+    }
+
+    /// Starts a Shuriken with per-start overrides and waits for it to become healthy.
+    ///
+    /// Behaves like [`ShurikenManager::start_profile_with_timeout`]; the
+    /// `env` overrides are additionally set for every process the start
+    /// script spawns, including on restarts.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to start
+    /// - `overrides`: Profile, timeout and environment for this start
+    ///
+    /// # Returns
+    /// - `Ok(())` if the Shuriken started (and became healthy, when waiting)
+    /// - `Err` if the profile doesn't exist, startup fails or the health check times out
+    pub async fn start_with_overrides(&self, name: &str, overrides: &StartOverrides) -> Result<()> {
+        let _guard = self
+            .operation_locks
+            .acquire(&normalize_shuriken_name(name))
+            .await;
+        self.start_locked(name, overrides, 0).await
+    }
+
+    /// Starts a Shuriken; the caller must hold its operation lock.
+    ///
+    /// `restarts` counts the restarts its [`RestartPolicy`] already made in a
+    /// row; it is 0 for every start that wasn't one.
+    async fn start_locked(
+        &self,
+        name: &str,
+        overrides: &StartOverrides,
+        restarts: u32,
+    ) -> Result<()> {
         let normalized_name = normalize_shuriken_name(name);
         info!("Starting shuriken: {}", name);
 
         let shurikens = self.shurikens.read().await;
-        let shuriken = shurikens
+        let mut shuriken = shurikens
             .get(&normalized_name)
             .ok_or_else(|| {
                 warn!("Shuriken not found: {}", name);
-                anyhow::Error::msg(format!("No such shuriken: {}", name))
+                ManagerError::NotFound(name.to_string())
             })?
             .clone();
         drop(shurikens);
 
+        if let Some(profile) = &overrides.profile {
+            debug!(
+                "Using profile '{}' for shuriken: {}",
+                profile, normalized_name
+            );
+            shuriken = shuriken.with_profile(profile)?;
+        }
+
         let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
         if !shuriken_dir.exists() {
             warn!("Shuriken directory not found: {}", shuriken_dir.display());
@@ -157,59 +744,314 @@ impl ShurikenManager {
             )));
         }
 
+        let old_state = shuriken.state.lock().await.clone();
+        if matches!(old_state, ShurikenState::Running | ShurikenState::Paused) {
+            let Some(pid) = self.stale_lock(&normalized_name).await else {
+                return Err(ManagerError::AlreadyRunning(normalized_name).into());
+            };
+            warn!(
+                "Removing stale lockfile of '{}' (PID {} is gone)",
+                normalized_name, pid
+            );
+            self.clear_stale_lock(&normalized_name).await?;
+        }
+
         debug!("Starting process for shuriken: {}", normalized_name);
-        if let Err(e) = shuriken
-            .start(
-                &*self.engine.lock().await,
-                &shuriken_dir,
-                Some(self.clone()),
-            )
-            .await
-        {
+        // Wait before taking the engine lock, so what we wait on can start
+        let started = match shuriken.wait_until_ready(&shuriken_dir).await {
+            Ok(()) => {
+                shuriken
+                    .start_with_env(
+                        &*self.engine.lock().await,
+                        &shuriken_dir,
+                        Some(self.clone()),
+                        &overrides.env,
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = started {
             warn!("Failed to start shuriken '{}': {}", name, e);
+            self.state_events.record(
+                &normalized_name,
+                LifecycleEventKind::Failed,
+                Some(e.to_string()),
+            );
             return Err(anyhow::Error::msg(format!(
                 "Failed to start shuriken '{}': {}",
                 name, e
             )));
         }
 
-        self.update_state(shuriken, ShurikenState::Running).await;
+        if let Some(delay) = shuriken.metadata.start_delay {
+            debug!("Giving {} {}s to come up", normalized_name, delay);
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+        }
+
+        self.update_state(&normalized_name, &shuriken, ShurikenState::Running)
+            .await?;
+
+        let timeout = overrides
+            .timeout
+            .or(shuriken.metadata.start_timeout.map(Duration::from_secs));
+        if let Some(timeout) = timeout
+            && let Err(e) = self.wait_until_healthy(name, &shuriken, timeout).await
+        {
+            self.state_events.record(
+                &normalized_name,
+                LifecycleEventKind::Failed,
+                Some(e.to_string()),
+            );
+            return Err(e);
+        }
+
+        if let Some(idle_timeout) = shuriken.metadata.idle_timeout {
+            self.watch_idle(&normalized_name, Duration::from_secs(idle_timeout));
+        }
+        if let Some(pid) = shuriken.pid(&shuriken_dir).await {
+            self.watch_process(&normalized_name, pid, overrides.clone(), restarts);
+        }
+
         info!("Successfully started shuriken: {}", name);
         Ok(())
     }
 
-    /// Reloads all Shurikens from disk.
-    ///
-    /// Rescans the ~/.ninja/shurikens directory and updates the in-memory cache.
-    /// Useful after manual file changes or to get latest state from disk.
+    /// Stops an on-demand Shuriken once it has gone `timeout` without activity.
     ///
-    /// # Returns
-    /// - `Ok(())` on success
-    /// - `Err` if file system operations fail
-    pub async fn refresh(&self) -> Result<()> {
-        info!("Refreshing shurikens from disk");
-        let new_shurikens = load_shurikens(&self.root_path).await?;
-        let count = new_shurikens.len();
-        *self.shurikens.write().await = new_shurikens;
-        info!("Shuriken manager refreshed. Found {} shurikens.", count);
-        Ok(())
+    /// Activity is reported through [`ShurikenManager::touch`]. The watcher
+    /// exits on its own when the Shuriken is stopped or started again.
+    fn watch_idle(&self, name: &str, timeout: Duration) {
+        let generation = self.idle_timers.arm(name);
+        let manager = self.clone();
+        let name = name.to_string();
+        debug!("Watching '{}' for {:?} of inactivity", name, timeout);
+
+        tokio::spawn(async move {
+            while let Some(deadline) = manager.idle_timers.deadline(&name, generation, timeout) {
+                if Instant::now() < deadline {
+                    tokio::time::sleep_until(deadline.into()).await;
+                    continue;
+                }
+
+                // It may have been touched, stopped or restarted while we
+                // waited for the lock; look again before stopping it
+                let _guard = manager.operation_locks.acquire(&name).await;
+                match manager.idle_timers.deadline(&name, generation, timeout) {
+                    Some(deadline) if Instant::now() >= deadline => {}
+                    Some(_) => continue,
+                    None => break,
+                }
+                let Ok(shuriken) = manager.get(name.clone()).await else {
+                    break;
+                };
+                let state = shuriken.state.lock().await.clone();
+                if !matches!(state, ShurikenState::Running | ShurikenState::Paused) {
+                    break;
+                }
+
+                info!("Shuriken '{}' was idle for {:?}, stopping", name, timeout);
+                if let Err(e) = manager.stop_locked(&name).await {
+                    warn!("Failed to stop idle shuriken '{}': {}", name, e);
+                }
+                break;
+            }
+        });
     }
 
-    /// Configures a Shuriken using its configuration script.
+    /// Notices when the process a Shuriken was started with exits on its own.
     ///
-    /// Executes the Shuriken's `post_config` function to apply configuration settings.
-    /// Configuration values are templated and written to the Shuriken's config file.
+    /// Polls `pid` once a second. When it is gone while the lockfile still
+    /// records it, the lockfile is removed and the Shuriken becomes `Idle`
+    /// after a clean exit or `Crashed` with the exit code otherwise. Its
+    /// [`RestartPolicy`] then decides whether it is started again, with the
+    /// same `overrides`. A process that stayed up for
+    /// [`STABLE_RUN`](crate::shuriken::STABLE_RUN) resets the count of
+    /// restarts in a row. The watcher exits on its own once the
+    /// Shuriken is stopped or restarted by hand.
+    fn watch_process(&self, name: &str, pid: u32, overrides: StartOverrides, restarts: u32) {
+        let manager = self.clone();
+        let name = name.to_string();
+        debug!("Watching process {} of '{}'", pid, name);
+        watch_exit_code(pid);
+
+        tokio::spawn(async move {
+            let shuriken_dir = manager.root_path.join("shurikens").join(&name);
+            let lock_path = shuriken_dir.join(".ninja").join("shuriken.lck");
+            let started = Instant::now();
+            loop {
+                tokio::time::sleep(PROCESS_POLL_INTERVAL).await;
+                if process_alive(pid) {
+                    continue;
+                }
+                // Collected first, so the code isn't kept however this ends
+                let code = take_exit_code(pid).unwrap_or(-1);
+
+                // Stopping also ends the process; wait for it and look again
+                let guard = manager.operation_locks.acquire(&name).await;
+                let Ok(shuriken) = manager.get(name.clone()).await else {
+                    break;
+                };
+                if shuriken.pid(&shuriken_dir).await != Some(pid) {
+                    break;
+                }
+
+                let new_state = if code == 0 {
+                    info!("Process {} of '{}' exited", pid, name);
+                    ShurikenState::Idle
+                } else {
+                    warn!("Process {} of '{}' crashed with code {}", pid, name, code);
+                    ShurikenState::Crashed(code)
+                };
+                if let Err(e) = fs::remove_file(&lock_path).await {
+                    warn!("Failed to remove lockfile of '{}': {}", name, e);
+                }
+                manager.idle_timers.disarm(&name);
+                if manager
+                    .update_state(&name, &shuriken, new_state.clone())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+
+                let policy = shuriken.metadata.restart.unwrap_or_default();
+                let attempt = RestartPolicy::next_attempt(restarts, started.elapsed());
+                let Some(delay) = policy.restart_delay(code, attempt) else {
+                    if code != 0 && matches!(policy, RestartPolicy::OnFailure { .. }) {
+                        warn!("Giving up on '{}' after {} restart(s)", name, attempt - 1);
+                    }
+                    break;
+                };
+
+                warn!(
+                    "Restarting '{}' in {:?} (restart {} in a row)",
+                    name, delay, attempt
+                );
+                drop(guard);
+                tokio::time::sleep(delay).await;
+
+                // Leave it alone if it was started or stopped by hand meanwhile
+                let _guard = manager.operation_locks.acquire(&name).await;
+                let Ok(shuriken) = manager.get(name.clone()).await else {
+                    break;
+                };
+                if *shuriken.state.lock().await != new_state || lock_path.exists() {
+                    break;
+                }
+                match manager.start_locked(&name, &overrides, attempt).await {
+                    Ok(()) => manager.state_events.record_restart(&name),
+                    Err(e) => warn!("Failed to restart '{}': {}", name, e),
+                }
+                break;
+            }
+        });
+    }
+
+    /// Records activity on a running Shuriken, resetting its idle timer.
     ///
-    /// # Arguments
-    /// - `name`: The name of the Shuriken to configure
+    /// Only Shurikens with an `idle-timeout` have a timer; for others this
+    /// just checks that they're running.
     ///
     /// # Returns
-    /// - `Ok(())` if configuration completed successfully
-    /// - `Err` if Shuriken not found or configuration fails
-    pub async fn configure_shuriken(&self, name: &str) -> Result<()> {
+    /// - `Ok(())` if the Shuriken is running
+    /// - `Err` if it isn't installed or isn't running
+    pub async fn touch(&self, name: &str) -> Result<()> {
         let normalized_name = normalize_shuriken_name(name);
-        info!(
-            "Configuring shuriken: {} with normalized name: {}",
+        let shuriken = self.get(normalized_name.clone()).await?;
+        if *shuriken.state.lock().await != ShurikenState::Running {
+            return Err(ManagerError::NotRunning(normalized_name).into());
+        }
+
+        if self.idle_timers.touch(&normalized_name) {
+            debug!("Reset idle timer of '{}'", normalized_name);
+        }
+        Ok(())
+    }
+
+    /// Polls a freshly started Shuriken until it is healthy or `timeout` elapses.
+    ///
+    /// On timeout the Shuriken is stopped so no half-started service is left behind.
+    async fn wait_until_healthy(
+        &self,
+        name: &str,
+        shuriken: &Shuriken,
+        timeout: Duration,
+    ) -> Result<()> {
+        debug!("Waiting up to {:?} for shuriken '{}'", timeout, name);
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            if shuriken.is_healthy().await {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+
+        warn!(
+            "Shuriken '{}' not healthy after {:?}, stopping",
+            name, timeout
+        );
+        // The caller already holds this Shuriken's operation lock
+        if let Err(e) = self.stop_locked(name).await {
+            warn!("Failed to stop unhealthy shuriken '{}': {}", name, e);
+        }
+
+        Err(anyhow::Error::msg(format!(
+            "Shuriken '{}' did not become healthy within {}s",
+            name,
+            timeout.as_secs()
+        )))
+    }
+
+    /// Reloads all Shurikens from disk.
+    ///
+    /// Rescans the ~/.ninja/shurikens directory and updates the in-memory cache.
+    /// Useful after manual file changes or to get latest state from disk.
+    /// Shurikens that are no longer on disk are announced to state subscribers
+    /// as removed.
+    ///
+    /// # Returns
+    /// - `Ok(())` on success
+    /// - `Err` if file system operations fail
+    pub async fn refresh(&self) -> Result<()> {
+        info!("Refreshing shurikens from disk");
+        let new_shurikens = load_shurikens(&self.root_path).await?;
+        let count = new_shurikens.len();
+        let mut shurikens = self.shurikens.write().await;
+        let old_shurikens = std::mem::replace(&mut *shurikens, new_shurikens);
+        let mut removed: Vec<_> = old_shurikens
+            .into_iter()
+            .filter(|(name, _)| !shurikens.contains_key(name))
+            .collect();
+        drop(shurikens);
+        self.states_cache.invalidate();
+
+        removed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, shuriken) in removed {
+            info!("Shuriken '{}' is no longer on disk", name);
+            let last_state = shuriken.state.lock().await.clone();
+            self.state_events.send_removed(&name, last_state);
+        }
+        info!("Shuriken manager refreshed. Found {} shurikens.", count);
+        Ok(())
+    }
+
+    /// Configures a Shuriken using its configuration script.
+    ///
+    /// Executes the Shuriken's `post_config` function to apply configuration settings.
+    /// Configuration values are templated and written to the Shuriken's config file.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to configure
+    ///
+    /// # Returns
+    /// - `Ok(())` if configuration completed successfully
+    /// - `Err` if Shuriken not found or configuration fails
+    pub async fn configure_shuriken(&self, name: &str) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        info!(
+            "Configuring shuriken: {} with normalized name: {}",
             name, normalized_name
         );
         let partial_shuriken = &self.shurikens.write().await;
@@ -253,6 +1095,155 @@ impl ShurikenManager {
         Ok(())
     }
 
+    /// Checks whether a Shuriken's lockfile was left behind by a dead process.
+    ///
+    /// # Returns
+    /// - `Some(pid)` if the lockfile records a PID that is no longer alive
+    /// - `None` if there's no lockfile, no recorded PID, or the process is alive
+    pub async fn stale_lock(&self, name: &str) -> Option<u32> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.shurikens.read().await.get(&normalized_name).cloned()?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+        shuriken
+            .pid(&shuriken_dir)
+            .await
+            .filter(|pid| !process_alive(*pid))
+    }
+
+    /// Checks the installation for problems that would stop Shurikens from running.
+    ///
+    /// Looks at the `shurikens` directory and, for every installed Shuriken,
+    /// whether its start script is in place and whether it left a stale
+    /// lockfile behind. Nothing is started or changed.
+    pub async fn doctor(&self) -> DoctorReport {
+        let mut checks = Vec::new();
+
+        let shurikens_dir = self.root_path.join("shurikens");
+        checks.push(if shurikens_dir.is_dir() {
+            DoctorCheck {
+                name: "shurikens directory".into(),
+                status: CheckStatus::Pass,
+                detail: shurikens_dir.display().to_string(),
+            }
+        } else {
+            DoctorCheck {
+                name: "shurikens directory".into(),
+                status: CheckStatus::Fail,
+                detail: format!("{} does not exist", shurikens_dir.display()),
+            }
+        });
+
+        let mut shurikens: Vec<(String, Shuriken)> = self
+            .shurikens
+            .read()
+            .await
+            .iter()
+            .map(|(name, shuriken)| (name.clone(), shuriken.clone()))
+            .collect();
+        shurikens.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (name, shuriken) in shurikens {
+            // Only daemons are started, other types have nothing to check
+            if shuriken.metadata.shuriken_type == "daemon" {
+                let (status, detail) = match shuriken.dry_start(&shurikens_dir.join(&name)) {
+                    Ok(plan) if plan.problems.is_empty() => (
+                        CheckStatus::Pass,
+                        plan.compiled_path
+                            .map(|path| path.display().to_string())
+                            .unwrap_or_default(),
+                    ),
+                    Ok(plan) => (CheckStatus::Fail, plan.problems.join("; ")),
+                    Err(e) => (CheckStatus::Fail, e.to_string()),
+                };
+                checks.push(DoctorCheck {
+                    name: format!("{}: start script", name),
+                    status,
+                    detail,
+                });
+            }
+
+            let (status, detail) = match self.stale_lock(&name).await {
+                Some(pid) => (
+                    CheckStatus::Warn,
+                    format!("stale lockfile left by PID {}, clear it with lockpick", pid),
+                ),
+                None => (CheckStatus::Pass, "no stale lockfile".into()),
+            };
+            checks.push(DoctorCheck {
+                name: format!("{}: lockfile", name),
+                status,
+                detail,
+            });
+        }
+
+        DoctorReport::new(checks)
+    }
+
+    /// Takes over a Shuriken's process that was started outside Ninja.
+    ///
+    /// Writes a lockfile recording `pid` and the time the process started,
+    /// so the Shuriken is reported `Running` and can be stopped or paused
+    /// like one Ninja started itself.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `pid`: The running process to adopt
+    ///
+    /// # Returns
+    /// - `Ok(())` if the process was adopted
+    /// - `Err` if the Shuriken doesn't exist, already has a lockfile, or `pid` isn't alive
+    pub async fn adopt(&self, name: &str, pid: u32) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let _guard = self.operation_locks.acquire(&normalized_name).await;
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+
+        if shuriken_dir.join(".ninja").join("shuriken.lck").exists() {
+            return Err(ManagerError::AlreadyRunning(normalized_name).into());
+        }
+        if !process_alive(pid) {
+            return Err(Error::msg(format!(
+                "No process with PID {} is running",
+                pid
+            )));
+        }
+
+        // Uptime should count from the real start, not from the adoption
+        let start_time = process_start_time(pid).unwrap_or_else(chrono::Utc::now);
+        shuriken
+            .adopt(&shuriken_dir, pid, start_time)
+            .await
+            .map_err(Error::msg)?;
+
+        self.update_state(&normalized_name, &shuriken, ShurikenState::Running)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes a stale lockfile and marks the Shuriken as `Idle` again.
+    ///
+    /// Unlike [`ShurikenManager::lockpick`], this also resets the state so
+    /// the Shuriken can be started right away.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the lockfile was removed (or didn't exist)
+    /// - `Err` if the Shuriken isn't installed or the file can't be removed
+    pub async fn clear_stale_lock(&self, name: &str) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self
+            .shurikens
+            .read()
+            .await
+            .get(&normalized_name)
+            .cloned()
+            .ok_or_else(|| ManagerError::NotFound(name.to_string()))?;
+
+        shuriken.lockpick(&self.root_path).await?;
+        self.update_state(&normalized_name, &shuriken, ShurikenState::Idle)
+            .await?;
+        Ok(())
+    }
+
     pub async fn save_config(&self) -> Result<()> {
         let path = &self.root_path.join("config.toml");
         let data = &self.config.read().await.clone();
@@ -271,7 +1262,8 @@ impl ShurikenManager {
     /// Saves configuration options for a Shuriken.
     ///
     /// Persists configuration to disk as TOML and updates the in-memory cache.
-    /// Creates necessary directories if they don't exist.
+    /// Creates necessary directories if they don't exist. The write happens
+    /// under the same `.ninja/options.lock` as [`ShurikenManager::patch_config`].
     ///
     /// # Arguments
     /// - `name`: The name of the Shuriken
@@ -279,39 +1271,56 @@ impl ShurikenManager {
     ///
     /// # Returns
     /// - `Ok(())` if configuration saved successfully
-    /// - `Err` if file operations fail
+    /// - `Err` if the options don't fit the Shuriken's schema
+    ///   (see [`ShurikenManager::validate_options`]) or file operations fail
     pub async fn save_shuriken_config(
         &self,
         name: &str,
         data: HashMap<String, FieldValue>,
     ) -> Result<()> {
+        let _lock = self.lock_options(&normalize_shuriken_name(name)).await?;
+        self.write_options(name, data).await
+    }
+
+    /// Takes an exclusive lock on a Shuriken's `.ninja/options.lock`.
+    ///
+    /// The lock is held, against this process and other `shurikenctl`s alike,
+    /// until the returned file is dropped.
+    async fn lock_options(&self, normalized_name: &str) -> Result<std::fs::File> {
+        let ninja_dir = self
+            .root_path
+            .join("shurikens")
+            .join(normalized_name)
+            .join(".ninja");
+        fs::create_dir_all(&ninja_dir).await?;
+
+        let lock_path = ninja_dir.join("options.lock");
+        let file = tokio::task::spawn_blocking(move || -> io::Result<std::fs::File> {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&lock_path)?;
+            file.lock()?;
+            Ok(file)
+        })
+        .await??;
+        Ok(file)
+    }
+
+    /// Writes a Shuriken's options; the caller must hold its options lock.
+    async fn write_options(&self, name: &str, data: HashMap<String, FieldValue>) -> Result<()> {
         info!("Saving config for shuriken: {}", name);
         debug!("Config data: {:#?}", data);
         let normalized_name = normalize_shuriken_name(name);
+        self.check_options(&normalized_name, &data).await?;
 
-        // Update in-memory config
-        {
-            let mut shurikens = self.shurikens.write().await;
-            if let Some(shuriken) = shurikens.get_mut(&normalized_name) {
-                if let Some(config) = &mut shuriken.config {
-                    config.options = Some(data.clone());
-                } else {
-                    shuriken.config = Some(ShurikenConfig {
-                        config_path: PathBuf::from("options.toml"),
-                        options: Some(data.clone()),
-                    });
-                }
-            }
-        }
+        self.cache_options(&normalized_name, Some(data.clone()))
+            .await;
 
         // Write to disk
         let serialized_data = toml::ser::to_string_pretty(&data)?;
-        let options_path = self
-            .root_path
-            .join("shurikens")
-            .join(&normalized_name)
-            .join(".ninja")
-            .join("options.toml");
+        let options_path = self.options_path(&normalized_name);
 
         // Ensure the parent directory exists
         if let Some(parent) = options_path.parent() {
@@ -328,6 +1337,493 @@ impl ShurikenManager {
         Ok(())
     }
 
+    /// Path of a Shuriken's `.ninja/options.toml`.
+    fn options_path(&self, normalized_name: &str) -> PathBuf {
+        self.ninja_file(normalized_name, "options.toml")
+    }
+
+    /// Path of a file in a Shuriken's `.ninja` directory.
+    fn ninja_file(&self, normalized_name: &str, file: &str) -> PathBuf {
+        self.root_path
+            .join("shurikens")
+            .join(normalized_name)
+            .join(".ninja")
+            .join(file)
+    }
+
+    /// Parses a TOML file from a Shuriken's `.ninja` directory, `None` if it
+    /// doesn't exist. `kind` names the file in parse errors, e.g. "Options".
+    async fn read_ninja_toml<T: serde::de::DeserializeOwned>(
+        path: &Path,
+        kind: &str,
+    ) -> Result<Option<T>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path).await?;
+        toml::from_str(&content)
+            .map(Some)
+            .map_err(|e| Error::msg(format!("{} error in {}: {}", kind, path.display(), e)))
+    }
+
+    /// Reads a Shuriken's saved `options.toml`, empty if there is none.
+    async fn read_saved_options(
+        &self,
+        normalized_name: &str,
+    ) -> Result<HashMap<String, FieldValue>> {
+        Ok(
+            Self::read_ninja_toml(&self.options_path(normalized_name), "Options")
+                .await?
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Fails with every schema violation in `data`, like saving would.
+    async fn check_options(
+        &self,
+        normalized_name: &str,
+        data: &HashMap<String, FieldValue>,
+    ) -> Result<()> {
+        self.validate_options(normalized_name, data)
+            .await
+            .map_err(|errors| {
+                let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                Error::msg(format!(
+                    "Invalid options for '{}': {}",
+                    normalized_name,
+                    details.join("; ")
+                ))
+            })
+    }
+
+    /// Replaces the options kept in memory for a Shuriken.
+    async fn cache_options(
+        &self,
+        normalized_name: &str,
+        data: Option<HashMap<String, FieldValue>>,
+    ) {
+        let mut shurikens = self.shurikens.write().await;
+        if let Some(shuriken) = shurikens.get_mut(normalized_name) {
+            if let Some(config) = &mut shuriken.config {
+                config.options = data;
+            } else if data.is_some() {
+                shuriken.config = Some(ShurikenConfig {
+                    config_path: PathBuf::from("options.toml"),
+                    options: data,
+                });
+            }
+        }
+    }
+
+    /// Merges some options into a Shuriken's saved `options.toml`.
+    ///
+    /// Unlike [`ShurikenManager::save_shuriken_config`], which replaces every
+    /// option, keys not in `partial` keep their saved values. The read, merge
+    /// and write happen under an exclusive lock on `.ninja/options.lock`, so
+    /// concurrent patches, from this process or another `shurikenctl`, never
+    /// lose each other's keys.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `partial`: Options to add or overwrite
+    ///
+    /// # Returns
+    /// - `Ok(())` if the merged options were saved
+    /// - `Err` if the Shuriken isn't installed, the saved options can't be
+    ///   parsed, the merged options don't fit the schema or file operations fail
+    pub async fn patch_config(
+        &self,
+        name: &str,
+        partial: HashMap<String, FieldValue>,
+    ) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        self.update_saved_options(&normalized_name, |options| {
+            debug!(
+                "Patching {} option(s) of '{}' over {} saved",
+                partial.len(),
+                normalized_name,
+                options.len()
+            );
+            options.extend(partial);
+            Ok(true)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Changes a Shuriken's saved options in place.
+    ///
+    /// Reads `options.toml`, lets `change` edit the options and saves them
+    /// again, all while holding `.ninja/options.lock`, so concurrent changes
+    /// from this process or another `shurikenctl` never lose each other's keys.
+    ///
+    /// # Returns
+    /// - `Ok(changed)` with what `change` returned; nothing is saved if it was `false`
+    /// - `Err` if the Shuriken isn't installed, `change` fails, the saved
+    ///   options can't be parsed or saving fails
+    async fn update_saved_options(
+        &self,
+        normalized_name: &str,
+        change: impl FnOnce(&mut HashMap<String, FieldValue>) -> Result<bool>,
+    ) -> Result<bool> {
+        self.get(normalized_name.to_string()).await?;
+        let _lock = self.lock_options(normalized_name).await?;
+
+        let mut options = self.read_saved_options(normalized_name).await?;
+
+        if !change(&mut options)? {
+            return Ok(false);
+        }
+        self.write_options(normalized_name, options).await?;
+        Ok(true)
+    }
+
+    /// Moves an option's value to a new key, e.g. after an upgrade renamed it.
+    ///
+    /// Other options are left as they are. The rename is applied to the
+    /// options saved in `options.toml` and saved right away; edits that
+    /// weren't saved yet stay unsaved, with the key renamed among them too.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `old_key`: The key the value is stored under now
+    /// - `new_key`: The key to store it under
+    ///
+    /// # Returns
+    /// - `Ok(())` if the option was renamed and saved
+    /// - `Err` if the Shuriken isn't installed, `old_key` is absent, `new_key`
+    ///   is already taken or saving fails
+    pub async fn rename_option(&self, name: &str, old_key: &str, new_key: &str) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let working = self
+            .get(normalized_name.clone())
+            .await?
+            .config
+            .and_then(|config| config.options);
+        self.update_saved_options(&normalized_name, |options| {
+            if options.contains_key(new_key) {
+                return Err(Error::msg(format!(
+                    "Option '{}' of '{}' already exists",
+                    new_key, normalized_name
+                )));
+            }
+            let value = options.remove(old_key).ok_or_else(|| {
+                Error::msg(format!(
+                    "Option '{}' of '{}' doesn't exist",
+                    old_key, normalized_name
+                ))
+            })?;
+            options.insert(new_key.to_string(), value);
+
+            info!(
+                "Renaming option '{}' of '{}' to '{}'",
+                old_key, normalized_name, new_key
+            );
+            Ok(true)
+        })
+        .await?;
+
+        // Saving cached what's on disk; put the unsaved edits back
+        if let Some(mut working) = working {
+            if !working.contains_key(new_key)
+                && let Some(value) = working.remove(old_key)
+            {
+                working.insert(new_key.to_string(), value);
+            }
+            self.cache_options(&normalized_name, Some(working)).await;
+        }
+        Ok(())
+    }
+
+    /// Reads a single option of a Shuriken.
+    ///
+    /// # Returns
+    /// - `Ok(Some(value))` if the option is set
+    /// - `Ok(None)` if it isn't
+    /// - `Err` if the Shuriken isn't installed
+    pub async fn get_option(&self, name: &str, key: &str) -> Result<Option<FieldValue>> {
+        let shuriken = self.get(normalize_shuriken_name(name)).await?;
+        Ok(shuriken
+            .config
+            .and_then(|config| config.options)
+            .and_then(|mut options| options.remove(key)))
+    }
+
+    /// Sets a single option of a Shuriken and saves it to `options.toml` right away.
+    ///
+    /// Like [`ShurikenManager::patch_config`], it edits the saved options under
+    /// `.ninja/options.lock`, as do [`ShurikenManager::unset_option`] and
+    /// [`ShurikenManager::rename_option`].
+    ///
+    /// # Returns
+    /// - `Ok(())` if the option was set and saved
+    /// - `Err` if the Shuriken isn't installed, the value doesn't fit its
+    ///   schema or saving fails
+    pub async fn set_option(&self, name: &str, key: &str, value: FieldValue) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        self.update_saved_options(&normalized_name, |options| {
+            info!("Setting option '{}' of '{}'", key, normalized_name);
+            options.insert(key.to_string(), value);
+            Ok(true)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a single option of a Shuriken and saves the rest to `options.toml`.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if the option was removed
+    /// - `Ok(false)` if it wasn't set; nothing is saved then
+    /// - `Err` if the Shuriken isn't installed or saving fails
+    pub async fn unset_option(&self, name: &str, key: &str) -> Result<bool> {
+        let normalized_name = normalize_shuriken_name(name);
+        self.update_saved_options(&normalized_name, |options| {
+            let removed = options.remove(key).is_some();
+            if removed {
+                info!("Removing option '{}' of '{}'", key, normalized_name);
+            }
+            Ok(removed)
+        })
+        .await
+    }
+
+    /// Compares a Shuriken's in-memory options with its saved `options.toml`.
+    ///
+    /// Options edited through the DSL or GUI live in memory until saved;
+    /// this shows what saving would change.
+    ///
+    /// # Returns
+    /// - `Ok(changes)` as `(key, saved, pending)` sorted by key; `saved` is
+    ///   `None` for added keys and `pending` is `None` for removed ones
+    /// - `Err` if the Shuriken isn't installed or `options.toml` can't be parsed
+    pub async fn diff_config(
+        &self,
+        name: &str,
+    ) -> Result<Vec<(String, Option<FieldValue>, Option<FieldValue>)>> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let pending = shuriken
+            .config
+            .and_then(|config| config.options)
+            .unwrap_or_default();
+
+        let saved = self.read_saved_options(&normalized_name).await?;
+
+        let keys: BTreeSet<&String> = saved.keys().chain(pending.keys()).collect();
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| {
+                let (old, new) = (saved.get(key), pending.get(key));
+                (old != new).then(|| (key.clone(), old.cloned(), new.cloned()))
+            })
+            .collect())
+    }
+
+    /// Writes a Shuriken's saved options and generated config to a tar.gz at `dest`.
+    ///
+    /// Lets operators share a configuration without the whole package; see
+    /// [`ShurikenManager::import_config_bundle`] for the other side.
+    ///
+    /// # Returns
+    /// - `Ok(())` once the bundle is written
+    /// - `Err` if the Shuriken isn't installed, has neither saved options nor
+    ///   a generated config, or file operations fail
+    pub async fn export_config_bundle(&self, name: &str, dest: &Path) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+
+        let options_path = self.options_path(&normalized_name);
+        let options = match fs::read_to_string(&options_path).await {
+            Ok(options) => Some(options),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(
+                    Error::new(e).context(format!("Failed to read {}", options_path.display()))
+                );
+            }
+        };
+        let config = match shuriken.config {
+            Some(config) => {
+                let config_path = shuriken_dir.join(&config.config_path);
+                match fs::read(&config_path).await {
+                    Ok(config) => Some(config),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => {
+                        return Err(Error::new(e)
+                            .context(format!("Failed to read {}", config_path.display())));
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let bundle = ConfigBundle { options, config };
+        if bundle.options.is_none() && bundle.config.is_none() {
+            return Err(Error::msg(format!(
+                "Shuriken '{}' has no saved options or generated config to export",
+                normalized_name
+            )));
+        }
+
+        info!(
+            "Exporting config of '{}' to {}",
+            normalized_name,
+            dest.display()
+        );
+        fs::write(dest, bundle.to_tar_gz()?)
+            .await
+            .with_context(|| format!("Failed to write {}", dest.display()))
+    }
+
+    /// Applies a bundle written by [`ShurikenManager::export_config_bundle`]
+    /// to an installed Shuriken.
+    ///
+    /// The bundled options are validated like
+    /// [`ShurikenManager::save_shuriken_config`] before anything is written.
+    /// With `regenerate`, the config file is rendered again from this
+    /// Shuriken's own template, and the previous options are put back if
+    /// that fails; otherwise the bundled options and config replace the old
+    /// ones together.
+    ///
+    /// # Returns
+    /// - `Ok(())` once the bundle is applied
+    /// - `Err` if the Shuriken isn't installed, the file isn't a config bundle,
+    ///   the options don't fit the schema, or the bundle carries a config
+    ///   file but the Shuriken declares no `config-path`
+    pub async fn import_config_bundle(
+        &self,
+        name: &str,
+        src: &Path,
+        regenerate: bool,
+    ) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+
+        let bytes = fs::read(src)
+            .await
+            .with_context(|| format!("Failed to read {}", src.display()))?;
+        let bundle = ConfigBundle::from_tar_gz(&bytes)
+            .with_context(|| format!("Invalid config bundle {}", src.display()))?;
+
+        // Check everything before writing anything
+        let options: Option<HashMap<String, FieldValue>> = bundle
+            .options
+            .as_deref()
+            .map(toml::from_str)
+            .transpose()
+            .map_err(|e| Error::msg(format!("Options error in {}: {}", src.display(), e)))?;
+        let config_path = match (&shuriken.config, &bundle.config) {
+            (Some(config), Some(_)) if !regenerate => Some(shuriken_dir.join(&config.config_path)),
+            (None, Some(_)) if !regenerate => {
+                return Err(Error::msg(format!(
+                    "Shuriken '{}' has no config-path to write the bundled config to",
+                    normalized_name
+                )));
+            }
+            _ => None,
+        };
+
+        if let Some(options) = &options {
+            self.check_options(&normalized_name, options).await?;
+        }
+
+        info!(
+            "Importing config of '{}' from {}",
+            normalized_name,
+            src.display()
+        );
+        let options_path = self.options_path(&normalized_name);
+        if regenerate {
+            let Some(options) = options else {
+                return self.configure_shuriken(&normalized_name).await;
+            };
+            let previous = {
+                let _lock = self.lock_options(&normalized_name).await?;
+                let previous = match fs::read(&options_path).await {
+                    Ok(previous) => Some(previous),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => {
+                        return Err(Error::new(e)
+                            .context(format!("Failed to read {}", options_path.display())));
+                    }
+                };
+                let serialized = toml::ser::to_string_pretty(&options)?;
+                replace_files(vec![(options_path.clone(), serialized.into_bytes())]).await?;
+                self.cache_options(&normalized_name, Some(options)).await;
+                previous
+            };
+
+            // A template that fails to render must not leave the new options behind
+            if let Err(e) = self.configure_shuriken(&normalized_name).await {
+                let _lock = self.lock_options(&normalized_name).await?;
+                match previous {
+                    Some(previous) => replace_files(vec![(options_path, previous)]).await?,
+                    None => fs::remove_file(&options_path).await?,
+                }
+                let restored = shuriken.config.and_then(|config| config.options);
+                self.cache_options(&normalized_name, restored).await;
+                return Err(e);
+            }
+            return Ok(());
+        }
+
+        let _lock = self.lock_options(&normalized_name).await?;
+        let mut files = Vec::new();
+        if let Some(options) = &options {
+            let serialized = toml::ser::to_string_pretty(options)?;
+            files.push((options_path, serialized.into_bytes()));
+        }
+        if let (Some(path), Some(config)) = (config_path, bundle.config) {
+            files.push((path, config));
+        }
+        replace_files(files).await?;
+        if let Some(options) = options {
+            self.cache_options(&normalized_name, Some(options)).await;
+        }
+        Ok(())
+    }
+
+    /// Saves and applies options for several Shurikens from one file.
+    ///
+    /// The file maps Shuriken names to their options (see
+    /// [`read_bulk_options`]). Each listed Shuriken gets its options saved
+    /// and is then configured. Shurikens that aren't installed are skipped
+    /// with a warning.
+    ///
+    /// # Arguments
+    /// - `path`: A TOML or JSON file
+    ///
+    /// # Returns
+    /// - `Ok(results)` with each installed Shuriken's outcome, sorted by name
+    /// - `Err` if the file can't be read or parsed
+    pub async fn bulk_configure_from_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<(String, Result<()>)>> {
+        let path = path.as_ref();
+        info!("Applying options from {}", path.display());
+        let entries = read_bulk_options(path).await?;
+
+        let mut results = Vec::new();
+        for (name, options) in entries {
+            let normalized_name = normalize_shuriken_name(&name);
+            if !self.shurikens.read().await.contains_key(&normalized_name) {
+                warn!("Skipping '{}' from {}: not installed", name, path.display());
+                continue;
+            }
+
+            let result = match self.save_shuriken_config(&name, options).await {
+                Ok(()) => self.configure_shuriken(&name).await,
+                Err(e) => Err(e),
+            };
+            results.push((name, result));
+        }
+        Ok(results)
+    }
+
     /// Stops a running Shuriken.
     ///
     /// Executes the Shuriken's stop script and halts the service.
@@ -340,11 +1836,78 @@ impl ShurikenManager {
     /// - `Ok(())` if stop completed successfully
     /// - `Err` if Shuriken not found or stop script fails
     pub async fn stop(&self, name: &str) -> Result<()> {
+        let _guard = self
+            .operation_locks
+            .acquire(&normalize_shuriken_name(name))
+            .await;
+        self.stop_locked(name).await
+    }
+
+    /// Stops a Shuriken and starts it again, as one operation.
+    ///
+    /// Holds the Shuriken's operation lock throughout, so a concurrent
+    /// start or stop can't slip in between the two halves. A Shuriken that
+    /// isn't running is simply started. Between the halves the state is
+    /// `Idle` and the lockfile is gone.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to restart
+    ///
+    /// # Returns
+    /// - `Ok(())` if the Shuriken is running again
+    /// - `Err` if stopping fails, the lockfile lingers, or starting fails
+    ///   (in which case the Shuriken stays stopped)
+    pub async fn restart(&self, name: &str) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let _guard = self.operation_locks.acquire(&normalized_name).await;
+        info!("Restarting shuriken: {}", name);
+
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let state = shuriken.state.lock().await.clone();
+        if matches!(state, ShurikenState::Running | ShurikenState::Paused) {
+            self.stop_locked(name).await?;
+        } else {
+            debug!(
+                "Shuriken '{}' isn't running, only starting it",
+                normalized_name
+            );
+        }
+
+        let lock_path = self
+            .root_path
+            .join("shurikens")
+            .join(&normalized_name)
+            .join(".ninja")
+            .join("shuriken.lck");
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        while lock_path.exists() {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(Error::msg(format!(
+                    "Shuriken '{}' stopped but its lockfile is still there: {}",
+                    normalized_name,
+                    lock_path.display()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        self.start_locked(name, &StartOverrides::default(), 0)
+            .await
+            .map_err(|e| {
+                Error::msg(format!(
+                    "Shuriken '{}' was stopped but failed to start again: {}",
+                    normalized_name, e
+                ))
+            })
+    }
+
+    /// Stops a Shuriken; the caller must hold its operation lock.
+    async fn stop_locked(&self, name: &str) -> Result<()> {
         let normalized_name = normalize_shuriken_name(name);
         let shurikens = self.shurikens.read().await;
         let mut shuriken = shurikens
             .get(&normalized_name)
-            .ok_or_else(|| anyhow::Error::msg(format!("No such shuriken: {}", name)))?
+            .ok_or_else(|| ManagerError::NotFound(name.to_string()))?
             .clone();
         drop(shurikens);
 
@@ -356,6 +1919,12 @@ impl ShurikenManager {
             )));
         }
 
+        let old_state = shuriken.state.lock().await.clone();
+        if old_state == ShurikenState::Paused {
+            // A frozen process can't act on the signals stop sends it
+            let pid = self.recorded_pid(&normalized_name, &shuriken).await?;
+            resume_process(pid)?;
+        }
         if let Err(e) = shuriken
             .stop(
                 &*self.engine.lock().await,
@@ -364,14 +1933,474 @@ impl ShurikenManager {
             )
             .await
         {
+            // A failed stop leaves the Shuriken in `Error`; a rejection is already logged
+            let _ = self
+                .update_state(&normalized_name, &shuriken, ShurikenState::Error(e.clone()))
+                .await;
             return Err(anyhow::Error::msg(format!(
                 "Failed to stop shuriken '{}': {}",
                 name, e
             )));
         }
 
-        self.update_state(shuriken, ShurikenState::Idle).await;
-        Ok(())
+        if let Err(e) = shuriken
+            .post_stop(
+                &*self.engine.lock().await,
+                &shuriken_dir,
+                Some(self.clone()),
+            )
+            .await
+        {
+            warn!("Post-stop hook for '{}' failed: {}", name, e);
+        }
+
+        self.idle_timers.disarm(&normalized_name);
+        self.update_state(&normalized_name, &shuriken, ShurikenState::Idle)
+            .await?;
+        Ok(())
+    }
+
+    /// Freezes a running Shuriken's process without killing it.
+    ///
+    /// Sends `SIGSTOP` to the PID recorded in the lockfile, which the
+    /// Shuriken's `start` function must have returned. Not supported on Windows.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to pause
+    ///
+    /// # Returns
+    /// - `Ok(())` if the process was frozen and the state is now `Paused`
+    /// - `Err` if the Shuriken isn't running, has no recorded PID or can't be signalled
+    pub async fn pause(&self, name: &str) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let _guard = self.operation_locks.acquire(&normalized_name).await;
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let old_state = shuriken.state.lock().await.clone();
+        if old_state != ShurikenState::Running {
+            return Err(ManagerError::NotRunning(normalized_name).into());
+        }
+
+        let pid = self.recorded_pid(&normalized_name, &shuriken).await?;
+        suspend_process(pid)?;
+        info!("Paused shuriken {} (PID {})", normalized_name, pid);
+
+        self.update_state(&normalized_name, &shuriken, ShurikenState::Paused)
+            .await?;
+        Ok(())
+    }
+
+    /// Continues a Shuriken frozen by [`ShurikenManager::pause`].
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken to resume
+    ///
+    /// # Returns
+    /// - `Ok(())` if the process continues and the state is `Running` again
+    /// - `Err` if the Shuriken isn't paused or can't be signalled
+    pub async fn resume(&self, name: &str) -> Result<()> {
+        let normalized_name = normalize_shuriken_name(name);
+        let _guard = self.operation_locks.acquire(&normalized_name).await;
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let old_state = shuriken.state.lock().await.clone();
+        if old_state != ShurikenState::Paused {
+            return Err(ManagerError::NotPaused(normalized_name).into());
+        }
+
+        let pid = self.recorded_pid(&normalized_name, &shuriken).await?;
+        resume_process(pid)?;
+        info!("Resumed shuriken {} (PID {})", normalized_name, pid);
+
+        self.update_state(&normalized_name, &shuriken, ShurikenState::Running)
+            .await?;
+        Ok(())
+    }
+
+    /// Looks up the PID a Shuriken recorded in its lockfile.
+    async fn recorded_pid(&self, name: &str, shuriken: &Shuriken) -> Result<u32> {
+        let shuriken_dir = self.root_path.join("shurikens").join(name);
+        shuriken.pid(&shuriken_dir).await.ok_or_else(|| {
+            Error::msg(format!(
+                "Shuriken '{}' has no recorded PID; its start function must return one",
+                name
+            ))
+        })
+    }
+
+    /// Returns a Shuriken's most recent lifecycle events, oldest first.
+    ///
+    /// Events are recorded in memory as Shurikens start, stop, pause, resume
+    /// or fail, and are lost when the manager is dropped.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `limit`: The most events to return
+    pub fn history(&self, name: &str, limit: usize) -> Vec<LifecycleEvent> {
+        self.state_events
+            .history(&normalize_shuriken_name(name), limit)
+    }
+
+    /// Returns the current state of one Shuriken.
+    ///
+    /// # Returns
+    /// - `Ok(state)` as last recorded by the manager
+    /// - `Err` if the Shuriken isn't installed
+    pub async fn status(&self, name: &str) -> Result<ShurikenState> {
+        let shuriken = self.get(normalize_shuriken_name(name)).await?;
+        let state = shuriken.state.lock().await.clone();
+        Ok(state)
+    }
+
+    /// Describes one Shuriken: its manifest identity, state, and process.
+    ///
+    /// The PID and start time come from the lockfile, so they are only
+    /// filled in while the Shuriken is running (or paused).
+    ///
+    /// # Returns
+    /// - `Ok(ShurikenInfo)` describing the Shuriken
+    /// - `Err` if the Shuriken isn't installed
+    pub async fn info(&self, name: &str) -> Result<ShurikenInfo> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let state = shuriken.state.lock().await.clone();
+
+        let (pid, start_time) = if matches!(state, ShurikenState::Running | ShurikenState::Paused) {
+            let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+            (
+                shuriken.pid(&shuriken_dir).await,
+                shuriken.start_time(&shuriken_dir).await,
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(ShurikenInfo {
+            name: normalized_name,
+            version: shuriken.metadata.version.clone(),
+            shuriken_type: shuriken.metadata.shuriken_type.clone(),
+            state,
+            pid,
+            start_time,
+        })
+    }
+
+    /// Checks whether a Shuriken is currently running.
+    ///
+    /// Cheaper than [`list`](Self::list) when only one answer is needed. If
+    /// the start function recorded a PID, that process must also still exist,
+    /// so a crashed Shuriken isn't reported as running.
+    ///
+    /// # Returns
+    /// - `true` if the Shuriken is `Running` (and its recorded process is alive)
+    /// - `false` if it's in any other state or isn't installed
+    pub async fn is_running(&self, name: &str) -> bool {
+        let normalized_name = normalize_shuriken_name(name);
+        let Some(shuriken) = self.shurikens.read().await.get(&normalized_name).cloned() else {
+            return false;
+        };
+        if *shuriken.state.lock().await != ShurikenState::Running {
+            return false;
+        }
+
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+        match shuriken.pid(&shuriken_dir).await {
+            Some(pid) => process_alive(pid),
+            None => true,
+        }
+    }
+
+    /// Exports per-Shuriken metrics in the OpenMetrics text format.
+    ///
+    /// Reports whether each Shuriken is up, how long its current run has
+    /// lasted (from the lockfile's start time) and how often it was
+    /// restarted, sorted by name. See [`crate::common::metrics`].
+    pub async fn export_openmetrics_per_shuriken(&self) -> String {
+        let mut shurikens: Vec<(String, Shuriken)> = self
+            .shurikens
+            .read()
+            .await
+            .iter()
+            .map(|(name, shuriken)| (name.clone(), shuriken.clone()))
+            .collect();
+        shurikens.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let now = chrono::Utc::now();
+        let mut samples = Vec::with_capacity(shurikens.len());
+        for (name, shuriken) in shurikens {
+            let up = self.is_running(&name).await;
+            let uptime_seconds = if up {
+                let shuriken_dir = self.root_path.join("shurikens").join(&name);
+                shuriken
+                    .start_time(&shuriken_dir)
+                    .await
+                    .and_then(|started| u64::try_from((now - started).num_seconds()).ok())
+                    .unwrap_or(0)
+            } else {
+                0
+            };
+            samples.push(ShurikenSample {
+                restarts: self.state_events.restarts(&name),
+                name,
+                up,
+                uptime_seconds,
+            });
+        }
+
+        render_openmetrics(&samples)
+    }
+
+    /// Orders Shurikens so that each comes after the ones it depends on.
+    ///
+    /// Only dependencies within `names` are considered; otherwise the
+    /// given order is preserved.
+    ///
+    /// # Returns
+    /// - `Ok(names)` in start order
+    /// - `Err` if a Shuriken is unknown or the dependencies form a cycle
+    pub async fn start_order(&self, names: &[String]) -> Result<Vec<String>> {
+        let shurikens = self.shurikens.read().await;
+
+        let mut wanted: Vec<String> = Vec::new();
+        for name in names.iter().map(|n| normalize_shuriken_name(n)) {
+            if !wanted.contains(&name) {
+                wanted.push(name);
+            }
+        }
+
+        let mut deps: HashMap<&str, Vec<String>> = HashMap::new();
+        for name in &wanted {
+            let shuriken = shurikens
+                .get(name)
+                .ok_or_else(|| ManagerError::NotFound(name.to_string()))?;
+            let within: Vec<String> = shuriken
+                .metadata
+                .depends_on
+                .iter()
+                .flatten()
+                .map(|d| normalize_shuriken_name(d))
+                .filter(|d| wanted.contains(d))
+                .collect();
+            deps.insert(name, within);
+        }
+
+        let mut ordered: Vec<String> = Vec::with_capacity(wanted.len());
+        while ordered.len() < wanted.len() {
+            let next = wanted.iter().find(|name| {
+                !ordered.contains(name) && deps[name.as_str()].iter().all(|d| ordered.contains(d))
+            });
+            match next {
+                Some(name) => ordered.push(name.clone()),
+                None => {
+                    let stuck: Vec<&str> = wanted
+                        .iter()
+                        .filter(|n| !ordered.contains(n))
+                        .map(String::as_str)
+                        .collect();
+                    return Err(Error::msg(format!(
+                        "Dependency cycle between: {}",
+                        stuck.join(", ")
+                    )));
+                }
+            }
+        }
+
+        Ok(ordered)
+    }
+
+    /// Lists a Shuriken and everything it transitively depends on.
+    ///
+    /// # Returns
+    /// - `Ok(names)` in start order, ending with `name` itself
+    /// - `Err` if a Shuriken in the graph isn't installed or the
+    ///   dependencies form a cycle, which the error spells out
+    pub async fn dependency_order(&self, name: &str) -> Result<Vec<String>> {
+        fn visit(
+            shurikens: &HashMap<String, Shuriken>,
+            name: String,
+            path: &mut Vec<String>,
+            ordered: &mut Vec<String>,
+        ) -> Result<()> {
+            if ordered.contains(&name) {
+                return Ok(());
+            }
+            if let Some(pos) = path.iter().position(|n| *n == name) {
+                let mut cycle = path[pos..].to_vec();
+                cycle.push(name);
+                return Err(Error::msg(format!(
+                    "Dependency cycle: {}",
+                    cycle.join(" -> ")
+                )));
+            }
+
+            let shuriken = shurikens
+                .get(&name)
+                .ok_or_else(|| ManagerError::NotFound(name.clone()))?;
+            path.push(name);
+            for dep in shuriken.metadata.depends_on.iter().flatten() {
+                visit(shurikens, normalize_shuriken_name(dep), path, ordered)?;
+            }
+            let name = path.pop().unwrap_or_default();
+            ordered.push(name);
+            Ok(())
+        }
+
+        let shurikens = self.shurikens.read().await;
+        let mut ordered = Vec::new();
+        visit(
+            &shurikens,
+            normalize_shuriken_name(name),
+            &mut Vec::new(),
+            &mut ordered,
+        )?;
+        Ok(ordered)
+    }
+
+    /// Starts a Shuriken after everything it depends on.
+    ///
+    /// Dependencies that are already running (or paused) are left alone.
+    ///
+    /// # Returns
+    /// - `Ok(())` once the Shuriken and its dependencies are up
+    /// - `Err` if the dependency graph is broken or any start fails; the
+    ///   Shurikens started before the failure are left running
+    pub async fn start_with_deps(&self, name: &str) -> Result<()> {
+        for dep in self.dependency_order(name).await? {
+            let shuriken = self.get(dep.clone()).await?;
+            let state = shuriken.state.lock().await.clone();
+            if matches!(state, ShurikenState::Running | ShurikenState::Paused) {
+                debug!("Dependency '{}' is already running", dep);
+                continue;
+            }
+            self.start(&dep).await?;
+        }
+        Ok(())
+    }
+
+    /// Stops a Shuriken, then its dependencies in reverse start order.
+    ///
+    /// Shurikens that aren't running are skipped, and so are dependencies
+    /// still needed by another running Shuriken.
+    ///
+    /// # Returns
+    /// - `Ok(())` once everything that could be stopped is
+    /// - `Err` if the dependency graph is broken or any stop fails
+    pub async fn stop_with_deps(&self, name: &str) -> Result<()> {
+        let order = self.dependency_order(name).await?;
+        let mut running: HashMap<String, Vec<String>> = HashMap::new();
+        for (other, shuriken) in self.shurikens.read().await.iter() {
+            let state = shuriken.state.lock().await.clone();
+            if matches!(state, ShurikenState::Running | ShurikenState::Paused) {
+                let deps = shuriken.metadata.depends_on.iter().flatten();
+                running.insert(
+                    other.clone(),
+                    deps.map(|d| normalize_shuriken_name(d)).collect(),
+                );
+            }
+        }
+
+        let target = order.last().cloned().unwrap_or_default();
+        for dep in order.iter().rev() {
+            if !running.contains_key(dep) {
+                continue;
+            }
+            if *dep != target
+                && let Some(user) = running
+                    .iter()
+                    .find(|(other, deps)| !order.contains(other) && deps.contains(dep))
+                    .map(|(other, _)| other)
+            {
+                info!("Leaving '{}' running, '{}' still needs it", dep, user);
+                continue;
+            }
+            self.stop(dep).await?;
+        }
+        Ok(())
+    }
+
+    /// Picks the bulk mode for `names` when the user didn't choose one.
+    ///
+    /// Sequential if any of them declares `depends-on`, parallel otherwise.
+    pub async fn default_bulk_mode(&self, names: &[String]) -> BulkMode {
+        let shurikens = self.shurikens.read().await;
+        let has_deps = names.iter().any(|name| {
+            shurikens
+                .get(&normalize_shuriken_name(name))
+                .and_then(|s| s.metadata.depends_on.as_ref())
+                .is_some_and(|deps| !deps.is_empty())
+        });
+
+        if has_deps {
+            BulkMode::Sequential
+        } else {
+            BulkMode::Parallel(DEFAULT_PARALLELISM)
+        }
+    }
+
+    /// Starts several Shurikens.
+    ///
+    /// # Arguments
+    /// - `names`: The Shurikens to start
+    /// - `mode`: Bounded-concurrency or dependency-ordered sequential
+    /// - `timeout`: Optional health timeout applied to each start
+    ///
+    /// # Returns
+    /// - `Ok(results)` with each Shuriken's outcome, in the order they were run
+    /// - `Err` if sequential ordering fails (unknown Shuriken or cycle)
+    pub async fn start_many(
+        &self,
+        names: &[String],
+        mode: BulkMode,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(String, Result<()>)>> {
+        match mode {
+            BulkMode::Sequential => {
+                let mut results = Vec::new();
+                for name in self.start_order(names).await? {
+                    let result = self.start_with_timeout(&name, timeout).await;
+                    results.push((name, result));
+                }
+                Ok(results)
+            }
+            BulkMode::Parallel(limit) => Ok(stream::iter(names.iter().cloned())
+                .map(|name| async move {
+                    let result = self.start_with_timeout(&name, timeout).await;
+                    (name, result)
+                })
+                .buffered(limit.max(1))
+                .collect()
+                .await),
+        }
+    }
+
+    /// Stops several Shurikens.
+    ///
+    /// Sequential mode stops dependents before the Shurikens they depend on.
+    ///
+    /// # Returns
+    /// - `Ok(results)` with each Shuriken's outcome, in the order they were run
+    /// - `Err` if sequential ordering fails (unknown Shuriken or cycle)
+    pub async fn stop_many(
+        &self,
+        names: &[String],
+        mode: BulkMode,
+    ) -> Result<Vec<(String, Result<()>)>> {
+        match mode {
+            BulkMode::Sequential => {
+                let mut results = Vec::new();
+                for name in self.start_order(names).await?.into_iter().rev() {
+                    let result = self.stop(&name).await;
+                    results.push((name, result));
+                }
+                Ok(results)
+            }
+            BulkMode::Parallel(limit) => Ok(stream::iter(names.iter().cloned())
+                .map(|name| async move {
+                    let result = self.stop(&name).await;
+                    (name, result)
+                })
+                .buffered(limit.max(1))
+                .collect()
+                .await),
+        }
     }
 
     /// Retrieves a Shuriken by name.
@@ -401,10 +2430,7 @@ impl ShurikenManager {
             Ok(shuriken.clone())
         } else {
             warn!("Shuriken '{}' not found", name);
-            Err(anyhow::Error::msg(format!(
-                "No shuriken of name {} found",
-                name
-            )))
+            Err(ManagerError::NotFound(name).into())
         }
     }
 
@@ -443,6 +2469,279 @@ impl ShurikenManager {
         }
     }
 
+    /// Lists every Shuriken's state as a JSON object of name to state.
+    ///
+    /// The serialized snapshot is reused until a state changes or the
+    /// Shurikens are refreshed, so busy dashboards polling it don't pay for
+    /// serialization on every call.
+    pub async fn list_states_json(&self) -> Result<Arc<str>> {
+        if let Some(snapshot) = self.states_cache.get() {
+            return Ok(snapshot);
+        }
+
+        let generation = self.states_cache.generation.load(Ordering::Acquire);
+        let mut states = BTreeMap::new();
+        for (name, shuriken) in self.shurikens.read().await.iter() {
+            states.insert(name.clone(), shuriken.state.lock().await.clone());
+        }
+        let snapshot: Arc<str> = serde_json::to_string(&states)?.into();
+        debug!("Serialized states of {} shurikens", states.len());
+        self.states_cache.store(generation, snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Lists the maintenance tools a Shuriken declares in its manifest.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    ///
+    /// # Returns
+    /// - `Ok(tools)` with each tool's name and description
+    /// - `Err` if the Shuriken doesn't exist
+    pub async fn tools(&self, name: &str) -> Result<Vec<ToolInfo>> {
+        let shuriken = self.get(normalize_shuriken_name(name)).await?;
+        Ok(shuriken
+            .tools
+            .iter()
+            .flatten()
+            .map(ToolInfo::from)
+            .collect())
+    }
+
+    /// Reads the option schema a Shuriken declares in `.ninja/schema.toml`.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    ///
+    /// # Returns
+    /// - `Ok(schema)` mapping option names to their [`InputType`], empty if
+    ///   the Shuriken declares no schema
+    /// - `Err` if the Shuriken doesn't exist or its schema can't be parsed
+    pub async fn options_schema(&self, name: &str) -> Result<HashMap<String, InputType>> {
+        let normalized_name = normalize_shuriken_name(name);
+        self.get(normalized_name.clone()).await?;
+
+        let schema_path = self.ninja_file(&normalized_name, "schema.toml");
+        Ok(Self::read_ninja_toml(&schema_path, "Schema")
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Checks option values against the Shuriken's schema without saving them.
+    ///
+    /// Options the schema doesn't mention are accepted as-is.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `options`: The values to check
+    ///
+    /// # Returns
+    /// - `Ok(())` if every value fits its declared type, range, pattern or choices
+    /// - `Err(errors)` with every violation, sorted by option name. If the
+    ///   schema itself can't be read, that is the single error returned
+    pub async fn validate_options(
+        &self,
+        name: &str,
+        options: &HashMap<String, FieldValue>,
+    ) -> std::result::Result<(), Vec<OptionError>> {
+        let schema = self.options_schema(name).await.map_err(|e| {
+            vec![OptionError {
+                option: String::new(),
+                message: e.to_string(),
+            }]
+        })?;
+
+        let mut errors: Vec<OptionError> = options
+            .iter()
+            .filter_map(|(option, value)| {
+                let input = schema.get(option)?;
+                input.check(value).err().map(|message| OptionError {
+                    option: option.clone(),
+                    message,
+                })
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            errors.sort_by(|a, b| a.option.cmp(&b.option));
+            Err(errors)
+        }
+    }
+
+    /// Reads a Shuriken's log, keeping only the lines `filter` selects.
+    ///
+    /// The log file from the `[logs]` section is preferred. Shurikens without
+    /// one but with a `log-buffer` serve the output captured in memory since
+    /// they were started by this process. A filter that only limits the
+    /// number of lines reads just the end of the file.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `filter`: Pattern, time window and line limit to apply
+    ///
+    /// # Returns
+    /// - `Ok(lines)` with the matching lines, oldest first
+    /// - `Err` if the Shuriken doesn't exist, has neither a `[logs]` section
+    ///   nor captured output, its log can't be read or the pattern is invalid
+    pub async fn read_logs(&self, name: &str, filter: &LogFilter) -> Result<Vec<String>> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+        let Some(logs) = shuriken.logs.as_ref() else {
+            let captured = shuriken
+                .metadata
+                .log_buffer
+                .and_then(|_| log_buffer::captured_lines(&shuriken_dir))
+                .ok_or_else(|| {
+                    Error::msg(format!(
+                        "Shuriken '{}' does not have logs configured",
+                        normalized_name
+                    ))
+                })?;
+            return filter_log(&captured.join("\n"), filter, chrono::Utc::now(), None);
+        };
+        let log_path = shuriken_dir.join(&logs.log_path);
+
+        if let LogFilter {
+            grep: None,
+            since: None,
+            lines: Some(lines),
+        } = filter
+        {
+            return tail_file(&log_path, *lines)
+                .await
+                .with_context(|| format!("Failed to read log {}", log_path.display()));
+        }
+
+        let content = fs::read_to_string(&log_path)
+            .await
+            .with_context(|| format!("Failed to read log {}", log_path.display()))?;
+        let modified = fs::metadata(&log_path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(chrono::DateTime::<chrono::Utc>::from);
+
+        filter_log(&content, filter, chrono::Utc::now(), modified)
+    }
+
+    /// Resolves the log file declared in a Shuriken's `[logs]` section.
+    ///
+    /// # Returns
+    /// - `Ok(path)` with the absolute path of the log file, which may not exist yet
+    /// - `Err` if the Shuriken doesn't exist or has no `[logs]` section
+    pub async fn log_path(&self, name: &str) -> Result<PathBuf> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let logs = shuriken
+            .logs
+            .as_ref()
+            .ok_or_else(|| ManagerError::NoLogFile(normalized_name.clone()))?;
+        Ok(self
+            .root_path
+            .join("shurikens")
+            .join(&normalized_name)
+            .join(&logs.log_path))
+    }
+
+    /// Returns the last `lines` lines of a Shuriken's log file.
+    ///
+    /// Only reads the end of the file, so it stays cheap on large logs.
+    /// Unlike [`ShurikenManager::read_logs`] it never falls back to
+    /// captured output.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `lines`: How many lines to return at most
+    ///
+    /// # Returns
+    /// - `Ok(lines)` oldest first
+    /// - `Err` if the Shuriken doesn't exist, has no log file
+    ///   ([`ManagerError::NoLogFile`]) or it can't be read
+    pub async fn tail_logs(&self, name: &str, lines: usize) -> Result<Vec<String>> {
+        let log_path = self.log_path(name).await?;
+        tail_file(&log_path, lines)
+            .await
+            .with_context(|| format!("Failed to read log {}", log_path.display()))
+    }
+
+    /// Runs a maintenance tool declared by a Shuriken.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    /// - `tool`: The name of the tool to run
+    ///
+    /// # Returns
+    /// - `Ok(output)` with whatever the tool script returned
+    /// - `Err` if the Shuriken or tool doesn't exist, or the script fails
+    pub async fn run_tool(&self, name: &str, tool: &str) -> Result<String> {
+        let normalized_name = normalize_shuriken_name(name);
+        let shuriken = self.get(normalized_name.clone()).await?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+
+        // Work on a handle to the shared engine so the tool can call back
+        // into the manager without deadlocking on the engine lock.
+        let engine = self.engine.lock().await.clone();
+        shuriken
+            .run_tool(tool, &engine, &shuriken_dir, Some(self.clone()))
+            .await
+            .map_err(Error::msg)
+    }
+
+    /// Runs a tool like [`ShurikenManager::run_tool`], capturing what it prints.
+    ///
+    /// Lets frontends without a terminal (API, GUI) show script output.
+    ///
+    /// # Returns
+    /// - `Ok(ToolOutput)` with the script's return value and printed text
+    /// - `Err` if the Shuriken or tool doesn't exist, or the script fails
+    pub async fn run_tool_captured(&self, name: &str, tool: &str) -> Result<ToolOutput> {
+        let (result, printed) = NinjaEngine::capture(self.run_tool(name, tool)).await;
+
+        Ok(ToolOutput {
+            result: result?,
+            printed,
+        })
+    }
+
+    /// Reports how many bytes a Shuriken's directory takes up on disk.
+    ///
+    /// Symlinks are counted as links rather than followed, and hard-linked
+    /// files are only counted once.
+    ///
+    /// # Arguments
+    /// - `name`: The name of the Shuriken
+    ///
+    /// # Returns
+    /// - `Ok(bytes)` with the summed size of every file under `shurikens/{name}`
+    /// - `Err` if the Shuriken doesn't exist or its directory can't be read
+    pub async fn disk_usage(&self, name: &str) -> Result<u64> {
+        let normalized_name = normalize_shuriken_name(name);
+        self.get(normalized_name.clone()).await?;
+        let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
+
+        let size = tokio::task::spawn_blocking(move || dir_size(&shuriken_dir)).await??;
+        Ok(size)
+    }
+
+    /// Reports [`ShurikenManager::disk_usage`] for every installed Shuriken.
+    ///
+    /// # Returns
+    /// - `Ok(map)` of Shuriken name to bytes used
+    /// - `Err` if any Shuriken directory can't be read
+    pub async fn disk_usage_all(&self) -> Result<HashMap<String, u64>> {
+        let names: Vec<String> = self.shurikens.read().await.keys().cloned().collect();
+
+        let mut usage = HashMap::with_capacity(names.len());
+        for name in names {
+            let size = self.disk_usage(&name).await?;
+            usage.insert(name, size);
+        }
+        Ok(usage)
+    }
+
     /// Creates a new DSL engine for flow/repl execution.
     ///
     /// # Returns
@@ -465,14 +2764,39 @@ impl ShurikenManager {
     /// - `output`: Optional output directory (defaults to ~/.ninja/blacksmith)
     ///
     /// # Returns
-    /// - `Ok(())` if packaging succeeded
+    /// - `Ok(ForgeOutput)` with where the package was written, its size and checksum
     /// - `Err` if metadata is too large, archive creation fails, or I/O fails
     pub async fn forge(
         &self,
         meta: ArmoryMetadata,
         path: PathBuf,
         output: Option<PathBuf>,
-    ) -> Result<()> {
+    ) -> Result<ForgeOutput> {
+        self.forge_with_options(meta, path, output, &ForgeOptions::default())
+            .await
+    }
+
+    /// Packages a Shuriken like [`ShurikenManager::forge`], with extra options.
+    ///
+    /// Files listed in the Shuriken's `.ninjaignore` are always left out;
+    /// `options.filter` adds include/exclude globs on top of that. With
+    /// `options.sign_key` set, an Ed25519 signature section is appended
+    /// (see [`crate::common::signing`]).
+    pub async fn forge_with_options(
+        &self,
+        meta: ArmoryMetadata,
+        path: PathBuf,
+        output: Option<PathBuf>,
+        options: &ForgeOptions,
+    ) -> Result<ForgeOutput> {
+        // Fail on a bad key before doing any work
+        let sign_key = options
+            .sign_key
+            .as_deref()
+            .map(signing::load_signing_key)
+            .transpose()?;
+        let filter = options.filter.clone();
+
         let output = output.unwrap_or_else(|| self.root_path.join("blacksmith"));
         if !output.exists() {
             fs::create_dir_all(&output).await?;
@@ -480,7 +2804,7 @@ impl ShurikenManager {
         let path = &self.root_path.join("shurikens").join(path);
 
         let shuriken_path = output.join(format!("{}-{}.shuriken", meta.id, meta.platform));
-        let mut file = File::create(shuriken_path).await?;
+        let mut file = File::create(&shuriken_path).await?;
 
         // ---- 1) Serialize metadata ----
 
@@ -497,8 +2821,9 @@ impl ShurikenManager {
         // ---- 2) Build archive bytes (tar.gz) in a non-blocking thread for some reason ----
         let path_clone = path.to_path_buf();
 
-        let archive =
-            tokio::task::spawn(async move { create_tar_gz_bytes(path_clone).await }).await??;
+        let (archive, report) =
+            tokio::task::spawn(async move { create_tar_gz_bytes(path_clone, &filter).await })
+                .await??;
         let archive_len: u64 = archive.len().try_into()?;
         // Define a reasonable upper bound to protect system memory (e.g., 5E GB)
         const MAX_ARCHIVE_SIZE: u64 = 5 * 1024 * 1024 * 1024;
@@ -543,6 +2868,112 @@ impl ShurikenManager {
         // signature
         file.write_all(&signature).await?;
 
+        // Everything written so far; the Ed25519 section signs this digest
+        // and the package checksum extends it with the section itself
+        let mut hasher = Sha256::new();
+        hasher.update(MAGIC_BYTES);
+        hasher.update(meta_len_le);
+        hasher.update(&serialized_metadata);
+        hasher.update(archive_len_le);
+        hasher.update(&archive);
+        hasher.update(signature);
+
+        // Optional Ed25519 section over everything written so far
+        if let Some(key) = &sign_key {
+            let section = signing::signature_section(key, hasher.clone().finalize().as_slice());
+            file.write_all(&section).await?;
+            hasher.update(&section);
+            info!(
+                "Signed package with key {}",
+                signing::to_hex(&signing::key_id(&key.verifying_key()))
+            );
+        }
+        file.flush().await?;
+
+        info!(
+            "Forged {} files ({} bytes) from {}",
+            report.file_count,
+            report.total_size,
+            path.display()
+        );
+        Ok(ForgeOutput {
+            size: fs::metadata(&shuriken_path).await?.len(),
+            checksum: signing::to_hex(&hasher.finalize()),
+            path: shuriken_path,
+            report,
+        })
+    }
+
+    /// Duplicates an installed Shuriken under a new name.
+    ///
+    /// Copies `shurikens/{src}` to `shurikens/{dest}`, renames the copy's
+    /// manifest `name` and `id` to its directory's name (`dest` normalized),
+    /// drops any copied lockfile and registers the new Shuriken as idle.
+    ///
+    /// # Arguments
+    /// - `src`: The name of the Shuriken to copy
+    /// - `dest`: The name for the copy
+    ///
+    /// # Returns
+    /// - `Ok(())` if the clone was created
+    /// - `Err` if `src` doesn't exist, `dest` is taken or invalid, or I/O fails
+    pub async fn clone_shuriken(&self, src: &str, dest: &str) -> Result<()> {
+        let src_name = normalize_shuriken_name(src);
+        let dest_name = normalize_shuriken_name(dest);
+        info!("Cloning shuriken {} into {}", src_name, dest_name);
+
+        if dest_name.is_empty()
+            || dest_name == "."
+            || dest_name == ".."
+            || dest_name.contains(['/', '\\'])
+        {
+            return Err(Error::msg(format!("Invalid shuriken name: '{}'", dest)));
+        }
+
+        self.get(src_name.clone()).await?;
+
+        let shurikens_dir = self.root_path.join("shurikens");
+        let src_dir = shurikens_dir.join(&src_name);
+        let dest_dir = shurikens_dir.join(&dest_name);
+        if dest_dir.exists() || self.shurikens.read().await.contains_key(&dest_name) {
+            return Err(Error::msg(format!(
+                "Shuriken '{}' already exists",
+                dest_name
+            )));
+        }
+
+        let copy_dest = dest_dir.clone();
+        let copied =
+            tokio::task::spawn_blocking(move || copy_dir_all(&src_dir, &copy_dest)).await?;
+        if let Err(e) = copied {
+            let _ = fs::remove_dir_all(&dest_dir).await;
+            return Err(e.into());
+        }
+
+        let ninja_dir = dest_dir.join(".ninja");
+        let lock_path = ninja_dir.join("shuriken.lck");
+        if lock_path.exists() {
+            fs::remove_file(&lock_path).await?;
+        }
+
+        // Rewrite the identity fields, keeping the rest of the manifest intact
+        let manifest_path = ninja_dir.join("manifest.toml");
+        let mut manifest: toml::Table = toml::from_str(&fs::read_to_string(&manifest_path).await?)?;
+        if let Some(toml::Value::Table(meta)) = manifest.get_mut("shuriken") {
+            meta.insert("name".into(), toml::Value::String(dest_name.clone()));
+            meta.insert("id".into(), toml::Value::String(dest_name.clone()));
+        }
+        fs::write(&manifest_path, toml::to_string_pretty(&manifest)?).await?;
+
+        let shuriken = load_shuriken(&dest_dir)
+            .await?
+            .ok_or_else(|| Error::msg(format!("Clone of '{}' has no manifest", src_name)))?;
+        self.shurikens
+            .write()
+            .await
+            .insert(dest_name.clone(), shuriken);
+
+        info!("Cloned shuriken {} into {}", src_name, dest_name);
         Ok(())
     }
 
@@ -563,12 +2994,71 @@ impl ShurikenManager {
         let shuriken_dir = self.root_path.join("shurikens").join(&normalized_name);
         fs::remove_dir_all(&shuriken_dir).await?;
         let _ = &self.shurikens.write().await.remove(&normalized_name);
+        self.states_cache.invalidate();
         info!("Successfully deleted shuriken {}, refreshing.", name);
         #[cfg(debug_assertions)]
         dbg!("{:#?}", &self.shurikens);
         Ok(())
     }
 
+    /// Lists installed Shurikens matching any of `patterns`.
+    ///
+    /// Patterns may use `*` and `?` (see [`name_matches`]); a pattern without
+    /// wildcards must name an installed Shuriken.
+    ///
+    /// # Returns
+    /// - `Ok(names)` sorted and without duplicates
+    /// - `Err` with [`ManagerError::NotFound`] for a plain name that isn't installed
+    pub async fn matching_names(&self, patterns: &[String]) -> Result<Vec<String>> {
+        let shurikens = self.shurikens.read().await;
+        let mut names = BTreeSet::new();
+        for pattern in patterns {
+            if is_name_pattern(pattern) {
+                names.extend(
+                    shurikens
+                        .keys()
+                        .filter(|name| name_matches(pattern, name))
+                        .cloned(),
+                );
+            } else {
+                let name = normalize_shuriken_name(pattern);
+                if !shurikens.contains_key(&name) {
+                    return Err(ManagerError::NotFound(name).into());
+                }
+                names.insert(name);
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    /// Removes several Shurikens, stopping running ones first.
+    ///
+    /// Dependents are removed before the Shurikens they depend on. A
+    /// Shuriken that fails to stop is left installed.
+    ///
+    /// # Returns
+    /// - `Ok(results)` with each Shuriken's outcome, in the order they were run
+    /// - `Err` if ordering fails (unknown Shuriken or cycle)
+    pub async fn remove_many(&self, names: &[String]) -> Result<Vec<(String, Result<()>)>> {
+        let mut results = Vec::new();
+        for name in self.start_order(names).await?.into_iter().rev() {
+            let running = matches!(
+                self.status(&name).await,
+                Ok(ShurikenState::Running | ShurikenState::Paused)
+            );
+            let result = if running {
+                match self.stop(&name).await {
+                    Ok(()) => self.remove(&name).await,
+                    Err(e) => Err(e.context(format!("Not removing '{}'", name))),
+                }
+            } else {
+                self.remove(&name).await
+            };
+            results.push((name, result));
+        }
+        Ok(results)
+    }
+
     /// Resets and reinitializes the Lua scripting engine.
     ///
     /// Useful when you need to clear engine state between operations.
@@ -601,18 +3091,57 @@ impl ShurikenManager {
     /// - `Ok(())` if installation completed
     /// - `Err` if source is invalid or installation fails
     pub async fn install<R>(&self, source: &str, report: R) -> Result<()>
+    where
+        R: Reporter + Send + Sync + 'static,
+    {
+        self.install_with_options(source, report, &InstallOptions::default())
+            .await
+    }
+
+    /// Installs a Shuriken like [`ShurikenManager::install`], with extra options.
+    ///
+    /// # Arguments
+    /// - `source`: The Shuriken source (reference, URL, or file path)
+    /// - `options`: E.g. whether to run the postinstall script
+    pub async fn install_with_options<R>(
+        &self,
+        source: &str,
+        report: R,
+        options: &InstallOptions,
+    ) -> Result<()>
     where
         R: Reporter + Send + Sync + 'static,
     {
         if ShurikenReference::parse(&source).is_ok() {
             let reference = ShurikenReference::parse(&source)?;
-            self.install_from_registry(&reference, report).await
+            self.install_from_registry(&reference, report, options)
+                .await
         } else if source.starts_with("http://") || source.starts_with("https://") {
-            self.install_url(&source, report).await
+            self.install_url(source, report, options).await
         } else {
             let arc_tx = Arc::new(report);
-            self.install_file(&PathBuf::from(source), arc_tx).await
+            let path = Self::package_path(source)?;
+            self.install_file(&path, arc_tx, options).await
+        }
+    }
+
+    /// Resolves a local package path against the process working directory.
+    ///
+    /// A relative path is an error when the cwd is gone (e.g. a removed
+    /// shuriken's directory), rather than quietly resolving somewhere else and
+    /// installing the wrong file.
+    fn package_path(source: &str) -> Result<PathBuf> {
+        let path = Path::new(source);
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
         }
+        let cwd = env::current_dir().map_err(|e| {
+            Error::msg(format!(
+                "Can't resolve '{}': the current directory is unavailable ({}). Use an absolute path",
+                source, e
+            ))
+        })?;
+        Ok(cwd.join(path))
     }
 
     /// Installs a Shuriken from a direct URL.
@@ -621,18 +3150,19 @@ impl ShurikenManager {
     ///
     /// # Arguments
     /// - `url`: The download URL for the .shuriken file
+    /// - `options`: Install options, see [`InstallOptions`]
     ///
     /// # Returns
     /// - `Ok(())` if installation succeeded
     /// - `Err` if download or installation fails
-    pub async fn install_url<R>(&self, url: &str, tx: R) -> Result<()>
+    pub async fn install_url<R>(&self, url: &str, tx: R, options: &InstallOptions) -> Result<()>
     where
         R: Reporter + Send + Sync + 'static,
     {
         let temp_path = self.root_path.join("temp_shuriken.shuriken");
         download_shuriken(&temp_path, url, &tx).await?;
         let arc_tx = Arc::new(tx);
-        let result = self.install_file(&temp_path, arc_tx).await;
+        let result = self.install_file(&temp_path, arc_tx, options).await;
         let _ = fs::remove_file(temp_path).await; // clean up temp file
         result
     }
@@ -642,6 +3172,7 @@ impl ShurikenManager {
         &self,
         reference: &crate::common::config::ShurikenReference,
         tx: R,
+        options: &InstallOptions,
     ) -> Result<()>
     where
         R: Reporter + Send + Sync + 'static,
@@ -653,7 +3184,7 @@ impl ShurikenManager {
             "Installing shuriken {} from {}",
             reference.shuriken, download_url
         );
-        self.install_url(&download_url, tx).await
+        self.install_url(&download_url, tx, options).await
     }
 
     /// Installs a Shuriken from a local file.
@@ -663,6 +3194,7 @@ impl ShurikenManager {
     ///
     /// # Arguments
     /// - `path`: Path to the .shuriken file
+    /// - `options`: Install options, see [`InstallOptions`]
     ///
     /// # Returns
     /// - `Ok(())` if installation succeeded
@@ -675,22 +3207,70 @@ impl ShurikenManager {
     /// - archive_length (u32 LE)  
     /// - archive (tar.gz)
     /// - signature (32 bytes SHA256)
-    pub async fn install_file<R>(&self, path: &Path, tx: Arc<R>) -> Result<(), anyhow::Error>
+    /// - optional Ed25519 section, checked when `options.verify_key` is set
+    pub async fn install_file<R>(
+        &self,
+        path: &Path,
+        tx: Arc<R>,
+        options: &InstallOptions,
+    ) -> Result<(), anyhow::Error>
     where
         R: Reporter + Send + Sync + 'static,
     {
-        use sha2::{Digest, Sha256};
-        use std::io::Cursor;
-
         info!("Starting installation of {:?}", path);
         if !path.exists() {
             return Err(anyhow::Error::msg("Path does not exist"));
         }
 
-        let mut file = tokio::fs::File::open(&path)
+        let file = tokio::fs::File::open(&path)
             .await
             .map_err(|e| io::Error::other(format!("Failed to open shuriken file: {e}")))?;
 
+        self.install_reader(file, tx, options).await
+    }
+
+    /// Installs a Shuriken from a `.shuriken` package already in memory.
+    ///
+    /// Behaves like [`ShurikenManager::install_file`] without writing the
+    /// package to disk first, e.g. for uploads.
+    ///
+    /// # Arguments
+    /// - `bytes`: The whole `.shuriken` package
+    /// - `options`: Install options, see [`InstallOptions`]
+    ///
+    /// # Returns
+    /// - `Ok(())` if installation succeeded
+    /// - `Err` if the package is invalid, corrupted, incompatible, or extraction fails
+    pub async fn install_bytes<R>(
+        &self,
+        bytes: &[u8],
+        tx: R,
+        options: &InstallOptions,
+    ) -> Result<()>
+    where
+        R: Reporter + Send + Sync + 'static,
+    {
+        info!("Starting installation of {} byte package", bytes.len());
+        self.install_reader(std::io::Cursor::new(bytes), Arc::new(tx), options)
+            .await
+    }
+
+    /// Reads a `.shuriken` package from `file` and installs it.
+    ///
+    /// See [`ShurikenManager::install_file`] for the package format.
+    async fn install_reader<F, R>(
+        &self,
+        mut file: F,
+        tx: Arc<R>,
+        options: &InstallOptions,
+    ) -> Result<()>
+    where
+        F: tokio::io::AsyncRead + Unpin,
+        R: Reporter + Send + Sync + 'static,
+    {
+        use sha2::{Digest, Sha256};
+        use std::io::Cursor;
+
         tx.stage(InstallStage::Validating)?;
         tx.progress(0)?;
 
@@ -746,6 +3326,10 @@ impl ShurikenManager {
         let mut signature = [0u8; 32];
         file.read_exact(&mut signature).await?;
 
+        // 7) optional Ed25519 signature section
+        let mut trailer = Vec::new();
+        file.read_to_end(&mut trailer).await?;
+
         // Verify checksum = SHA256(MAGIC_BYTES + metadata_len + metadata + archive_len + archive)
         let mut hasher = Sha256::new();
         hasher.update(&archive_buf);
@@ -757,6 +3341,21 @@ impl ShurikenManager {
             ));
         }
 
+        if let Some(key_path) = &options.verify_key {
+            let key = signing::load_verifying_key(key_path)?;
+            let mut hasher = Sha256::new();
+            hasher.update(magic_buf);
+            hasher.update(meta_len_buf);
+            hasher.update(&metadata_buf);
+            hasher.update(archive_len_buf);
+            hasher.update(&archive_buf);
+            hasher.update(signature);
+            signing::verify_section(&trailer, hasher.finalize().as_slice(), &key)?;
+            info!("Package signature verified");
+        } else if !trailer.is_empty() {
+            debug!("Package is signed, but no key was given to verify it with");
+        }
+
         // Platform check
         if !metadata.platform.contains(env::consts::OS)
             && !metadata.platform.contains(env::consts::ARCH)
@@ -770,71 +3369,127 @@ impl ShurikenManager {
         tx.progress(20)?;
 
         // Unpack archive in blocking task
+        let format = ArchiveFormat::detect(&archive_buf)?;
+        debug!("Package payload is {:?}", format);
         let archive_cursor = Cursor::new(archive_buf);
         let archive_name = normalize_shuriken_name(&metadata.name);
         let unpack_path = self.root_path.clone().join("shurikens").join(&archive_name);
         let root_path = self.root_path.clone().join("shurikens").join(&archive_name);
         let thread_tx = tx.clone();
 
-        fs::create_dir_all(&unpack_path).await?;
+        // Move an existing install aside rather than unpacking over it, so no
+        // stale files survive and a rejected archive can be rolled back.
+        let mut replaced: Option<tempfile::TempDir> = None;
+        let mut preserved_options: Option<Vec<u8>> = None;
+        if unpack_path.exists() {
+            if !options.replace {
+                return Err(ManagerError::AlreadyInstalled(archive_name).into());
+            }
 
-        tokio::task::spawn_blocking(move || -> Result<()> {
-            let gz_decoder = GzDecoder::new(archive_cursor);
-            let mut archive = tar::Archive::new(gz_decoder);
-            let entries = archive.entries()?;
-            let total = entries.size_hint().0.max(1);
+            let state = match self.shurikens.read().await.get(&archive_name) {
+                Some(shuriken) => Some(shuriken.state.lock().await.clone()),
+                None => None,
+            };
+            if matches!(state, Some(ShurikenState::Running | ShurikenState::Paused)) {
+                info!("Stopping {} before replacing it", archive_name);
+                self.stop(&archive_name).await?;
+            }
 
-            let mut count = 0;
+            preserved_options = fs::read(unpack_path.join(".ninja").join("options.toml"))
+                .await
+                .ok();
+            let backup = tempfile::Builder::new()
+                .prefix(".replaced-")
+                .tempdir_in(&self.root_path)?;
+            fs::rename(&unpack_path, backup.path().join(&archive_name)).await?;
+            replaced = Some(backup);
+        }
+        fs::create_dir_all(&unpack_path).await?;
 
-            for entry in entries {
-                let mut entry = entry?;
+        let allow_links = self.config.read().await.allow_symlinks;
+        let target = unpack_path.clone();
 
-                entry.unpack_in(&unpack_path)?;
+        let unpacked = tokio::task::spawn_blocking(move || -> Result<usize> {
+            let decoder = format.decoder(archive_cursor)?;
+            unpack_archive_safely(decoder, &target, allow_links, |count| {
+                // Entry count isn't known upfront, so creep towards 80%
+                thread_tx.progress((20 + count).min(80) as u8)
+            })
+        })
+        .await?;
+
+        if let Err(e) = unpacked {
+            warn!("Rejected archive for {}: {}", archive_name, e);
+            // Don't leave half of a rejected archive behind
+            let _ = fs::remove_dir_all(&unpack_path).await;
+            if let Some(backup) = &replaced {
+                fs::rename(backup.path().join(&archive_name), &unpack_path).await?;
+            }
+            return Err(e);
+        }
 
-                count += 1;
+        // The previous install is kept until everything below succeeds, so a
+        // failing postinstall or configure can put it back
+        let finished: Result<()> = async {
+            if let Some(options_toml) = preserved_options {
+                let ninja_dir = unpack_path.join(".ninja");
+                fs::create_dir_all(&ninja_dir).await?;
+                fs::write(ninja_dir.join("options.toml"), options_toml).await?;
+            }
 
-                let progress =
-                    (20.0 + (count as f64 / total as f64 * 60.0)).clamp(20.0, 80.0) as u8;
+            tx.stage(InstallStage::PostInstall)?;
+            tx.progress(90)?;
+
+            // Run postinstall script if present
+            if metadata.postinstall.is_some() && !options.run_postinstall {
+                info!("Skipping postinstall script for {}", metadata.name);
+            } else if let Some(pi_script) = &metadata.postinstall {
+                debug!("original postinstall script path: {:#?}", &pi_script);
+                let path = normalize_path(pi_script.as_path());
+                let path = parse_path(&root_path, path.display().to_string(), None);
+                debug!("normalized postinstall script path: {:#?}", &path);
+                info!("Running postinstall script");
+                let engine = &self.engine.lock().await;
+                engine
+                    .execute_file(&path, Some(&root_path), Some(self.clone()))
+                    .await?;
+            }
 
-                thread_tx.progress(progress)?;
+            // save config so the paths are correct when we launch.
+            self.refresh().await?;
+            debug!("Shurikens currently: {:#?}", self.list(false).await);
+            if let Some(shuriken) = self
+                .shurikens
+                .read()
+                .await
+                .get(&normalize_shuriken_name(&metadata.name))
+                && shuriken.config.is_some()
+            {
+                shuriken
+                    .configure(
+                        &self.root_path,
+                        &*self.engine.lock().await,
+                        Some(self.clone()),
+                    )
+                    .await?;
             }
             Ok(())
-        })
-        .await??;
-
-        tx.stage(InstallStage::PostInstall)?;
-        tx.progress(90)?;
-
-        // Run postinstall script if present
-        if let Some(pi_script) = &metadata.postinstall {
-            debug!("original postinstall script path: {:#?}", &pi_script);
-            let path = normalize_path(pi_script.as_path());
-            let path = parse_path(&root_path, path.display().to_string(), None);
-            debug!("normalized postinstall script path: {:#?}", &path);
-            info!("Running postinstall script");
-            let engine = &self.engine.lock().await;
-            engine
-                .execute_file(&path, Some(&root_path), Some(self.clone()))
-                .await?;
-        }
-
-        // save config so the paths are correct when we launch.
-        self.refresh().await?;
-        debug!("Shurikens currently: {:#?}", self.list(false).await);
-        if let Some(shuriken) = self
-            .shurikens
-            .read()
-            .await
-            .get(&normalize_shuriken_name(&metadata.name))
-            && shuriken.config.is_some()
-        {
-            shuriken
-                .configure(
-                    &self.root_path,
-                    &*self.engine.lock().await,
-                    Some(self.clone()),
-                )
-                .await?;
+        }
+        .await;
+
+        if let Err(e) = finished {
+            if let Some(backup) = &replaced {
+                warn!(
+                    "Installing {} failed, restoring the previous install: {}",
+                    archive_name, e
+                );
+                let _ = fs::remove_dir_all(&unpack_path).await;
+                fs::rename(backup.path().join(&archive_name), &unpack_path).await?;
+                if let Err(refresh_err) = self.refresh().await {
+                    warn!("Failed to reload {}: {}", archive_name, refresh_err);
+                }
+            }
+            return Err(e);
         }
 
         tx.stage(InstallStage::Installed)?;
@@ -867,6 +3522,41 @@ impl ShurikenManager {
             .await
     }
 
+    /// Updates the executable at `exe` to the newest CLI release of the update registry.
+    ///
+    /// Only the registry named by `update_registry` in the config is asked,
+    /// and releases must be signed with its `update_key`. See
+    /// [`self_update`](crate::common::self_update) for how releases are
+    /// published and verified.
+    ///
+    /// # Returns
+    /// - `Ok(UpdateOutcome)` telling whether `exe` was replaced
+    /// - `Err` if no update key is configured, no release is available for
+    ///   this platform, `exe` can't be written to, or the download doesn't
+    ///   match its checksum or signature
+    pub async fn self_update(&self, current_version: &str, exe: &Path) -> Result<UpdateOutcome> {
+        let config = self.config.read().await.clone();
+        let url = config
+            .registries
+            .get(&config.update_registry)
+            .with_context(|| {
+                format!(
+                    "Update registry '{}' is not configured",
+                    config.update_registry
+                )
+            })?;
+        let key = config.update_key.as_deref().context(
+            "No update_key is configured; self-update only installs releases signed with it",
+        )?;
+        let key = signing::parse_verifying_key(key)?;
+
+        let sources = RegistrySources::new(HashMap::from([(
+            config.update_registry.clone(),
+            url.clone(),
+        )]));
+        self_update::self_update(&sources, current_version, exe, &key).await
+    }
+
     // -------------------- Project management API --------------------
 
     /// Lists all projects in the projects directory.
@@ -1,6 +1,5 @@
 use crate::common::structs::NoopReporter;
-use crate::scripting::NinjaEngine;
-use crate::{common::types::FieldValue, manager::ShurikenManager};
+use crate::{common::types::FieldValue, manager::ShurikenManager, scripting::NinjaEngine};
 use anyhow::{Error, Result, bail};
 use either::Either;
 use log::debug;
@@ -21,7 +20,7 @@ pub enum Command {
     Stop,
     /// Select a Shuriken by name for subsequent operations
     Select(String),
-    /// Get a configuration value by key
+    /// Get a configuration value by key, dotted path or expression
     Get(String),
     /// Deselect the current Shuriken
     Exit,
@@ -39,6 +38,10 @@ pub enum Command {
     Install(String),
     /// Toggle a boolean configuration value
     Toggle(String),
+    /// Persist the selected Shuriken's pending options to disk
+    Save,
+    /// Show how the selected Shuriken's pending options differ from disk
+    Diff,
     /// Execute a Ninja script file
     Execute(PathBuf),
     /// Display help information
@@ -67,31 +70,11 @@ fn strip_comments(line: &str) -> &str {
 
 /// Parses a raw string into a typed `FieldValue`.
 ///
-/// Supports quoted strings, booleans (`true`/`false`), and integers.
-/// Falls back to a string if no specific type matches.
+/// Supports quoted strings, booleans (`true`/`false`), integers and floats.
+/// Falls back to a string if no specific type matches. Shares its rules
+/// with `FieldValue::from` so both paths agree on the inferred type.
 fn parse_value(raw: &str) -> FieldValue {
-    let v = raw.trim();
-
-    // quoted string support
-    if (v.starts_with('"') && v.ends_with('"')) || (v.starts_with('\'') && v.ends_with('\'')) {
-        let inner = &v[1..v.len() - 1];
-        return FieldValue::String(inner.to_string());
-    }
-
-    // boolean
-    match v.to_ascii_lowercase().as_str() {
-        "true" => return FieldValue::Bool(true),
-        "false" => return FieldValue::Bool(false),
-        _ => {}
-    }
-
-    // integer (i64)
-    if let Ok(i) = v.parse::<i64>() {
-        return FieldValue::Number(i);
-    }
-
-    // fallback: raw string
-    FieldValue::String(v.to_string())
+    FieldValue::from(raw)
 }
 
 /// Parses a single key-value assignment (e.g., `key = value`).
@@ -249,7 +232,7 @@ fn command_parser(script: &str) -> Result<Vec<Command>> {
                 "help" => Command::Help,
                 "get" => {
                     if tokens.len() > 1 {
-                        Command::Get(tokens[1].clone())
+                        Command::Get(tokens[1..].join(" "))
                     } else {
                         Command::None
                     }
@@ -292,6 +275,8 @@ fn command_parser(script: &str) -> Result<Vec<Command>> {
                         Command::None
                     }
                 }
+                "save" => Command::Save,
+                "diff" => Command::Diff,
                 "exit" => Command::Exit,
                 "configure" => Command::Configure, // fallback if no {}
                 _ => Command::None,
@@ -362,6 +347,45 @@ impl DslEngine {
         }
     }
 
+    /// Looks up an option of a Shuriken, as `get` does.
+    ///
+    /// `expression` is first tried as a literal option name, then evaluated
+    /// with [`NinjaEngine::eval_expression`](crate::scripting::NinjaEngine::eval_expression),
+    /// so dotted paths like `server.port` and small expressions work too.
+    /// Pending values from `set` are visible before they're saved.
+    ///
+    /// # Returns
+    /// - `Ok(value)` as JSON, `null` if the option isn't set
+    /// - `Err` if the Shuriken is unknown or the expression is invalid
+    pub async fn get_value(
+        &self,
+        shuriken_name: &str,
+        expression: &str,
+    ) -> Result<serde_json::Value> {
+        let options = self
+            .manager
+            .shurikens
+            .read()
+            .await
+            .get(shuriken_name)
+            .ok_or_else(|| Error::msg(format!("No such shuriken: {}", shuriken_name)))?
+            .config
+            .as_ref()
+            .and_then(|cfg| cfg.options.clone())
+            .unwrap_or_default();
+
+        if let Some(value) = options.get(expression) {
+            return Ok(serde_json::to_value(value)?);
+        }
+
+        self.manager
+            .engine
+            .lock()
+            .await
+            .eval_expression(expression, &options)
+            .map_err(|e| Error::msg(format!("Invalid expression '{}': {}", expression, e)))
+    }
+
     /// Parses and executes a series of DSL commands.
     ///
     /// Processes the DSL script, executing each command in sequence
@@ -432,26 +456,55 @@ impl DslEngine {
 
                 // New: configure block
                 Command::ConfigureBlock(kvs) => {
-                    if let Some(shuriken_name) = &*self.selected.read().await {
+                    let Some(shuriken_name) = self.selected.read().await.clone() else {
+                        output.push("No shuriken selected — configure block ignored.".into());
+                        continue;
+                    };
+
+                    let merged_options = {
                         let mut shurikens = self.manager.shurikens.write().await;
-                        if let Some(shuriken) = shurikens.get_mut(shuriken_name)
+                        if let Some(shuriken) = shurikens.get_mut(&shuriken_name)
                             && let Some(cfg) = &mut shuriken.config
                         {
                             let partial_options = cfg.options.get_or_insert_with(Default::default);
                             for (k, v) in kvs {
-                                partial_options.insert(k.clone(), v.clone());
                                 output.push(format!(
                                     "Set {} = {} for {}",
                                     k,
                                     v.render(),
                                     shuriken_name
                                 ));
+                                partial_options.insert(k, v);
                             }
+                            Some(partial_options.clone())
                         } else {
-                            output.push("No selected shuriken or missing config while applying configure block.".to_string());
+                            None
+                        }
+                    };
+
+                    let Some(merged_options) = merged_options else {
+                        output.push("No selected shuriken or missing config while applying configure block.".to_string());
+                        continue;
+                    };
+
+                    match self
+                        .manager
+                        .save_shuriken_config(&shuriken_name, merged_options)
+                        .await
+                    {
+                        Ok(_) => output.push(format!("Saved options for {}", shuriken_name)),
+                        Err(e) => {
+                            output.push(format!("Failed to save options: {}", e));
+                            continue;
                         }
-                    } else {
-                        output.push("No shuriken selected — configure block ignored.".into());
+                    }
+
+                    match self.manager.configure_shuriken(&shuriken_name).await {
+                        Ok(_) => output.push(format!(
+                            "Generated configuration for shuriken {} successfully.",
+                            shuriken_name
+                        )),
+                        Err(e) => output.push(format!("Failed to generate configuration: {}", e)),
                     }
                 }
 
@@ -463,8 +516,10 @@ impl DslEngine {
                   configure                - Generate configuration for the selected shuriken
                   configure { k = v }      - Apply config assignments to the selected shuriken
                   set <key> <value>        - Set a config key for the selected shuriken
-                  get <key>                - Get a config key's value
+                  get <key|expr>           - Get a config value, dotted path or expression
                   toggle <key>             - Toggle a boolean config key
+                  save                     - Write pending options to options.toml
+                  diff                     - Show options that differ from options.toml
                   start                    - Start the selected shuriken
                   stop                     - Stop the selected shuriken
                   install <url | registry_entry | path>           - Install a new shuriken from a file, url or a registry entry
@@ -484,30 +539,24 @@ impl DslEngine {
                         if let Some(shuriken) = shurikens.get_mut(shuriken_name)
                             && let Some(cfg) = &mut shuriken.config
                         {
-                            let cloned_value: FieldValue = value.clone();
-                            if let Some(partial_options) = &mut cfg.options {
-                                partial_options
-                                    .insert(key.clone(), FieldValue::from(value.render()));
-                            }
-
                             output.push(format!(
                                 "Set {} = {} for {}",
                                 key,
-                                cloned_value.render(),
+                                value.render(),
                                 shuriken_name
                             ));
+                            if let Some(partial_options) = &mut cfg.options {
+                                partial_options.insert(key, value);
+                            }
                         }
                     }
                 }
 
                 Command::Get(key) => {
                     if let Some(shuriken_name) = &*self.selected.read().await {
-                        let shurikens = &self.manager.shurikens.read().await;
-                        if let Some(shuriken) = shurikens.get(shuriken_name)
-                            && let Some(cfg) = &shuriken.config
-                            && let Some(options) = &cfg.options
-                        {
-                            output.push(format!("{:?} = {:?}", key, options.get(&key)));
+                        match self.get_value(shuriken_name, &key).await {
+                            Ok(value) => output.push(format!("{} = {}", key, value)),
+                            Err(e) => output.push(format!("Failed to get {}: {}", key, e)),
                         }
                     }
                 }
@@ -526,6 +575,60 @@ impl DslEngine {
                     }
                 }
 
+                Command::Save => {
+                    let Some(shuriken_name) = self.selected.read().await.clone() else {
+                        output.push("No shuriken selected — nothing to save.".into());
+                        continue;
+                    };
+
+                    // Saving nothing would wipe options.toml, so a Shuriken
+                    // without loaded options is an error rather than empty
+                    let options = self
+                        .manager
+                        .get(shuriken_name.clone())
+                        .await?
+                        .config
+                        .and_then(|cfg| cfg.options)
+                        .ok_or_else(|| {
+                            Error::msg(format!("{} has no loaded options to save", shuriken_name))
+                        })?;
+
+                    match self
+                        .manager
+                        .save_shuriken_config(&shuriken_name, options)
+                        .await
+                    {
+                        Ok(_) => output.push(format!("Saved options for {}", shuriken_name)),
+                        Err(e) => output.push(format!("Failed to save options: {}", e)),
+                    }
+                }
+
+                Command::Diff => {
+                    let Some(shuriken_name) = self.selected.read().await.clone() else {
+                        output.push("No shuriken selected — nothing to diff.".into());
+                        continue;
+                    };
+
+                    match self.manager.diff_config(&shuriken_name).await {
+                        Ok(changes) if changes.is_empty() => {
+                            output.push(format!("No unsaved changes for {}", shuriken_name))
+                        }
+                        Ok(changes) => {
+                            for (key, old, new) in changes {
+                                output.push(match (old, new) {
+                                    (None, Some(new)) => format!("+ {} = {}", key, new.render()),
+                                    (Some(old), None) => format!("- {} = {}", key, old.render()),
+                                    (Some(old), Some(new)) => {
+                                        format!("~ {} = {} -> {}", key, old.render(), new.render())
+                                    }
+                                    (None, None) => continue,
+                                });
+                            }
+                        }
+                        Err(e) => output.push(format!("Failed to diff options: {}", e)),
+                    }
+                }
+
                 // Shuriken management
                 Command::List => {
                     if let Either::Right(names) = &self.manager.list(false).await? {
@@ -558,13 +661,18 @@ impl DslEngine {
                 }
 
                 Command::Execute(script_path) => {
-                    let engine = NinjaEngine::new()
-                        .await
-                        .map_err(|e| io::Error::other(e.to_string()))?;
-                    engine
-                        .execute_file(&script_path, None, Some(self.manager.clone()))
-                        .await
-                        .map_err(|e| io::Error::other(e.to_string()))?;
+                    // Clone the handle rather than holding the lock, so the
+                    // script can call back into the manager (e.g. `ninja.start`).
+                    let mut engine = self.manager.engine.lock().await.clone();
+                    engine.bind_selection(self.selected.clone());
+                    let (result, printed) = NinjaEngine::capture(engine.execute_file(
+                        &script_path,
+                        None,
+                        Some(self.manager.clone()),
+                    ))
+                    .await;
+                    output.extend(printed.lines().map(String::from));
+                    result.map_err(|e| io::Error::other(e.to_string()))?;
                 }
                 Command::Install(file_path) => {
                     let reporter = NoopReporter {};
@@ -598,6 +706,7 @@ impl DslEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_strip_comments() {
@@ -655,6 +764,25 @@ mod tests {
             _ => panic!("Expected Number"),
         }
 
+        // Test float
+        assert_eq!(parse_value("1.5"), FieldValue::Float(1.5));
+        assert_eq!(parse_value("-2.5"), FieldValue::Float(-2.5));
+        assert_eq!(parse_value("2"), FieldValue::Number(2));
+        assert_eq!(parse_value("1.0"), FieldValue::Float(1.0));
+        assert_eq!(parse_value("2.0").render(), "2.0");
+        assert_eq!(parse_value("2e3"), FieldValue::Float(2000.0));
+        assert_eq!(parse_value("1.5E-2"), FieldValue::Float(0.015));
+        // Version-like decimals stay as written
+        assert_eq!(parse_value("3.10"), FieldValue::String("3.10".to_string()));
+        assert_eq!(parse_value("3.10").render(), "3.10");
+        assert_eq!(parse_value("1."), FieldValue::String("1.".to_string()));
+        assert_eq!(
+            parse_value("1.2.3"),
+            FieldValue::String("1.2.3".to_string())
+        );
+        assert_eq!(parse_value("nan"), FieldValue::String("nan".to_string()));
+        assert_eq!(parse_value("inf"), FieldValue::String("inf".to_string()));
+
         // Test fallback to string
         let val = parse_value("unquoted");
         match val {
@@ -663,6 +791,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_float_options_round_trip_through_toml() {
+        let options = HashMap::from([
+            ("ratio".to_string(), parse_value("1.5")),
+            ("whole".to_string(), FieldValue::Float(2.0)),
+            ("count".to_string(), parse_value("2")),
+        ]);
+
+        let text = toml::to_string(&options).unwrap();
+        let parsed: HashMap<String, FieldValue> = toml::from_str(&text).unwrap();
+        assert_eq!(parsed, options);
+        assert_eq!(parsed["whole"].render(), "2.0");
+        assert_eq!(parsed["ratio"].render(), "1.5");
+    }
+
     #[test]
     fn test_parse_kv() {
         // Test valid key-value pair
@@ -780,6 +923,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_value_agrees_with_from() {
+        for raw in ["8080", "true", "FALSE", "'quoted'", "\"42\"", "plain"] {
+            let parsed = toml::Value::from(parse_value(raw));
+            let converted = toml::Value::from(FieldValue::from(raw.to_string()));
+            assert_eq!(parsed, converted, "disagreement on {raw}");
+        }
+    }
+
+    #[test]
+    fn test_command_parser_set_keeps_number() {
+        let result = command_parser("set port 8080").unwrap();
+        match &result[0] {
+            Command::Set {
+                value: FieldValue::Number(n),
+                ..
+            } => assert_eq!(*n, 8080),
+            other => panic!("Expected numeric Set, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_command_parser_save() {
+        let result = command_parser("save").unwrap();
+        assert!(matches!(result[..], [Command::Save]));
+    }
+
     #[test]
     fn test_command_parser_set() {
         // Test set command
@@ -13,22 +13,37 @@ pub mod dsl;
 pub mod templater;
 
 mod modules;
-use crate::{manager::ShurikenManager, utils::resolve_path};
+use crate::{common::types::FieldValue, manager::ShurikenManager, utils::resolve_path};
 use log::info;
-use mlua::{Error as LuaError, Lua};
+use mlua::{
+    Error as LuaError, FromLuaMulti, Function, IntoLua, IntoLuaMulti, Lua, LuaSerdeExt, MultiValue,
+    Table,
+};
 use modules::{
-    make_env_module, make_fs_module, make_modules, make_ninja_module, make_proc_module,
-    make_shell_module,
+    make_env_module, make_fs_module, make_http_module, make_modules, make_ninja_module,
+    make_proc_module, make_shell_module, make_store_module,
 };
 use regex::Regex;
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     fs,
+    future::Future,
     path::{Path, PathBuf},
+    sync::Arc,
 };
+use tokio::sync::RwLock;
 
+/// The Lua scripting engine used to run Shuriken scripts.
+///
+/// Cloning is cheap: clones are handles to the same Lua state, so globals
+/// registered through one clone are visible through all of them.
 #[derive(Clone, Debug)]
 pub struct NinjaEngine {
     preload_dir: Option<PathBuf>,
+    selected: Option<Arc<RwLock<Option<String>>>>,
+    /// Globals each script loaded by [`NinjaEngine::reload_script`] defined
+    reloaded: Arc<std::sync::Mutex<HashMap<PathBuf, Vec<String>>>>,
     #[cfg(feature = "testing")]
     pub lua: Lua,
     #[cfg(not(feature = "testing"))]
@@ -87,10 +102,17 @@ impl NinjaEngine {
         globals.set("http", http)?;
         globals.set("log", log)?;
         globals.set("proc", proc)?;
+        globals.set("store", make_store_module(&lua, None)?)?;
+
+        Self::confine_require(&lua)?;
+
+        Self::redirect_print(&lua)?;
 
         let engine = Self {
             lua,
             preload_dir: Some(PathBuf::from(".ninja/preloads")),
+            selected: None,
+            reloaded: Default::default(),
         };
 
         engine.load_preloads()?;
@@ -98,11 +120,221 @@ impl NinjaEngine {
         Ok(engine)
     }
 
+    /// Restricts `require` to directories registered with [`NinjaEngine::add_require_path`].
+    ///
+    /// The default search paths (cwd, system dirs) are dropped and module
+    /// names that could walk out of an allowed root are rejected.
+    fn confine_require(lua: &Lua) -> Result<(), LuaError> {
+        let globals = lua.globals();
+        let package: Table = globals.get("package")?;
+        package.set("path", "")?;
+        package.set("cpath", "")?;
+
+        let require: Function = globals.get("require")?;
+        let guarded = lua.create_function(move |_, name: String| {
+            check_module_name(&name)?;
+            require.call::<MultiValue>(name)
+        })?;
+        globals.set("require", guarded)
+    }
+
+    /// Gives `env` a `require` and `package` of its own, rooted at `dir`.
+    ///
+    /// Modules run in `env` and are cached in its own `package.loaded`, so
+    /// scripts that overlap never change each other's search path or see
+    /// each other's modules. The global `package` is left alone.
+    fn install_require(&self, env: &Table, dir: &Path) -> Result<(), LuaError> {
+        let lua = &self.lua;
+        let root = dir.display().to_string();
+
+        let loaded = lua.create_table()?;
+        let package = lua.create_table()?;
+        package.set("path", format!("{root}/?.lua;{root}/?/init.lua"))?;
+        package.set("loaded", loaded.clone())?;
+        env.set("package", package)?;
+
+        // The env and cache are passed in rather than captured, a Rust closure
+        // holding them would keep the env alive for as long as the Lua state
+        let loader = lua.create_function(
+            |lua, (env, loaded, root, name): (Table, Table, String, String)| {
+                check_module_name(&name)?;
+                let cached: mlua::Value = loaded.raw_get(name.as_str())?;
+                if !cached.is_nil() {
+                    return Ok(cached);
+                }
+
+                let relative = name.replace('.', "/");
+                let file = [format!("{relative}.lua"), format!("{relative}/init.lua")]
+                    .into_iter()
+                    .map(|candidate| Path::new(&root).join(candidate))
+                    .find(|path| path.is_file())
+                    .ok_or_else(|| {
+                        LuaError::RuntimeError(format!("module '{name}' not found in {root}"))
+                    })?;
+
+                let module: mlua::Value = lua
+                    .load(fs::read_to_string(&file)?)
+                    .set_name(file.display().to_string())
+                    .set_environment(env)
+                    .call(name.as_str())?;
+                let module = if module.is_nil() {
+                    mlua::Value::Boolean(true)
+                } else {
+                    module
+                };
+                loaded.raw_set(name.as_str(), module.clone())?;
+                Ok(module)
+            },
+        )?;
+        let require: Function = lua
+            .load(
+                "local loader, env, loaded, root = ...\n\
+                 return function(name) return loader(env, loaded, root, name) end",
+            )
+            .set_name("require")
+            .call((loader, env.clone(), loaded, root))?;
+        env.set("require", require)
+    }
+
+    /// Wraps `print` so its output goes to the capture of the execution
+    /// calling it, if it runs under [`NinjaEngine::capture`].
+    fn redirect_print(lua: &Lua) -> Result<(), LuaError> {
+        let globals = lua.globals();
+        let print: Function = globals.get("print")?;
+        let tostring: Function = globals.get("tostring")?;
+
+        let redirected = lua.create_function(move |_, args: MultiValue| {
+            if CAPTURED.try_with(|_| ()).is_err() {
+                return print.call::<()>(args);
+            }
+
+            // Render before borrowing the buffer, `__tostring` may print too
+            let line = args
+                .into_iter()
+                .map(|value| tostring.call::<String>(value))
+                .collect::<Result<Vec<_>, _>>()?
+                .join("\t");
+
+            let _ = CAPTURED.try_with(|buffer| {
+                let mut buffer = buffer.borrow_mut();
+                buffer.push_str(&line);
+                buffer.push('\n');
+            });
+            Ok(())
+        })?;
+        globals.set("print", redirected)
+    }
+
+    /// Runs `execution`, collecting what its scripts `print` instead of
+    /// writing it to stdout.
+    ///
+    /// The capture belongs to this call alone: scripts that run concurrently
+    /// on other tasks keep printing to stdout.
+    ///
+    /// # Returns
+    /// What `execution` returned and everything it printed
+    pub async fn capture<F: Future>(execution: F) -> (F::Output, String) {
+        CAPTURED
+            .scope(RefCell::new(String::new()), async move {
+                let output = execution.await;
+                (output, CAPTURED.with(RefCell::take))
+            })
+            .await
+    }
+
+    /// Lets scripts `require` Lua modules that live in `dir`.
+    ///
+    /// `require("helpers")` resolves to `dir/helpers.lua` or `dir/helpers/init.lua`.
+    /// The most recently added directory is searched first.
+    pub fn add_require_path(&self, dir: impl AsRef<Path>) -> Result<(), LuaError> {
+        let root = dir.as_ref().display().to_string();
+        let entries = [format!("{root}/?.lua"), format!("{root}/?/init.lua")];
+
+        let package: Table = self.lua.globals().get("package")?;
+        let current: String = package.get("path")?;
+        let path = entries
+            .iter()
+            .cloned()
+            .chain(
+                current
+                    .split(';')
+                    .filter(|entry| !entry.is_empty() && !entries.iter().any(|e| e == entry))
+                    .map(String::from),
+            )
+            .collect::<Vec<_>>()
+            .join(";");
+
+        package.set("path", path)
+    }
+
+    /// Binds the DSL's selected Shuriken so scripts can read it through `ninja.selected()`.
+    pub fn bind_selection(&mut self, selected: Arc<RwLock<Option<String>>>) {
+        self.selected = Some(selected);
+    }
+
+    /// Exposes a host-provided value to scripts as a global named `name`.
+    ///
+    /// The value stays set until it is overwritten, so callers that inject
+    /// per-shuriken context should register it right before executing.
+    pub fn register_global(&self, name: &str, value: impl IntoLua) -> Result<(), LuaError> {
+        self.lua.globals().set(name, value)
+    }
+
     pub async fn check_function_exists(
         &self,
         function: &str,
-        path: &PathBuf,
+        path: impl AsRef<Path>,
     ) -> Result<bool, LuaError> {
+        let (env, returned) = self.load_isolated(path.as_ref()).await?;
+
+        // Try to get the function from the returned value first (if it's a table)
+        let exists = match returned {
+            mlua::Value::Table(table) => {
+                !matches!(table.get::<mlua::Value>(function)?, mlua::Value::Nil)
+            }
+            _ => !matches!(env.get::<mlua::Value>(function)?, mlua::Value::Nil),
+        };
+
+        Ok(exists)
+    }
+
+    /// Lists which of `functions` a script doesn't define.
+    ///
+    /// The script's top level runs in an isolated environment, since that is
+    /// where its functions get defined, but none of them are called. A
+    /// function counts if it is a global or a field of the table the script
+    /// returns; anything other than a function under that name is missing.
+    ///
+    /// # Returns
+    /// - `Ok(missing)` in the order given, empty if all are defined
+    /// - `Err` if the script can't be read, doesn't parse or its top level fails
+    pub async fn missing_functions(
+        &self,
+        path: impl AsRef<Path>,
+        functions: &[&str],
+    ) -> Result<Vec<String>, LuaError> {
+        let (env, returned) = self.load_isolated(path.as_ref()).await?;
+
+        let mut missing = Vec::new();
+        for function in functions {
+            let in_table = match &returned {
+                mlua::Value::Table(table) => table.get::<mlua::Value>(*function)?,
+                _ => mlua::Value::Nil,
+            };
+            let defined =
+                in_table.is_function() || env.raw_get::<mlua::Value>(*function)?.is_function();
+            if !defined {
+                missing.push(function.to_string());
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Runs a script's top level in a scratch environment that inherits the globals.
+    ///
+    /// # Returns
+    /// The environment the script defined its globals in and what it returned.
+    async fn load_isolated(&self, path: &Path) -> Result<(Table, mlua::Value), LuaError> {
         let lua = &self.lua;
 
         let script = fs::read_to_string(path)?;
@@ -114,21 +346,15 @@ impl NinjaEngine {
         let globals = lua.globals();
         env.set_metatable(Some(lua.create_table_from([("__index", globals)])?))?;
 
-        // Load script into the isolated environment
-        let chunk = lua.load(&script).set_environment(env.clone());
-
-        // Execute and capture the return value
-        let result: mlua::Value = chunk.eval_async().await?;
+        // Load script into the isolated environment and capture the return value
+        let returned = lua
+            .load(&script)
+            .set_name(path.display().to_string())
+            .set_environment(env.clone())
+            .eval_async()
+            .await?;
 
-        // Try to get the function from the returned value first (if it's a table)
-        let exists = match result {
-            mlua::Value::Table(table) => {
-                !matches!(table.get::<mlua::Value>(function)?, mlua::Value::Nil)
-            }
-            _ => !matches!(env.get::<mlua::Value>(function)?, mlua::Value::Nil),
-        };
-
-        Ok(exists)
+        Ok((env, returned))
     }
 
     /// Execute a raw Lua script in the global environment.
@@ -141,97 +367,344 @@ impl NinjaEngine {
         let globals = self.lua.globals();
 
         if let Some(mgr) = mgr {
-            let ninja = make_ninja_module(&self.lua, mgr)?;
+            let ninja = make_ninja_module(&self.lua, mgr, self.selected.clone())?;
             globals.set("ninja", ninja)?;
         }
 
         let fs = make_fs_module(&self.lua, cwd)?;
         let env = make_env_module(&self.lua, cwd)?;
         let shell = make_shell_module(&self.lua, cwd)?;
-        let proc = make_proc_module(&self.lua, cwd)?;
+        let http = make_http_module(&self.lua, cwd)?;
+        let proc = make_proc_module(&self.lua, cwd, &HashMap::new())?;
+        let store = make_store_module(&self.lua, cwd)?;
         globals.set("fs", fs)?;
         globals.set("env", env)?;
         globals.set("shell", shell)?;
+        globals.set("http", http)?;
         globals.set("proc", proc)?;
+        globals.set("store", store)?;
 
         info!("Executing lua script.");
         self.lua.load(script).exec_async().await
     }
 
+    /// Builds the `ninja` module and the modules bound to `cwd`, if given.
+    ///
+    /// `spawn_env` is added to the environment of processes `proc` starts.
+    fn script_modules(
+        &self,
+        cwd: Option<&Path>,
+        mgr: Option<ShurikenManager>,
+        spawn_env: &HashMap<String, String>,
+    ) -> Result<Vec<(&'static str, Table)>, LuaError> {
+        let mut modules = Vec::new();
+
+        if let Some(mgr) = mgr {
+            modules.push((
+                "ninja",
+                make_ninja_module(&self.lua, mgr, self.selected.clone())?,
+            ));
+        }
+
+        if let Some(cwd) = cwd {
+            modules.extend([
+                ("fs", make_fs_module(&self.lua, Some(cwd))?),
+                ("env", make_env_module(&self.lua, Some(cwd))?),
+                ("shell", make_shell_module(&self.lua, Some(cwd))?),
+                ("http", make_http_module(&self.lua, Some(cwd))?),
+                ("proc", make_proc_module(&self.lua, Some(cwd), spawn_env)?),
+                ("store", make_store_module(&self.lua, Some(cwd))?),
+            ]);
+        }
+
+        Ok(modules)
+    }
+
+    /// Installs the `ninja` module and cwd-bound modules, then reads the script at `path`.
+    fn load_script(
+        &self,
+        path: &Path,
+        cwd: Option<&Path>,
+        mgr: Option<ShurikenManager>,
+        spawn_env: &HashMap<String, String>,
+    ) -> Result<String, LuaError> {
+        let globals = self.lua.globals();
+        for (name, module) in self.script_modules(cwd, mgr, spawn_env)? {
+            globals.set(name, module)?;
+        }
+
+        let script = match cwd {
+            Some(cwd) => fs::read_to_string(resolve_path(cwd, path))?,
+            None => fs::read_to_string(path)?,
+        };
+
+        Ok(script)
+    }
+
     /// Execute a file in the global environment, resolving path optionally against `cwd`.
+    ///
+    /// `path` can be anything path-like (`&str`, `&Path`, `PathBuf`, ...).
     pub async fn execute_file(
         &self,
-        path: &PathBuf,
+        path: impl AsRef<Path>,
         cwd: Option<&Path>,
         mgr: Option<ShurikenManager>,
     ) -> Result<(), LuaError> {
+        let path = path.as_ref();
         info!("Executing file: {:#?}", path);
-        let globals = self.lua.globals();
+        let script = self.load_script(path, cwd, mgr, &HashMap::new())?;
 
-        if let Some(mgr) = mgr {
-            let ninja = make_ninja_module(&self.lua, mgr)?;
-            globals.set("ninja", ninja)?;
+        self.lua.load(script).exec_async().await
+    }
+
+    /// Re-runs a script into the existing Lua state, replacing what it defines.
+    ///
+    /// Meant for quick iteration on a script such as a Shuriken's `start`/`stop`:
+    /// the modules and globals registered on the engine are kept, only the
+    /// script's own globals are swapped. Globals the previous version of the
+    /// same script defined but the new one doesn't are removed. A script that
+    /// returns a table has its fields exposed as globals too.
+    ///
+    /// If the script fails to load, the previous version stays in place.
+    pub async fn reload_script(&self, path: impl AsRef<Path>) -> Result<(), LuaError> {
+        let path = path.as_ref();
+        info!("Reloading script: {:#?}", path);
+        let script = fs::read_to_string(path)?;
+
+        // Run into a scratch env first, so a broken edit changes nothing
+        let lua = &self.lua;
+        let globals = lua.globals();
+        let env = lua.create_table()?;
+        env.set_metatable(Some(lua.create_table_from([("__index", globals.clone())])?))?;
+        let returned: mlua::Value = lua
+            .load(&script)
+            .set_name(path.display().to_string())
+            .set_environment(env.clone())
+            .eval_async()
+            .await?;
+
+        let mut sources = vec![env];
+        if let mlua::Value::Table(table) = returned {
+            sources.push(table);
+        }
+        let mut defined: Vec<(String, mlua::Value)> = Vec::new();
+        for source in sources {
+            for pair in source.pairs::<mlua::Value, mlua::Value>() {
+                // Only names can become globals
+                if let (mlua::Value::String(key), value) = pair? {
+                    defined.push((key.to_str()?.to_string(), value));
+                }
+            }
         }
 
-        let script = if let Some(cwd) = cwd {
-            let fs = make_fs_module(&self.lua, Some(cwd))?;
-            let env = make_env_module(&self.lua, Some(cwd))?;
-            let shell = make_shell_module(&self.lua, Some(cwd))?;
-            let proc = make_proc_module(&self.lua, Some(cwd))?;
-            globals.set("fs", fs)?;
-            globals.set("env", env)?;
-            globals.set("shell", shell)?;
-            globals.set("proc", proc)?;
-            fs::read_to_string(resolve_path(cwd, path))?
-        } else {
-            fs::read_to_string(path)?
-        };
+        let mut reloaded = self
+            .reloaded
+            .lock()
+            .map_err(|e| LuaError::RuntimeError(e.to_string()))?;
+        for stale in reloaded.remove(path).unwrap_or_default() {
+            globals.raw_remove(stale)?;
+        }
+        let mut names = Vec::with_capacity(defined.len());
+        for (name, value) in defined {
+            globals.set(name.as_str(), value)?;
+            names.push(name);
+        }
+        reloaded.insert(path.to_path_buf(), names);
+        Ok(())
+    }
 
-        self.lua.load(script).exec_async().await
+    /// Execute a file like [`NinjaEngine::execute_file`] and return what it returns.
+    ///
+    /// Strings and numbers are returned as-is, `nil` becomes an empty string
+    /// and anything else (tables, booleans) is rendered as JSON.
+    pub async fn eval_file(
+        &self,
+        path: impl AsRef<Path>,
+        cwd: Option<&Path>,
+        mgr: Option<ShurikenManager>,
+    ) -> Result<String, LuaError> {
+        let path = path.as_ref();
+        info!("Evaluating file: {:#?}", path);
+        let script = self.load_script(path, cwd, mgr, &HashMap::new())?;
+
+        let value: mlua::Value = self.lua.load(script).eval_async().await?;
+        self.render_value(value)
+    }
+
+    /// Evaluates a file like [`NinjaEngine::eval_file`] without touching the globals.
+    ///
+    /// The `ninja` and cwd-bound modules, `name` set to `value` and a
+    /// `require` rooted at `cwd` live in an environment of the script's own
+    /// that inherits the globals. Scripts that overlap (e.g. a tool and a
+    /// start calling back into the manager) each keep seeing their own
+    /// context and modules. `spawn_env` is added to the environment of the
+    /// processes this run starts.
+    pub async fn eval_file_with(
+        &self,
+        path: impl AsRef<Path>,
+        cwd: &Path,
+        mgr: Option<ShurikenManager>,
+        spawn_env: &HashMap<String, String>,
+        name: &str,
+        value: impl IntoLua,
+    ) -> Result<String, LuaError> {
+        let path = path.as_ref();
+        info!("Evaluating file: {:#?}", path);
+        let lua = &self.lua;
+
+        let env = self.run_env(Some(cwd), mgr, spawn_env)?;
+        env.set(name, value)?;
+        self.install_require(&env, cwd)?;
+
+        let script = fs::read_to_string(resolve_path(cwd, path))?;
+        let value: mlua::Value = lua
+            .load(script)
+            .set_name(path.display().to_string())
+            .set_environment(env)
+            .eval_async()
+            .await?;
+        self.render_value(value)
+    }
+
+    /// Renders what a script returned the way [`NinjaEngine::eval_file`] does.
+    fn render_value(&self, value: mlua::Value) -> Result<String, LuaError> {
+        match value {
+            mlua::Value::Nil => Ok(String::new()),
+            mlua::Value::String(s) => Ok(s.to_str()?.to_string()),
+            mlua::Value::Integer(i) => Ok(i.to_string()),
+            mlua::Value::Number(n) => Ok(n.to_string()),
+            other => {
+                let json: serde_json::Value = self.lua.from_value(other)?;
+                Ok(json.to_string())
+            }
+        }
+    }
+
+    /// Evaluates a small Lua expression against a Shuriken's options.
+    ///
+    /// Each option is a global in the expression's environment, so a dotted
+    /// path like `server.port` reads a nested value and `port + 1` does math.
+    /// Only `math` and `string` from the standard library are visible.
+    ///
+    /// # Returns
+    /// - `Ok(value)` with the result as JSON (`null` for missing options)
+    /// - `Err` if the expression doesn't parse or fails to evaluate
+    pub fn eval_expression(
+        &self,
+        expression: &str,
+        options: &HashMap<String, FieldValue>,
+    ) -> Result<serde_json::Value, LuaError> {
+        let env = self.lua.create_table()?;
+        for (key, value) in options {
+            env.set(key.as_str(), self.lua.to_value(value)?)?;
+        }
+        let globals = self.lua.globals();
+        for lib in ["math", "string"] {
+            env.set(lib, globals.get::<mlua::Value>(lib)?)?;
+        }
+
+        let value: mlua::Value = self
+            .lua
+            .load(format!("return {}", expression))
+            .set_name("expression")
+            .set_environment(env)
+            .eval()?;
+        self.lua.from_value(value)
     }
 
     /// Execute a specific function from a script in an isolated environment.
     /// The script is loaded from `path` (optionally resolved against `cwd`),
-    /// its globals live in a fresh env that inherits from `lua.globals()`,
-    /// and then `function` is retrieved from that env and called.
+    /// its globals and the `ninja` and cwd-bound modules live in a fresh env
+    /// that inherits from `lua.globals()`, and then `function` is retrieved
+    /// from that env and called with no arguments.
     pub async fn execute_function(
         &self,
         function: &str,
-        path: &PathBuf,
+        path: impl AsRef<Path>,
         cwd: Option<&Path>,
         mgr: Option<ShurikenManager>,
     ) -> Result<(), LuaError> {
-        let lua = &self.lua;
-        let globals = self.lua.globals();
+        self.execute_function_with_args(function, path, cwd, mgr, &HashMap::new(), ())
+            .await
+    }
 
-        if let Some(mgr) = mgr {
-            let ninja = make_ninja_module(&self.lua, mgr)?;
-            globals.set("ninja", ninja)?;
-        }
+    /// Like [`NinjaEngine::execute_function`], but calls `function` with `args`
+    /// and converts whatever it returns into `R`.
+    ///
+    /// `spawn_env` is added to the environment of the processes this run
+    /// starts and wins over variables the script sets itself.
+    pub async fn execute_function_with_args<R: FromLuaMulti>(
+        &self,
+        function: &str,
+        path: impl AsRef<Path>,
+        cwd: Option<&Path>,
+        mgr: Option<ShurikenManager>,
+        spawn_env: &HashMap<String, String>,
+        args: impl IntoLuaMulti,
+    ) -> Result<R, LuaError> {
+        let env = self.run_env(cwd, mgr, spawn_env)?;
+        self.call_in_env(env, function, path.as_ref(), cwd, args)
+            .await
+    }
 
-        let script = if let Some(cwd) = cwd {
-            let fs = make_fs_module(&self.lua, Some(cwd))?;
-            let env = make_env_module(&self.lua, Some(cwd))?;
-            let shell = make_shell_module(&self.lua, Some(cwd))?;
-            let proc = make_proc_module(&self.lua, Some(cwd))?;
-            globals.set("fs", fs)?;
-            globals.set("env", env)?;
-            globals.set("shell", shell)?;
-            globals.set("proc", proc)?;
-            fs::read_to_string(resolve_path(cwd, path))?
-        } else {
-            fs::read_to_string(path)?
-        };
+    /// Runs a Shuriken's lifecycle `function` (e.g. `start` or `stop`) like
+    /// [`NinjaEngine::execute_function_with_args`], without touching the globals.
+    ///
+    /// `context` is both the `shuriken` global of the run's environment and
+    /// the function's argument, and `require` is rooted at `cwd` with a module
+    /// cache of the run's own, the way [`NinjaEngine::eval_file_with`] runs
+    /// tools. Shurikens started and stopped on the same engine never see each
+    /// other's context or modules.
+    pub async fn execute_lifecycle_function<R: FromLuaMulti>(
+        &self,
+        function: &str,
+        path: impl AsRef<Path>,
+        cwd: &Path,
+        mgr: Option<ShurikenManager>,
+        spawn_env: &HashMap<String, String>,
+        context: impl IntoLua,
+    ) -> Result<R, LuaError> {
+        let env = self.run_env(Some(cwd), mgr, spawn_env)?;
+        let context = context.into_lua(&self.lua)?;
+        env.set("shuriken", context.clone())?;
+        self.install_require(&env, cwd)?;
+        self.call_in_env(env, function, path.as_ref(), Some(cwd), context)
+            .await
+    }
 
-        // Create isolated env for the script
+    /// Creates a run's own environment: it inherits the globals and holds
+    /// the `ninja` and cwd-bound modules, so overlapping runs keep theirs.
+    fn run_env(
+        &self,
+        cwd: Option<&Path>,
+        mgr: Option<ShurikenManager>,
+        spawn_env: &HashMap<String, String>,
+    ) -> Result<Table, LuaError> {
+        let lua = &self.lua;
         let env = lua.create_table()?;
+        env.set_metatable(Some(lua.create_table_from([("__index", lua.globals())])?))?;
+        for (module, table) in self.script_modules(cwd, mgr, spawn_env)? {
+            env.set(module, table)?;
+        }
+        Ok(env)
+    }
 
-        // Make environment inherit standard globals
-        let globals = lua.globals();
-        env.set_metatable(Some(lua.create_table_from([("__index", globals)])?))?;
+    /// Loads the script at `path` into `env` and calls its `function` with `args`.
+    async fn call_in_env<R: FromLuaMulti>(
+        &self,
+        env: Table,
+        function: &str,
+        path: &Path,
+        cwd: Option<&Path>,
+        args: impl IntoLuaMulti,
+    ) -> Result<R, LuaError> {
+        let script = match cwd {
+            Some(cwd) => fs::read_to_string(resolve_path(cwd, path))?,
+            None => fs::read_to_string(path)?,
+        };
 
         // Load script into the isolated environment
-        let chunk = lua.load(&script).set_environment(env.clone());
+        let chunk = self.lua.load(&script).set_environment(env.clone());
 
         // Execute and capture the return value
         let result: mlua::Value = chunk.eval_async().await?;
@@ -245,7 +718,26 @@ impl NinjaEngine {
             env.get(function)?
         };
 
-        // Call the function with no arguments
-        func.call_async::<()>(()).await
+        func.call_async::<R>(args).await
+    }
+}
+
+tokio::task_local! {
+    /// Where `print` writes for the execution running under [`NinjaEngine::capture`]
+    static CAPTURED: RefCell<String>;
+}
+
+/// Rejects module names that could walk out of a require root.
+fn check_module_name(name: &str) -> Result<(), LuaError> {
+    if name.is_empty()
+        || name.contains("..")
+        || name.contains('\\')
+        || Path::new(name).is_absolute()
+    {
+        return Err(LuaError::RuntimeError(format!(
+            "module '{}' is outside the allowed require paths",
+            name
+        )));
     }
+    Ok(())
 }
@@ -2,25 +2,24 @@ use chrono::prelude::*;
 use log::{debug, error, info, warn};
 use mlua::{ExternalError, Lua, LuaSerdeExt, Result, Table};
 use serde_json::Value;
-use std::{
-    fs,
-    path::{Path, PathBuf},
-    time::Duration,
-};
+use std::{collections::HashMap, path::Path, time::Duration};
 
 mod env_module;
 mod fs_module;
+mod http_module;
 mod ninja_module;
 mod proc_module;
 mod shared;
 mod shell_module;
+mod store_module;
 
 pub(crate) use env_module::make_env_module;
 pub(crate) use fs_module::make_fs_module;
+pub(crate) use http_module::make_http_module;
 pub(crate) use ninja_module::make_ninja_module;
 pub(crate) use proc_module::make_proc_module;
-use shared::{FetchArgs, http_download, http_request};
 pub(crate) use shell_module::make_shell_module;
+pub(crate) use store_module::make_store_module;
 
 pub async fn make_modules(
     lua: &Lua,
@@ -36,9 +35,9 @@ pub async fn make_modules(
     let shell_module = make_shell_module(lua, cwd)?;
     let time_module = lua.create_table()?;
     let json_module = lua.create_table()?;
-    let http_module = lua.create_table()?;
+    let http_module = make_http_module(lua, cwd)?;
     let log_module = lua.create_table()?;
-    let proc_module = make_proc_module(lua, cwd)?;
+    let proc_module = make_proc_module(lua, cwd, &HashMap::new())?;
 
     time_module.set(
         "year",
@@ -178,83 +177,6 @@ pub async fn make_modules(
         })?,
     )?;
 
-    http_module.set(
-        "fetch",
-        lua.create_async_function(|lua, (url, headers, method, body): FetchArgs| async move {
-            debug!(
-                "http.fetch: url='{}', headers={:?}, method={:?}, body={:?}",
-                url, headers, method, body
-            );
-            let method = method.unwrap_or_else(|| "GET".to_string());
-            let (status, response_body) = http_request(&method, &url, body, headers).await?;
-            debug!(
-                "http.fetch: url='{}' -> status={}, body_len={}",
-                url,
-                status,
-                response_body.len()
-            );
-            let result_table = lua.create_table()?;
-            result_table.set("status", status)?;
-            result_table.set("body", response_body)?;
-            Ok(result_table)
-        })?,
-    )?;
-
-    http_module.set(
-        "download",
-        lua.create_async_function({
-            let cwd_buf = shared::canonicalize_cwd(cwd);
-            move |_, (url, dest): (String, PathBuf)| {
-                let value = cwd_buf.clone();
-                async move {
-                    debug!("http.download: url='{}', dest='{}'", url, dest.display());
-                    let bytes = http_download(&url).await?;
-                    debug!("http.download: url='{}' -> {} bytes", url, bytes.len());
-
-                    let dest = if let Some(cwd) = value.clone() {
-                        cwd.join(dest)
-                    } else {
-                        dest
-                    };
-
-                    if let Some(parent) = dest.parent()
-                        && let Err(e) = fs::create_dir_all(parent)
-                    {
-                        error!(
-                            "http.download: failed to create parent directories for '{}': {}",
-                            dest.display(),
-                            e
-                        );
-                        return Err(mlua::Error::external(format!(
-                            "Failed to create parent directories for '{}': {}",
-                            dest.display(),
-                            e
-                        )));
-                    }
-
-                    match fs::write(&dest, &bytes) {
-                        Ok(_) => {
-                            debug!("http.download: successfully wrote to '{}'", dest.display());
-                            Ok(())
-                        }
-                        Err(e) => {
-                            error!(
-                                "http.download: failed to write to '{}': {}",
-                                dest.display(),
-                                e
-                            );
-                            Err(mlua::Error::external(format!(
-                                "Failed to write to '{}': {}",
-                                dest.display(),
-                                e
-                            )))
-                        }
-                    }
-                }
-            }
-        })?,
-    )?;
-
     debug!("make_modules: all modules created");
     Ok((
         fs_module,
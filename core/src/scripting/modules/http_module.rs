@@ -0,0 +1,303 @@
+use super::shared::{
+    self, DownloadCheck, FetchArgs, HTTP_TIMEOUT, HttpResponse, http_download_to, http_request,
+    http_send,
+};
+use log::{debug, error};
+use mlua::{Error as LuaError, Function, Lua, Result, Table, UserData, UserDataMethods};
+use reqwest::{Client, cookie::Jar, redirect::Policy};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Per-request settings read from the optional `opts` table.
+struct RequestOptions {
+    headers: HashMap<String, String>,
+    follow_redirects: bool,
+}
+
+impl RequestOptions {
+    fn from_table(opts: Option<Table>) -> Result<Self> {
+        let Some(opts) = opts else {
+            return Ok(Self {
+                headers: HashMap::new(),
+                follow_redirects: true,
+            });
+        };
+
+        Ok(Self {
+            headers: opts
+                .get::<Option<HashMap<String, String>>>("headers")?
+                .unwrap_or_default(),
+            follow_redirects: opts
+                .get::<Option<bool>>("follow_redirects")?
+                .unwrap_or(true),
+        })
+    }
+}
+
+/// An HTTP client whose requests share one cookie jar, returned by `http.session()`.
+///
+/// reqwest fixes the redirect policy per client, so the session keeps a
+/// second client that never follows redirects for `follow_redirects = false`.
+/// Both clients read and write the same jar.
+#[derive(Clone)]
+pub(crate) struct HttpSession {
+    following: Client,
+    manual: Client,
+}
+
+impl HttpSession {
+    pub(crate) fn new() -> Result<Self> {
+        let jar = Arc::new(Jar::default());
+        let build = |policy: Policy| {
+            Client::builder()
+                .cookie_provider(jar.clone())
+                .redirect(policy)
+                .build()
+                .map_err(LuaError::external)
+        };
+
+        Ok(Self {
+            following: build(Policy::default())?,
+            manual: build(Policy::none())?,
+        })
+    }
+
+    async fn send(
+        &self,
+        lua: &Lua,
+        method: &str,
+        url: &str,
+        body: Option<String>,
+        opts: Option<Table>,
+    ) -> Result<Table> {
+        let opts = RequestOptions::from_table(opts)?;
+        debug!(
+            "http.session: {} '{}', follow_redirects={}, body_len={}",
+            method,
+            url,
+            opts.follow_redirects,
+            body.as_ref().map(|b| b.len()).unwrap_or(0)
+        );
+
+        let client = if opts.follow_redirects {
+            &self.following
+        } else {
+            &self.manual
+        };
+        let mut request = match method {
+            "POST" => client.post(url),
+            _ => client.get(url),
+        };
+        for (name, value) in &opts.headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            error!(
+                "http.session: request failed for '{} {}': {}",
+                method, url, e
+            );
+            LuaError::external(e)
+        })?;
+
+        let status = response.status().as_u16();
+        let final_url = response.url().to_string();
+        let headers = lua.create_table()?;
+        for (name, value) in response.headers() {
+            headers.set(name.as_str(), String::from_utf8_lossy(value.as_bytes()))?;
+        }
+        let text = response.text().await.map_err(|e| {
+            error!(
+                "http.session: failed to read response text for '{} {}': {}",
+                method, url, e
+            );
+            LuaError::external(e)
+        })?;
+
+        debug!(
+            "http.session: completed '{} {}' with status {}, response_len={}",
+            method,
+            url,
+            status,
+            text.len()
+        );
+
+        let result = lua.create_table()?;
+        result.set("status", status)?;
+        result.set("body", text)?;
+        result.set("url", final_url)?;
+        result.set("headers", headers)?;
+        Ok(result)
+    }
+}
+
+impl UserData for HttpSession {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method(
+            "get",
+            |lua, this, (url, opts): (String, Option<Table>)| async move {
+                this.send(&lua, "GET", &url, None, opts).await
+            },
+        );
+        methods.add_async_method(
+            "post",
+            |lua, this, (url, body, opts): (String, Option<String>, Option<Table>)| async move {
+                this.send(&lua, "POST", &url, body, opts).await
+            },
+        );
+    }
+}
+
+/// Turns an [`HttpResponse`] into the `{ status, body, headers }` table scripts get.
+fn response_table(lua: &Lua, response: HttpResponse) -> Result<Table> {
+    let result = lua.create_table()?;
+    result.set("status", response.status)?;
+    result.set("body", response.body)?;
+    result.set("headers", response.headers)?;
+    Ok(result)
+}
+
+/// Builds the `http` module; `http.download` resolves destinations against `cwd`.
+pub(crate) fn make_http_module(lua: &Lua, cwd: Option<&Path>) -> Result<Table> {
+    let http_module = lua.create_table()?;
+
+    http_module.set(
+        "fetch",
+        lua.create_async_function(|lua, (url, headers, method, body): FetchArgs| async move {
+            debug!(
+                "http.fetch: url='{}', headers={:?}, method={:?}, body={:?}",
+                url, headers, method, body
+            );
+            let method = method.unwrap_or_else(|| "GET".to_string());
+            let (status, response_body) = http_request(&method, &url, body, headers).await?;
+            debug!(
+                "http.fetch: url='{}' -> status={}, body_len={}",
+                url,
+                status,
+                response_body.len()
+            );
+            let result_table = lua.create_table()?;
+            result_table.set("status", status)?;
+            result_table.set("body", response_body)?;
+            Ok(result_table)
+        })?,
+    )?;
+
+    http_module.set(
+        "get",
+        lua.create_async_function(
+            |lua, (url, headers): (String, Option<HashMap<String, String>>)| async move {
+                debug!("http.get: url='{}'", url);
+                let response = http_send("GET", &url, None, headers, Some(HTTP_TIMEOUT)).await?;
+                response_table(&lua, response)
+            },
+        )?,
+    )?;
+
+    http_module.set(
+        "post",
+        lua.create_async_function(
+            |lua,
+             (url, body, headers): (
+                String,
+                Option<String>,
+                Option<HashMap<String, String>>,
+            )| async move {
+                debug!("http.post: url='{}'", url);
+                let response = http_send("POST", &url, body, headers, Some(HTTP_TIMEOUT)).await?;
+                response_table(&lua, response)
+            },
+        )?,
+    )?;
+
+    http_module.set(
+        "request",
+        lua.create_async_function(|lua, request: Table| async move {
+            let url: String = request.get("url")?;
+            let method = request
+                .get::<Option<String>>("method")?
+                .unwrap_or_else(|| "GET".to_string());
+            let headers: Option<HashMap<String, String>> = request.get("headers")?;
+            let body: Option<String> = request.get("body")?;
+            let timeout = match request.get::<Option<f64>>("timeout")? {
+                Some(seconds) if seconds.is_finite() && seconds > 0.0 => {
+                    Duration::from_secs_f64(seconds)
+                }
+                Some(seconds) => {
+                    return Err(mlua::Error::external(format!(
+                        "http.request: timeout must be a positive number of seconds, got {}",
+                        seconds
+                    )));
+                }
+                None => HTTP_TIMEOUT,
+            };
+
+            debug!(
+                "http.request: method='{}', url='{}', timeout={:?}",
+                method, url, timeout
+            );
+            let response = http_send(&method, &url, body, headers, Some(timeout)).await?;
+            response_table(&lua, response)
+        })?,
+    )?;
+
+    http_module.set(
+        "session",
+        lua.create_function(|_, ()| {
+            debug!("http.session: new session");
+            HttpSession::new()
+        })?,
+    )?;
+
+    http_module.set(
+        "download",
+        lua.create_async_function({
+            let cwd_buf = shared::canonicalize_cwd(cwd);
+            move |_, (url, dest, opts): (String, PathBuf, mlua::Value)| {
+                let value = cwd_buf.clone();
+                async move {
+                    // The third argument is a progress function or an options table
+                    let (progress, check) = match opts {
+                        mlua::Value::Nil => (None, DownloadCheck::default()),
+                        mlua::Value::Function(progress) => (Some(progress), DownloadCheck::default()),
+                        mlua::Value::Table(opts) => (
+                            opts.get::<Option<Function>>("progress")?,
+                            DownloadCheck {
+                                size: opts.get("size")?,
+                                sha256: opts.get("sha256")?,
+                            },
+                        ),
+                        other => {
+                            return Err(mlua::Error::external(format!(
+                                "http.download: expected a progress function or options table, got {}",
+                                other.type_name()
+                            )));
+                        }
+                    };
+
+                    let dest = if let Some(cwd) = value.clone() {
+                        cwd.join(dest)
+                    } else {
+                        dest
+                    };
+                    debug!("http.download: url='{}', dest='{}'", url, dest.display());
+
+                    http_download_to(&url, &dest, &check, |done, total| match &progress {
+                        Some(progress) => progress.call::<()>((done, total)),
+                        None => Ok(()),
+                    })
+                    .await
+                }
+            }
+        })?,
+    )?;
+
+    Ok(http_module)
+}
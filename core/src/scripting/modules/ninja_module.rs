@@ -4,11 +4,30 @@ use crate::{
 };
 use mlua::{Either, Error as LuaError, Lua, Result, Table};
 use std::sync::Arc;
+use tokio::sync::RwLock;
 
-pub(crate) fn make_ninja_module(lua: &Lua, manager: ShurikenManager) -> Result<Table> {
+pub(crate) fn make_ninja_module(
+    lua: &Lua,
+    manager: ShurikenManager,
+    selected: Option<Arc<RwLock<Option<String>>>>,
+) -> Result<Table> {
     let ninja_module = lua.create_table()?;
     let mgr = Arc::new(manager.clone());
 
+    ninja_module.set(
+        "selected",
+        lua.create_async_function(move |_, _: ()| {
+            let selected = selected.clone();
+
+            async move {
+                match selected {
+                    Some(selected) => Ok(selected.read().await.clone()),
+                    None => Ok(None),
+                }
+            }
+        })?,
+    )?;
+
     ninja_module.set(
         "start",
         lua.create_async_function({
@@ -1,31 +1,167 @@
 use super::shared::{canonicalize_cwd, resolve_spawn_command};
-use crate::utils::{kill_process_by_name, kill_process_by_pid};
+use crate::common::log_buffer::{self, LogBuffer};
+use crate::utils::{
+    forget_exit_code, kill_process_by_name, kill_process_by_pid, list_processes, record_exit_code,
+};
 use log::{debug, error, info, warn};
 use mlua::{Lua, Result, Table};
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     process::Stdio,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
-pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Table> {
+/// Points a detached command's stdio at nothing, or its output at `capture`.
+///
+/// A captured child keeps the default `SIGPIPE` handling, an ignored signal
+/// would stay ignored in everything it runs (so `yes | head` never ends).
+fn detach_stdio(cmd: &mut tokio::process::Command, capture: bool) {
+    let output = || {
+        if capture {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        }
+    };
+    cmd.stdin(Stdio::null()).stdout(output()).stderr(output());
+}
+
+/// Waits for a detached child in the background and records its exit code.
+///
+/// The code is what lets the manager tell how a Shuriken's process ended.
+/// Piped output is copied into `capture`. Returns the child's PID, or 0 if
+/// it already exited.
+fn reap_in_background(
+    mut child: tokio::process::Child,
+    capture: Option<Arc<Mutex<LogBuffer>>>,
+) -> u32 {
+    if let Some(buffer) = capture {
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(log_buffer::pump(stdout, buffer.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(log_buffer::pump(stderr, buffer));
+        }
+    }
+
+    let pid = child.id().unwrap_or(0);
+    if pid != 0 {
+        forget_exit_code(pid);
+        tokio::spawn(async move {
+            if let Ok(status) = child.wait().await {
+                debug!("proc.spawn: pid={} exited with {}", pid, status);
+                record_exit_code(pid, status.code().unwrap_or(-1));
+            }
+        });
+    }
+    pid
+}
+
+/// Spawns `program` detached with `args` passed through as-is, no shell involved.
+///
+/// On Unix the arguments become argv directly. On Windows the standard
+/// library quotes them into a command line the program's runtime splits
+/// back into the same arguments. `env` is added to the inherited environment.
+fn spawn_argv(
+    lua: &Lua,
+    program: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+    cwd: Option<&Path>,
+    capture: Option<Arc<Mutex<LogBuffer>>>,
+) -> Result<Table> {
+    // Relative programs like `./bin/server` are resolved against the cwd
+    let program = resolve_spawn_command(program, cwd, Some(cfg!(windows)))?;
+    debug!(
+        "proc.spawn: program='{}', args={:?}, env keys={:?}, cwd={:?}",
+        program,
+        args,
+        env.keys().collect::<Vec<_>>(),
+        cwd
+    );
+
+    let mut cmd = tokio::process::Command::new(&program);
+    cmd.args(args).envs(env);
+    detach_stdio(&mut cmd, capture.is_some());
+    if let Some(cwd) = cwd {
+        cmd.current_dir(cwd);
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    #[cfg(windows)]
+    {
+        use windows::Win32::System::Threading::{
+            CREATE_NEW_PROCESS_GROUP, CREATE_NO_WINDOW, DETACHED_PROCESS,
+        };
+        cmd.creation_flags((CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW | DETACHED_PROCESS).0);
+    }
+
+    let child = cmd.spawn().map_err(|e| {
+        error!("proc.spawn: failed to spawn '{}': {}", program, e);
+        mlua::Error::external(format!("spawn failed: {}", e))
+    })?;
+
+    let pid = reap_in_background(child, capture);
+    info!("proc.spawn: spawned detached process with pid={}", pid);
+
+    let result_table = lua.create_table()?;
+    result_table.set("pid", pid)?;
+    Ok(result_table)
+}
+
+/// Builds the `proc` module.
+///
+/// Processes it starts run in `base_cwd` unless a script passes its own
+/// `cwd`, and get `overrides` added to their environment.
+pub(crate) fn make_proc_module(
+    lua: &Lua,
+    base_cwd: Option<&Path>,
+    overrides: &HashMap<String, String>,
+) -> Result<Table> {
     debug!(
-        "make_proc_module: base_cwd = {:?}",
-        base_cwd.map(|p| p.display().to_string())
+        "make_proc_module: base_cwd = {:?}, override keys = {:?}",
+        base_cwd.map(|p| p.display().to_string()),
+        overrides.keys().collect::<Vec<_>>()
     );
 
     let proc_module = lua.create_table()?;
     let proc_cwd: Option<PathBuf> = canonicalize_cwd(base_cwd);
+    let overrides = Arc::new(overrides.clone());
 
     proc_module.set(
         "spawn",
         lua.create_async_function({
             let proc_cwd = proc_cwd.clone();
+            let overrides = overrides.clone();
             move |lua, args: mlua::Value| {
                 let proc_cwd = proc_cwd.clone();
+                let overrides = overrides.clone();
                 async move {
+                    // Output is captured for the Shuriken the script belongs to
+                    let capture = proc_cwd.as_deref().and_then(log_buffer::buffer_for);
                     let (command, custom_cwd): (String, Option<PathBuf>) = match args {
                         mlua::Value::String(s) => (s.to_str()?.to_string(), None),
+                        mlua::Value::Table(t) if t.contains_key("program")? => {
+                            let program: String = t.get("program")?;
+                            let args: Vec<String> =
+                                t.get::<Option<Vec<String>>>("args")?.unwrap_or_default();
+                            let mut env: HashMap<String, String> = t
+                                .get::<Option<HashMap<String, String>>>("env")?
+                                .unwrap_or_default();
+                            env.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+                            let cwd: Option<PathBuf> = t.get("cwd")?;
+                            let cwd_to_use = cwd.as_deref().or(proc_cwd.as_deref());
+                            return spawn_argv(&lua, &program, &args, &env, cwd_to_use, capture);
+                        }
                         mlua::Value::Table(t) => {
                             let cmd: String = t.get("command").or_else(|_| t.get(1))?;
                             let cwd: Option<PathBuf> = t.get("cwd").ok();
@@ -33,7 +169,7 @@ pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Tab
                         }
                         _ => {
                             return Err(mlua::Error::external(
-                                "spawn requires string or table with 'command' field",
+                                "spawn requires string or table with 'command' or 'program' field",
                             ));
                         }
                     };
@@ -48,15 +184,13 @@ pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Tab
                         let resolved = resolve_spawn_command(&command, cwd_to_use, None)?;
 
                         let mut cmd = tokio::process::Command::new("sh");
-                        cmd.args(["-c", &resolved]);
+                        cmd.args(["-c", &resolved]).envs(overrides.iter());
 
                         if let Some(cwd) = cwd_to_use {
                             cmd.current_dir(cwd);
                         }
 
-                        cmd.stdin(Stdio::null())
-                            .stdout(Stdio::null())
-                            .stderr(Stdio::null());
+                        detach_stdio(&mut cmd, capture.is_some());
 
                         unsafe {
                             cmd.pre_exec(|| {
@@ -70,7 +204,7 @@ pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Tab
                             mlua::Error::external(format!("spawn failed: {}", e))
                         })?;
 
-                        let pid = child.id().unwrap_or(0);
+                        let pid = reap_in_background(child, capture);
                         debug!("proc.spawn: spawned detached process with pid={}", pid);
 
                         let result_table = lua.create_table()?;
@@ -82,6 +216,15 @@ pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Tab
                     unsafe {
                         use std::iter::once;
 
+                        // No pipes are set up for CreateProcessW, so nothing is captured
+                        drop(capture);
+                        if !overrides.is_empty() {
+                            warn!(
+                                "proc.spawn: environment overrides need the 'program' form on Windows, ignoring them for '{}'",
+                                command
+                            );
+                        }
+
                         use windows::{
                             Win32::{
                                 System::Threading::{
@@ -176,12 +319,29 @@ pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Tab
         })?,
     )?;
 
+    proc_module.set(
+        "list",
+        lua.create_function(|lua, ()| {
+            let processes = list_processes();
+            debug!("proc.list: {} processes", processes.len());
+            let list = lua.create_table()?;
+            for process in processes {
+                let entry = lua.create_table()?;
+                entry.set("pid", process.pid)?;
+                entry.set("name", process.name)?;
+                list.push(entry)?;
+            }
+            Ok(list)
+        })?,
+    )?;
+
     proc_module.set(
         "exec",
         lua.create_async_function({
             let proc_cwd = proc_cwd.clone();
             move |lua, args: mlua::Value| {
                 let proc_cwd = proc_cwd.clone();
+                let overrides = overrides.clone();
                 async move {
                     let (command, timeout_secs, custom_cwd): (
                         String,
@@ -225,7 +385,9 @@ pub(crate) fn make_proc_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Tab
                         cmd.current_dir(cwd);
                     }
 
-                    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+                    cmd.envs(overrides.iter())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::piped());
 
                     let mut child = cmd.spawn().map_err(|e| {
                         error!("proc.exec: failed to spawn '{}': {}", resolved, e);
@@ -1,12 +1,17 @@
+use crate::common::signing::to_hex;
+use futures_util::StreamExt;
 use log::{debug, error, warn};
 use mlua::{Error as LuaError, Result};
 use regex;
 use relative_path::RelativePath;
+use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
     env,
     path::{Path, PathBuf},
+    time::Duration,
 };
+use tokio::io::AsyncWriteExt;
 
 pub(crate) type FetchArgs = (
     String,
@@ -43,98 +48,202 @@ pub(crate) fn strip_windows_prefix(p: &Path) -> PathBuf {
     p.to_path_buf()
 }
 
-pub(crate) async fn http_request(
+/// How long `http.get`, `http.post` and `http.request` wait by default.
+pub(crate) const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What an HTTP request returned, as handed to scripts.
+pub(crate) struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+    /// Response headers with lowercased names
+    pub headers: HashMap<String, String>,
+}
+
+/// Sends a request with any method, applying `headers` and an optional `timeout`.
+pub(crate) async fn http_send(
     method: &str,
     url: &str,
     body: Option<String>,
     headers: Option<HashMap<String, String>>,
-) -> Result<(u16, String)> {
+    timeout: Option<Duration>,
+) -> Result<HttpResponse> {
     debug!(
-        "http_request: method='{}', url='{}', body_len={}",
+        "http_send: method='{}', url='{}', body_len={}, timeout={:?}",
         method,
         url,
-        body.as_ref().map(|b| b.len()).unwrap_or(0)
+        body.as_ref().map(|b| b.len()).unwrap_or(0),
+        timeout
     );
 
+    let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|e| {
+        error!("http_send: invalid method '{}': {}", method, e);
+        LuaError::external(format!("Invalid HTTP method '{}'", method))
+    })?;
+
     let client = reqwest::Client::new();
-    if let Some(headers) = &headers {
-        for (k, v) in headers.iter() {
-            debug!("http_request: header '{}: {}'", k, v);
-        }
+    let mut request_builder = client.request(method.clone(), url);
+    for (k, v) in headers.iter().flatten() {
+        debug!("http_send: header '{}: {}'", k, v);
+        request_builder = request_builder.header(k, v);
+    }
+    if let Some(timeout) = timeout {
+        request_builder = request_builder.timeout(timeout);
+    }
+    if let Some(body) = body {
+        request_builder = request_builder.body(body);
     }
-    let request_builder = match method.to_uppercase().as_str() {
-        "GET" => client.get(url),
-        "POST" => client.post(url),
-        "PUT" => client.put(url),
-        "DELETE" => client.delete(url),
-        "PATCH" => client.patch(url),
-        _ => {
-            error!(
-                "http_request: unsupported method '{}'. Defaulting to GET.",
-                method
-            );
-            client.get(url)
-        }
-    };
-
-    let request_builder = if let Some(body) = body {
-        request_builder.body(body)
-    } else {
-        request_builder
-    };
 
     let response = request_builder.send().await.map_err(|e| {
-        error!(
-            "http_request: request failed for '{} {}': {}",
-            method, url, e
-        );
+        error!("http_send: request failed for '{} {}': {}", method, url, e);
         LuaError::external(e)
     })?;
 
     let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect();
     let text = response.text().await.map_err(|e| {
         error!(
-            "http_request: failed to read response text for '{} {}': {}",
+            "http_send: failed to read response text for '{} {}': {}",
             method, url, e
         );
         LuaError::external(e)
     })?;
 
     debug!(
-        "http_request: completed '{} {}' with status {}, response_len={}",
+        "http_send: completed '{} {}' with status {}, response_len={}",
         method,
         url,
         status,
         text.len()
     );
 
-    Ok((status, text))
+    Ok(HttpResponse {
+        status,
+        body: text,
+        headers,
+    })
 }
 
-pub(crate) async fn http_download(url: &str) -> Result<Vec<u8>> {
-    debug!("http_download: url='{}'", url);
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("http_download: request failed for '{}': {}", url, e);
-            LuaError::external(e)
-        })?
-        .bytes()
-        .await
-        .map_err(|e| {
-            error!("http_download: failed to read bytes for '{}': {}", url, e);
-            LuaError::external(e)
-        })?
-        .to_vec();
-    debug!(
-        "http_download: completed '{}', bytes_len={}",
-        url,
-        response.len()
-    );
-    Ok(response)
+pub(crate) async fn http_request(
+    method: &str,
+    url: &str,
+    body: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<(u16, String)> {
+    let response = http_send(method, url, body, headers, None).await?;
+    Ok((response.status, response.body))
+}
+
+/// What an `http.download` must match before it replaces its destination.
+#[derive(Debug, Default)]
+pub(crate) struct DownloadCheck {
+    /// Expected size in bytes
+    pub size: Option<u64>,
+    /// Expected SHA-256 as hex
+    pub sha256: Option<String>,
+}
+
+/// Streams `url` into `dest` without holding the body in memory.
+///
+/// The body is written to `<dest>.part` first and only renamed over `dest`
+/// once it passed `check`, so a failed or tampered download never
+/// replaces an existing file. `progress` is called after every chunk with
+/// the bytes written so far and the total, if the server announced one.
+///
+/// # Returns
+/// The number of bytes written.
+pub(crate) async fn http_download_to(
+    url: &str,
+    dest: &Path,
+    check: &DownloadCheck,
+    mut progress: impl FnMut(u64, Option<u64>) -> Result<()>,
+) -> Result<u64> {
+    debug!("http_download_to: url='{}', dest='{}'", url, dest.display());
+    let response = reqwest::Client::new().get(url).send().await.map_err(|e| {
+        error!("http_download_to: request failed for '{}': {}", url, e);
+        LuaError::external(e)
+    })?;
+    if !response.status().is_success() {
+        return Err(LuaError::external(format!(
+            "Failed to download '{}': HTTP {}",
+            url,
+            response.status()
+        )));
+    }
+    let total = response.content_length();
+
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| {
+            LuaError::external(format!(
+                "Failed to create parent directories for '{}': {}",
+                dest.display(),
+                e
+            ))
+        })?;
+    }
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&part).await?;
+        let mut hasher = Sha256::new();
+        let mut written = 0u64;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(LuaError::external)?;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+            progress(written, total)?;
+        }
+        file.flush().await?;
+
+        if let Some(size) = check.size
+            && size != written
+        {
+            return Err(LuaError::external(format!(
+                "Size mismatch for '{}': expected {} bytes, got {}",
+                url, size, written
+            )));
+        }
+        if let Some(expected) = &check.sha256 {
+            let checksum = to_hex(&hasher.finalize());
+            if !checksum.eq_ignore_ascii_case(expected.trim()) {
+                return Err(LuaError::external(format!(
+                    "Checksum mismatch for '{}': expected {}, got {}",
+                    url, expected, checksum
+                )));
+            }
+        }
+        tokio::fs::rename(&part, dest).await?;
+        Ok(written)
+    }
+    .await;
+
+    match result {
+        Ok(written) => {
+            debug!(
+                "http_download_to: wrote {} bytes to '{}'",
+                written,
+                dest.display()
+            );
+            Ok(written)
+        }
+        Err(e) => {
+            error!("http_download_to: '{}' failed: {}", url, e);
+            let _ = tokio::fs::remove_file(&part).await;
+            Err(e)
+        }
+    }
 }
 
 pub(crate) fn canonicalize_cwd(base: Option<&Path>) -> Option<PathBuf> {
@@ -221,10 +330,59 @@ pub(crate) fn resolve_spawn_command(
     Ok(result.into_owned())
 }
 
+/// Removes ANSI escape sequences (colors, cursor movement, window titles) from `text`.
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates, then a final byte in '@'..='~'
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: runs until BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        chars.next_if_eq(&'\\');
+                        break;
+                    }
+                }
+            }
+            // Two-character escapes like ESC 7
+            _ => {}
+        }
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1;31mred\x1b[0m plain"), "red plain");
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+        assert_eq!(strip_ansi("\x1b]0;title\x1b\\text"), "text");
+        assert_eq!(strip_ansi("\x1b7saved\x1b[2K"), "saved");
+        assert_eq!(strip_ansi("no escapes"), "no escapes");
+    }
+
     #[test]
     fn test_strip_windows_prefix_with_prefix() {
         #[cfg(windows)]
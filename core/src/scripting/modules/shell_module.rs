@@ -1,10 +1,97 @@
-use super::shared::canonicalize_cwd;
+use super::shared::{canonicalize_cwd, strip_ansi};
 use log::{debug, error};
 use mlua::{Lua, Result, Table};
-use std::{path::Path, path::PathBuf, process::Command};
+use std::{
+    io::{self, Read},
+    path::Path,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
 
+/// Default cap on each captured stream, in bytes.
+const DEFAULT_MAX_OUTPUT: usize = 1024 * 1024;
+
+#[derive(Default)]
 struct ShellCommandResult {
     code: i32,
+    stdout: Option<String>,
+    stderr: Option<String>,
+    truncated: bool,
+}
+
+/// How `shell.exec` captures output, from its options table.
+struct CaptureOptions {
+    strip_ansi: bool,
+    max_output: usize,
+}
+
+impl CaptureOptions {
+    /// Reads the options table; `None` means output goes to the terminal.
+    fn from_table(opts: Option<&Table>) -> Result<Option<Self>> {
+        let Some(opts) = opts else {
+            return Ok(None);
+        };
+        let strip_ansi: Option<bool> = opts.get("strip_ansi")?;
+        let max_output: Option<usize> = opts.get("max_output")?;
+        let capture: Option<bool> = opts.get("capture")?;
+
+        // Asking to clean or cap output only makes sense when capturing it
+        if !capture.unwrap_or(strip_ansi.is_some() || max_output.is_some()) {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            strip_ansi: strip_ansi.unwrap_or(false),
+            max_output: max_output.unwrap_or(DEFAULT_MAX_OUTPUT),
+        }))
+    }
+
+    fn finish(&self, bytes: Vec<u8>) -> String {
+        let text = String::from_utf8_lossy(&bytes);
+        if self.strip_ansi {
+            strip_ansi(&text)
+        } else {
+            text.into_owned()
+        }
+    }
+}
+
+// Reads up to `max` bytes, then drains the rest so the child never blocks on a full pipe
+fn read_capped(mut reader: impl Read, max: usize) -> (Vec<u8>, bool) {
+    let mut buf = Vec::new();
+    let _ = reader.by_ref().take(max as u64).read_to_end(&mut buf);
+    let truncated = io::copy(&mut reader, &mut io::sink()).is_ok_and(|rest| rest > 0);
+    (buf, truncated)
+}
+
+// Runs `cmd`, inheriting stdio or capturing both streams when asked to
+fn run(cmd: &mut Command, capture: Option<&CaptureOptions>) -> io::Result<ShellCommandResult> {
+    let Some(capture) = capture else {
+        let status = cmd.status()?;
+        return Ok(ShellCommandResult {
+            code: status.code().unwrap_or(-1),
+            ..Default::default()
+        });
+    };
+
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let max = capture.max_output;
+    let stderr = child.stderr.take();
+    let stderr_reader =
+        std::thread::spawn(move || stderr.map(|s| read_capped(s, max)).unwrap_or_default());
+    let (stdout, stdout_truncated) = child
+        .stdout
+        .take()
+        .map(|s| read_capped(s, max))
+        .unwrap_or_default();
+    let (stderr, stderr_truncated) = stderr_reader.join().unwrap_or_default();
+    let status = child.wait()?;
+
+    Ok(ShellCommandResult {
+        code: status.code().unwrap_or(-1),
+        stdout: Some(capture.finish(stdout)),
+        stderr: Some(capture.finish(stderr)),
+        truncated: stdout_truncated || stderr_truncated,
+    })
 }
 
 // Helper: Build command with cd prepended
@@ -24,11 +111,18 @@ fn build_command_with_cwd(command: &str, cwd: Option<&Path>) -> String {
 // ============================================================================
 
 #[cfg(windows)]
-fn run_windows_admin(command: &str, cwd: Option<&Path>) -> Result<ShellCommandResult> {
+fn run_windows_admin(
+    command: &str,
+    cwd: Option<&Path>,
+    capture: Option<&CaptureOptions>,
+) -> Result<ShellCommandResult> {
     use runas::Command as RunasCommand;
 
     let full_cmd = build_command_with_cwd(command, cwd);
     debug!("run_windows_admin: {}", full_cmd);
+    if capture.is_some() {
+        log::warn!("shell.exec: output of elevated commands can't be captured on Windows");
+    }
 
     let status = RunasCommand::new("cmd")
         .arg("/C")
@@ -41,11 +135,18 @@ fn run_windows_admin(command: &str, cwd: Option<&Path>) -> Result<ShellCommandRe
 
     let code = status.code().unwrap_or(-1);
     debug!("run_windows_admin: exit={}", code);
-    Ok(ShellCommandResult { code })
+    Ok(ShellCommandResult {
+        code,
+        ..Default::default()
+    })
 }
 
 #[cfg(windows)]
-fn run_windows_non_admin(command: &str, cwd: Option<&Path>) -> Result<ShellCommandResult> {
+fn run_windows_non_admin(
+    command: &str,
+    cwd: Option<&Path>,
+    capture: Option<&CaptureOptions>,
+) -> Result<ShellCommandResult> {
     let mut cmd = Command::new("cmd");
 
     if let Some(cwd) = cwd {
@@ -56,14 +157,13 @@ fn run_windows_non_admin(command: &str, cwd: Option<&Path>) -> Result<ShellComma
 
     debug!("run_windows_non_admin: {}", command);
 
-    let status = cmd.status().map_err(|e| {
+    let result = run(&mut cmd, capture).map_err(|e| {
         error!("run_windows_non_admin: {}", e);
         mlua::Error::external(e)
     })?;
 
-    let code = status.code().unwrap_or(-1);
-    debug!("run_windows_non_admin: exit={}", code);
-    Ok(ShellCommandResult { code })
+    debug!("run_windows_non_admin: exit={}", result.code);
+    Ok(result)
 }
 
 #[cfg(windows)]
@@ -71,11 +171,12 @@ fn run_windows_command(
     command: &str,
     cwd: Option<&Path>,
     admin: bool,
+    capture: Option<&CaptureOptions>,
 ) -> Result<ShellCommandResult> {
     if admin {
-        run_windows_admin(command, cwd)
+        run_windows_admin(command, cwd, capture)
     } else {
-        run_windows_non_admin(command, cwd)
+        run_windows_non_admin(command, cwd, capture)
     }
 }
 
@@ -84,11 +185,19 @@ fn run_windows_command(
 // ============================================================================
 
 #[cfg(target_os = "macos")]
-fn run_unix_admin(command: &str, cwd: Option<&Path>, shell: &str) -> Result<ShellCommandResult> {
+fn run_unix_admin(
+    command: &str,
+    cwd: Option<&Path>,
+    shell: &str,
+    capture: Option<&CaptureOptions>,
+) -> Result<ShellCommandResult> {
     use runas::Command as RunasCommand;
     let full_cmd = build_command_with_cwd(command, cwd);
 
     debug!("run_unix_admin (macOS): {}", full_cmd);
+    if capture.is_some() {
+        log::warn!("shell.exec: output of elevated commands can't be captured on macOS");
+    }
 
     let status = RunasCommand::new(shell)
         .arg("-c")
@@ -101,11 +210,19 @@ fn run_unix_admin(command: &str, cwd: Option<&Path>, shell: &str) -> Result<Shel
 
     let code = status.code().unwrap_or(-1);
     debug!("run_unix_admin: exit={}", code);
-    Ok(ShellCommandResult { code })
+    Ok(ShellCommandResult {
+        code,
+        ..Default::default()
+    })
 }
 
 #[cfg(target_os = "linux")]
-fn run_unix_admin(command: &str, cwd: Option<&Path>, shell: &str) -> Result<ShellCommandResult> {
+fn run_unix_admin(
+    command: &str,
+    cwd: Option<&Path>,
+    shell: &str,
+    capture: Option<&CaptureOptions>,
+) -> Result<ShellCommandResult> {
     use std::io::{self, IsTerminal};
     let full_cmd = build_command_with_cwd(command, cwd);
 
@@ -123,35 +240,35 @@ fn run_unix_admin(command: &str, cwd: Option<&Path>, shell: &str) -> Result<Shel
 
     debug!("run_unix_admin: {}", full_cmd);
 
-    let status = cmd.status().map_err(|e| {
+    let result = run(&mut cmd, capture).map_err(|e| {
         error!("run_unix_admin: {}", e);
         mlua::Error::external(e)
     })?;
 
-    let code = status.code().unwrap_or(-1);
-    debug!("run_unix_admin: exit={}", code);
-    Ok(ShellCommandResult { code })
+    debug!("run_unix_admin: exit={}", result.code);
+    Ok(result)
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
-fn run_unix_admin(command: &str, cwd: Option<&Path>, shell: &str) -> Result<ShellCommandResult> {
+fn run_unix_admin(
+    command: &str,
+    cwd: Option<&Path>,
+    shell: &str,
+    capture: Option<&CaptureOptions>,
+) -> Result<ShellCommandResult> {
     let full_cmd = build_command_with_cwd(command, cwd);
 
     debug!("run_unix_admin: using sudo");
 
-    let status = Command::new("sudo")
-        .arg(shell)
-        .arg("-c")
-        .arg(&full_cmd)
-        .status()
-        .map_err(|e| {
-            error!("run_unix_admin: {}", e);
-            mlua::Error::external(e)
-        })?;
+    let mut cmd = Command::new("sudo");
+    cmd.arg(shell).arg("-c").arg(&full_cmd);
+    let result = run(&mut cmd, capture).map_err(|e| {
+        error!("run_unix_admin: {}", e);
+        mlua::Error::external(e)
+    })?;
 
-    let code = status.code().unwrap_or(-1);
-    debug!("run_unix_admin: exit={}", code);
-    Ok(ShellCommandResult { code })
+    debug!("run_unix_admin: exit={}", result.code);
+    Ok(result)
 }
 
 #[cfg(unix)]
@@ -159,6 +276,7 @@ fn run_unix_non_admin(
     command: &str,
     cwd: Option<&Path>,
     shell: &str,
+    capture: Option<&CaptureOptions>,
 ) -> Result<ShellCommandResult> {
     let mut cmd = Command::new(shell);
 
@@ -170,18 +288,22 @@ fn run_unix_non_admin(
 
     debug!("run_unix_non_admin: {}", command);
 
-    let status = cmd.status().map_err(|e| {
+    let result = run(&mut cmd, capture).map_err(|e| {
         error!("run_unix_non_admin: {}", e);
         mlua::Error::external(e)
     })?;
 
-    let code = status.code().unwrap_or(-1);
-    debug!("run_unix_non_admin: exit={}", code);
-    Ok(ShellCommandResult { code })
+    debug!("run_unix_non_admin: exit={}", result.code);
+    Ok(result)
 }
 
 #[cfg(unix)]
-fn run_unix_command(command: &str, cwd: Option<&Path>, admin: bool) -> Result<ShellCommandResult> {
+fn run_unix_command(
+    command: &str,
+    cwd: Option<&Path>,
+    admin: bool,
+    capture: Option<&CaptureOptions>,
+) -> Result<ShellCommandResult> {
     use std::env;
     let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
 
@@ -193,9 +315,9 @@ fn run_unix_command(command: &str, cwd: Option<&Path>, admin: bool) -> Result<Sh
     );
 
     if admin {
-        run_unix_admin(command, cwd, &shell)
+        run_unix_admin(command, cwd, &shell, capture)
     } else {
-        run_unix_non_admin(command, cwd, &shell)
+        run_unix_non_admin(command, cwd, &shell, capture)
     }
 }
 
@@ -218,42 +340,50 @@ pub(crate) fn make_shell_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Ta
 
     shell_module.set(
         "exec",
-        lua.create_function(move |lua, (command, admin): (String, Option<bool>)| {
-            let admin = admin.unwrap_or(false);
-            debug!(
-                "shell.exec: command='{}', admin={}, cwd={:?}",
-                command,
-                admin,
-                cwd_buf.as_ref().map(|p| p.display().to_string())
-            );
-            let result_table = lua.create_table()?;
-
-            let cwd_opt = cwd_buf.as_deref();
-
-            let output: Result<ShellCommandResult> = {
-                #[cfg(windows)]
-                {
-                    run_windows_command(&command, cwd_opt, admin)
-                }
-                #[cfg(unix)]
-                {
-                    run_unix_command(&command, cwd_opt, admin)
-                }
-            };
-
-            match output {
-                Ok(cmd_output) => {
-                    debug!("shell.exec: exit_code={}", cmd_output.code);
-                    result_table.set("code", cmd_output.code)?;
-                }
-                Err(e) => {
-                    error!("shell.exec: failed to execute '{}': {}", command, e);
-                    result_table.set("code", -1)?;
+        lua.create_function(
+            move |lua, (command, admin, opts): (String, Option<bool>, Option<Table>)| {
+                let admin = admin.unwrap_or(false);
+                let capture = CaptureOptions::from_table(opts.as_ref())?;
+                debug!(
+                    "shell.exec: command='{}', admin={}, cwd={:?}",
+                    command,
+                    admin,
+                    cwd_buf.as_ref().map(|p| p.display().to_string())
+                );
+                let result_table = lua.create_table()?;
+
+                let cwd_opt = cwd_buf.as_deref();
+
+                let output: Result<ShellCommandResult> = {
+                    #[cfg(windows)]
+                    {
+                        run_windows_command(&command, cwd_opt, admin, capture.as_ref())
+                    }
+                    #[cfg(unix)]
+                    {
+                        run_unix_command(&command, cwd_opt, admin, capture.as_ref())
+                    }
+                };
+
+                match output {
+                    Ok(cmd_output) => {
+                        debug!("shell.exec: exit_code={}", cmd_output.code);
+                        result_table.set("code", cmd_output.code)?;
+                        if capture.is_some() {
+                            result_table.set("stdout", cmd_output.stdout.unwrap_or_default())?;
+                            result_table.set("stderr", cmd_output.stderr.unwrap_or_default())?;
+                            result_table.set("truncated", cmd_output.truncated)?;
+                        }
+                    }
+                    Err(e) => {
+                        error!("shell.exec: failed to execute '{}': {}", command, e);
+                        result_table.set("code", -1)?;
+                    }
                 }
-            }
 
-            Ok(result_table)
-        })?,
+                Ok(result_table)
+            },
+        )?,
     )?;
 
     debug!("make_shell_module: done");
@@ -0,0 +1,118 @@
+use super::shared::canonicalize_cwd;
+use log::{debug, error};
+use mlua::{Error as LuaError, Lua, LuaSerdeExt, Result, Table, Value as LuaValue};
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File the store keeps its values in, relative to the script's directory.
+const STORE_FILE: &str = ".ninja/store.json";
+
+fn read_store(path: &Path) -> Result<Map<String, Value>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Map::new()),
+        Err(e) => {
+            error!("store: failed to read '{}': {}", path.display(), e);
+            return Err(LuaError::external(e));
+        }
+    };
+    serde_json::from_str(&content).map_err(|e| {
+        error!("store: '{}' is not a JSON object: {}", path.display(), e);
+        LuaError::external(e)
+    })
+}
+
+/// Writes the store through a temporary file, so a crash never leaves half a file.
+fn write_store(path: &Path, values: &Map<String, Value>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(LuaError::external)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let data = serde_json::to_vec_pretty(values).map_err(LuaError::external)?;
+    fs::write(&tmp_path, data).map_err(LuaError::external)?;
+    fs::rename(&tmp_path, path).map_err(|e| {
+        error!("store: failed to replace '{}': {}", path.display(), e);
+        LuaError::external(e)
+    })
+}
+
+/// Builds the `store` module: JSON values kept across runs in `.ninja/store.json`.
+///
+/// The file lives in the directory the script runs in, which for a
+/// Shuriken's scripts is the Shuriken's own directory. Scripts run
+/// without a directory get a module whose functions raise an error.
+pub(crate) fn make_store_module(lua: &Lua, base_cwd: Option<&Path>) -> Result<Table> {
+    debug!(
+        "make_store_module: base_cwd = {:?}",
+        base_cwd.map(|p| p.display().to_string())
+    );
+    let store_module = lua.create_table()?;
+    let store_path: Option<PathBuf> = canonicalize_cwd(base_cwd).map(|cwd| cwd.join(STORE_FILE));
+
+    let path_or_err = |path: &Option<PathBuf>| {
+        path.clone().ok_or_else(|| {
+            LuaError::RuntimeError(
+                "store is only available to scripts run in a shuriken directory".into(),
+            )
+        })
+    };
+
+    {
+        let store_path = store_path.clone();
+        store_module.set(
+            "get",
+            lua.create_function(move |lua, key: String| {
+                let path = path_or_err(&store_path)?;
+                let values = read_store(&path)?;
+                debug!(
+                    "store.get: key='{}', found={}",
+                    key,
+                    values.contains_key(&key)
+                );
+                match values.get(&key) {
+                    Some(value) => lua.to_value(value),
+                    None => Ok(LuaValue::Nil),
+                }
+            })?,
+        )?;
+    }
+
+    {
+        let store_path = store_path.clone();
+        store_module.set(
+            "set",
+            lua.create_function(move |lua, (key, value): (String, LuaValue)| {
+                let path = path_or_err(&store_path)?;
+                let mut values = read_store(&path)?;
+                // Setting nil is the same as deleting
+                if value.is_nil() {
+                    values.remove(&key);
+                } else {
+                    let value: Value = lua.from_value(value)?;
+                    values.insert(key.clone(), value);
+                }
+                debug!("store.set: key='{}'", key);
+                write_store(&path, &values)
+            })?,
+        )?;
+    }
+
+    store_module.set(
+        "delete",
+        lua.create_function(move |_, key: String| {
+            let path = path_or_err(&store_path)?;
+            let mut values = read_store(&path)?;
+            let existed = values.remove(&key).is_some();
+            if existed {
+                write_store(&path, &values)?;
+            }
+            debug!("store.delete: key='{}', existed={}", key, existed);
+            Ok(existed)
+        })?,
+    )?;
+
+    Ok(store_module)
+}
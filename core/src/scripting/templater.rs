@@ -1,8 +1,9 @@
-use crate::common::types::FieldValue;
+use crate::common::types::{FieldValue, RenderFormat};
 use anyhow::Result;
 use log::{debug, error, info};
-use std::{collections::HashMap, env, error::Error, fmt::Display, path::PathBuf};
-use tera::{Context, Error as TeraError, ErrorKind, Function, Tera, Value};
+use regex::Regex;
+use std::{collections::HashMap, env, error::Error, fmt::Display, path::PathBuf, sync::LazyLock};
+use tera::{Context, Error as TeraError, ErrorKind, Filter, Function, Tera, Value};
 use tokio::{fs, sync::RwLock};
 
 #[derive(Debug)]
@@ -28,6 +29,34 @@ impl Display for TemplateError {
 
 impl Error for TemplateError {}
 
+// `{{ name:directive }}`, a placeholder with a render format
+static DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.]*)\s*:\s*([A-Za-z_]+)\s*\}\}").unwrap()
+});
+
+// `{% raw %}...{% endraw %}` blocks and `{# ... #}` comments, which Tera
+// passes through or drops without parsing
+static VERBATIM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)\{%-?\s*raw\s*-?%\}.*?\{%-?\s*endraw\s*-?%\}|\{#.*?#\}").unwrap()
+});
+
+/// Rewrites `{{ name:directive }}` placeholders into Tera's
+/// `{{ name | render(format="directive") }}`, which Tera can parse.
+///
+/// Text inside `{% raw %}` blocks and comments is left as written.
+pub fn expand_directives(template: &str) -> String {
+    const EXPANDED: &str = r#"{{ $1 | render(format="$2") }}"#;
+    let mut out = String::with_capacity(template.len());
+    let mut last = 0;
+    for verbatim in VERBATIM.find_iter(template) {
+        out.push_str(&DIRECTIVE.replace_all(&template[last..verbatim.start()], EXPANDED));
+        out.push_str(verbatim.as_str());
+        last = verbatim.end();
+    }
+    out.push_str(&DIRECTIVE.replace_all(&template[last..], EXPANDED));
+    out
+}
+
 pub struct Templater {
     context: HashMap<String, FieldValue>,
     root: PathBuf,
@@ -88,15 +117,19 @@ impl Templater {
             pattern_str
         );
 
-        let mut tera = Tera::new(&pattern_str).map_err(|e| {
-            error!(
-                "Templater::new: failed to compile templates (pattern '{}'): {}",
-                pattern_str, e
-            );
-            TemplateError::Internal(format!("Failed to compile templates: {}", e))
-        })?;
-
+        // Templates are read by hand so directives can be expanded before
+        // Tera parses them
+        let mut tera = Tera::default();
         tera.register_function("path", PathFunction);
+        tera.register_filter("render", RenderFilter);
+        tera.add_raw_templates(Self::load_templates(&root_path)?)
+            .map_err(|e| {
+                error!(
+                    "Templater::new: failed to compile templates (pattern '{}'): {}",
+                    pattern_str, e
+                );
+                TemplateError::Internal(format!("Failed to compile templates: {}", e))
+            })?;
 
         info!(
             "Templater::new: initialized successfully (root = {}, pattern = {})",
@@ -111,6 +144,38 @@ impl Templater {
         })
     }
 
+    // Every `.tmpl` under `.ninja`, named by its path relative to it
+    fn load_templates(root_path: &std::path::Path) -> Result<Vec<(String, String)>, TemplateError> {
+        let ninja_dir = root_path.join(".ninja");
+        let Ok(walker) =
+            globwalk::GlobWalkerBuilder::from_patterns(&ninja_dir, &["**/*.tmpl"]).build()
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut templates = Vec::new();
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            let Ok(relative) = entry.path().strip_prefix(&ninja_dir) else {
+                continue;
+            };
+            let name = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let content = std::fs::read_to_string(entry.path()).map_err(|e| {
+                error!(
+                    "Templater::load_templates: failed to read '{}': {}",
+                    entry.path().display(),
+                    e
+                );
+                TemplateError::Internal(format!("Failed to read template '{}': {}", name, e))
+            })?;
+            templates.push((name, expand_directives(&content)));
+        }
+        Ok(templates)
+    }
+
     fn to_tera_context(&self) -> Context {
         let mut ctx = Context::new();
         for (key, value) in &self.context {
@@ -133,7 +198,7 @@ impl Templater {
         let ctx = self.to_tera_context();
         let mut tera_guard = self.tera.write().await;
 
-        match tera_guard.render_str(template, &ctx) {
+        match tera_guard.render_str(&expand_directives(template), &ctx) {
             Ok(output) => {
                 debug!(
                     "Templater::render_with_diagnostics: rendered '{}' (output len = {})",
@@ -233,14 +298,26 @@ impl Templater {
             config_path.display()
         );
 
-        fs::write(&config_path, rendered).await.map_err(|e| {
+        // Write next to the target and rename over it, so a failed write
+        // never leaves a truncated config in place of the previous one
+        let file_name = config_path
+            .file_name()
+            .ok_or_else(|| TemplateError::PathNotFound(config_path.clone()))?;
+        let tmp_path = config_path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+        let written = match fs::write(&tmp_path, rendered).await {
+            Ok(()) => fs::rename(&tmp_path, &config_path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
             error!(
                 "Templater::generate_config: failed to write config '{}': {}",
                 config_path.display(),
                 e
             );
-            TemplateError::PathNotFound(config_path.clone())
-        })?;
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(TemplateError::PathNotFound(config_path.clone()));
+        }
 
         info!(
             "Templater::generate_config: config generated at '{}'",
@@ -250,6 +327,28 @@ impl Templater {
     }
 }
 
+/// The `render` filter behind `{{ name:directive }}` placeholders.
+///
+/// Takes the directive as its `format` argument and renders the value with
+/// [`FieldValue::render_for`].
+struct RenderFilter;
+
+impl Filter for RenderFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let directive = args.get("format").and_then(Value::as_str).unwrap_or("raw");
+        let format = RenderFormat::from_directive(directive).ok_or_else(|| {
+            TeraError::msg(format!(
+                "Unknown render format '{}' (expected one of: {})",
+                directive,
+                RenderFormat::DIRECTIVES.join(", ")
+            ))
+        })?;
+        let value: FieldValue = serde_json::from_value(value.clone())
+            .map_err(|e| TeraError::msg(format!("Can't render value: {}", e)))?;
+        Ok(Value::String(value.render_for(format)))
+    }
+}
+
 #[allow(dead_code)]
 struct PathFunction;
 
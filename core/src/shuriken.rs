@@ -1,18 +1,27 @@
-use crate::common::types::ShurikenState;
+use crate::common::{log_buffer, types::ShurikenState};
 use crate::manager::ShurikenManager;
-use crate::utils::{get_port_owner, normalize_path, parse_path};
+use crate::utils::{
+    get_port_owner, kill_process_by_pid, normalize_path, parse_path, process_alive,
+};
 use crate::{common::types::FieldValue, scripting::NinjaEngine, scripting::templater::Templater};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use log::{debug, error, info, warn};
+use mlua::{IntoLua, Lua, LuaSerdeExt, Value as LuaValue};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value as JsonValue, json};
 use std::sync::Arc;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    net::{SocketAddr, TcpStream},
     path::{Path, PathBuf},
+    time::Duration,
 };
 use tokio::{fs, sync::Mutex};
 
+/// Functions a daemon's lifecycle script must define.
+pub const LIFECYCLE_FUNCTIONS: &[&str] = &["start", "stop"];
+
 /// Represents a tool script associated with a Shuriken.
 ///
 /// Tools are executable scripts that can be invoked to perform
@@ -27,13 +36,46 @@ pub struct Tool {
     pub description: Option<String>,
 }
 
+/// Client-facing view of a [`Tool`].
+///
+/// Carries only what a UI needs to offer the tool, leaving out the
+/// script it runs.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ToolInfo {
+    /// The name of the tool
+    pub name: String,
+    /// Optional human-readable description
+    pub description: Option<String>,
+}
+
+impl From<&Tool> for ToolInfo {
+    fn from(tool: &Tool) -> Self {
+        Self {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+        }
+    }
+}
+
+/// What running a tool produced.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct ToolOutput {
+    /// Whatever the tool script returned
+    pub result: String,
+    /// Everything the script printed while running
+    pub printed: String,
+}
+
 /// Configuration settings for a Shuriken.
 ///
 /// Specifies the path to configuration templates and runtime options.
+/// Keys may be spelled with underscores (`config_path`) and are written back
+/// with dashes. Loading a manifest warns about any other key, so a typo
+/// doesn't silently drop the template.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ShurikenConfig {
     /// Path to the configuration file template (relative to Shuriken directory)
-    #[serde(rename = "config-path")]
+    #[serde(rename = "config-path", alias = "config_path")]
     pub config_path: PathBuf,
     /// Runtime configuration options applied during execution
     pub options: Option<HashMap<String, FieldValue>>,
@@ -53,14 +95,195 @@ pub struct ShurikenMetadata {
     /// Optional list of TCP ports used by the service
     pub ports: Option<Vec<u16>>,
     /// Whether to verify ports are free before starting (default: false)
-    #[serde(rename = "check-ports")]
+    #[serde(rename = "check-ports", alias = "check_ports")]
     pub check_ports: Option<bool>,
     /// Path to the main startup script
-    #[serde(rename = "script-path")]
+    #[serde(rename = "script-path", alias = "script_path")]
     pub script_path: Option<PathBuf>,
     /// Type of Shuriken: "daemon", "binary", "library", etc.
     #[serde(rename = "type")]
     pub shuriken_type: String,
+    /// Seconds to wait for the Shuriken to become healthy after starting
+    #[serde(rename = "start-timeout", alias = "start_timeout")]
+    pub start_timeout: Option<u64>,
+    /// Seconds without activity after which a running Shuriken is stopped
+    #[serde(rename = "idle-timeout", alias = "idle_timeout")]
+    pub idle_timeout: Option<u64>,
+    /// Path to a cleanup script run after the Shuriken has stopped
+    #[serde(rename = "post-stop", alias = "post_stop")]
+    pub post_stop: Option<PathBuf>,
+    /// Names of Shurikens that must be started before this one
+    #[serde(rename = "depends-on", alias = "depends_on")]
+    pub depends_on: Option<Vec<String>>,
+    /// Alternative ways to start the Shuriken, selected by name
+    pub profiles: Option<HashMap<String, ShurikenProfile>>,
+    /// Whether to start the Shuriken again when its process exits (default: never)
+    pub restart: Option<RestartPolicy>,
+    /// Lines of output to keep in memory when there's no `[logs]` section.
+    ///
+    /// Output of processes the start script spawns is piped through the
+    /// process that started the Shuriken, so this suits Shurikens started
+    /// by a long-running frontend (GUI, HTTP API) rather than `shurikenctl`.
+    /// Once that process exits, they get `SIGPIPE` on their next write.
+    #[serde(rename = "log-buffer", alias = "log_buffer")]
+    pub log_buffer: Option<usize>,
+    /// Seconds to wait after the start script returns before the Shuriken
+    /// counts as running, for binaries that need a moment to bind.
+    /// Applied by [`ShurikenManager`] after releasing the engine lock
+    #[serde(rename = "start-delay", alias = "start_delay")]
+    pub start_delay: Option<u64>,
+    /// Something that must be ready before the start script runs,
+    /// see [`Shuriken::wait_until_ready`]
+    #[serde(rename = "wait-for", alias = "wait_for")]
+    pub wait_for: Option<WaitCondition>,
+}
+
+/// How long [`WaitCondition::wait`] waits when the manifest sets no `start-timeout`.
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A precondition checked before a Shuriken's start script runs.
+///
+/// Written in the manifest as a one-key table:
+///
+/// ```toml
+/// [shuriken]
+/// wait-for = { port = 5432 }
+/// # or
+/// wait-for = { file = "run/db.sock" }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WaitCondition {
+    /// A file exists, relative to the Shuriken directory unless absolute
+    File(PathBuf),
+    /// A TCP port accepts connections on localhost
+    Port(u16),
+}
+
+impl WaitCondition {
+    /// Whether the condition holds right now.
+    pub fn is_met(&self, shuriken_dir: &Path) -> bool {
+        match self {
+            WaitCondition::File(path) => shuriken_dir.join(path).exists(),
+            WaitCondition::Port(port) => {
+                let addr = SocketAddr::from(([127, 0, 0, 1], *port));
+                TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok()
+            }
+        }
+    }
+
+    /// Polls until the condition holds.
+    ///
+    /// # Returns
+    /// - `Ok(())` once the condition is met
+    /// - `Err(msg)` if it still isn't after `timeout`
+    pub async fn wait(&self, shuriken_dir: &Path, timeout: Duration) -> Result<(), String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let dir = shuriken_dir.to_path_buf();
+            let condition = self.clone();
+            let met = tokio::task::spawn_blocking(move || condition.is_met(&dir))
+                .await
+                .unwrap_or(false);
+            if met {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!("Gave up after {:?} waiting for {}", timeout, self));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+impl std::fmt::Display for WaitCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaitCondition::File(path) => write!(f, "file {}", path.display()),
+            WaitCondition::Port(port) => write!(f, "port {}", port),
+        }
+    }
+}
+
+/// When a Shuriken whose process exited on its own is started again.
+///
+/// Written in the manifest as `restart = "always"`, or as a table for
+/// `on-failure`:
+///
+/// ```toml
+/// [shuriken.restart.on-failure]
+/// max-retries = 3
+/// backoff-secs = 2
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", rename_all_fields = "kebab-case")]
+pub enum RestartPolicy {
+    /// Leave it stopped
+    #[default]
+    Never,
+    /// Restart after a non-zero exit, up to `max_retries` times in a row.
+    /// The n-th retry waits `backoff_secs * 2^(n-1)` seconds.
+    OnFailure { max_retries: u32, backoff_secs: u64 },
+    /// Restart after every exit, clean ones included. The n-th restart in a
+    /// row waits `2^(n-1)` seconds, at most [`MAX_ALWAYS_BACKOFF`].
+    Always,
+}
+
+/// How long a process has to stay up for the restarts before it to stop
+/// counting as "in a row".
+pub const STABLE_RUN: Duration = Duration::from_secs(30);
+
+/// The longest [`RestartPolicy::Always`] waits before a restart.
+pub const MAX_ALWAYS_BACKOFF: Duration = Duration::from_secs(60);
+
+impl RestartPolicy {
+    /// Which restart in a row the next one is, given `restarts` made in a
+    /// row before a process that stayed up for `ran_for`.
+    ///
+    /// A process that ran for at least [`STABLE_RUN`] starts the count over.
+    pub fn next_attempt(restarts: u32, ran_for: Duration) -> u32 {
+        if ran_for >= STABLE_RUN {
+            1
+        } else {
+            restarts.saturating_add(1)
+        }
+    }
+
+    /// How long to wait before restart number `attempt` (starting at 1) after
+    /// an exit with `code`.
+    ///
+    /// # Returns
+    /// - `Some(delay)` if the policy restarts the Shuriken
+    /// - `None` if it should stay stopped, including when retries ran out
+    pub fn restart_delay(&self, code: i32, attempt: u32) -> Option<Duration> {
+        match *self {
+            RestartPolicy::Never => None,
+            RestartPolicy::Always => {
+                let factor = 1u64 << attempt.saturating_sub(1).min(16);
+                Some(Duration::from_secs(factor).min(MAX_ALWAYS_BACKOFF))
+            }
+            RestartPolicy::OnFailure {
+                max_retries,
+                backoff_secs,
+            } => {
+                if code == 0 || attempt == 0 || attempt > max_retries {
+                    return None;
+                }
+                let factor = 1u64 << (attempt - 1).min(16);
+                Some(Duration::from_secs(backoff_secs.saturating_mul(factor)))
+            }
+        }
+    }
+}
+
+/// A named alternative start definition, declared under `[shuriken.profiles.<name>]`.
+///
+/// Lets one Shuriken ship e.g. separate `dev` and `prod` start scripts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ShurikenProfile {
+    /// Path to the startup script used instead of the base `script-path`
+    #[serde(rename = "script-path", alias = "script_path")]
+    pub script_path: PathBuf,
 }
 
 /// Logging configuration for a Shuriken.
@@ -69,10 +292,16 @@ pub struct ShurikenMetadata {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LogsConfig {
     /// Path where logs should be written
-    #[serde(rename = "log-path")]
+    #[serde(rename = "log-path", alias = "log_path")]
     pub log_path: PathBuf,
 }
 
+async fn read_lockfile(shuriken_dir: &Path) -> Option<JsonValue> {
+    let lock_path = shuriken_dir.join(".ninja").join("shuriken.lck");
+    let content = fs::read_to_string(&lock_path).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
 async fn atomic_write_json(path: &Path, value: &JsonValue) -> Result<(), String> {
     use tokio::fs;
 
@@ -89,6 +318,67 @@ async fn atomic_write_json(path: &Path, value: &JsonValue) -> Result<(), String>
     Ok(())
 }
 
+/// Reads the PID a `start` function returned, directly or as a `proc.spawn` result.
+fn returned_pid(value: &LuaValue) -> Option<u32> {
+    let pid = match value {
+        LuaValue::Integer(pid) => u32::try_from(*pid).ok(),
+        LuaValue::Table(table) => table.get::<Option<u32>>("pid").ok().flatten(),
+        _ => None,
+    };
+    // `proc.spawn` reports 0 when it couldn't tell
+    pid.filter(|pid| *pid != 0)
+}
+
+/// What [`Shuriken::start`] would do, as resolved by [`Shuriken::dry_start`].
+///
+/// Nothing is executed to build it, so it is safe to print for a
+/// misconfigured Shuriken.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct StartPlan {
+    /// Type of Shuriken from the manifest
+    pub shuriken_type: String,
+    /// Directory scripts run in
+    pub cwd: PathBuf,
+    /// The manifest's `script-path`, resolved against `cwd`
+    pub script_path: Option<PathBuf>,
+    /// The compiled `.ns` script that is actually executed
+    pub compiled_path: Option<PathBuf>,
+    /// Function called in the compiled script
+    pub function: Option<String>,
+    /// The context table `function` is called with, as JSON
+    pub args: Option<JsonValue>,
+    /// Variables set for the processes the start script spawns, on top of
+    /// the ones it passes itself
+    pub env: BTreeMap<String, String>,
+    /// Lockfile written once the Shuriken is up
+    pub lockfile: PathBuf,
+    /// Ports checked for availability before starting
+    pub checked_ports: Vec<u16>,
+    /// Problems that would make the real start fail or do nothing
+    pub problems: Vec<String>,
+}
+
+/// Context exposed to a Shuriken's script as the `shuriken` global.
+///
+/// Lets scripts read their own name, resolved directory and options
+/// without hardcoding them. `start` and `stop` also receive it as their argument.
+#[derive(Serialize)]
+struct ShurikenContext {
+    name: String,
+    dir: PathBuf,
+    options: HashMap<String, FieldValue>,
+}
+
+impl IntoLua for ShurikenContext {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<LuaValue> {
+        let table = lua.create_table()?;
+        table.set("name", self.name)?;
+        table.set("dir", self.dir.display().to_string())?;
+        table.set("options", lua.to_value(&self.options)?)?;
+        Ok(LuaValue::Table(table))
+    }
+}
+
 /// Represents the complete TOML structure of a shuriken.toml file.
 ///
 /// This is the raw representation before being parsed into a `Shuriken` struct.
@@ -129,11 +419,51 @@ pub struct Shuriken {
 }
 
 impl Shuriken {
+    /// Builds the `shuriken` global handed to scripts.
+    fn script_context(&self, shuriken_dir: &Path) -> ShurikenContext {
+        ShurikenContext {
+            name: self.metadata.name.clone(),
+            dir: shuriken_dir.to_path_buf(),
+            options: self
+                .config
+                .as_ref()
+                .and_then(|c| c.options.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns this Shuriken with the start definition of `profile` in place
+    /// of the base one.
+    ///
+    /// The copy shares its state with `self`, so starting it updates this
+    /// Shuriken's state as well.
+    ///
+    /// # Returns
+    /// - `Ok(Shuriken)` using the profile's `script-path`
+    /// - `Err` if the manifest declares no such profile
+    pub fn with_profile(&self, profile: &str) -> Result<Shuriken> {
+        let selected = self
+            .metadata
+            .profiles
+            .as_ref()
+            .and_then(|profiles| profiles.get(profile))
+            .ok_or_else(|| {
+                anyhow::Error::msg(format!(
+                    "Shuriken '{}' has no profile '{}'",
+                    self.metadata.name, profile
+                ))
+            })?;
+
+        let mut shuriken = self.clone();
+        shuriken.metadata.script_path = Some(selected.script_path.clone());
+        Ok(shuriken)
+    }
+
     /// Starts this Shuriken by executing its startup script.
     ///
     /// Performs port availability checks if configured, creates necessary directories,
     /// compiles and executes the startup script, and writes a lock file.
-    /// Updates internal state to `Running` on success.
+    /// The state is left to [`ShurikenManager`], which moves it to `Running`.
     ///
     /// # Arguments
     /// - `engine`: Reference to the Lua scripting engine
@@ -148,6 +478,21 @@ impl Shuriken {
         engine: &NinjaEngine,
         shuriken_dir: &Path,
         mgr: Option<ShurikenManager>,
+    ) -> Result<(), String> {
+        self.start_with_env(engine, shuriken_dir, mgr, &HashMap::new())
+            .await
+    }
+
+    /// Starts this Shuriken like [`Shuriken::start`], with extra environment variables.
+    ///
+    /// `env` is set for every process the start script spawns through the
+    /// `proc` module, on top of the variables the script passes itself.
+    pub async fn start_with_env(
+        &self,
+        engine: &NinjaEngine,
+        shuriken_dir: &Path,
+        mgr: Option<ShurikenManager>,
+        env: &HashMap<String, String>,
     ) -> Result<(), String> {
         info!("Starting shuriken {}", self.metadata.name);
 
@@ -192,26 +537,201 @@ impl Shuriken {
                 .to_string();
             let compiled_path = lock_dir.join(format!("{stem}.ns"));
 
+            if self.logs.is_none()
+                && let Some(lines) = self.metadata.log_buffer
+            {
+                log_buffer::enable(shuriken_dir, lines);
+            }
+
+            let mut pid = None;
             if let Some(mgr) = mgr {
-                engine
-                    .execute_function("start", &compiled_path, Some(shuriken_dir), Some(mgr))
+                let returned = engine
+                    .execute_lifecycle_function::<LuaValue>(
+                        "start",
+                        &compiled_path,
+                        shuriken_dir,
+                        Some(mgr),
+                        env,
+                        self.script_context(shuriken_dir),
+                    )
                     .await
                     .map_err(|e| format!("Script start failed: {}", e))?;
+                pid = returned_pid(&returned);
             }
 
-            let lockfile_data = json!({
+            // Stop runs the same script, even when a profile picked it
+            let mut lockfile_data = json!({
                 "name": self.metadata.name,
                 "type": "Script",
+                "start_time": Utc::now().timestamp(),
+                "script_path": script_path,
             });
+            if let Some(pid) = pid {
+                lockfile_data["pid"] = json!(pid);
+            }
 
             atomic_write_json(&lock_path, &lockfile_data).await?;
-
-            let mut state = self.state.lock().await;
-            *state = ShurikenState::Running;
         }
         Ok(())
     }
 
+    /// Waits for this Shuriken's `wait-for` condition, if it has one.
+    ///
+    /// [`ShurikenManager`] calls this before taking the engine lock, so
+    /// whatever is being waited on can start in the meantime.
+    ///
+    /// # Returns
+    /// - `Ok(())` once the condition holds, or right away without one
+    /// - `Err(msg)` if it still doesn't hold after the start timeout
+    pub async fn wait_until_ready(&self, shuriken_dir: &Path) -> Result<(), String> {
+        let Some(condition) = &self.metadata.wait_for else {
+            return Ok(());
+        };
+        info!(
+            "Waiting for {} before starting {}",
+            condition, self.metadata.name
+        );
+        let timeout = self
+            .metadata
+            .start_timeout
+            .map_or(DEFAULT_WAIT_TIMEOUT, Duration::from_secs);
+        condition.wait(shuriken_dir, timeout).await
+    }
+
+    /// Resolves what [`Shuriken::start`] would run, without running anything.
+    ///
+    /// Port checks are listed but not performed, and paths are resolved
+    /// exactly like `start` resolves them.
+    ///
+    /// # Arguments
+    /// - `shuriken_dir`: Directory containing the Shuriken's files
+    ///
+    /// # Returns
+    /// - `Ok(StartPlan)` describing the start, with any problems found
+    /// - `Err` if the script path has no file name to compile from
+    pub fn dry_start(&self, shuriken_dir: &Path) -> Result<StartPlan> {
+        self.dry_start_with_env(shuriken_dir, &HashMap::new())
+    }
+
+    /// Resolves what [`Shuriken::start_with_env`] would run with `env`,
+    /// without running anything.
+    pub fn dry_start_with_env(
+        &self,
+        shuriken_dir: &Path,
+        env: &HashMap<String, String>,
+    ) -> Result<StartPlan> {
+        let lock_dir = shuriken_dir.join(".ninja");
+        let mut problems = Vec::new();
+
+        if !shuriken_dir.is_dir() {
+            problems.push(format!(
+                "Shuriken directory not found: {}",
+                shuriken_dir.display()
+            ));
+        }
+
+        let checked_ports = if self.metadata.check_ports.unwrap_or(false) {
+            self.metadata.ports.clone().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let (script_path, compiled_path, function) = match (
+            self.metadata.shuriken_type.as_str(),
+            &self.metadata.script_path,
+        ) {
+            ("daemon", Some(script_path)) => {
+                let path = normalize_path(script_path.as_path());
+                let full_script_path = parse_path(
+                    &shuriken_dir.to_path_buf(),
+                    path.display().to_string(),
+                    None,
+                );
+                let stem = full_script_path
+                    .file_stem()
+                    .ok_or_else(|| anyhow::Error::msg("Invalid script path"))?
+                    .to_string_lossy()
+                    .to_string();
+                let compiled_path = lock_dir.join(format!("{stem}.ns"));
+
+                if !compiled_path.is_file() {
+                    problems.push(format!(
+                        "Compiled script not found: {}",
+                        compiled_path.display()
+                    ));
+                }
+
+                (
+                    Some(full_script_path),
+                    Some(compiled_path),
+                    Some("start".to_string()),
+                )
+            }
+            ("daemon", None) => {
+                problems.push("Daemon has no script-path, so start does nothing".into());
+                (None, None, None)
+            }
+            (other, _) => {
+                problems.push(format!(
+                    "Shuriken type '{}' has no start behaviour; only 'daemon' is started",
+                    other
+                ));
+                (None, None, None)
+            }
+        };
+
+        Ok(StartPlan {
+            shuriken_type: self.metadata.shuriken_type.clone(),
+            cwd: shuriken_dir.to_path_buf(),
+            script_path,
+            compiled_path,
+            args: match function {
+                Some(_) => Some(serde_json::to_value(self.script_context(shuriken_dir))?),
+                None => None,
+            },
+            function,
+            env: env.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            lockfile: lock_dir.join("shuriken.lck"),
+            checked_ports,
+            problems,
+        })
+    }
+
+    /// Serializes this Shuriken back into manifest TOML.
+    ///
+    /// Shows the manifest as the manager parsed it: renamed keys in their
+    /// canonical form, defaults applied and options merged into `[config]`.
+    pub fn to_manifest(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Checks whether this Shuriken is serving.
+    ///
+    /// A Shuriken with declared ports is healthy once every port accepts a
+    /// TCP connection on localhost. Without ports, being `Running` is enough.
+    pub async fn is_healthy(&self) -> bool {
+        if *self.state.lock().await != ShurikenState::Running {
+            return false;
+        }
+
+        let Some(ports) = &self.metadata.ports else {
+            return true;
+        };
+
+        for port in ports {
+            let addr = SocketAddr::from(([127, 0, 0, 1], *port));
+            let connect = tokio::net::TcpStream::connect(addr);
+            if !matches!(
+                tokio::time::timeout(Duration::from_millis(200), connect).await,
+                Ok(Ok(_))
+            ) {
+                return false;
+            }
+        }
+
+        true
+    }
+
     /// Removes the lock file for this Shuriken (without stopping it).
     ///
     /// Useful for recovering from crashes where the lock file wasn't cleaned up.
@@ -235,6 +755,69 @@ impl Shuriken {
         Ok(())
     }
 
+    /// Writes a lockfile for a process started outside Ninja, so it is
+    /// treated as this Shuriken running.
+    ///
+    /// # Arguments
+    /// - `shuriken_dir`: Directory containing the Shuriken's files
+    /// - `pid`: The already running process
+    /// - `start_time`: When that process started
+    ///
+    /// # Returns
+    /// - `Ok(())` once the lockfile is written
+    /// - `Err(msg)` if the lockfile can't be written
+    pub async fn adopt(
+        &self,
+        shuriken_dir: &Path,
+        pid: u32,
+        start_time: DateTime<Utc>,
+    ) -> Result<(), String> {
+        info!("Adopting PID {} as shuriken {}", pid, self.metadata.name);
+
+        let lock_dir = shuriken_dir.join(".ninja");
+        tokio::fs::create_dir_all(&lock_dir)
+            .await
+            .map_err(|e| format!("Failed to create .ninja directory: {}", e))?;
+
+        let lockfile_data = json!({
+            "name": self.metadata.name,
+            "type": "Adopted",
+            "start_time": start_time.timestamp(),
+            "pid": pid,
+        });
+        atomic_write_json(&lock_dir.join("shuriken.lck"), &lockfile_data).await?;
+        Ok(())
+    }
+
+    /// Reads the PID recorded in this Shuriken's lockfile.
+    ///
+    /// A PID is only recorded when the `start` function returns one, either
+    /// as a number or as the table `proc.spawn` gives back.
+    ///
+    /// # Returns
+    /// - `Some(pid)` if the Shuriken is running and recorded its PID
+    /// - `None` otherwise
+    pub async fn pid(&self, shuriken_dir: &Path) -> Option<u32> {
+        read_lockfile(shuriken_dir)
+            .await?
+            .get("pid")?
+            .as_u64()
+            .and_then(|pid| u32::try_from(pid).ok())
+    }
+
+    /// Reads when this Shuriken was started from its lockfile.
+    ///
+    /// # Returns
+    /// - `Some(time)` if the Shuriken is running and its lockfile records it
+    /// - `None` otherwise (lockfiles written by older versions lack it)
+    pub async fn start_time(&self, shuriken_dir: &Path) -> Option<DateTime<Utc>> {
+        let seconds = read_lockfile(shuriken_dir)
+            .await?
+            .get("start_time")?
+            .as_i64()?;
+        DateTime::from_timestamp(seconds, 0)
+    }
+
     /// Configures this Shuriken by templating its configuration file.
     ///
     /// Uses the `Templater` to render configuration templates with provided field values,
@@ -289,15 +872,15 @@ impl Shuriken {
                     info!("Successfully generated '{}'", config_full_path.display());
                 }
                 Err(e) => {
+                    // The previous config is still in place, generation never touches it on failure
                     error!("Failed to generate config: {e}");
-                    return Err(anyhow::Error::msg(e.to_string()));
+                    return Err(e.into());
                 }
             }
         } else {
             warn!("Shuriken '{}' has no configuration", self.metadata.name);
         }
 
-
         // TODO: implement in the future
         // if let Some(script_path) = &self.metadata.script_path {
         //     let resolved_script = parse_path(&root_path.to_path_buf(), script_path.display().to_string(), None);
@@ -371,6 +954,112 @@ impl Shuriken {
         }
     }
 
+    /// Runs one of this Shuriken's declared maintenance tools.
+    ///
+    /// The tool's script runs with the Shuriken's directory as cwd, in an
+    /// environment of its own holding the `shuriken` context and a `require`
+    /// rooted at that directory.
+    ///
+    /// # Arguments
+    /// - `tool`: Name of the tool as declared in the manifest
+    /// - `engine`: Reference to the Lua scripting engine
+    /// - `shuriken_dir`: Directory containing the Shuriken's files
+    /// - `mgr`: Optional manager reference for script context
+    ///
+    /// # Returns
+    /// - `Ok(output)` with whatever the tool script returned
+    /// - `Err(msg)` if the tool isn't declared or its script fails
+    pub async fn run_tool(
+        &self,
+        tool: &str,
+        engine: &NinjaEngine,
+        shuriken_dir: &Path,
+        mgr: Option<ShurikenManager>,
+    ) -> Result<String, String> {
+        let declared = self
+            .tools
+            .iter()
+            .flatten()
+            .find(|t| t.name == tool)
+            .ok_or_else(|| {
+                format!(
+                    "Shuriken '{}' has no tool named '{}'",
+                    self.metadata.name, tool
+                )
+            })?;
+
+        let script_path = normalize_path(&declared.script);
+        info!(
+            "Running tool '{}' ({}) for {}",
+            tool,
+            script_path.display(),
+            self.metadata.name
+        );
+
+        // Tools run without the engine lock, so their context and require
+        // path stay out of the globals a concurrent start writes to
+        engine
+            .eval_file_with(
+                &script_path,
+                shuriken_dir,
+                mgr,
+                &HashMap::new(),
+                "shuriken",
+                self.script_context(shuriken_dir),
+            )
+            .await
+            .map_err(|e| format!("Tool '{}' failed: {}", tool, e))
+    }
+
+    /// Runs this Shuriken's post-stop cleanup script, if it declares one.
+    ///
+    /// The script runs with the Shuriken's directory as cwd and can read
+    /// its context (name, dir, options) from the `shuriken` global.
+    ///
+    /// # Arguments
+    /// - `engine`: Reference to the Lua scripting engine
+    /// - `shuriken_dir`: Directory containing the Shuriken's files
+    /// - `mgr`: Optional manager reference for script context
+    ///
+    /// # Returns
+    /// - `Ok(())` if there is no post-stop script or it succeeded
+    /// - `Err(msg)` if the script fails
+    pub async fn post_stop(
+        &self,
+        engine: &NinjaEngine,
+        shuriken_dir: &Path,
+        mgr: Option<ShurikenManager>,
+    ) -> Result<(), String> {
+        let Some(script_path) = &self.metadata.post_stop else {
+            return Ok(());
+        };
+
+        let path = normalize_path(script_path.as_path());
+        let full_script_path = parse_path(
+            &shuriken_dir.to_path_buf(),
+            path.display().to_string(),
+            None,
+        );
+        info!(
+            "Running post-stop script '{}' for {}",
+            full_script_path.display(),
+            self.metadata.name
+        );
+
+        engine
+            .eval_file_with(
+                &full_script_path,
+                shuriken_dir,
+                mgr,
+                &HashMap::new(),
+                "shuriken",
+                self.script_context(shuriken_dir),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Post-stop script failed: {}", e))
+    }
+
     /// Resolves a script path, handling both absolute and relative paths.
     ///
     /// If the path is absolute, returns it as-is.
@@ -390,10 +1079,12 @@ impl Shuriken {
         }
     }
 
-    /// Stops this running Shuriken wby executing its stop script.
+    /// Stops this running Shuriken by executing its stop script.
     ///
-    /// Calls the `stop` function if defined, removes the lock file,
-    /// and updates internal state to `Idle`.
+    /// Calls the `stop` function if defined and removes the lock file;
+    /// [`ShurikenManager`] then moves the state to `Idle`. A process taken
+    /// over with [`Shuriken::adopt`] has no script to stop it, so its
+    /// recorded PID is terminated instead.
     ///
     /// # Arguments
     /// - `engine`: Reference to the Lua scripting engine
@@ -412,8 +1103,25 @@ impl Shuriken {
         info!("Stopping shuriken {}", self.metadata.name);
         let lock_path = shuriken_dir.join(".ninja").join("shuriken.lck");
 
+        let lock = read_lockfile(shuriken_dir).await;
+
+        // An adopted process has no script of ours to stop it
+        if let Some(lock) = &lock
+            && lock.get("type").and_then(JsonValue::as_str) == Some("Adopted")
+        {
+            return self.stop_adopted(lock, &lock_path).await;
+        }
+
+        // The lockfile names the script that was started, which differs
+        // from the manifest's when a profile was used
+        let started_script = lock
+            .as_ref()
+            .and_then(|lock| lock.get("script_path")?.as_str().map(PathBuf::from));
+
         if self.metadata.shuriken_type == "daemon"
-            && let Some(script_path) = &self.metadata.script_path
+            && let Some(script_path) = started_script
+                .as_ref()
+                .or(self.metadata.script_path.as_ref())
         {
             let path = normalize_path(&script_path.as_path());
             let full_script_path = parse_path(
@@ -431,16 +1139,17 @@ impl Shuriken {
             let compiled_path = lock_dir.join(format!("{stem}.ns"));
 
             if let Some(mgr) = mgr {
-                {
-                    let mut state = self.state.lock().await;
-                    engine
-                        .execute_function("stop", &compiled_path, Some(shuriken_dir), Some(mgr))
-                        .await
-                        .map_err(|e| {
-                            *state = ShurikenState::Error(e.to_string());
-                            format!("Script stop failed: {}", e)
-                        })?;
-                } // Lock released here
+                engine
+                    .execute_lifecycle_function::<()>(
+                        "stop",
+                        &compiled_path,
+                        shuriken_dir,
+                        Some(mgr),
+                        &HashMap::new(),
+                        self.script_context(shuriken_dir),
+                    )
+                    .await
+                    .map_err(|e| format!("Script stop failed: {}", e))?;
             }
 
             if lock_path.exists() {
@@ -448,12 +1157,35 @@ impl Shuriken {
                     .await
                     .map_err(|e| format!("Failed to remove lockfile: {}", e))?;
             }
-
-            let mut state = self.state.lock().await;
-            *state = ShurikenState::Idle;
             Ok(())
         } else {
             return Err("Shuriken does not have a script path or is not a daemon".to_string());
         }
     }
+
+    /// Terminates a process taken over with [`Shuriken::adopt`] and releases it.
+    async fn stop_adopted(&self, lock: &JsonValue, lock_path: &Path) -> Result<(), String> {
+        let pid = lock
+            .get("pid")
+            .and_then(JsonValue::as_u64)
+            .and_then(|pid| u32::try_from(pid).ok())
+            .ok_or_else(|| "Adopted lockfile has no PID".to_string())?;
+
+        info!("Terminating adopted PID {} for {}", pid, self.metadata.name);
+        if process_alive(pid) {
+            let killed = tokio::task::spawn_blocking(move || kill_process_by_pid(pid))
+                .await
+                .map_err(|e| format!("Failed to terminate PID {}: {}", pid, e))?;
+            if !killed {
+                return Err(format!("Failed to terminate adopted PID {}", pid));
+            }
+        }
+
+        if lock_path.exists() {
+            tokio::fs::remove_file(lock_path)
+                .await
+                .map_err(|e| format!("Failed to remove lockfile: {}", e))?;
+        }
+        Ok(())
+    }
 }
@@ -3,31 +3,35 @@
 //! Provides helper functions for:
 //!
 //! - [`download`]: Downloading files from URLs with progress tracking
-//! - File operations: Creating tar.gz archives, loading Shurikens from disk
-//! - Process management: Killing processes by PID or name
+//! - File operations: Creating and safely unpacking tar.gz archives, loading Shurikens from disk
+//! - Process management: Killing, suspending, resuming and probing processes
 //! - Port detection: Finding which process is using a given port
 //! - Configuration parsing: Extracting ports from Apache/Nginx configs
 
 pub mod download;
 
 use crate::{
-    common::types::{FieldValue, ShurikenState},
+    common::types::{FieldValue, ForgeFilter, ForgeReport, ShurikenState},
     shuriken::{Shuriken, ShurikenConfig},
 };
 use anyhow::{Error, Result};
-use flate2::{Compression, write::GzEncoder};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use ignore::{WalkBuilder, overrides::OverrideBuilder};
+use log::warn;
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    path::{Component, Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
-use tar::Builder as TarBuilder;
+use tar::{Archive, Builder as TarBuilder};
 use tokio::{fs as async_fs, sync::Mutex};
 
 // fuh apache
 
-pub fn resolve_path(virtual_cwd: &Path, path: &PathBuf) -> PathBuf {
-    let p = Path::new(path);
+pub fn resolve_path(virtual_cwd: &Path, path: impl AsRef<Path>) -> PathBuf {
+    let p = path.as_ref();
 
     if p.is_absolute() {
         p.to_path_buf()
@@ -36,6 +40,29 @@ pub fn resolve_path(virtual_cwd: &Path, path: &PathBuf) -> PathBuf {
     }
 }
 
+/// Reads a file mapping Shuriken names to their options.
+///
+/// The file holds one table per Shuriken, e.g. `[webserver]` followed by its
+/// options. Files ending in `.json` are read as JSON, anything else as TOML.
+pub async fn read_bulk_options(
+    path: &Path,
+) -> Result<BTreeMap<String, HashMap<String, FieldValue>>> {
+    let content = async_fs::read_to_string(path)
+        .await
+        .map_err(|e| Error::msg(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        serde_json::from_str(&content)
+            .map_err(|e| Error::msg(format!("Invalid JSON in {}: {}", path.display(), e)))
+    } else {
+        toml::from_str(&content)
+            .map_err(|e| Error::msg(format!("Invalid TOML in {}: {}", path.display(), e)))
+    }
+}
+
 pub fn parse_path(seed: &PathBuf, path: String, delimiter: Option<&str>) -> PathBuf {
     let delimiter = delimiter.unwrap_or("/");
     let directories: Vec<String> = path.split(delimiter).map(String::from).collect();
@@ -79,8 +106,47 @@ pub fn kill_process_by_pid(pid: u32) -> bool {
     }
 }
 
+#[cfg(unix)]
+pub fn kill_process_by_pid(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::{Signal, kill};
+    use nix::unistd::Pid;
+    use std::thread;
+    use std::time::Duration;
+
+    let pid = Pid::from_raw(pid as i32);
+
+    match kill(pid, None) {
+        Ok(_) => {
+            if kill(pid, Signal::SIGTERM).is_ok() {
+                for _ in 0..10 {
+                    thread::sleep(Duration::from_millis(100));
+                    if kill(pid, None).is_err() {
+                        return true;
+                    }
+                }
+            }
+
+            kill(pid, Signal::SIGKILL).is_ok()
+        }
+        Err(Errno::ESRCH) => false,
+        Err(Errno::EPERM) => kill(pid, Signal::SIGKILL).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// A running process, as listed by [`list_processes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Executable name without its directory. Names that aren't valid
+    /// UTF-8 are converted lossily.
+    pub name: String,
+}
+
+/// Lists the running processes, using a ToolHelp snapshot.
 #[cfg(windows)]
-pub fn kill_process_by_name(name: &str) -> bool {
+pub fn list_processes() -> Vec<ProcessInfo> {
     use std::ffi::OsString;
     use std::os::windows::ffi::OsStringExt;
     use windows::Win32::Foundation::{CloseHandle, HANDLE};
@@ -89,20 +155,22 @@ pub fn kill_process_by_name(name: &str) -> bool {
         TH32CS_SNAPPROCESS,
     };
 
-    let target = name.to_ascii_lowercase();
+    let mut processes = Vec::new();
 
     unsafe {
         let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
             Ok(s) => s,
-            Err(_) => return false,
+            Err(_) => return processes,
         };
 
         if snapshot.is_invalid() {
-            return false;
+            return processes;
         }
 
-        let mut entry = PROCESSENTRY32W::default();
-        let mut any_killed = false;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
 
         if Process32FirstW(snapshot, &mut entry).is_ok() {
             loop {
@@ -113,13 +181,10 @@ pub fn kill_process_by_name(name: &str) -> bool {
                     .unwrap_or(entry.szExeFile.len());
 
                 let exe_os: OsString = OsString::from_wide(&entry.szExeFile[..len]);
-                let exe = exe_os.to_string_lossy().to_string();
-
-                if exe.to_ascii_lowercase() == target {
-                    if kill_process_by_pid(entry.th32ProcessID) {
-                        any_killed = true;
-                    }
-                }
+                processes.push(ProcessInfo {
+                    pid: entry.th32ProcessID,
+                    name: exe_os.to_string_lossy().to_string(),
+                });
 
                 if Process32NextW(snapshot, &mut entry).is_err() {
                     break;
@@ -128,132 +193,277 @@ pub fn kill_process_by_name(name: &str) -> bool {
         }
 
         let _ = CloseHandle(HANDLE(snapshot.0));
-        any_killed
     }
+
+    processes
 }
 
-#[cfg(unix)]
-pub fn kill_process_by_pid(pid: u32) -> bool {
-    use nix::errno::Errno;
-    use nix::sys::signal::{Signal, kill};
-    use nix::unistd::Pid;
-    use std::thread;
-    use std::time::Duration;
+/// Lists the running processes, reading `/proc/<pid>/comm`.
+#[cfg(target_os = "linux")]
+pub fn list_processes() -> Vec<ProcessInfo> {
+    use std::fs;
 
-    let pid = Pid::from_raw(pid as i32);
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
 
-    match kill(pid, None) {
-        Ok(_) => {
-            if kill(pid, Signal::SIGTERM).is_ok() {
-                for _ in 0..10 {
-                    thread::sleep(Duration::from_millis(100));
-                    if kill(pid, None).is_err() {
-                        return true;
-                    }
-                }
-            }
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            // Skip non-numeric entries, they aren't PID dirs
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            // Processes can exit between read_dir and here
+            let comm = fs::read(entry.path().join("comm")).ok()?;
+            let name = String::from_utf8_lossy(&comm);
+            // comm ends in a newline but may contain spaces of its own
+            let name = name.strip_suffix('\n').unwrap_or(&name).to_string();
+            Some(ProcessInfo { pid, name })
+        })
+        .collect()
+}
 
-            kill(pid, Signal::SIGKILL).is_ok()
+/// Lists the running processes on macOS and other Unixes, using `ps`.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn list_processes() -> Vec<ProcessInfo> {
+    use std::process::Command;
+
+    let output = match Command::new("ps")
+        .args(["-axo", "pid,comm"]) // pid + command name
+        .output()
+    {
+        Ok(o) => o,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(parse_ps_line)
+        .collect()
+}
+
+/// Parses a `ps -axo pid,comm` line like `  1234 /Applications/My App/myprog`.
+///
+/// Everything after the PID is the command, spaces included; only its
+/// basename is kept.
+#[cfg(any(test, all(unix, not(target_os = "linux"))))]
+fn parse_ps_line(line: &str) -> Option<ProcessInfo> {
+    let (pid, comm) = line.trim_start().split_once(char::is_whitespace)?;
+    let pid: u32 = pid.parse().ok()?;
+    let comm = comm.trim();
+    let name = comm.rsplit('/').next().unwrap_or(comm);
+    Some(ProcessInfo {
+        pid,
+        name: name.to_string(),
+    })
+}
+
+/// Kills every process whose executable name is `name`.
+///
+/// Names are compared case-insensitively on Windows. Returns whether any
+/// process was killed.
+pub fn kill_process_by_name(name: &str) -> bool {
+    let mut any_killed = false;
+
+    for process in list_processes() {
+        let matches = if cfg!(windows) {
+            process.name.eq_ignore_ascii_case(name)
+        } else {
+            process.name == name
+        };
+        if matches && kill_process_by_pid(process.pid) {
+            any_killed = true;
         }
-        Err(Errno::ESRCH) => false,
-        Err(Errno::EPERM) => kill(pid, Signal::SIGKILL).is_ok(),
-        Err(_) => false,
     }
+
+    any_killed
 }
 
+/// Checks whether a process with this PID still exists.
 #[cfg(unix)]
-pub fn kill_process_by_name(name: &str) -> bool {
-    #[cfg(target_os = "linux")]
-    {
-        use std::fs;
-        use std::io::Read;
-        use std::path::Path;
+pub fn process_alive(pid: u32) -> bool {
+    use nix::{errno::Errno, sys::signal::kill, unistd::Pid};
 
-        let target = name.to_string();
-        let mut any_killed = false;
+    let Some(raw) = i32::try_from(pid).ok().filter(|raw| *raw > 0) else {
+        return false;
+    };
+    // Signal 0 only checks; EPERM means it exists but belongs to someone else
+    matches!(kill(Pid::from_raw(raw), None), Ok(()) | Err(Errno::EPERM))
+}
 
-        if let Ok(entries) = fs::read_dir("/proc") {
-            for entry in entries.flatten() {
-                let file_name = entry.file_name();
-                let file_name_str = match file_name.to_string_lossy().parse::<u32>() {
-                    Ok(_) => file_name.to_string_lossy().to_string(),
-                    Err(_) => continue, // skip non-numeric (not a PID dir)
-                };
+/// Checks whether a process with this PID still exists.
+#[cfg(windows)]
+pub fn process_alive(pid: u32) -> bool {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
 
-                let pid: u32 = match file_name_str.parse() {
-                    Ok(p) => p,
-                    Err(_) => continue,
-                };
+    const STILL_ACTIVE: u32 = 259;
 
-                let comm_path = entry.path().join("comm"); // /proc/<pid>/comm
+    unsafe {
+        let handle = match OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+            Ok(h) => h,
+            Err(_) => return false,
+        };
 
-                if !Path::new(&comm_path).exists() {
-                    continue;
-                }
+        let mut code = 0u32;
+        let alive = GetExitCodeProcess(handle, &mut code).is_ok() && code == STILL_ACTIVE;
+        let _ = CloseHandle(handle);
+        alive
+    }
+}
 
-                let mut comm_file = match fs::File::open(&comm_path) {
-                    Ok(f) => f,
-                    Err(_) => continue,
-                };
+/// How long the exit code of a process nobody watches is kept.
+///
+/// A start script's process can exit before the manager gets its PID and
+/// calls [`watch_exit_code`], so codes are kept for a while rather than
+/// dropped on the spot.
+const UNWATCHED_EXIT_CODE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// What [`record_exit_code`] knows about one PID.
+struct ExitCode {
+    code: Option<i32>,
+    watched: bool,
+    recorded: std::time::Instant,
+}
 
-                let mut comm = String::new();
-                if comm_file.read_to_string(&mut comm).is_err() {
-                    continue;
-                }
+/// Exit codes of processes spawned by `proc.spawn`, kept until collected.
+static EXIT_CODES: std::sync::LazyLock<std::sync::Mutex<HashMap<u32, ExitCode>>> =
+    std::sync::LazyLock::new(Default::default);
+
+/// Remembers how a spawned process exited, for [`take_exit_code`].
+///
+/// Codes of processes nobody called [`watch_exit_code`] for are dropped
+/// after [`UNWATCHED_EXIT_CODE_TTL`].
+pub fn record_exit_code(pid: u32, code: i32) {
+    let Ok(mut codes) = EXIT_CODES.lock() else {
+        return;
+    };
+    let now = std::time::Instant::now();
+    codes.retain(|_, entry| {
+        entry.watched || now.duration_since(entry.recorded) < UNWATCHED_EXIT_CODE_TTL
+    });
+    let entry = codes.entry(pid).or_insert(ExitCode {
+        code: None,
+        watched: false,
+        recorded: now,
+    });
+    entry.code = Some(code);
+    entry.recorded = now;
+}
 
-                let comm = comm.trim(); // usually just "bash" or "node", etc.
+/// Keeps the exit code of `pid` until [`take_exit_code`] collects it.
+pub fn watch_exit_code(pid: u32) {
+    if let Ok(mut codes) = EXIT_CODES.lock() {
+        codes
+            .entry(pid)
+            .or_insert(ExitCode {
+                code: None,
+                watched: true,
+                recorded: std::time::Instant::now(),
+            })
+            .watched = true;
+    }
+}
 
-                if comm == target && kill_process_by_pid(pid) {
-                    any_killed = true;
-                }
-            }
-        }
+/// Drops what is known about `pid`, called when a new process gets it.
+///
+/// PIDs are reused, so a code recorded for an earlier process must not be
+/// reported for the new one.
+pub fn forget_exit_code(pid: u32) {
+    if let Ok(mut codes) = EXIT_CODES.lock() {
+        codes.remove(&pid);
+    }
+}
+
+/// Takes the exit code [`record_exit_code`] kept for `pid` and stops watching it.
+///
+/// # Returns
+/// - `Some(code)` if this process spawned `pid` and saw it exit
+/// - `None` otherwise, e.g. for processes started elsewhere
+pub fn take_exit_code(pid: u32) -> Option<i32> {
+    EXIT_CODES.lock().ok()?.remove(&pid)?.code
+}
 
-        any_killed
+/// Reads when a process started, from `/proc` on Linux.
+///
+/// # Returns
+/// - `Some(time)` accurate to the second
+/// - `None` if the process doesn't exist or on other platforms
+#[cfg(target_os = "linux")]
+pub fn process_start_time(pid: u32) -> Option<chrono::DateTime<chrono::Utc>> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, so split after its closing paren.
+    // What's left starts at field 3, putting `starttime` (field 22) at 19.
+    let (_, rest) = stat.rsplit_once(')')?;
+    let ticks: i64 = rest.split_whitespace().nth(19)?.parse().ok()?;
+
+    let boot: i64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return None;
     }
 
-    // Fallback for macOS / other Unix: use `ps` to find matches
-    #[cfg(not(target_os = "linux"))]
-    {
-        use std::process::Command;
-
-        let target = name.to_string();
-        let output = match Command::new("ps")
-            .args(["-axo", "pid,comm"]) // pid + command name
-            .output()
-        {
-            Ok(o) => o,
-            Err(_) => return false,
-        };
+    chrono::DateTime::from_timestamp(boot + ticks / ticks_per_second as i64, 0)
+}
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut any_killed = false;
+/// Reads when a process started, from `/proc` on Linux.
+///
+/// # Returns
+/// - `Some(time)` accurate to the second
+/// - `None` if the process doesn't exist or on other platforms
+#[cfg(not(target_os = "linux"))]
+pub fn process_start_time(_pid: u32) -> Option<chrono::DateTime<chrono::Utc>> {
+    None
+}
 
-        for line in stdout.lines().skip(1) {
-            // format: "  1234 /usr/bin/myprog"
-            let mut parts = line.split_whitespace();
-            let pid_str = match parts.next() {
-                Some(p) => p,
-                None => continue,
-            };
-            let comm = parts.next().unwrap_or("");
+/// Freezes a process with `SIGSTOP` so it can be inspected and resumed later.
+#[cfg(unix)]
+pub fn suspend_process(pid: u32) -> Result<()> {
+    signal_process(pid, nix::sys::signal::Signal::SIGSTOP)
+}
 
-            let pid: u32 = match pid_str.parse() {
-                Ok(p) => p,
-                Err(_) => continue,
-            };
+/// Continues a process frozen by [`suspend_process`] with `SIGCONT`.
+#[cfg(unix)]
+pub fn resume_process(pid: u32) -> Result<()> {
+    signal_process(pid, nix::sys::signal::Signal::SIGCONT)
+}
 
-            // Compare just the basename
-            let base = comm.rsplit('/').next().unwrap_or(comm);
-            if base == target {
-                if kill_process_by_pid(pid) {
-                    any_killed = true;
-                }
-            }
-        }
+#[cfg(unix)]
+fn signal_process(pid: u32, signal: nix::sys::signal::Signal) -> Result<()> {
+    use nix::{sys::signal::kill, unistd::Pid};
+
+    let raw = i32::try_from(pid)
+        .ok()
+        .filter(|raw| *raw > 0)
+        .ok_or_else(|| Error::msg(format!("Invalid PID {}", pid)))?;
+    kill(Pid::from_raw(raw), signal)
+        .map_err(|e| Error::msg(format!("Failed to send {} to PID {}: {}", signal, pid, e)))
+}
 
-        any_killed
-    }
+#[cfg(windows)]
+pub fn suspend_process(pid: u32) -> Result<()> {
+    warn!(
+        "Pausing processes is not supported on Windows (PID {})",
+        pid
+    );
+    Err(Error::msg("Pausing processes is not supported on Windows"))
+}
+
+#[cfg(windows)]
+pub fn resume_process(pid: u32) -> Result<()> {
+    warn!(
+        "Resuming processes is not supported on Windows (PID {})",
+        pid
+    );
+    Err(Error::msg("Resuming processes is not supported on Windows"))
 }
 
 /// Normalizes a shuriken name to lowercase for consistent directory naming.
@@ -262,6 +472,45 @@ pub fn normalize_shuriken_name(name: &str) -> String {
     name.to_lowercase()
 }
 
+/// Whether `pattern` contains `*` or `?` and so names a set of Shurikens.
+pub fn is_name_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Matches a Shuriken name against a shell-style pattern.
+///
+/// `*` matches any run of characters, `?` exactly one; everything else
+/// matches itself. Both sides are normalized like Shuriken names first.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = normalize_shuriken_name(pattern).chars().collect();
+    let name: Vec<char> = normalize_shuriken_name(name).chars().collect();
+
+    // Greedy match that backtracks to the last `*` on a mismatch
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut result = PathBuf::new();
 
@@ -278,7 +527,20 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     result
 }
 
-pub async fn create_tar_gz_bytes(src_dir: PathBuf) -> Result<Vec<u8>> {
+/// Name of the gitignore-style file listing paths `forge` should leave out.
+pub const NINJAIGNORE: &str = ".ninjaignore";
+
+/// Packs `src_dir` into an in-memory tar.gz.
+///
+/// Honors `.ninjaignore` files and the patterns in `filter`; `.git` folders
+/// and the ignore files themselves are never packed. Symlinks are stored as
+/// links, not followed, and anything else that isn't a file or directory
+/// (sockets, FIFOs, devices) is left out and listed in the report. Returns
+/// the archive bytes along with the report.
+pub async fn create_tar_gz_bytes(
+    src_dir: PathBuf,
+    filter: &ForgeFilter,
+) -> Result<(Vec<u8>, ForgeReport)> {
     if !src_dir.is_dir() {
         return Err(anyhow::Error::msg(format!(
             "Source directory does not exist or is not a directory: {}",
@@ -286,22 +548,539 @@ pub async fn create_tar_gz_bytes(src_dir: PathBuf) -> Result<Vec<u8>> {
         )));
     }
 
+    let mut overrides = OverrideBuilder::new(&src_dir);
+    for pattern in &filter.include {
+        overrides.add(pattern)?;
+    }
+    if !filter.include.is_empty() {
+        // The manifest always has to ship, whatever was asked for
+        overrides.add(".ninja/**")?;
+    }
+    for pattern in &filter.exclude {
+        overrides.add(&format!("!{pattern}"))?;
+    }
+
+    let walker = WalkBuilder::new(&src_dir)
+        .hidden(false)
+        .parents(false)
+        .ignore(false)
+        .git_ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .add_custom_ignore_filename(NINJAIGNORE)
+        .overrides(overrides.build()?)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
     let mut buf = Vec::new();
+    let mut report = ForgeReport::default();
 
     {
         // Gzip wraps the in-memory buffer
         let enc = GzEncoder::new(&mut buf, Compression::default());
         let mut tar = TarBuilder::new(enc);
 
-        // This recursively adds `src_dir` contents under "." in the archive
-        tar.append_dir_all(".", src_dir)?;
+        for entry in walker {
+            let entry = entry?;
+            if entry.depth() == 0 || entry.file_name() == NINJAIGNORE {
+                continue;
+            }
+
+            // Entries go under "." in the archive, like `append_dir_all(".", ..)` did
+            let relative = entry.path().strip_prefix(&src_dir)?;
+            let name = Path::new(".").join(relative);
+            match entry.file_type() {
+                Some(kind) if kind.is_file() => {
+                    tar.append_path_with_name(entry.path(), &name)?;
+                    report.file_count += 1;
+                    report.total_size += entry.metadata()?.len();
+                }
+                // With an include filter every directory is walked, and only
+                // the ones holding included files should end up in the archive
+                Some(kind) if kind.is_dir() => {
+                    if filter.include.is_empty() {
+                        tar.append_dir(&name, entry.path())?;
+                    }
+                }
+                Some(kind) if kind.is_symlink() => {
+                    let target = std::fs::read_link(entry.path())?;
+                    let mut header = tar::Header::new_gnu();
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_size(0);
+                    header.set_mode(0o777);
+                    tar.append_link(&mut header, &name, &target)?;
+                    report.link_count += 1;
+                }
+                _ => {
+                    warn!(
+                        "Skipping '{}': only files, directories and symlinks can be packed",
+                        relative.display()
+                    );
+                    report.skipped.push(relative.to_path_buf());
+                }
+            }
+        }
 
         // Finish tar, then finish gzip
         let enc = tar.into_inner()?; // GzEncoder
         enc.finish()?; // flush into buf
     }
 
-    Ok(buf)
+    Ok((buf, report))
+}
+
+/// A Shuriken's configuration without the rest of its package, as moved by
+/// [`ShurikenManager::export_config_bundle`](crate::manager::ShurikenManager::export_config_bundle).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigBundle {
+    /// Contents of `.ninja/options.toml`
+    pub options: Option<String>,
+    /// The config file generated from the options
+    pub config: Option<Vec<u8>>,
+}
+
+const BUNDLE_OPTIONS: &str = "options.toml";
+const BUNDLE_CONFIG: &str = "config";
+/// Largest entry a config bundle may carry; options and configs are text files.
+pub const MAX_BUNDLE_ENTRY: u64 = 16 * 1024 * 1024;
+
+impl ConfigBundle {
+    /// Packs the bundle as a tar.gz holding `options.toml` and `config`.
+    pub fn to_tar_gz(&self) -> Result<Vec<u8>> {
+        let mut tar = TarBuilder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let entries = [
+            (BUNDLE_OPTIONS, self.options.as_ref().map(|o| o.as_bytes())),
+            (BUNDLE_CONFIG, self.config.as_deref()),
+        ];
+        for (name, data) in entries {
+            let Some(data) = data else { continue };
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, data)?;
+        }
+        Ok(tar.into_inner()?.finish()?)
+    }
+
+    /// Reads a bundle written by [`ConfigBundle::to_tar_gz`].
+    ///
+    /// Only the two known entries are read and nothing is written to disk,
+    /// so a crafted archive can't place files anywhere. Either entry being
+    /// larger than [`MAX_BUNDLE_ENTRY`] is an error.
+    pub fn from_tar_gz(bytes: &[u8]) -> Result<Self> {
+        let mut bundle = ConfigBundle::default();
+        let mut archive = Archive::new(GzDecoder::new(bytes));
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            let name = name.trim_start_matches("./");
+            if matches!(name, BUNDLE_OPTIONS | BUNDLE_CONFIG)
+                && entry.header().size()? > MAX_BUNDLE_ENTRY
+            {
+                return Err(Error::msg(format!(
+                    "Config bundle entry '{}' is larger than {} bytes",
+                    name, MAX_BUNDLE_ENTRY
+                )));
+            }
+            let mut entry = entry.take(MAX_BUNDLE_ENTRY);
+            match name {
+                BUNDLE_OPTIONS => {
+                    let mut options = String::new();
+                    entry.read_to_string(&mut options)?;
+                    bundle.options = Some(options);
+                }
+                BUNDLE_CONFIG => {
+                    let mut config = Vec::new();
+                    entry.read_to_end(&mut config)?;
+                    bundle.config = Some(config);
+                }
+                other => warn!("Ignoring unexpected entry '{}' in config bundle", other),
+            }
+        }
+
+        if bundle.options.is_none() && bundle.config.is_none() {
+            return Err(Error::msg(
+                "Not a config bundle: it has neither options.toml nor config",
+            ));
+        }
+        Ok(bundle)
+    }
+}
+
+/// Replaces each file with its new contents, all or nothing as far as writing goes.
+///
+/// Every file is first written next to its target; only once all of them
+/// are written are they renamed over the old ones. If a write fails, the
+/// files written so far are removed and every target is left untouched.
+pub async fn replace_files(files: Vec<(PathBuf, Vec<u8>)>) -> Result<()> {
+    let mut staged = Vec::with_capacity(files.len());
+    for (path, data) in files {
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        let written = match path.parent() {
+            Some(parent) => async_fs::create_dir_all(parent).await,
+            None => Ok(()),
+        };
+        let written = match written {
+            Ok(()) => async_fs::write(&tmp_path, data).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            for (tmp_path, _) in &staged {
+                let _ = async_fs::remove_file(tmp_path).await;
+            }
+            let _ = async_fs::remove_file(&tmp_path).await;
+            return Err(Error::new(e).context(format!("Failed to write {}", path.display())));
+        }
+        staged.push((tmp_path, path));
+    }
+
+    for (tmp_path, path) in staged {
+        async_fs::rename(&tmp_path, &path)
+            .await
+            .map_err(|e| Error::new(e).context(format!("Failed to replace {}", path.display())))?;
+    }
+    Ok(())
+}
+
+/// Sums the size of every file under `root` without following symlinks.
+///
+/// Symlinks count as the size of the link itself, and on Unix files with
+/// several hard links inside `root` are only counted once.
+pub fn dir_size(root: &Path) -> std::io::Result<u64> {
+    #[cfg(unix)]
+    let mut seen = std::collections::HashSet::new();
+    let mut pending = vec![root.to_path_buf()];
+    let mut total = 0;
+
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            // `DirEntry::metadata` doesn't traverse symlinks, so links can't loop
+            let metadata = entry.metadata()?;
+
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                if metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino())) {
+                    continue;
+                }
+            }
+
+            total += metadata.len();
+        }
+    }
+
+    Ok(total)
+}
+
+/// Whether an I/O error is worth retrying, e.g. a network filesystem still mounting.
+fn is_transient(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::NotFound
+            | ErrorKind::WouldBlock
+            | ErrorKind::Interrupted
+            | ErrorKind::TimedOut
+            | ErrorKind::ResourceBusy
+    )
+}
+
+/// Runs `op` until it succeeds, retrying transient I/O errors with exponential backoff.
+///
+/// Waits `base_delay`, then twice that, and so on between attempts. Errors
+/// that aren't transient are returned right away.
+///
+/// # Returns
+/// - `Ok(T)` from the first successful attempt
+/// - `Err` with the last error, once `attempts` are used up
+pub async fn retry_with_backoff<T, F, Fut>(
+    attempts: u32,
+    base_delay: Duration,
+    mut op: F,
+) -> std::io::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::io::Result<T>>,
+{
+    let mut delay = base_delay;
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts && is_transient(&e) => {
+                warn!(
+                    "Attempt {}/{} failed ({}), retrying in {:?}",
+                    attempt, attempts, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Recursively copies `src` into `dest`, creating `dest` if needed.
+///
+/// Symlinks are recreated as links (on Unix) instead of being followed.
+pub fn copy_dir_all(src: &Path, dest: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dest)?;
+
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dest.join(entry.file_name());
+
+        if file_type.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+            #[cfg(not(unix))]
+            std::fs::copy(entry.path(), &target).map(|_| ())?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if `path`, taken relative to some root, never climbs above it.
+fn stays_inside(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(parent) => depth = parent,
+                None => return false,
+            },
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+/// How a package's archive payload is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// A gzip-compressed tar, what `forge` writes
+    Gzip,
+    /// A zstd-compressed tar
+    Zstd,
+    /// An uncompressed tar
+    Tar,
+}
+
+impl ArchiveFormat {
+    /// Recognizes a payload by its magic bytes.
+    ///
+    /// # Returns
+    /// - `Ok(format)` for gzip (`1f 8b`), zstd (`28 b5 2f fd`) and tar
+    ///   (`ustar` at offset 257)
+    /// - `Err` for anything else
+    pub fn detect(payload: &[u8]) -> Result<Self> {
+        if payload.starts_with(&[0x1f, 0x8b]) {
+            Ok(Self::Gzip)
+        } else if payload.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Ok(Self::Zstd)
+        } else if payload.get(257..262) == Some(b"ustar".as_slice()) {
+            Ok(Self::Tar)
+        } else {
+            let magic: Vec<String> = payload
+                .iter()
+                .take(4)
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            Err(Error::msg(format!(
+                "Unsupported archive format (starts with {})",
+                magic.join(" ")
+            )))
+        }
+    }
+
+    /// Wraps `reader` so it yields the uncompressed tar stream.
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::Gzip => Box::new(GzDecoder::new(reader)),
+            Self::Zstd => Box::new(zstd::Decoder::new(reader)?),
+            Self::Tar => Box::new(reader),
+        })
+    }
+}
+
+/// Unpacks a tar stream into `target`, refusing entries that could write outside it.
+///
+/// Absolute paths and paths that climb out through `..` are rejected. Symlinks
+/// and hard links are rejected too unless `allow_links` is set, and even then
+/// they must point inside `target`. `on_entry` is called with the number of
+/// entries unpacked so far.
+///
+/// # Returns
+/// - `Ok(count)` with the number of unpacked entries
+/// - `Err` naming the first offending entry; entries before it stay unpacked
+pub fn unpack_archive_safely<R: Read>(
+    reader: R,
+    target: &Path,
+    allow_links: bool,
+    mut on_entry: impl FnMut(usize) -> Result<()>,
+) -> Result<usize> {
+    let mut archive = Archive::new(reader);
+    let mut count = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        if path.has_root() {
+            return Err(Error::msg(format!(
+                "Refusing to extract '{}': absolute paths are not allowed",
+                path.display()
+            )));
+        }
+        if !stays_inside(&path) {
+            return Err(Error::msg(format!(
+                "Refusing to extract '{}': path escapes the install directory",
+                path.display()
+            )));
+        }
+
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            if !allow_links {
+                return Err(Error::msg(format!(
+                    "Refusing to extract link '{}': archive links are disabled (see allow_symlinks)",
+                    path.display()
+                )));
+            }
+
+            let link = entry.link_name()?.unwrap_or_default().into_owned();
+            // Symlinks resolve next to the entry, hard links from the archive root
+            let resolved = match (kind.is_symlink(), path.parent()) {
+                (true, Some(parent)) => parent.join(&link),
+                _ => link.clone(),
+            };
+            if link.as_os_str().is_empty() || link.has_root() || !stays_inside(&resolved) {
+                return Err(Error::msg(format!(
+                    "Refusing to extract link '{}' -> '{}': target escapes the install directory",
+                    path.display(),
+                    link.display()
+                )));
+            }
+        }
+
+        if !entry.unpack_in(target)? {
+            return Err(Error::msg(format!(
+                "Refusing to extract '{}': it resolves outside the install directory",
+                path.display()
+            )));
+        }
+
+        count += 1;
+        on_entry(count)?;
+    }
+
+    Ok(count)
+}
+
+/// Loads the Shuriken stored in `shuriken_path`, with its state and options.
+///
+/// # Returns
+/// - `Ok(None)` if the directory has no `.ninja/manifest.toml`
+/// - `Err` if the manifest or options can't be read or parsed
+pub async fn load_shuriken(shuriken_path: &Path) -> Result<Option<Shuriken>> {
+    let ninja_dir = shuriken_path.join(".ninja");
+
+    // 1. Load manifest (required)
+    let manifest_path = ninja_dir.join("manifest.toml");
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+
+    let content: String = async_fs::read_to_string(&manifest_path).await?;
+
+    // Unknown keys (older or misspelled ones) are reported, not fatal, so
+    // manifests written for other versions keep loading
+    let mut unknown = Vec::new();
+    let parsed = toml::Deserializer::parse(&content).and_then(|de| {
+        serde_ignored::deserialize(de, |path| unknown.push(path.to_string())).map_err(|mut e| {
+            e.set_input(Some(&content));
+            e
+        })
+    });
+    let mut shuriken: Shuriken = match parsed {
+        Ok(shuriken) => shuriken,
+        // A misspelled required key shows up as missing, name the unknown one too
+        Err(e) if !unknown.is_empty() => {
+            return Err(Error::msg(format!(
+                "TOML error in {}: {} (unknown keys: {})",
+                manifest_path.display(),
+                e,
+                unknown.join(", ")
+            )));
+        }
+        Err(e) => {
+            return Err(Error::msg(format!(
+                "TOML error in {}: {}",
+                manifest_path.display(),
+                e
+            )));
+        }
+    };
+    for key in &unknown {
+        warn!(
+            "Ignoring unknown key '{}' in {}",
+            key,
+            manifest_path.display()
+        );
+    }
+
+    // 2. Check for lock file
+    let lock_path = ninja_dir.join("shuriken.lck");
+    let state = if lock_path.exists() {
+        ShurikenState::Running
+    } else {
+        ShurikenState::Idle
+    };
+
+    shuriken.state = Arc::new(Mutex::new(state.clone()));
+
+    // 3. Load options (optional)
+    let options_path = ninja_dir.join("options.toml");
+    if options_path.exists() {
+        let content: String = async_fs::read_to_string(&options_path).await?;
+        let options: HashMap<String, FieldValue> = toml::from_str(&content).map_err(|e| {
+            Error::msg(format!(
+                "Options error in {}: {}",
+                options_path.display(),
+                e
+            ))
+        })?;
+
+        if let Some(config) = &mut shuriken.config {
+            config.options = Some(options);
+        } else {
+            shuriken.config = Some(ShurikenConfig {
+                config_path: PathBuf::from("options.toml"),
+                options: Some(options),
+            });
+        }
+    }
+
+    Ok(Some(shuriken))
 }
 
 // Shared logic for loading shurikens from disk
@@ -326,50 +1105,15 @@ pub async fn load_shurikens(root_path: &Path) -> Result<HashMap<String, Shuriken
             None => continue, // skip non-UTF8 names
         };
 
-        let ninja_dir = shuriken_path.join(".ninja");
-
-        // 1. Load manifest (required)
-        let manifest_path = ninja_dir.join("manifest.toml");
-        if !manifest_path.exists() {
-            continue; // not a valid shuriken
-        }
-
-        let content: String = async_fs::read_to_string(&manifest_path).await?;
-
-        let mut shuriken: Shuriken = toml::from_str(&content)
-            .map_err(|e| Error::msg(format!("TOML error in {}: {}", manifest_path.display(), e)))?;
-
-        // 2. Check for lock file
-        let lock_path = ninja_dir.join("shuriken.lck");
-        let state = if lock_path.exists() {
-            ShurikenState::Running
-        } else {
-            ShurikenState::Idle
-        };
-
-        shuriken.state = Arc::new(Mutex::new(state.clone()));
-
-        // 3. Load options (optional)
-        let options_path = ninja_dir.join("options.toml");
-        if options_path.exists() {
-            let content: String = async_fs::read_to_string(&options_path).await?;
-            let options: HashMap<String, FieldValue> = toml::from_str(&content).map_err(|e| {
-                Error::msg(format!(
-                    "Options error in {}: {}",
-                    options_path.display(),
-                    e
-                ))
-            })?;
-
-            if let Some(config) = &mut shuriken.config {
-                config.options = Some(options);
-            } else {
-                shuriken.config = Some(ShurikenConfig {
-                    config_path: PathBuf::from("options.toml"),
-                    options: Some(options),
-                });
+        // One broken manifest shouldn't hide every other Shuriken
+        let shuriken = match load_shuriken(&shuriken_path).await {
+            Ok(Some(shuriken)) => shuriken,
+            Ok(None) => continue, // not a valid shuriken
+            Err(e) => {
+                warn!("Skipping shuriken '{}': {}", name, e);
+                continue;
             }
-        }
+        };
 
         // Store using the directory name (which should already be lowercase)
         // but normalize it to be sure
@@ -514,3 +1258,220 @@ pub fn get_port_owner(port: u16) -> Option<PortOwner> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tar::{EntryType, Header};
+    use tempfile::tempdir;
+
+    /// Builds an uncompressed tar holding one entry, writing the raw name so
+    /// the path checks `tar::Builder` applies can't clean it up.
+    fn crafted_archive(name: &str, kind: EntryType, link: Option<&str>) -> Vec<u8> {
+        let data: &[u8] = if link.is_some() { b"" } else { b"payload" };
+
+        let mut header = Header::new_gnu();
+        let gnu = header.as_gnu_mut().unwrap();
+        gnu.name[..name.len()].copy_from_slice(name.as_bytes());
+        if let Some(link) = link {
+            gnu.linkname[..link.len()].copy_from_slice(link.as_bytes());
+        }
+        header.set_entry_type(kind);
+        header.set_mode(0o644);
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+
+        let mut builder = TarBuilder::new(Vec::new());
+        builder.append(&header, data).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_exit_codes_are_kept_per_process() {
+        // PIDs well above what a system hands out, so no real process collides
+        let (watched, reused) = (u32::MAX - 1, u32::MAX - 2);
+
+        watch_exit_code(watched);
+        assert_eq!(take_exit_code(watched), None);
+        watch_exit_code(watched);
+        record_exit_code(watched, 3);
+        assert_eq!(take_exit_code(watched), Some(3));
+        assert_eq!(take_exit_code(watched), None);
+
+        // A new process under the same PID doesn't inherit the old code
+        record_exit_code(reused, 1);
+        forget_exit_code(reused);
+        watch_exit_code(reused);
+        assert_eq!(take_exit_code(reused), None);
+    }
+
+    #[test]
+    fn test_unpack_detects_payload_compression() {
+        let tar = crafted_archive("./.ninja/manifest.toml", EntryType::Regular, None);
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        std::io::Write::write_all(&mut gzip, &tar).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::encode_all(tar.as_slice(), 0).unwrap();
+
+        for (payload, expected) in [
+            (gzip, ArchiveFormat::Gzip),
+            (zstd, ArchiveFormat::Zstd),
+            (tar, ArchiveFormat::Tar),
+        ] {
+            let dir = tempdir().unwrap();
+            let format = ArchiveFormat::detect(&payload).unwrap();
+            assert_eq!(format, expected);
+
+            let reader = format.decoder(payload.as_slice()).unwrap();
+            assert_eq!(
+                unpack_archive_safely(reader, dir.path(), false, |_| Ok(())).unwrap(),
+                1
+            );
+            assert_eq!(
+                std::fs::read(dir.path().join(".ninja/manifest.toml")).unwrap(),
+                b"payload"
+            );
+        }
+
+        let err = ArchiveFormat::detect(b"PK\x03\x04 not a tar").unwrap_err();
+        assert!(err.to_string().contains("Unsupported archive format"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_survives_transient_errors() {
+        use std::io::{Error as IoError, ErrorKind};
+
+        let mut calls = 0;
+        let result = retry_with_backoff(5, Duration::from_millis(1), || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 3 {
+                    Err(IoError::new(ErrorKind::NotFound, "mount not ready"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_gives_up() {
+        use std::io::{Error as IoError, ErrorKind};
+
+        let mut calls = 0;
+        let result: std::io::Result<()> = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            async { Err(IoError::new(ErrorKind::WouldBlock, "still busy")) }
+        })
+        .await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(calls, 3);
+
+        calls = 0;
+        let result: std::io::Result<()> = retry_with_backoff(3, Duration::from_millis(1), || {
+            calls += 1;
+            async { Err(IoError::new(ErrorKind::PermissionDenied, "nope")) }
+        })
+        .await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_unpack_refuses_parent_escape() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("install");
+        std::fs::create_dir_all(&target).unwrap();
+
+        let archive = crafted_archive("../escape", EntryType::Regular, None);
+        let err =
+            unpack_archive_safely(archive.as_slice(), &target, false, |_| Ok(())).unwrap_err();
+
+        assert!(err.to_string().contains("../escape"));
+        assert!(!dir.path().join("escape").exists());
+    }
+
+    #[test]
+    fn test_unpack_refuses_absolute_path() {
+        let dir = tempdir().unwrap();
+        let archive = crafted_archive("/tmp/ninja-absolute", EntryType::Regular, None);
+
+        let err =
+            unpack_archive_safely(archive.as_slice(), dir.path(), false, |_| Ok(())).unwrap_err();
+        assert!(err.to_string().contains("absolute"));
+    }
+
+    #[test]
+    fn test_unpack_symlinks_require_opt_in() {
+        let dir = tempdir().unwrap();
+        let inside = crafted_archive("bin/current", EntryType::Symlink, Some("../lib/app"));
+        let outside = crafted_archive("bin/passwd", EntryType::Symlink, Some("../../etc/passwd"));
+
+        assert!(unpack_archive_safely(inside.as_slice(), dir.path(), false, |_| Ok(())).is_err());
+        assert!(unpack_archive_safely(outside.as_slice(), dir.path(), true, |_| Ok(())).is_err());
+        assert_eq!(
+            unpack_archive_safely(inside.as_slice(), dir.path(), true, |_| Ok(())).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_unpack_extracts_regular_entries() {
+        let dir = tempdir().unwrap();
+        let archive = crafted_archive("./.ninja/manifest.toml", EntryType::Regular, None);
+
+        let count =
+            unpack_archive_safely(archive.as_slice(), dir.path(), false, |_| Ok(())).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(".ninja/manifest.toml")).unwrap(),
+            "payload"
+        );
+    }
+
+    #[test]
+    fn test_name_matches_wildcards() {
+        assert!(name_matches("test-*", "test-api"));
+        assert!(name_matches("test-*", "test-"));
+        assert!(name_matches("*-db", "Orders-DB"));
+        assert!(name_matches("web?", "web1"));
+        assert!(name_matches("a*b*c", "axxbyybc"));
+        assert!(name_matches("nginx", "nginx"));
+        assert!(!name_matches("test-*", "prod-test-api"));
+        assert!(!name_matches("web?", "web"));
+        assert!(!name_matches("a*b*c", "axxbyy"));
+        assert!(is_name_pattern("test-*"));
+        assert!(!is_name_pattern("nginx"));
+    }
+
+    #[test]
+    fn test_parse_ps_line_keeps_spaces_in_names() {
+        assert_eq!(
+            parse_ps_line("  412 /Applications/My App.app/Contents/MacOS/My App"),
+            Some(ProcessInfo {
+                pid: 412,
+                name: "My App".to_string()
+            })
+        );
+        assert_eq!(
+            parse_ps_line("1 launchd").map(|p| p.name),
+            Some("launchd".to_string())
+        );
+        assert_eq!(parse_ps_line("  PID COMM"), None);
+    }
+
+    #[test]
+    fn test_list_processes_includes_self() {
+        let own = std::process::id();
+        assert!(
+            list_processes()
+                .iter()
+                .any(|p| p.pid == own && !p.name.is_empty())
+        );
+    }
+}
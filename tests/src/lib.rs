@@ -2,7 +2,7 @@
 mod ninja_runtime_integration_tests {
     use ninja::scripting::NinjaEngine;
     use std::io::Write;
-    use std::{fs, path::Path};
+    use std::{collections::HashMap, fs, path::Path};
     use tempfile::NamedTempFile;
 
     pub fn write_stub_script(dest: &Path) {
@@ -27,6 +27,7 @@ mod ninja_runtime_integration_tests {
         assert!(globals.contains_key("json").unwrap());
         assert!(globals.contains_key("http").unwrap());
         assert!(globals.contains_key("log").unwrap());
+        assert!(globals.contains_key("store").unwrap());
     }
 
     #[tokio::test]
@@ -43,12 +44,161 @@ mod ninja_runtime_integration_tests {
         let mut tmp = NamedTempFile::new().unwrap();
         writeln!(tmp, "x = 123").unwrap();
 
+        assert!(engine.execute_file(tmp.path(), None, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_missing_functions_reports_absent_stop() {
+        let engine = NinjaEngine::new().await.unwrap();
+        let lifecycle = ninja::shuriken::LIFECYCLE_FUNCTIONS;
+
+        let mut no_stop = NamedTempFile::new().unwrap();
+        writeln!(
+            no_stop,
+            "called = false\nfunction start() called = true end"
+        )
+        .unwrap();
+        assert_eq!(
+            engine
+                .missing_functions(no_stop.path(), lifecycle)
+                .await
+                .unwrap(),
+            vec!["stop".to_string()]
+        );
+        // Defined, never run
+        assert!(!engine.lua.globals().contains_key("called").unwrap());
+
+        let complete = NamedTempFile::new().unwrap();
+        write_stub_script(complete.path());
+        assert!(
+            engine
+                .missing_functions(complete.path(), lifecycle)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let mut returned = NamedTempFile::new().unwrap();
+        writeln!(
+            returned,
+            "return {{ start = function() end, stop = 'nope' }}"
+        )
+        .unwrap();
+        assert_eq!(
+            engine
+                .missing_functions(returned.path(), lifecycle)
+                .await
+                .unwrap(),
+            vec!["stop".to_string()]
+        );
+
+        let mut broken = NamedTempFile::new().unwrap();
+        writeln!(broken, "function start(").unwrap();
+        assert!(
+            engine
+                .missing_functions(broken.path(), lifecycle)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_file_accepts_str_and_path() {
+        let engine = NinjaEngine::new().await.unwrap();
+
+        let mut tmp = NamedTempFile::new().unwrap();
+        writeln!(tmp, "x = 123").unwrap();
+
+        let as_str = tmp.path().to_str().unwrap();
+        assert!(engine.execute_file(as_str, None, None).await.is_ok());
+        assert!(
+            engine
+                .execute_file(Path::new(as_str), None, None)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_require_sibling_module() {
+        let engine = NinjaEngine::new().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("helpers.lua"),
+            "return { answer = function() return 42 end }",
+        )
+        .unwrap();
+        let main = dir.path().join("main.lua");
+        fs::write(
+            &main,
+            "local helpers = require('helpers')\nassert(helpers.answer() == 42)",
+        )
+        .unwrap();
+
+        engine.add_require_path(dir.path()).unwrap();
+        assert!(engine.execute_file(&main, None, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_runs_do_not_leak_between_shurikens() {
+        let engine = NinjaEngine::new().await.unwrap();
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        for (dir, name) in [(&first, "first"), (&second, "second")] {
+            fs::write(dir.path().join("helpers.lua"), format!("return '{name}'")).unwrap();
+            fs::write(
+                dir.path().join("start.ns"),
+                "function start(ctx)\n  assert(shuriken == ctx)\n  return require('helpers') .. ':' .. shuriken\nend\n",
+            )
+            .unwrap();
+        }
+
+        for (dir, name) in [(&first, "first"), (&second, "second")] {
+            let returned: String = engine
+                .execute_lifecycle_function(
+                    "start",
+                    "start.ns",
+                    dir.path(),
+                    None,
+                    &HashMap::new(),
+                    name,
+                )
+                .await
+                .unwrap();
+            assert_eq!(returned, format!("{name}:{name}"));
+        }
+
+        // Neither the context nor the search path ends up in the globals
+        assert!(!engine.lua.globals().contains_key("shuriken").unwrap());
         assert!(
             engine
-                .execute_file(&tmp.path().to_path_buf(), None, None)
+                .execute("assert(package.path == '')", None, None)
                 .await
                 .is_ok()
         );
+        assert!(
+            engine
+                .execute("require('helpers')", None, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_require_cannot_escape_roots() {
+        let engine = NinjaEngine::new().await.unwrap();
+        let outer = tempfile::tempdir().unwrap();
+        let root = outer.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(outer.path().join("secret.lua"), "return 1").unwrap();
+
+        engine.add_require_path(&root).unwrap();
+        assert!(
+            engine
+                .execute("require('../secret')", None, None)
+                .await
+                .is_err()
+        );
     }
 
     #[tokio::test]
@@ -61,7 +211,7 @@ mod ninja_runtime_integration_tests {
         let path = tmp.into_temp_path();
         assert!(
             engine
-                .execute_function("greet", &path.to_path_buf(), None, None)
+                .execute_function("greet", &path, None, None)
                 .await
                 .is_ok()
         );
@@ -77,7 +227,7 @@ mod ninja_runtime_integration_tests {
         let path = tmp.into_temp_path();
         assert!(
             engine
-                .execute_function("greet", &path.to_path_buf(), None, None)
+                .execute_function("greet", &path, None, None)
                 .await
                 .is_ok()
         );
@@ -94,12 +244,30 @@ mod ninja_runtime_integration_tests {
         // Function executes successfully even with return value
         assert!(
             engine
-                .execute_function("add", &path.to_path_buf(), None, None)
+                .execute_function("add", &path, None, None)
                 .await
                 .is_ok()
         );
     }
 
+    #[tokio::test]
+    async fn test_execute_function_with_args_passes_context() {
+        let engine = NinjaEngine::new().await.unwrap();
+
+        let mut tmp = NamedTempFile::new().unwrap();
+        writeln!(tmp, "function start(ctx) return ctx.name end").unwrap();
+
+        let path = tmp.into_temp_path();
+        let ctx = engine.lua.create_table().unwrap();
+        ctx.set("name", "demo").unwrap();
+
+        let name: String = engine
+            .execute_function_with_args("start", &path, None, None, &HashMap::new(), ctx)
+            .await
+            .unwrap();
+        assert_eq!(name, "demo");
+    }
+
     #[tokio::test]
     async fn test_execute_function_nonexistent() {
         let engine = NinjaEngine::new().await.unwrap();
@@ -111,12 +279,25 @@ mod ninja_runtime_integration_tests {
         // Trying to execute a function that doesn't exist should fail
         assert!(
             engine
-                .execute_function("nonexistent", &path.to_path_buf(), None, None)
+                .execute_function("nonexistent", &path, None, None)
                 .await
                 .is_err()
         );
     }
 
+    #[tokio::test]
+    async fn test_register_global() {
+        let engine = NinjaEngine::new().await.unwrap();
+        engine.register_global("greeting", "hello").unwrap();
+
+        assert!(
+            engine
+                .execute("assert(greeting == 'hello')", None, None)
+                .await
+                .is_ok()
+        );
+    }
+
     #[tokio::test]
     async fn test_execute_inline_with_globals() {
         let engine = NinjaEngine::new().await.unwrap();
@@ -127,21 +308,4373 @@ mod ninja_runtime_integration_tests {
         assert!(engine.execute("local x = shell", None, None).await.is_ok());
         assert!(engine.execute("local x = time", None, None).await.is_ok());
     }
+
+    /// Serves a login page that sets a cookie, a page echoing the cookie
+    /// it receives and a redirect to that page.
+    fn spawn_cookie_server() -> String {
+        use std::io::{BufRead, BufReader, Read};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut cookie = None;
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(':').unwrap();
+                    match name.to_ascii_lowercase().as_str() {
+                        "cookie" => cookie = Some(value.trim().to_string()),
+                        "content-length" => length = value.trim().parse().unwrap(),
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (status, extra, body) = match path {
+                    "/login" => (
+                        "200 OK",
+                        "Set-Cookie: sid=secret; Path=/\r\n",
+                        "ok".to_string(),
+                    ),
+                    "/hop" => ("302 Found", "Location: /whoami\r\n", String::new()),
+                    _ => (
+                        "200 OK",
+                        "",
+                        cookie.unwrap_or_else(|| "anonymous".to_string()),
+                    ),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\n{extra}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Echoes each request back as `METHOD|x-test header|body`, with an
+    /// `X-Echo` header. `/slow` waits two seconds before answering.
+    fn spawn_echo_server() -> String {
+        use std::io::{BufRead, BufReader, Read};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+
+                    let mut test_header = String::new();
+                    let mut length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+                        let (name, value) = line.split_once(':').unwrap();
+                        match name.to_ascii_lowercase().as_str() {
+                            "x-test" => test_header = value.trim().to_string(),
+                            "content-length" => length = value.trim().parse().unwrap(),
+                            _ => {}
+                        }
+                    }
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    let mut parts = request_line.split_whitespace();
+                    let method = parts.next().unwrap_or_default();
+                    if parts.next() == Some("/slow") {
+                        std::thread::sleep(std::time::Duration::from_secs(2));
+                    }
+                    let body = format!("{method}|{test_header}|{}", String::from_utf8_lossy(&body));
+                    let response = format!(
+                        "HTTP/1.1 201 Created\r\nX-Echo: yes\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes());
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_http_get_post_and_request() {
+        let base = spawn_echo_server();
+        let engine = NinjaEngine::new().await.unwrap();
+
+        engine
+            .execute(
+                &format!(
+                    "local got = http.get('{base}/', {{ ['X-Test'] = 'g' }})\n\
+                     get_body, get_status, get_echo = got.body, got.status, got.headers['x-echo']\n\
+                     post_body = http.post('{base}/', 'payload', {{ ['X-Test'] = 'p' }}).body\n\
+                     put_body = http.request({{ method = 'put', url = '{base}/', body = 'new', headers = {{ ['X-Test'] = 'r' }} }}).body\n\
+                     slow_ok = pcall(http.request, {{ url = '{base}/slow', timeout = 0.2 }})\n\
+                     bad_method_ok = pcall(http.request, {{ method = 'NOT VALID', url = '{base}/' }})"
+                ),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let globals = engine.lua.globals();
+        assert_eq!(globals.get::<String>("get_body").unwrap(), "GET|g|");
+        assert_eq!(globals.get::<u16>("get_status").unwrap(), 201);
+        assert_eq!(globals.get::<String>("get_echo").unwrap(), "yes");
+        assert_eq!(
+            globals.get::<String>("post_body").unwrap(),
+            "POST|p|payload"
+        );
+        assert_eq!(globals.get::<String>("put_body").unwrap(), "PUT|r|new");
+        assert!(!globals.get::<bool>("slow_ok").unwrap());
+        assert!(!globals.get::<bool>("bad_method_ok").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_http_session_keeps_cookies_and_redirect_choice() {
+        let base = spawn_cookie_server();
+        let engine = NinjaEngine::new().await.unwrap();
+
+        engine
+            .execute(
+                &format!(
+                    "local session = http.session()\n\
+                     before = session:get('{base}/whoami').body\n\
+                     login_status = session:post('{base}/login', 'user=admin').status\n\
+                     after = session:get('{base}/whoami').body\n\
+                     local hop = session:get('{base}/hop', {{ follow_redirects = false }})\n\
+                     hop_status, hop_location = hop.status, hop.headers['location']\n\
+                     followed = session:get('{base}/hop').body\n\
+                     fresh = http.session():get('{base}/whoami').body"
+                ),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let globals = engine.lua.globals();
+        assert_eq!(globals.get::<String>("before").unwrap(), "anonymous");
+        assert_eq!(globals.get::<u16>("login_status").unwrap(), 200);
+        assert_eq!(globals.get::<String>("after").unwrap(), "sid=secret");
+        assert_eq!(globals.get::<u16>("hop_status").unwrap(), 302);
+        assert_eq!(globals.get::<String>("hop_location").unwrap(), "/whoami");
+        assert_eq!(globals.get::<String>("followed").unwrap(), "sid=secret");
+        // Sessions don't share cookies with each other
+        assert_eq!(globals.get::<String>("fresh").unwrap(), "anonymous");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_proc_spawn_passes_argv_and_env_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.txt");
+        let engine = NinjaEngine::new().await.unwrap();
+
+        engine
+            .execute(
+                &format!(
+                    "proc.spawn({{\n\
+                         program = 'sh',\n\
+                         args = {{ '-c', 'printf \"%s|%s\" \"$1\" \"$GREETING\" > \"$2\"', 'sh', 'hello  spaced world', [[{}]] }},\n\
+                         env = {{ GREETING = 'hi there' }},\n\
+                     }})",
+                    out.display()
+                ),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // The process is detached, so wait for it to write
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !fs::read_to_string(&out).is_ok_and(|text| !text.is_empty())
+            && std::time::Instant::now() < deadline
+        {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "hello  spaced world|hi there"
+        );
+
+        // A cwd that isn't a path fails like any other mistyped field
+        let err = engine
+            .execute(
+                "proc.spawn({ program = 'true', cwd = { 'nope' } })",
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("expected string"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_proc_list_finds_own_process() {
+        let engine = NinjaEngine::new().await.unwrap();
+        let script = format!(
+            "own_name = nil\n\
+             count = 0\n\
+             for _, p in ipairs(proc.list()) do\n\
+                 count = count + 1\n\
+                 if p.pid == {} then own_name = p.name end\n\
+             end",
+            std::process::id()
+        );
+        engine.execute(&script, None, None).await.unwrap();
+
+        let globals = engine.lua.globals();
+        assert!(globals.get::<i64>("count").unwrap() > 1);
+        assert!(!globals.get::<String>("own_name").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reload_script_replaces_functions_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("manage.ns");
+        let engine = NinjaEngine::new().await.unwrap();
+        engine.register_global("host_value", 7).unwrap();
+
+        fs::write(
+            &script,
+            "helper = 'old'\n\
+             function start() return 'v1:' .. host_value end\n\
+             function stop() end",
+        )
+        .unwrap();
+        engine.reload_script(&script).await.unwrap();
+        let globals = engine.lua.globals();
+        engine
+            .execute("result = start()", None, None)
+            .await
+            .unwrap();
+        assert_eq!(globals.get::<String>("result").unwrap(), "v1:7");
+
+        fs::write(
+            &script,
+            "function start() return 'v2:' .. host_value end\n\
+             function stop() end",
+        )
+        .unwrap();
+        engine.reload_script(&script).await.unwrap();
+        engine
+            .execute("result = start()", None, None)
+            .await
+            .unwrap();
+        assert_eq!(globals.get::<String>("result").unwrap(), "v2:7");
+        assert!(!globals.contains_key("helper").unwrap());
+        assert!(globals.contains_key("fs").unwrap());
+
+        // A broken edit keeps the working version
+        fs::write(&script, "function start( return 'v3' end").unwrap();
+        assert!(engine.reload_script(&script).await.is_err());
+        engine
+            .execute("result = start()", None, None)
+            .await
+            .unwrap();
+        assert_eq!(globals.get::<String>("result").unwrap(), "v2:7");
+    }
+
+    #[tokio::test]
+    async fn test_store_persists_across_engines() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let engine = NinjaEngine::new().await.unwrap();
+        engine
+            .execute(
+                "store.set('last_run', 1700000000)\n\
+                 store.set('token', { value = 'abc', scopes = { 'read', 'write' } })\n\
+                 store.set('scratch', true)\n\
+                 store.delete('scratch')",
+                Some(dir.path()),
+                None,
+            )
+            .await
+            .unwrap();
+        drop(engine);
+        assert!(dir.path().join(".ninja").join("store.json").is_file());
+
+        let engine = NinjaEngine::new().await.unwrap();
+        engine
+            .execute(
+                "last_run = store.get('last_run')\n\
+                 token = store.get('token').value .. ':' .. store.get('token').scopes[2]\n\
+                 scratch_gone = store.get('scratch') == nil",
+                Some(dir.path()),
+                None,
+            )
+            .await
+            .unwrap();
+        let globals = engine.lua.globals();
+        assert_eq!(globals.get::<i64>("last_run").unwrap(), 1700000000);
+        assert_eq!(globals.get::<String>("token").unwrap(), "abc:write");
+        assert!(globals.get::<bool>("scratch_gone").unwrap());
+
+        // Without a directory there's nowhere to keep the values
+        assert!(engine.execute("store.get('x')", None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_function_runs_keep_their_store_out_of_the_globals() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("start.ns");
+        fs::write(&script, "function start() store.set('owner', 'start') end").unwrap();
+        let stray = dir.path().join("stray.lua");
+        fs::write(&stray, "return store.set('owner', 'stray')").unwrap();
+
+        let engine = NinjaEngine::new().await.unwrap();
+        engine
+            .execute_function("start", &script, Some(dir.path()), None)
+            .await
+            .unwrap();
+
+        // A later run without a directory must not reach the start's store
+        assert!(engine.eval_file(&stray, None, None).await.is_err());
+        let store = fs::read_to_string(dir.path().join(".ninja/store.json")).unwrap();
+        assert!(store.contains("\"start\""));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_exec_strips_ansi_when_asked() {
+        let engine = NinjaEngine::new().await.unwrap();
+        let command = r"printf '\033[1;31mred\033[0m and plain'";
+
+        engine
+            .execute(
+                &format!(
+                    "local raw = shell.exec([[{command}]], false, {{ capture = true }})\n\
+                     local clean = shell.exec([[{command}]], false, {{ strip_ansi = true }})\n\
+                     local capped = shell.exec([[{command}]], false, {{ strip_ansi = true, max_output = 12 }})\n\
+                     raw_stdout, clean_stdout, clean_truncated = raw.stdout, clean.stdout, clean.truncated\n\
+                     capped_stdout, capped_truncated = capped.stdout, capped.truncated"
+                ),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let globals = engine.lua.globals();
+        assert!(
+            globals
+                .get::<String>("raw_stdout")
+                .unwrap()
+                .contains('\x1b')
+        );
+        assert_eq!(
+            globals.get::<String>("clean_stdout").unwrap(),
+            "red and plain"
+        );
+        assert!(!globals.get::<bool>("clean_truncated").unwrap());
+        assert_eq!(globals.get::<String>("capped_stdout").unwrap(), "red");
+        assert!(globals.get::<bool>("capped_truncated").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_print_capture_collects_output() {
+        let engine = NinjaEngine::new().await.unwrap();
+
+        let (result, printed) =
+            NinjaEngine::capture(engine.execute("print('hi')\nprint('a', 1, true)", None, None))
+                .await;
+        result.unwrap();
+        assert_eq!(printed, "hi\na\t1\ttrue\n");
+
+        // A script running next to a capture doesn't print into it
+        let other = engine.clone();
+        let noisy = tokio::spawn(async move {
+            for _ in 0..20 {
+                other
+                    .execute("print('elsewhere')", None, None)
+                    .await
+                    .unwrap();
+                tokio::task::yield_now().await;
+            }
+        });
+        let ((), printed) = NinjaEngine::capture(async {
+            for _ in 0..20 {
+                engine.execute("print('mine')", None, None).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        })
+        .await;
+        noisy.await.unwrap();
+        assert_eq!(printed, "mine\n".repeat(20));
+    }
 }
 
 #[cfg(test)]
 mod ninja_api_integration_tests {
     use crate::ninja_runtime_integration_tests::write_stub_script;
     use ninja::{
-        common::{config::NinjaConfig, types::ShurikenState},
-        manager::ShurikenManager,
+        common::{
+            config::NinjaConfig,
+            error::{ManagerError, error_code},
+            registry::is_newer_version,
+            self_update::{self, UpdateOutcome},
+            signing,
+            traits::Reporter,
+            types::{
+                ArmoryMetadata, BulkMode, CheckStatus, FieldValue, ForgeFilter, ForgeOptions,
+                InputType, InstallOptions, InstallStage, LifecycleEventKind, LogFilter,
+                RenderFormat, ShurikenState, StartOverrides,
+            },
+        },
+        manager::{DEFAULT_PARALLELISM, ShurikenManager},
         scripting::NinjaEngine,
-        shuriken::{Shuriken, ShurikenMetadata},
+        shuriken::{MAX_ALWAYS_BACKOFF, RestartPolicy, STABLE_RUN, Shuriken, ShurikenMetadata},
+        utils::{ArchiveFormat, ConfigBundle, MAX_BUNDLE_ENTRY, load_shuriken},
+    };
+    use std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Duration,
     };
-    use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
     use tempfile::tempdir;
     use tokio::sync::{Mutex, RwLock};
 
+    async fn manager_in(root: &Path) -> ShurikenManager {
+        let engine = NinjaEngine::new().await.unwrap();
+        ShurikenManager {
+            config: Arc::new(RwLock::new(NinjaConfig::default())),
+            root_path: root.to_path_buf(),
+            engine: Arc::new(Mutex::new(engine)),
+            shurikens: Arc::new(RwLock::new(HashMap::new())),
+            state_events: Default::default(),
+            operation_locks: Default::default(),
+            idle_timers: Default::default(),
+            states_cache: Default::default(),
+        }
+    }
+
+    fn write_daemon_shuriken(root: &Path, name: &str) -> PathBuf {
+        let shuriken_dir = root.join("shurikens").join(name);
+        let ninja_dir = shuriken_dir.join(".ninja");
+        fs::create_dir_all(&ninja_dir).unwrap();
+        fs::write(
+            ninja_dir.join("manifest.toml"),
+            format!(
+                "[shuriken]\nname = \"{name}\"\nid = \"{name}\"\nversion = \"1.0.0\"\ntype = \"daemon\"\nscript-path = \"{name}.ns\"\n"
+            ),
+        )
+        .unwrap();
+        write_stub_script(&ninja_dir.join(format!("{name}.ns")));
+        shuriken_dir
+    }
+
+    /// Appends `extra` to the end of a shuriken's manifest.
+    fn append_manifest(shuriken_dir: &Path, extra: &str) {
+        let manifest = shuriken_dir.join(".ninja/manifest.toml");
+        let content = fs::read_to_string(&manifest).unwrap();
+        fs::write(&manifest, format!("{content}{extra}")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dsl_script_starts_selected_shuriken() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let script = dir.path().join("orchestrate.lua");
+        fs::write(&script, "ninja.start(ninja.selected())").unwrap();
+
+        let dsl = manager.new_dsl();
+        dsl.execute(format!("select demo\nexecute {}", script.display()))
+            .await
+            .unwrap();
+
+        let states = manager.list(true).await.unwrap().left().unwrap();
+        assert!(states.contains(&("demo".to_string(), ShurikenState::Running)));
+    }
+
+    #[tokio::test]
+    async fn test_dsl_set_stores_typed_value() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "typed");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 80\n").unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let dsl = manager.new_dsl();
+        dsl.execute("select typed\nset port 8080".to_string())
+            .await
+            .unwrap();
+
+        let shuriken = manager.get("typed".to_string()).await.unwrap();
+        let options = shuriken.config.unwrap().options.unwrap();
+        assert!(matches!(
+            options.get("port"),
+            Some(FieldValue::Number(8080))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_configure_keeps_previous_config_on_template_error() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(&shuriken_dir, "\n[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 8080\n").unwrap();
+        fs::write(
+            shuriken_dir.join(".ninja/config.tmpl"),
+            "port = {{ port }}\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.configure_shuriken("demo").await.unwrap();
+        let config_path = shuriken_dir.join("demo.conf");
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "port = 8080\n");
+
+        fs::write(
+            shuriken_dir.join(".ninja/config.tmpl"),
+            "port = {{ port }}\nhost = {{ missing_host }}\n",
+        )
+        .unwrap();
+        let err = manager.configure_shuriken("demo").await.unwrap_err();
+        // The templater error comes back as-is rather than flattened into a message
+        match err.downcast_ref::<ninja::scripting::templater::TemplateError>() {
+            Some(ninja::scripting::templater::TemplateError::Internal(msg)) => {
+                assert!(msg.contains("missing_host"), "unexpected message: {msg}");
+            }
+            other => panic!("expected a templater error, got {other:?} ({err})"),
+        }
+
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "port = 8080\n");
+        let leftovers: Vec<_> = fs::read_dir(&shuriken_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_field_value_render_formats() {
+        let on = FieldValue::Bool(true);
+        let off = FieldValue::Bool(false);
+        let text = FieldValue::String(r#"say "hi" \ bye"#.into());
+        let port = FieldValue::Number(8080);
+
+        assert_eq!(on.render_for(RenderFormat::Raw), "true");
+        assert_eq!(text.render_for(RenderFormat::Raw), text.render());
+
+        assert_eq!(
+            text.render_for(RenderFormat::Quoted),
+            r#""say \"hi\" \\ bye""#
+        );
+        assert_eq!(port.render_for(RenderFormat::Quoted), "8080");
+        assert_eq!(on.render_for(RenderFormat::Quoted), "true");
+
+        assert_eq!(on.render_for(RenderFormat::BoolOnOff), "on");
+        assert_eq!(off.render_for(RenderFormat::BoolOnOff), "off");
+        assert_eq!(on.render_for(RenderFormat::BoolYesNo), "yes");
+        assert_eq!(off.render_for(RenderFormat::BoolYesNo), "no");
+        assert_eq!(on.render_for(RenderFormat::BoolNumeric), "1");
+        assert_eq!(off.render_for(RenderFormat::BoolNumeric), "0");
+        assert_eq!(port.render_for(RenderFormat::BoolOnOff), "8080");
+
+        for directive in RenderFormat::DIRECTIVES {
+            assert!(RenderFormat::from_directive(directive).is_some());
+        }
+        assert_eq!(RenderFormat::from_directive("loud"), None);
+    }
+
+    #[tokio::test]
+    async fn test_configure_applies_placeholder_directives() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(&shuriken_dir, "[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(
+            shuriken_dir.join(".ninja/options.toml"),
+            "ssl = true\nname = \"main site\"\n[log]\nverbose = false\n",
+        )
+        .unwrap();
+        fs::write(
+            shuriken_dir.join(".ninja/config.tmpl"),
+            "SSLEngine {{ssl:on_off}}\nserver_name = {{ name:quoted }}\nverbose: {{ log.verbose:yes_no }}\nraw = {{ ssl }}\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.configure_shuriken("demo").await.unwrap();
+        assert_eq!(
+            fs::read_to_string(shuriken_dir.join("demo.conf")).unwrap(),
+            "SSLEngine on\nserver_name = \"main site\"\nverbose: no\nraw = true\n"
+        );
+
+        fs::write(shuriken_dir.join(".ninja/config.tmpl"), "{{ ssl:loud }}\n").unwrap();
+        manager.refresh().await.unwrap();
+        let err = manager.configure_shuriken("demo").await.unwrap_err();
+        assert!(format!("{:#}", err).contains("Unknown render format 'loud'"));
+    }
+
+    #[test]
+    fn test_directives_are_not_expanded_inside_raw_blocks_or_comments() {
+        use ninja::scripting::templater::expand_directives;
+
+        assert_eq!(
+            expand_directives(
+                "a = {{ ssl:on_off }}\n{% raw %}b = {{ ssl:on_off }}{% endraw %}\n{# {{ ssl:yes_no }} #}\nc = {{ ssl:yes_no }}\n"
+            ),
+            "a = {{ ssl | render(format=\"on_off\") }}\n{% raw %}b = {{ ssl:on_off }}{% endraw %}\n{# {{ ssl:yes_no }} #}\nc = {{ ssl | render(format=\"yes_no\") }}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configure_keeps_raw_blocks_verbatim() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(&shuriken_dir, "[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "ssl = true\n").unwrap();
+        fs::write(
+            shuriken_dir.join(".ninja/config.tmpl"),
+            "SSLEngine {{ ssl:on_off }}\n{%- raw %}\nexample: {{ ssl:on_off }}\n{% endraw -%}\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.configure_shuriken("demo").await.unwrap();
+        assert_eq!(
+            fs::read_to_string(shuriken_dir.join("demo.conf")).unwrap(),
+            "SSLEngine on\nexample: {{ ssl:on_off }}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configure_reports_unreadable_templates() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(&shuriken_dir, "[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 80\n").unwrap();
+        fs::write(
+            shuriken_dir.join(".ninja/config.tmpl"),
+            "port = {{ port }}\n",
+        )
+        .unwrap();
+        // Not valid UTF-8, so it can't be read as a template
+        fs::write(shuriken_dir.join(".ninja/extra.tmpl"), [0xff, 0xfe, 0x00]).unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let err = manager.configure_shuriken("demo").await.unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Failed to read template 'extra.tmpl'"),
+            "unexpected error: {err:#}"
+        );
+        assert!(!shuriken_dir.join("demo.conf").exists());
+    }
+
+    #[tokio::test]
+    async fn test_config_bundle_round_trip() {
+        let dir = tempdir().unwrap();
+        let mut managers = Vec::new();
+        for (machine, port) in [("source", 9090), ("target", 80)] {
+            let root = dir.path().join(machine);
+            let shuriken_dir = write_daemon_shuriken(&root, "demo");
+            append_manifest(&shuriken_dir, "\n[config]\nconfig-path = \"demo.conf\"\n");
+            fs::write(
+                shuriken_dir.join(".ninja/options.toml"),
+                format!("port = {port}\n"),
+            )
+            .unwrap();
+            fs::write(
+                shuriken_dir.join(".ninja/config.tmpl"),
+                "port = {{ port }}\n",
+            )
+            .unwrap();
+            let manager = manager_in(&root).await;
+            manager.refresh().await.unwrap();
+            manager.configure_shuriken("demo").await.unwrap();
+            managers.push(manager);
+        }
+        let (source, target) = (&managers[0], &managers[1]);
+        let target_dir = dir.path().join("target/shurikens/demo");
+
+        let bundle = dir.path().join("demo-config.tar.gz");
+        source.export_config_bundle("demo", &bundle).await.unwrap();
+        target
+            .import_config_bundle("demo", &bundle, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            target.get_option("demo", "port").await.unwrap(),
+            Some(FieldValue::Number(9090))
+        );
+        assert!(
+            fs::read_to_string(target_dir.join(".ninja/options.toml"))
+                .unwrap()
+                .contains("9090")
+        );
+        assert_eq!(
+            fs::read_to_string(target_dir.join("demo.conf")).unwrap(),
+            "port = 9090\n"
+        );
+
+        // Regenerating uses the target's own template with the bundled options
+        fs::write(target_dir.join(".ninja/config.tmpl"), "listen {{ port }}\n").unwrap();
+        target
+            .import_config_bundle("demo", &bundle, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(target_dir.join("demo.conf")).unwrap(),
+            "listen 9090\n"
+        );
+
+        let err = target
+            .import_config_bundle("ghost", &bundle, false)
+            .await
+            .unwrap_err();
+        assert_eq!(error_code(&err), Some("not_found"));
+    }
+
+    #[tokio::test]
+    async fn test_config_bundle_import_is_all_or_nothing() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(&shuriken_dir, "\n[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 80\n").unwrap();
+        fs::write(
+            shuriken_dir.join(".ninja/schema.toml"),
+            "[port]\ntype = \"number\"\nmax = 1000\n",
+        )
+        .unwrap();
+        fs::write(shuriken_dir.join("demo.conf"), "port = 80\n").unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let bundle = dir.path().join("demo-config.tar.gz");
+        let write_bundle = |options: &str, config: &str| {
+            let bundle_bytes = ConfigBundle {
+                options: Some(options.to_string()),
+                config: Some(config.as_bytes().to_vec()),
+            }
+            .to_tar_gz()
+            .unwrap();
+            fs::write(&bundle, bundle_bytes).unwrap();
+        };
+        let unchanged = || {
+            assert_eq!(
+                fs::read_to_string(shuriken_dir.join(".ninja/options.toml")).unwrap(),
+                "port = 80\n"
+            );
+            assert_eq!(
+                fs::read_to_string(shuriken_dir.join("demo.conf")).unwrap(),
+                "port = 80\n"
+            );
+        };
+
+        // Options outside the schema leave the config alone too
+        write_bundle("port = 9090\n", "port = 9090\n");
+        let err = manager
+            .import_config_bundle("demo", &bundle, false)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid options for 'demo'"));
+        unchanged();
+
+        // A template that fails to render puts the previous options back
+        write_bundle("port = 443\n", "port = 443\n");
+        fs::write(shuriken_dir.join(".ninja/config.tmpl"), "{{ port:loud }}\n").unwrap();
+        assert!(
+            manager
+                .import_config_bundle("demo", &bundle, true)
+                .await
+                .is_err()
+        );
+        unchanged();
+        assert_eq!(
+            manager.get_option("demo", "port").await.unwrap(),
+            Some(FieldValue::Number(80))
+        );
+
+        let oversized = ConfigBundle {
+            options: None,
+            config: Some(vec![b' '; MAX_BUNDLE_ENTRY as usize + 1]),
+        };
+        let err = ConfigBundle::from_tar_gz(&oversized.to_tar_gz().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("larger than"));
+    }
+
+    #[tokio::test]
+    async fn test_manager_retries_transient_dir_errors_only() {
+        use std::io::{Error as IoError, ErrorKind};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let dir = tempdir().unwrap();
+        // `shurikens` is still mounting for the first two attempts
+        let calls = Arc::new(AtomicU32::new(0));
+        let manager = ShurikenManager::with_root_using(dir.path().join("slow"), {
+            let calls = calls.clone();
+            move |path: PathBuf| {
+                let calls = calls.clone();
+                async move {
+                    if path.ends_with("shurikens") && calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                        return Err(IoError::new(ErrorKind::NotFound, "mount not ready"));
+                    }
+                    tokio::fs::create_dir_all(&path).await
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(manager.root_path.join("shurikens").is_dir());
+
+        // A permanent error fails on the first attempt
+        let calls = Arc::new(AtomicU32::new(0));
+        let err = ShurikenManager::with_root_using(dir.path().join("denied"), {
+            let calls = calls.clone();
+            move |path: PathBuf| {
+                let calls = calls.clone();
+                async move {
+                    if path.ends_with("shurikens") {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        return Err(IoError::new(ErrorKind::PermissionDenied, "read-only"));
+                    }
+                    tokio::fs::create_dir_all(&path).await
+                }
+            }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(format!("{err:#}").contains("read-only"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_configure_from_file() {
+        let dir = tempdir().unwrap();
+        for name in ["alpha", "beta"] {
+            let shuriken_dir = write_daemon_shuriken(dir.path(), name);
+            append_manifest(
+                &shuriken_dir,
+                &format!("\n[config]\nconfig-path = \"{name}.conf\"\n"),
+            );
+            fs::write(
+                shuriken_dir.join(".ninja/config.tmpl"),
+                "port = {{ port }}\n",
+            )
+            .unwrap();
+        }
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let env_file = dir.path().join("env.toml");
+        fs::write(
+            &env_file,
+            "[alpha]\nport = 8080\n\n[beta]\nport = 5432\n\n[ghost]\nport = 1\n",
+        )
+        .unwrap();
+
+        let results = manager.bulk_configure_from_file(&env_file).await.unwrap();
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        // Ghost isn't installed, so it's skipped rather than failing the run
+        assert_eq!(names, vec!["alpha", "beta"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        for (name, port) in [("alpha", 8080), ("beta", 5432)] {
+            let shuriken_dir = dir.path().join("shurikens").join(name);
+            assert_eq!(
+                fs::read_to_string(shuriken_dir.join(format!("{name}.conf"))).unwrap(),
+                format!("port = {port}\n")
+            );
+            assert!(
+                fs::read_to_string(shuriken_dir.join(".ninja/options.toml"))
+                    .unwrap()
+                    .contains(&port.to_string())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_option_get_set_unset() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "web");
+        fs::write(
+            shuriken_dir.join(".ninja/options.toml"),
+            "port = 8080\nlegacy = true\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        // get
+        assert_eq!(
+            manager.get_option("web", "port").await.unwrap(),
+            Some(FieldValue::Number(8080))
+        );
+        assert_eq!(manager.get_option("web", "missing").await.unwrap(), None);
+        assert!(manager.get_option("ghost", "port").await.is_err());
+
+        // set, with the CLI's type inference
+        manager
+            .set_option("web", "ratio", FieldValue::from("1.5"))
+            .await
+            .unwrap();
+        manager
+            .set_option("web", "port", FieldValue::from("8081"))
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_option("web", "ratio").await.unwrap(),
+            Some(FieldValue::Float(1.5))
+        );
+        assert_eq!(
+            manager.get_option("web", "port").await.unwrap(),
+            Some(FieldValue::Number(8081))
+        );
+        assert!(manager.diff_config("web").await.unwrap().is_empty());
+
+        // unset
+        assert!(manager.unset_option("web", "legacy").await.unwrap());
+        assert!(!manager.unset_option("web", "legacy").await.unwrap());
+        assert_eq!(manager.get_option("web", "legacy").await.unwrap(), None);
+
+        // Everything survives a reload from disk
+        manager.refresh().await.unwrap();
+        assert_eq!(
+            manager.get_option("web", "port").await.unwrap(),
+            Some(FieldValue::Number(8081))
+        );
+        assert_eq!(
+            manager.get_option("web", "ratio").await.unwrap(),
+            Some(FieldValue::Float(1.5))
+        );
+        assert_eq!(manager.get_option("web", "legacy").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_patches_keep_both_keys() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "web");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 8080\n").unwrap();
+        // Two managers stand in for two processes sharing the same directory
+        let first = manager_in(dir.path()).await;
+        let second = manager_in(dir.path()).await;
+        first.refresh().await.unwrap();
+        second.refresh().await.unwrap();
+
+        let (a, b) = tokio::join!(
+            first.patch_config(
+                "web",
+                HashMap::from([("host".to_string(), FieldValue::from("localhost"))]),
+            ),
+            second.patch_config(
+                "web",
+                HashMap::from([("workers".to_string(), FieldValue::Number(4))]),
+            ),
+        );
+        a.unwrap();
+        b.unwrap();
+
+        // Single-option edits work from the saved file too, not from each
+        // manager's stale copy
+        let (a, b) = tokio::join!(
+            first.set_option("web", "debug", FieldValue::Bool(true)),
+            second.rename_option("web", "port", "listen"),
+        );
+        a.unwrap();
+        b.unwrap();
+
+        let reloaded = manager_in(dir.path()).await;
+        reloaded.refresh().await.unwrap();
+        assert_eq!(
+            reloaded.get_option("web", "listen").await.unwrap(),
+            Some(FieldValue::Number(8080))
+        );
+        assert_eq!(reloaded.get_option("web", "port").await.unwrap(), None);
+        assert_eq!(
+            reloaded.get_option("web", "debug").await.unwrap(),
+            Some(FieldValue::Bool(true))
+        );
+        assert_eq!(
+            reloaded.get_option("web", "host").await.unwrap(),
+            Some(FieldValue::String("localhost".into()))
+        );
+        assert_eq!(
+            reloaded.get_option("web", "workers").await.unwrap(),
+            Some(FieldValue::Number(4))
+        );
+        assert!(first.patch_config("ghost", HashMap::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rename_option_carries_value_over() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "web");
+        fs::write(
+            shuriken_dir.join(".ninja/options.toml"),
+            "listen_port = 8080\nhost = \"localhost\"\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager
+            .rename_option("web", "listen_port", "port")
+            .await
+            .unwrap();
+
+        let options = manager
+            .get("web".to_string())
+            .await
+            .unwrap()
+            .config
+            .unwrap()
+            .options
+            .unwrap();
+        assert_eq!(options.get("port"), Some(&FieldValue::Number(8080)));
+        assert_eq!(options.get("listen_port"), None);
+        assert_eq!(
+            options.get("host"),
+            Some(&FieldValue::String("localhost".to_string()))
+        );
+        // Saved to disk, not just in memory
+        assert!(manager.diff_config("web").await.unwrap().is_empty());
+
+        assert!(
+            manager
+                .rename_option("web", "missing", "other")
+                .await
+                .is_err()
+        );
+        assert!(manager.rename_option("web", "port", "host").await.is_err());
+
+        // Unsaved edits aren't written along with the rename, and aren't lost
+        let dsl = manager.new_dsl();
+        dsl.execute("select web\nset host example.com".to_string())
+            .await
+            .unwrap();
+        manager
+            .rename_option("web", "port", "listen")
+            .await
+            .unwrap();
+        let saved = fs::read_to_string(shuriken_dir.join(".ninja/options.toml")).unwrap();
+        assert!(saved.contains("listen = 8080"), "{saved}");
+        assert!(saved.contains("host = \"localhost\""), "{saved}");
+        assert_eq!(
+            manager.get_option("web", "host").await.unwrap(),
+            Some(FieldValue::String("example.com".to_string()))
+        );
+        assert_eq!(
+            manager.get_option("web", "listen").await.unwrap(),
+            Some(FieldValue::Number(8080))
+        );
+        assert_eq!(manager.get_option("web", "port").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_diff_config_shows_unsaved_set() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "web");
+        fs::write(
+            shuriken_dir.join(".ninja/options.toml"),
+            "port = 80\nhost = \"localhost\"\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        assert!(manager.diff_config("web").await.unwrap().is_empty());
+
+        let dsl = manager.new_dsl();
+        dsl.execute("select web\nset port 8080\nset debug true".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.diff_config("web").await.unwrap(),
+            vec![
+                ("debug".to_string(), None, Some(FieldValue::Bool(true))),
+                (
+                    "port".to_string(),
+                    Some(FieldValue::Number(80)),
+                    Some(FieldValue::Number(8080))
+                ),
+            ]
+        );
+
+        dsl.execute("select web\nsave".to_string()).await.unwrap();
+        assert!(manager.diff_config("web").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dsl_get_reads_flat_keys_and_nested_paths() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "web");
+        fs::write(
+            shuriken_dir.join(".ninja/options.toml"),
+            "host = \"localhost\"\n\n[server]\nport = 8080\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let dsl = manager.new_dsl();
+        let output = dsl
+            .execute("select web\nget host\nget server.port\nget server.port + 1".to_string())
+            .await
+            .unwrap();
+        assert!(output.contains(&"host = \"localhost\"".to_string()));
+        assert!(output.contains(&"server.port = 8080".to_string()));
+        assert!(output.contains(&"server.port + 1 = 8081".to_string()));
+
+        assert_eq!(
+            dsl.get_value("web", "server.port").await.unwrap(),
+            serde_json::json!(8080)
+        );
+        assert_eq!(
+            dsl.get_value("web", "missing").await.unwrap(),
+            serde_json::Value::Null
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dsl_execute_returns_printed_lines() {
+        let dir = tempdir().unwrap();
+        let manager = manager_in(dir.path()).await;
+        let script = dir.path().join("chatty.lua");
+        fs::write(&script, "print('one')\nprint('two')").unwrap();
+
+        let output = manager
+            .new_dsl()
+            .execute(format!("execute {}", script.display()))
+            .await
+            .unwrap();
+        assert_eq!(output, vec!["one".to_string(), "two".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_dsl_configure_block_persists() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "blocky");
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 80\n").unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let dsl = manager.new_dsl();
+        let output = dsl
+            .execute("select blocky\nconfigure { port = 8080; host = \"0.0.0.0\" }".to_string())
+            .await
+            .unwrap();
+        assert!(output.iter().any(|line| line == "Saved options for blocky"));
+
+        // A fresh manager only sees what made it to options.toml
+        let reloaded = manager_in(dir.path()).await;
+        reloaded.refresh().await.unwrap();
+        let shuriken = reloaded.get("blocky".to_string()).await.unwrap();
+        let options = shuriken.config.unwrap().options.unwrap();
+        assert!(matches!(
+            options.get("port"),
+            Some(FieldValue::Number(8080))
+        ));
+        assert_eq!(
+            options.get("host").and_then(|v| v.as_str()),
+            Some("0.0.0.0")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dsl_set_then_save_writes_options() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "saver");
+        let options_path = shuriken_dir.join(".ninja/options.toml");
+        fs::write(&options_path, "port = 80\n").unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let dsl = manager.new_dsl();
+        dsl.execute("select saver\nset port 8080".to_string())
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&options_path).unwrap(), "port = 80\n");
+
+        let output = dsl.execute("save".to_string()).await.unwrap();
+        assert_eq!(output, vec!["Saved options for saver".to_string()]);
+        assert_eq!(fs::read_to_string(&options_path).unwrap(), "port = 8080\n");
+
+        // Without loaded options there's nothing to save, not an empty file
+        let bare_dir = write_daemon_shuriken(dir.path(), "bare");
+        manager.refresh().await.unwrap();
+        let err = dsl
+            .execute("select bare\nsave".to_string())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no loaded options"), "{err}");
+        assert!(!bare_dir.join(".ninja/options.toml").exists());
+    }
+
+    #[tokio::test]
+    async fn test_manager_clones_share_engine() {
+        let dir = tempdir().unwrap();
+        let manager = manager_in(dir.path()).await;
+        let other = manager.clone();
+
+        manager
+            .engine
+            .lock()
+            .await
+            .register_global("shared_marker", 42)
+            .unwrap();
+
+        let seen: i64 = other
+            .engine
+            .lock()
+            .await
+            .lua
+            .globals()
+            .get("shared_marker")
+            .unwrap();
+        assert_eq!(seen, 42);
+    }
+
+    #[tokio::test]
+    async fn test_tools_lists_declared_tools_without_scripts() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "toolbox");
+        append_manifest(
+            &shuriken_dir,
+            "\n[[tools]]\nname = \"vacuum\"\nscript = \"tools/vacuum-secret.lua\"\ndescription = \"Compact the database\"\n",
+        );
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let tools = manager.tools("toolbox").await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "vacuum");
+        assert_eq!(
+            tools[0].description.as_deref(),
+            Some("Compact the database")
+        );
+
+        let json = serde_json::to_string(&tools).unwrap();
+        assert!(!json.contains("vacuum-secret"));
+
+        assert!(manager.tools("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_returns_script_output() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "mechanic");
+        append_manifest(
+            &shuriken_dir,
+            "\n[[tools]]\nname = \"greet\"\nscript = \"greet.lua\"\n",
+        );
+        fs::write(
+            shuriken_dir.join("greet.lua"),
+            "return 'hello from ' .. shuriken.name",
+        )
+        .unwrap();
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let output = manager.run_tool("mechanic", "greet").await.unwrap();
+        assert_eq!(output, "hello from mechanic");
+        assert!(manager.run_tool("mechanic", "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_keeps_its_context_across_a_start() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "mechanic");
+        write_daemon_shuriken(dir.path(), "other");
+        append_manifest(
+            &shuriken_dir,
+            "\n[[tools]]\nname = \"kick\"\nscript = \"kick.lua\"\n",
+        );
+        // Starting another shuriken runs its script while this tool is running
+        fs::write(
+            shuriken_dir.join("kick.lua"),
+            "local before = shuriken.name\nninja.start('other')\nreturn before .. ':' .. shuriken.name",
+        )
+        .unwrap();
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let output = manager.run_tool("mechanic", "kick").await.unwrap();
+        assert_eq!(output, "mechanic:mechanic");
+        assert!(manager.is_running("other").await);
+        manager.stop("other").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_and_start_require_their_own_modules_concurrently() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "mechanic");
+        let other_dir = write_daemon_shuriken(dir.path(), "other");
+        append_manifest(
+            &shuriken_dir,
+            "\n[[tools]]\nname = \"kick\"\nscript = \"kick.lua\"\n",
+        );
+        for (dir, prefix) in [(&shuriken_dir, "m"), (&other_dir, "o")] {
+            fs::write(dir.join("alpha.lua"), format!("return '{prefix}-alpha'")).unwrap();
+            fs::write(dir.join("beta.lua"), format!("return '{prefix}-beta'")).unwrap();
+        }
+        // Both scripts sleep between two requires, so the start runs while the tool waits
+        fs::write(
+            shuriken_dir.join("kick.lua"),
+            "local a = require('alpha')\nproc.exec('sleep 0.4')\nreturn a .. ':' .. require('beta')",
+        )
+        .unwrap();
+        fs::write(
+            other_dir.join(".ninja/other.ns"),
+            "function start()\n  assert(require('alpha') == 'o-alpha')\n  proc.exec('sleep 0.2')\n  assert(require('beta') == 'o-beta')\nend\nfunction stop() end\n",
+        )
+        .unwrap();
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let (output, started) = tokio::join!(manager.run_tool("mechanic", "kick"), async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            manager.start("other").await
+        });
+        assert_eq!(output.unwrap(), "m-alpha:m-beta");
+        started.unwrap();
+        manager.stop("other").await.unwrap();
+
+        // Start and stop keep their context out of the shared globals too
+        let engine = manager.engine.lock().await;
+        assert!(!engine.lua.globals().contains_key("shuriken").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_read_logs_filters_by_pattern_and_time() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "apache");
+        append_manifest(&shuriken_dir, "\n[logs]\nlog-path = \"logs/error.log\"\n");
+        fs::create_dir_all(shuriken_dir.join("logs")).unwrap();
+        fs::write(
+            shuriken_dir.join("logs/error.log"),
+            "2000-01-01T00:00:00Z ERROR ancient failure\n\
+             2000-01-01T00:00:01Z INFO ancient notice\n\
+             2999-01-01T00:00:00Z ERROR future failure\n\
+             2999-01-01T00:00:01Z INFO future notice\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let errors = LogFilter {
+            grep: Some("ERROR".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            manager.read_logs("apache", &errors).await.unwrap(),
+            vec![
+                "2000-01-01T00:00:00Z ERROR ancient failure",
+                "2999-01-01T00:00:00Z ERROR future failure",
+            ]
+        );
+
+        let recent = LogFilter {
+            since: Some(Duration::from_secs(10 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(
+            manager.read_logs("apache", &recent).await.unwrap(),
+            vec![
+                "2999-01-01T00:00:00Z ERROR future failure",
+                "2999-01-01T00:00:01Z INFO future notice",
+            ]
+        );
+
+        let recent_errors = LogFilter {
+            grep: Some("ERROR".to_string()),
+            since: Some(Duration::from_secs(10 * 60)),
+            lines: None,
+        };
+        assert_eq!(
+            manager.read_logs("apache", &recent_errors).await.unwrap(),
+            vec!["2999-01-01T00:00:00Z ERROR future failure"]
+        );
+
+        write_daemon_shuriken(dir.path(), "quiet");
+        manager.refresh().await.unwrap();
+        assert!(manager.read_logs("quiet", &errors).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tail_logs_returns_last_lines_or_no_log_file() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "apache");
+        append_manifest(&shuriken_dir, "\n[logs]\nlog-path = \"logs/access.log\"\n");
+        fs::create_dir_all(shuriken_dir.join("logs")).unwrap();
+        let log: String = (1..=20_000).map(|i| format!("request {i}\n")).collect();
+        fs::write(shuriken_dir.join("logs/access.log"), log).unwrap();
+        write_daemon_shuriken(dir.path(), "quiet");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        assert_eq!(
+            manager.tail_logs("apache", 2).await.unwrap(),
+            vec!["request 19999", "request 20000"]
+        );
+        assert_eq!(
+            manager.log_path("apache").await.unwrap(),
+            shuriken_dir.join("logs/access.log")
+        );
+
+        let err = manager.tail_logs("quiet", 2).await.unwrap_err();
+        assert_eq!(error_code(&err), Some("no_log_file"));
+        assert!(err.to_string().contains("no log file"));
+    }
+
+    /// Writes a daemon shuriken whose start script appends its name to `log`.
+    fn write_ordered_shuriken(root: &Path, name: &str, depends_on: &[&str], log: &Path) {
+        let shuriken_dir = write_daemon_shuriken(root, name);
+        let ninja_dir = shuriken_dir.join(".ninja");
+        let deps = depends_on
+            .iter()
+            .map(|d| format!("\"{d}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        append_manifest(&shuriken_dir, &format!("depends-on = [{deps}]\n"));
+        fs::write(
+            ninja_dir.join(format!("{name}.ns")),
+            format!(
+                "function start() fs.append({:?}, shuriken.name .. \"\\n\") end\nfunction stop() end\n",
+                log.display().to_string()
+            ),
+        )
+        .unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_captured_processes_keep_default_sigpipe() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "piped");
+        append_manifest(&shuriken_dir, "log-buffer = 3\n");
+        fs::write(
+            shuriken_dir.join(".ninja/piped.ns"),
+            "function start()\n\
+                 return proc.spawn({ program = 'grep', args = { 'SigIgn', '/proc/self/status' } })\n\
+             end\n\
+             function stop() end\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("piped").await.unwrap();
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            lines = manager
+                .read_logs("piped", &LogFilter::default())
+                .await
+                .unwrap();
+            if !lines.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let mask = lines[0].trim_start_matches("SigIgn:").trim();
+        let ignored = u64::from_str_radix(mask, 16).unwrap();
+        // SIGPIPE is signal 13
+        assert_eq!(ignored & (1 << 12), 0, "{}", lines[0]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_read_logs_serves_captured_output_without_log_file() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "chatty");
+        append_manifest(&shuriken_dir, "log-buffer = 3\n");
+        fs::write(
+            shuriken_dir.join(".ninja/chatty.ns"),
+            "function start()\n\
+                 return proc.spawn({ program = 'sh', args = { '-c', 'for i in 1 2 3 4 5; do echo line $i; done; sleep 0.2; echo oops >&2; sleep 1' } })\n\
+             end\n\
+             function stop() end\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("chatty").await.unwrap();
+        let expected = vec!["line 4", "line 5", "oops"];
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            lines = manager
+                .read_logs("chatty", &LogFilter::default())
+                .await
+                .unwrap();
+            if lines.len() == 3 && lines.contains(&"oops".to_string()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(lines, expected);
+
+        let grep = LogFilter {
+            grep: Some("line".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            manager
+                .read_logs("chatty", &grep)
+                .await
+                .unwrap()
+                .iter()
+                .all(|line| line.starts_with("line"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_concurrent_starts_each_run_in_their_own_directory() {
+        let dir = tempdir().unwrap();
+        let names = ["alpha", "beta"];
+        for name in names {
+            let shuriken_dir = write_daemon_shuriken(dir.path(), name);
+            fs::write(
+                shuriken_dir.join(format!(".ninja/{name}.ns")),
+                "function start()\n\
+                     fs.write('ran-in.txt', shuriken.name)\n\
+                     return proc.spawn('pwd > pwd.txt; sleep 1')\n\
+                 end\n\
+                 function stop() end\n",
+            )
+            .unwrap();
+        }
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let (alpha, beta) = tokio::join!(manager.start("alpha"), manager.start("beta"));
+        alpha.unwrap();
+        beta.unwrap();
+
+        for name in names {
+            let shuriken_dir = dir.path().join("shurikens").join(name);
+            assert_eq!(
+                fs::read_to_string(shuriken_dir.join("ran-in.txt")).unwrap(),
+                name
+            );
+            let pwd_file = shuriken_dir.join("pwd.txt");
+            for _ in 0..50 {
+                if fs::read_to_string(&pwd_file).is_ok_and(|s| !s.is_empty()) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert_eq!(
+                PathBuf::from(fs::read_to_string(&pwd_file).unwrap().trim()),
+                shuriken_dir.canonicalize().unwrap()
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_env_overrides_reach_spawned_process() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "greeter");
+        fs::write(
+            shuriken_dir.join(".ninja/greeter.ns"),
+            "function start()\n\
+                 proc.spawn('printf %s \"$EXTRA\" > extra.txt')\n\
+                 return proc.spawn({ program = 'sh', args = { '-c', 'printf %s \"$GREETING\" > greeting.txt' }, env = { GREETING = 'script' } })\n\
+             end\n\
+             function stop() end\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let overrides = StartOverrides {
+            env: HashMap::from([
+                ("GREETING".to_string(), "override".to_string()),
+                ("EXTRA".to_string(), "a=b".to_string()),
+            ]),
+            ..Default::default()
+        };
+        manager
+            .start_with_overrides("greeter", &overrides)
+            .await
+            .unwrap();
+
+        let read = |file: &str| fs::read_to_string(shuriken_dir.join(file)).ok();
+        for _ in 0..50 {
+            if read("greeting.txt").is_some_and(|s| !s.is_empty()) && read("extra.txt").is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(read("greeting.txt").as_deref(), Some("override"));
+        assert_eq!(read("extra.txt").as_deref(), Some("a=b"));
+    }
+
+    #[test]
+    fn test_parse_env_override_validates_format() {
+        assert_eq!(
+            StartOverrides::parse_env("KEY=a=b"),
+            Ok(("KEY".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            StartOverrides::parse_env("EMPTY="),
+            Ok(("EMPTY".to_string(), String::new()))
+        );
+        assert!(StartOverrides::parse_env("NOVALUE").is_err());
+        assert!(StartOverrides::parse_env("=value").is_err());
+        assert!(StartOverrides::parse_env("BAD KEY=1").is_err());
+    }
+
+    /// Lists the entries of a forged package's archive with their type.
+    fn packed_entries(package: &Path) -> HashMap<PathBuf, tar::EntryType> {
+        let bytes = fs::read(package).unwrap();
+        // 6 magic bytes, u16 metadata length, metadata, u64 archive length, archive
+        let meta_len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        let start = 8 + meta_len;
+        let archive_len = u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap()) as usize;
+        let archive = &bytes[start + 8..start + 8 + archive_len];
+        let tar_stream = ArchiveFormat::detect(archive)
+            .unwrap()
+            .decoder(archive)
+            .unwrap();
+
+        tar::Archive::new(tar_stream)
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let path = entry.path().unwrap();
+                let path = path.strip_prefix(".").unwrap_or(&path).to_path_buf();
+                (path, entry.header().entry_type())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_forge_skips_ninjaignored_and_excluded_files() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        fs::write(shuriken_dir.join("main.lua"), "return 1").unwrap();
+        fs::write(shuriken_dir.join("debug.log"), "noise").unwrap();
+        fs::create_dir_all(shuriken_dir.join("target/cache")).unwrap();
+        fs::write(shuriken_dir.join("target/app.bin"), vec![0u8; 4096]).unwrap();
+        fs::write(shuriken_dir.join("target/cache/blob"), "cached").unwrap();
+        fs::write(shuriken_dir.join(".ninjaignore"), "target/\n").unwrap();
+        fs::create_dir_all(shuriken_dir.join("data")).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("main.lua", shuriken_dir.join("entry.lua")).unwrap();
+        let manager = manager_in(dir.path()).await;
+
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: "any".into(),
+        };
+        let filter = ForgeFilter {
+            include: Vec::new(),
+            exclude: vec!["*.log".into()],
+        };
+        let output = dir.path().join("out");
+        let report = manager
+            .forge_with_options(
+                meta,
+                "demo".into(),
+                Some(output.clone()),
+                &ForgeOptions {
+                    filter,
+                    sign_key: None,
+                },
+            )
+            .await
+            .unwrap()
+            .report;
+
+        let packed = ["main.lua", ".ninja/manifest.toml", ".ninja/demo.ns"];
+        let expected_size: u64 = packed
+            .iter()
+            .map(|file| fs::metadata(shuriken_dir.join(file)).unwrap().len())
+            .sum();
+        assert_eq!(report.file_count, packed.len());
+        assert_eq!(report.total_size, expected_size);
+
+        let entries = packed_entries(&output.join("demo-any.shuriken"));
+        for file in packed {
+            assert_eq!(entries.get(Path::new(file)), Some(&tar::EntryType::Regular));
+        }
+        assert_eq!(
+            entries.get(Path::new("data")),
+            Some(&tar::EntryType::Directory)
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            entries.get(Path::new("entry.lua")),
+            Some(&tar::EntryType::Symlink)
+        );
+        assert!(!entries.contains_key(Path::new("debug.log")));
+        assert!(
+            entries.keys().all(|path| !path.starts_with("target")),
+            "{entries:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forge_packs_directories_and_symlinks() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(&dir.path().join("source"), "demo");
+        fs::write(shuriken_dir.join("main.lua"), "return 1").unwrap();
+        fs::create_dir_all(shuriken_dir.join("data/empty")).unwrap();
+        std::os::unix::fs::symlink("main.lua", shuriken_dir.join("entry.lua")).unwrap();
+        let fifo = std::process::Command::new("mkfifo")
+            .arg(shuriken_dir.join("pipe"))
+            .status()
+            .unwrap();
+        assert!(fifo.success());
+
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let output = dir.path().join("out");
+        let forged = manager_in(&dir.path().join("source"))
+            .await
+            .forge(meta, "demo".into(), Some(output))
+            .await
+            .unwrap();
+        assert_eq!(forged.report.link_count, 1);
+        assert_eq!(forged.report.skipped, vec![PathBuf::from("pipe")]);
+
+        let manager = manager_in(&dir.path().join("installed")).await;
+        manager.config.write().await.allow_symlinks = true;
+        manager
+            .install(forged.path.to_str().unwrap(), SilentReporter)
+            .await
+            .unwrap();
+        let installed = manager.root_path.join("shurikens/demo");
+        assert!(installed.join("data/empty").is_dir());
+        assert_eq!(
+            fs::read_link(installed.join("entry.lua")).unwrap(),
+            PathBuf::from("main.lua")
+        );
+        assert!(!installed.join("pipe").exists());
+    }
+
+    #[tokio::test]
+    async fn test_forge_returns_package_path_size_and_checksum() {
+        use sha2::{Digest, Sha256};
+
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: "any".into(),
+        };
+
+        let output = dir.path().join("out");
+        let forged = manager
+            .forge(meta, "demo".into(), Some(output.clone()))
+            .await
+            .unwrap();
+        assert_eq!(forged.path, output.join("demo-any.shuriken"));
+        assert!(forged.path.is_file());
+        assert_eq!(forged.size, fs::metadata(&forged.path).unwrap().len());
+        let digest = Sha256::digest(fs::read(&forged.path).unwrap());
+        assert_eq!(forged.checksum, signing::to_hex(&digest));
+        assert!(forged.report.file_count > 0);
+    }
+
+    #[tokio::test]
+    async fn test_forge_from_toml_manifest() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        let manifest = dir.path().join("forge.toml");
+        fs::write(
+            &manifest,
+            "id = \"demo\"\n\
+             name = \"Demo\"\n\
+             version = \"2.0.0\"\n\
+             platform = \"any\"\n\
+             authors = [\"Jane Doe\"]\n",
+        )
+        .unwrap();
+
+        let meta = ArmoryMetadata::from_manifest(&fs::read_to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(meta.version, "2.0.0");
+        assert_eq!(meta.authors, Some(vec!["Jane Doe".to_string()]));
+
+        let output = dir.path().join("out");
+        let forged = manager
+            .forge(meta, "demo".into(), Some(output.clone()))
+            .await
+            .unwrap();
+        assert_eq!(forged.path, output.join("demo-any.shuriken"));
+        assert!(forged.path.is_file());
+
+        let err = ArmoryMetadata::from_manifest("id = \"demo\"\nname = \"Demo\"\n").unwrap_err();
+        assert!(err.contains("Malformed forge manifest"));
+        assert!(err.contains("version"));
+        assert!(ArmoryMetadata::from_manifest("id = demo").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disk_usage_sums_shuriken_files() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        fs::create_dir_all(shuriken_dir.join("data/nested")).unwrap();
+        fs::write(shuriken_dir.join("data/a.bin"), vec![0u8; 1000]).unwrap();
+        fs::write(shuriken_dir.join("data/nested/b.bin"), vec![0u8; 2500]).unwrap();
+        #[cfg(unix)]
+        {
+            // Neither a loop nor a hard link may be counted twice
+            std::os::unix::fs::symlink(&shuriken_dir, shuriken_dir.join("data/loop")).unwrap();
+            fs::hard_link(
+                shuriken_dir.join("data/a.bin"),
+                shuriken_dir.join("data/a-link.bin"),
+            )
+            .unwrap();
+        }
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let manifest = fs::metadata(shuriken_dir.join(".ninja/manifest.toml"))
+            .unwrap()
+            .len();
+        let script = fs::metadata(shuriken_dir.join(".ninja/demo.ns"))
+            .unwrap()
+            .len();
+        let mut expected = manifest + script + 3500;
+        #[cfg(unix)]
+        {
+            expected += fs::symlink_metadata(shuriken_dir.join("data/loop"))
+                .unwrap()
+                .len();
+        }
+
+        assert_eq!(manager.disk_usage("demo").await.unwrap(), expected);
+        let all = manager.disk_usage_all().await.unwrap();
+        assert_eq!(all.get("demo"), Some(&expected));
+        assert!(manager.disk_usage("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_clone_shuriken_gets_fresh_name_and_state() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        manager.start("demo").await.unwrap();
+
+        // The name follows the directory, not how it was spelled
+        manager.clone_shuriken("demo", "Demo2").await.unwrap();
+        assert!(dir.path().join("shurikens/demo2").is_dir());
+
+        let clone = manager.get("demo2".into()).await.unwrap();
+        assert_eq!(clone.metadata.name, "demo2");
+        assert_eq!(clone.metadata.id, "demo2");
+        assert!(
+            !dir.path()
+                .join("shurikens/demo2/.ninja/shuriken.lck")
+                .exists()
+        );
+
+        let states = manager.list(true).await.unwrap().left().unwrap();
+        assert!(states.contains(&("demo".to_string(), ShurikenState::Running)));
+        assert!(states.contains(&("demo2".to_string(), ShurikenState::Idle)));
+
+        assert!(manager.clone_shuriken("demo", "demo2").await.is_err());
+        manager.stop("demo").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_and_stop_receive_context_argument() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        let log = dir.path().join("ctx.log");
+        fs::write(
+            shuriken_dir.join(".ninja/demo.ns"),
+            format!(
+                "function start(ctx) fs.append({0:?}, 'start:' .. ctx.name .. '\\n') end\n\
+                 function stop(ctx) fs.append({0:?}, 'stop:' .. ctx.name .. '\\n') end\n",
+                log.display().to_string()
+            ),
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("demo").await.unwrap();
+        manager.stop("demo").await.unwrap();
+
+        assert_eq!(fs::read_to_string(&log).unwrap(), "start:demo\nstop:demo\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pause_and_resume_freeze_the_recorded_process() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "sleepy");
+        fs::write(
+            shuriken_dir.join(".ninja/sleepy.ns"),
+            "function start(ctx) return proc.spawn('exec sleep 30') end\n\
+             function stop(ctx) end\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        // Only running shurikens can be paused
+        assert!(manager.pause("sleepy").await.is_err());
+
+        manager.start("sleepy").await.unwrap();
+        let shuriken = manager.get("sleepy".to_string()).await.unwrap();
+        let pid = shuriken
+            .pid(&shuriken_dir)
+            .await
+            .expect("start should record the spawned PID");
+        let process_state = |pid: u32| {
+            let output = std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", &pid.to_string()])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+
+        manager.pause("sleepy").await.unwrap();
+        assert_eq!(*shuriken.state.lock().await, ShurikenState::Paused);
+        assert!(process_state(pid).starts_with('T'));
+        assert!(manager.pause("sleepy").await.is_err());
+
+        manager.resume("sleepy").await.unwrap();
+        assert_eq!(*shuriken.state.lock().await, ShurikenState::Running);
+        assert!(!process_state(pid).starts_with('T'));
+        assert!(manager.resume("sleepy").await.is_err());
+
+        manager.stop("sleepy").await.unwrap();
+        std::process::Command::new("kill")
+            .arg(pid.to_string())
+            .status()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_listener_sees_old_and_new_state() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        manager.set_state_listener(move |name, old, new| {
+            sink.lock().unwrap().push((name.to_string(), old, new));
+        });
+
+        manager.start("demo").await.unwrap();
+        assert_eq!(
+            seen.lock().unwrap().as_slice(),
+            &[(
+                "demo".to_string(),
+                ShurikenState::Idle,
+                ShurikenState::Running
+            )]
+        );
+
+        manager.stop("demo").await.unwrap();
+        assert_eq!(
+            seen.lock().unwrap().last(),
+            Some(&(
+                "demo".to_string(),
+                ShurikenState::Running,
+                ShurikenState::Idle
+            ))
+        );
+    }
+
+    #[test]
+    fn test_state_transitions_reject_illegal_moves() {
+        use ShurikenState::*;
+
+        let lifecycle = [Idle, Running, Paused, Running, Crashed(1), Running, Idle];
+        for pair in lifecycle.windows(2) {
+            assert!(pair[0].can_transition_to(&pair[1]), "{:?}", pair);
+        }
+
+        assert!(!Idle.can_transition_to(&Paused));
+        assert!(!Idle.can_transition_to(&Crashed(1)));
+        assert!(!Crashed(1).can_transition_to(&Paused));
+        assert!(Idle.can_transition_to(&Idle));
+    }
+
+    #[tokio::test]
+    async fn test_exported_manifest_round_trips() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(
+            &shuriken_dir,
+            "ports = [8080]\n\n[config]\nconfig-path = \"conf/demo.conf\"\n",
+        );
+        fs::write(shuriken_dir.join(".ninja/options.toml"), "port = 8080\n").unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let exported = manager
+            .get("demo".to_string())
+            .await
+            .unwrap()
+            .to_manifest()
+            .unwrap();
+        assert!(exported.contains("config-path"));
+        assert!(exported.contains("script-path"));
+
+        let reparsed: Shuriken = toml::from_str(&exported).unwrap();
+        assert_eq!(reparsed.metadata.name, "demo");
+        assert_eq!(reparsed.metadata.ports, Some(vec![8080]));
+        assert_eq!(
+            reparsed.config.as_ref().unwrap().config_path,
+            PathBuf::from("conf/demo.conf")
+        );
+        assert_eq!(reparsed.to_manifest().unwrap(), exported);
+    }
+
+    struct SilentReporter;
+
+    impl Reporter for SilentReporter {
+        fn stage(&self, _stage: InstallStage) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn progress(&self, _percent: u8) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_install_can_skip_failing_postinstall() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        let shuriken_dir = write_daemon_shuriken(&source_root, "demo");
+        fs::write(
+            shuriken_dir.join(".ninja/postinstall.lua"),
+            "error('postinstall needs the network')",
+        )
+        .unwrap();
+
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: Some(PathBuf::from(".ninja/postinstall.lua")),
+            platform: std::env::consts::OS.into(),
+        };
+        let output = dir.path().join("out");
+        manager_in(&source_root)
+            .await
+            .forge(meta, "demo".into(), Some(output.clone()))
+            .await
+            .unwrap();
+        let package = output.join(format!("demo-{}.shuriken", std::env::consts::OS));
+        let package = package.to_str().unwrap();
+
+        let offline = manager_in(&dir.path().join("offline")).await;
+        let options = InstallOptions {
+            run_postinstall: false,
+            ..Default::default()
+        };
+        offline
+            .install_with_options(package, SilentReporter, &options)
+            .await
+            .unwrap();
+        assert!(offline.get("demo".into()).await.is_ok());
+
+        let online = manager_in(&dir.path().join("online")).await;
+        assert!(online.install(package, SilentReporter).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_install_bytes_from_memory() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        write_daemon_shuriken(&source_root, "demo");
+
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let output = dir.path().join("out");
+        manager_in(&source_root)
+            .await
+            .forge(meta, "demo".into(), Some(output.clone()))
+            .await
+            .unwrap();
+        let bytes =
+            fs::read(output.join(format!("demo-{}.shuriken", std::env::consts::OS))).unwrap();
+
+        let target_root = dir.path().join("target");
+        let manager = manager_in(&target_root).await;
+        manager
+            .install_bytes(&bytes, SilentReporter, &InstallOptions::default())
+            .await
+            .unwrap();
+        assert!(manager.get("demo".into()).await.is_ok());
+        assert!(
+            target_root
+                .join("shurikens/demo/.ninja/manifest.toml")
+                .exists()
+        );
+
+        let garbage = manager_in(&dir.path().join("garbage")).await;
+        assert!(
+            garbage
+                .install_bytes(
+                    b"not a shuriken",
+                    SilentReporter,
+                    &InstallOptions::default()
+                )
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manifest_config_path_survives_forge_and_reload() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        let shuriken_dir = write_daemon_shuriken(&source_root, "demo");
+        let content = fs::read_to_string(shuriken_dir.join(".ninja/manifest.toml")).unwrap();
+        // The Rust field name is accepted and normalized
+        append_manifest(
+            &shuriken_dir,
+            "\n[config]\nconfig_path = \"conf/demo.conf\"\n",
+        );
+        fs::write(shuriken_dir.join(".ninja/config.tmpl"), "demo\n").unwrap();
+
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let output = dir.path().join("out");
+        let forged = manager_in(&source_root)
+            .await
+            .forge(meta, "demo".into(), Some(output))
+            .await
+            .unwrap();
+
+        let target_root = dir.path().join("target");
+        let manager = manager_in(&target_root).await;
+        manager
+            .install(forged.path.to_str().unwrap(), SilentReporter)
+            .await
+            .unwrap();
+        manager.refresh().await.unwrap();
+        let installed = manager.get("demo".into()).await.unwrap();
+        assert_eq!(
+            installed.config.as_ref().unwrap().config_path,
+            PathBuf::from("conf/demo.conf")
+        );
+
+        // Written back under the dashed key, and read back unchanged
+        let written = installed.to_manifest().unwrap();
+        let table: toml::Table = toml::from_str(&written).unwrap();
+        assert_eq!(
+            table["config"]["config-path"].as_str(),
+            Some("conf/demo.conf")
+        );
+        assert!(table["config"].get("config_path").is_none());
+        let reloaded: Shuriken = toml::from_str(&written).unwrap();
+        assert_eq!(
+            reloaded.config.unwrap().config_path,
+            PathBuf::from("conf/demo.conf")
+        );
+
+        // A misspelled key is an error instead of a silently missing template
+        let installed_manifest = target_root.join("shurikens/demo/.ninja/manifest.toml");
+        fs::write(
+            &installed_manifest,
+            format!("{content}\n[config]\nconfig-pth = \"conf/demo.conf\"\n"),
+        )
+        .unwrap();
+        let err = load_shuriken(&target_root.join("shurikens/demo"))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("config-pth"), "{err}");
+
+        // Refreshing skips broken Shurikens instead of failing for all of
+        // them, while keys from older manifests are only warned about
+        write_daemon_shuriken(&target_root, "legacy");
+        fs::write(
+            target_root.join("shurikens/legacy/.ninja/manifest.toml"),
+            "[shuriken]\nname = \"legacy\"\nid = \"legacy\"\nversion = \"1.0.0\"\ntype = \"daemon\"\nscript-path = \"legacy.ns\"\nrequire-admin = false\n\n[shuriken.management]\ntype = \"script\"\n",
+        )
+        .unwrap();
+        write_daemon_shuriken(&target_root, "other");
+        manager.refresh().await.unwrap();
+        assert!(manager.get("demo".into()).await.is_err());
+        let legacy = manager.get("legacy".into()).await.unwrap();
+        assert_eq!(
+            legacy.metadata.script_path,
+            Some(PathBuf::from("legacy.ns"))
+        );
+        assert!(manager.get("other".into()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_install_replace_removes_stale_files_and_keeps_options() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        let source_dir = write_daemon_shuriken(&source_root, "demo");
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let forge = |output: PathBuf| {
+            let source_root = source_root.clone();
+            let meta = meta.clone();
+            async move {
+                manager_in(&source_root)
+                    .await
+                    .forge(meta, "demo".into(), Some(output.clone()))
+                    .await
+                    .unwrap();
+                output
+                    .join(format!("demo-{}.shuriken", std::env::consts::OS))
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }
+        };
+
+        append_manifest(&source_dir, "\n[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(
+            source_dir.join(".ninja/config.tmpl"),
+            "port = {{ port | default(value=80) }}\n",
+        )
+        .unwrap();
+
+        fs::write(source_dir.join("stale.txt"), "old").unwrap();
+        let old_package = forge(dir.path().join("v1")).await;
+        fs::remove_file(source_dir.join("stale.txt")).unwrap();
+        fs::write(source_dir.join("fresh.txt"), "new").unwrap();
+        let new_package = forge(dir.path().join("v2")).await;
+
+        let manager = manager_in(&dir.path().join("installed")).await;
+        manager.install(&old_package, SilentReporter).await.unwrap();
+        let installed = manager.root_path.join("shurikens").join("demo");
+        fs::write(installed.join(".ninja/options.toml"), "port = 8080\n").unwrap();
+        manager.start("demo").await.unwrap();
+
+        // Without replace an existing install is left alone
+        assert!(manager.install(&new_package, SilentReporter).await.is_err());
+        assert!(installed.join("stale.txt").exists());
+
+        let replace = InstallOptions {
+            replace: true,
+            ..Default::default()
+        };
+        manager
+            .install_with_options(&new_package, SilentReporter, &replace)
+            .await
+            .unwrap();
+
+        assert!(!installed.join("stale.txt").exists());
+        assert!(installed.join("fresh.txt").exists());
+        assert_eq!(
+            fs::read_to_string(installed.join(".ninja/options.toml")).unwrap(),
+            "port = 8080\n"
+        );
+        // The reinstalled config is rendered from the kept options
+        assert_eq!(
+            fs::read_to_string(installed.join("demo.conf")).unwrap(),
+            "port = 8080\n"
+        );
+        let states = manager.list(true).await.unwrap().left().unwrap();
+        assert!(states.contains(&("demo".to_string(), ShurikenState::Idle)));
+    }
+
+    #[tokio::test]
+    async fn test_install_replace_restores_previous_install_when_postinstall_fails() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        let source_dir = write_daemon_shuriken(&source_root, "demo");
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let forge = |meta: ArmoryMetadata, output: PathBuf| {
+            let source_root = source_root.clone();
+            async move {
+                manager_in(&source_root)
+                    .await
+                    .forge(meta, "demo".into(), Some(output.clone()))
+                    .await
+                    .unwrap();
+                output
+                    .join(format!("demo-{}.shuriken", std::env::consts::OS))
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }
+        };
+
+        fs::write(source_dir.join("old.txt"), "old").unwrap();
+        let old_package = forge(meta.clone(), dir.path().join("v1")).await;
+        fs::remove_file(source_dir.join("old.txt")).unwrap();
+        fs::write(
+            source_dir.join(".ninja/postinstall.lua"),
+            "error('postinstall needs the network')",
+        )
+        .unwrap();
+        let new_meta = ArmoryMetadata {
+            version: "2.0.0".into(),
+            postinstall: Some(PathBuf::from(".ninja/postinstall.lua")),
+            ..meta
+        };
+        let new_package = forge(new_meta, dir.path().join("v2")).await;
+
+        let manager = manager_in(&dir.path().join("installed")).await;
+        manager.install(&old_package, SilentReporter).await.unwrap();
+        let installed = manager.root_path.join("shurikens").join("demo");
+        fs::write(installed.join(".ninja/options.toml"), "port = 8080\n").unwrap();
+
+        let replace = InstallOptions {
+            replace: true,
+            ..Default::default()
+        };
+        let err = manager
+            .install_with_options(&new_package, SilentReporter, &replace)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("postinstall needs the network"));
+
+        // The previous install is back in place, not half of the new one
+        assert!(installed.join("old.txt").exists());
+        assert!(!installed.join(".ninja/postinstall.lua").exists());
+        assert_eq!(
+            fs::read_to_string(installed.join(".ninja/options.toml")).unwrap(),
+            "port = 8080\n"
+        );
+        let shuriken = manager.get("demo".into()).await.unwrap();
+        assert_eq!(shuriken.metadata.version, "1.0.0");
+        let leftovers: Vec<_> = fs::read_dir(&manager.root_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().starts_with(".replaced-"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+    }
+
+    #[tokio::test]
+    async fn test_dry_start_matches_manifest_without_starting() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let plan = manager.dry_start("demo").await.unwrap();
+
+        assert_eq!(plan.shuriken_type, "daemon");
+        assert_eq!(plan.cwd, shuriken_dir);
+        assert_eq!(
+            plan.compiled_path,
+            Some(shuriken_dir.join(".ninja/demo.ns"))
+        );
+        assert_eq!(plan.function.as_deref(), Some("start"));
+        assert_eq!(plan.lockfile, shuriken_dir.join(".ninja/shuriken.lck"));
+        assert!(plan.problems.is_empty(), "{:?}", plan.problems);
+
+        assert!(!plan.lockfile.exists());
+        let states = manager.list(true).await.unwrap().left().unwrap();
+        assert!(states.contains(&("demo".to_string(), ShurikenState::Idle)));
+
+        assert_eq!(plan.args.as_ref().unwrap()["name"], "demo");
+        assert!(plan.env.is_empty());
+
+        // Per-start environment overrides show up as well
+        let overrides = StartOverrides {
+            env: HashMap::from([("MODE".to_string(), "debug".to_string())]),
+            ..Default::default()
+        };
+        let plan = manager
+            .dry_start_with_overrides("demo", &overrides)
+            .await
+            .unwrap();
+        assert_eq!(plan.env.get("MODE").map(String::as_str), Some("debug"));
+
+        fs::remove_file(shuriken_dir.join(".ninja/demo.ns")).unwrap();
+        let plan = manager.dry_start("demo").await.unwrap();
+        assert_eq!(plan.problems.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_options_schema_reads_ranges_and_choices() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        assert!(manager.options_schema("demo").await.unwrap().is_empty());
+
+        fs::write(
+            shuriken_dir.join(".ninja/schema.toml"),
+            r#"
+[port]
+type = "number"
+min = 1
+max = 65535
+default = 8080
+
+[mode]
+type = "choice"
+choices = ["dev", "prod"]
+default = "dev"
+"#,
+        )
+        .unwrap();
+
+        let schema = manager.options_schema("demo").await.unwrap();
+        assert_eq!(
+            schema.get("port"),
+            Some(&InputType::Number {
+                min: Some(1),
+                max: Some(65535),
+                step: None,
+                default: Some(8080),
+                description: None,
+            })
+        );
+        assert_eq!(
+            schema.get("mode"),
+            Some(&InputType::Choice {
+                choices: vec!["dev".into(), "prod".into()],
+                default: Some("dev".into()),
+                description: None,
+            })
+        );
+        assert!(manager.options_schema("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_options_reports_every_violation() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        fs::write(
+            shuriken_dir.join(".ninja/schema.toml"),
+            r#"
+[port]
+type = "number"
+min = 1
+max = 65535
+
+[mode]
+type = "choice"
+choices = ["dev", "prod"]
+
+[host]
+type = "text"
+pattern = "[a-z0-9.-]+"
+
+[debug]
+type = "bool"
+"#,
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let valid = HashMap::from([
+            ("port".to_string(), FieldValue::Number(8080)),
+            ("mode".to_string(), FieldValue::String("prod".into())),
+            ("host".to_string(), FieldValue::String("example.org".into())),
+            ("debug".to_string(), FieldValue::Bool(false)),
+            ("extra".to_string(), FieldValue::String("unchecked".into())),
+        ]);
+        assert!(manager.validate_options("demo", &valid).await.is_ok());
+
+        let invalid = HashMap::from([
+            ("port".to_string(), FieldValue::Number(70000)),
+            ("mode".to_string(), FieldValue::String("staging".into())),
+            ("host".to_string(), FieldValue::String("Not A Host!".into())),
+            ("debug".to_string(), FieldValue::String("yes".into())),
+        ]);
+        let errors = manager
+            .validate_options("demo", &invalid)
+            .await
+            .unwrap_err();
+        let options: Vec<&str> = errors.iter().map(|e| e.option.as_str()).collect();
+        assert_eq!(options, vec!["debug", "host", "mode", "port"]);
+        assert!(errors[3].message.contains("65535"));
+
+        // Saving runs the same checks and leaves the options untouched
+        assert!(manager.save_shuriken_config("demo", invalid).await.is_err());
+        assert!(!shuriken_dir.join(".ninja/options.toml").exists());
+        manager.save_shuriken_config("demo", valid).await.unwrap();
+        assert!(shuriken_dir.join(".ninja/options.toml").exists());
+    }
+
+    #[tokio::test]
+    async fn test_signed_packages_verify_and_reject_tampering() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        write_daemon_shuriken(&source_root, "demo");
+        let source = manager_in(&source_root).await;
+
+        let secret = dir.path().join("signing.key");
+        fs::write(&secret, signing::to_hex(&[7u8; 32])).unwrap();
+        let public = dir.path().join("signing.pub");
+        let verifying = signing::load_signing_key(&secret).unwrap().verifying_key();
+        fs::write(&public, signing::to_hex(verifying.as_bytes())).unwrap();
+
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let package_name = format!("demo-{}.shuriken", std::env::consts::OS);
+        let signed_dir = dir.path().join("signed");
+        let unsigned_dir = dir.path().join("unsigned");
+        let sign = ForgeOptions {
+            sign_key: Some(secret),
+            ..Default::default()
+        };
+        source
+            .forge_with_options(meta.clone(), "demo".into(), Some(signed_dir.clone()), &sign)
+            .await
+            .unwrap();
+        source
+            .forge(meta, "demo".into(), Some(unsigned_dir.clone()))
+            .await
+            .unwrap();
+        let signed = signed_dir.join(&package_name);
+        let unsigned = unsigned_dir.join(&package_name);
+
+        // Metadata isn't covered by the plain checksum, only by the signature
+        let mut bytes = fs::read(&signed).unwrap();
+        let at = bytes.windows(5).position(|w| w == b"1.0.0").unwrap();
+        bytes[at + 4] = b'1';
+        let tampered = dir.path().join("tampered.shuriken");
+        fs::write(&tampered, bytes).unwrap();
+
+        let verify = InstallOptions {
+            verify_key: Some(public),
+            ..Default::default()
+        };
+        let install = |root: &str, package: &Path, options: InstallOptions| {
+            let root = dir.path().join(root);
+            let package = package.to_str().unwrap().to_string();
+            async move {
+                manager_in(&root)
+                    .await
+                    .install_with_options(&package, SilentReporter, &options)
+                    .await
+            }
+        };
+
+        install("a", &signed, verify.clone()).await.unwrap();
+        let err = install("b", &tampered, verify.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("verification failed"), "{err}");
+        let err = install("c", &unsigned, verify).await.unwrap_err();
+        assert!(err.to_string().contains("not signed"), "{err}");
+        install("d", &unsigned, InstallOptions::default())
+            .await
+            .unwrap();
+        install("e", &signed, InstallOptions::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_many_sequential_respects_dependencies() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("order.log");
+        write_ordered_shuriken(dir.path(), "web", &["db"], &log);
+        write_ordered_shuriken(dir.path(), "db", &[], &log);
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let names = vec!["web".to_string(), "db".to_string()];
+        assert_eq!(
+            manager.default_bulk_mode(&names).await,
+            BulkMode::Sequential
+        );
+
+        let results = manager
+            .start_many(&names, BulkMode::Sequential, None)
+            .await
+            .unwrap();
+        assert!(results.iter().all(|(_, r)| r.is_ok()));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "db\nweb\n");
+
+        let stopped: Vec<String> = manager
+            .stop_many(&names, BulkMode::Sequential)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(stopped, vec!["web".to_string(), "db".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_start_many_parallel_keeps_input_order() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("order.log");
+        write_ordered_shuriken(dir.path(), "alpha", &[], &log);
+        write_ordered_shuriken(dir.path(), "beta", &[], &log);
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let names = vec!["beta".to_string(), "alpha".to_string()];
+        assert_eq!(
+            manager.default_bulk_mode(&names).await,
+            BulkMode::Parallel(DEFAULT_PARALLELISM)
+        );
+
+        let results = manager
+            .start_many(&names, BulkMode::Parallel(2), None)
+            .await
+            .unwrap();
+        let order: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(order, vec!["beta", "alpha"]);
+        assert!(results.iter().all(|(_, r)| r.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_starts_spawn_once() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("order.log");
+        write_ordered_shuriken(dir.path(), "apache", &[], &log);
+        write_ordered_shuriken(dir.path(), "mysql", &[], &log);
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let (first, second, other) = tokio::join!(
+            manager.start("apache"),
+            manager.start("Apache"),
+            manager.start("mysql")
+        );
+        assert!(other.is_ok());
+
+        let errors: Vec<_> = [first, second]
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(error_code(&errors[0]), Some("already_running"));
+
+        let log = fs::read_to_string(&log).unwrap();
+        assert_eq!(log.lines().filter(|l| *l == "apache").count(), 1);
+        assert_eq!(log.lines().filter(|l| *l == "mysql").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_start_order_detects_cycles() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("order.log");
+        write_ordered_shuriken(dir.path(), "ping", &["pong"], &log);
+        write_ordered_shuriken(dir.path(), "pong", &["ping"], &log);
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let names = vec!["ping".to_string(), "pong".to_string()];
+        assert!(manager.start_order(&names).await.is_err());
+
+        let err = manager.start_with_deps("ping").await.unwrap_err();
+        assert!(err.to_string().contains("ping -> pong -> ping"));
+    }
+
+    #[tokio::test]
+    async fn test_start_with_deps_starts_prerequisites_first() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("order.log");
+        write_ordered_shuriken(dir.path(), "web", &["db", "cache"], &log);
+        write_ordered_shuriken(dir.path(), "db", &["cache"], &log);
+        write_ordered_shuriken(dir.path(), "cache", &[], &log);
+        write_ordered_shuriken(dir.path(), "worker", &["cache"], &log);
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("cache").await.unwrap();
+        manager.start("worker").await.unwrap();
+        manager.start_with_deps("web").await.unwrap();
+        let log_text = fs::read_to_string(&log).unwrap();
+        assert_eq!(
+            log_text.lines().collect::<Vec<_>>(),
+            vec!["cache", "worker", "db", "web"]
+        );
+
+        manager.stop_with_deps("web").await.unwrap();
+        let states = manager.list(true).await.unwrap().left().unwrap();
+        assert!(states.contains(&("web".to_string(), ShurikenState::Idle)));
+        assert!(states.contains(&("db".to_string(), ShurikenState::Idle)));
+        // Still needed by worker
+        assert!(states.contains(&("cache".to_string(), ShurikenState::Running)));
+    }
+
+    #[tokio::test]
+    async fn test_remove_many_by_pattern_stops_running_first() {
+        let dir = tempdir().unwrap();
+        for name in ["test-api", "test-db", "prod-api"] {
+            write_daemon_shuriken(dir.path(), name);
+        }
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        manager.start("test-api").await.unwrap();
+
+        let names = manager
+            .matching_names(&["test-*".to_string(), "test-db".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(names, vec!["test-api", "test-db"]);
+        let err = manager
+            .matching_names(&["ghost".to_string()])
+            .await
+            .unwrap_err();
+        assert_eq!(error_code(&err), Some("not_found"));
+
+        let results = manager.remove_many(&names).await.unwrap();
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        let remaining = manager.list(false).await.unwrap().right().unwrap();
+        assert_eq!(remaining, vec!["prod-api"]);
+        assert!(!dir.path().join("shurikens/test-api").exists());
+        assert!(dir.path().join("shurikens/prod-api").exists());
+    }
+
+    #[tokio::test]
+    async fn test_start_delay_holds_back_running_state() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "sluggish");
+        append_manifest(&shuriken_dir, "start-delay = 1\n");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let started = std::time::Instant::now();
+        manager.start("sluggish").await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(
+            manager.status("sluggish").await.unwrap(),
+            ShurikenState::Running
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_port_blocks_start_until_it_opens() {
+        let dir = tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "app");
+        append_manifest(
+            &shuriken_dir,
+            &format!("start-timeout = 5\nwait-for = {{ port = {port} }}\n"),
+        );
+        let never = write_daemon_shuriken(dir.path(), "stuck");
+        append_manifest(
+            &never,
+            "start-timeout = 1\nwait-for = { file = \"run/never.sock\" }\n",
+        );
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let opener = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(500));
+            let listener = std::net::TcpListener::bind(("127.0.0.1", port)).unwrap();
+            std::thread::sleep(Duration::from_secs(2));
+            drop(listener);
+        });
+        let started = std::time::Instant::now();
+        manager.start("app").await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        assert_eq!(manager.status("app").await.unwrap(), ShurikenState::Running);
+        opener.join().unwrap();
+
+        let err = manager.start("stuck").await.unwrap_err();
+        assert!(err.to_string().contains("waiting for file run/never.sock"));
+        assert_eq!(manager.status("stuck").await.unwrap(), ShurikenState::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_shuriken_does_not_block_the_one_it_waits_on() {
+        let dir = tempdir().unwrap();
+        let ready = dir.path().join("ready");
+        let app = write_daemon_shuriken(dir.path(), "app");
+        append_manifest(
+            &app,
+            &format!(
+                "start-timeout = 5\nwait-for = {{ file = \"{}\" }}\n",
+                ready.display()
+            ),
+        );
+        let owner = write_daemon_shuriken(dir.path(), "owner");
+        fs::write(
+            owner.join(".ninja/owner.ns"),
+            format!(
+                "function start() fs.write(\"{}\", \"up\") end\nfunction stop() end\n",
+                ready.display()
+            ),
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        // The owner only starts once the app is already waiting on it
+        let (app_started, owner_started) = tokio::join!(manager.start("app"), async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            manager.start("owner").await
+        });
+        owner_started.unwrap();
+        app_started.unwrap();
+        assert_eq!(manager.status("app").await.unwrap(), ShurikenState::Running);
+    }
+
+    /// Changing the cwd affects every test in the process, so the install
+    /// runs in a child process that executes only this test.
+    #[tokio::test]
+    async fn test_install_survives_deleted_cwd() {
+        const CHILD: &str = "NINJA_TEST_DELETED_CWD";
+        if std::env::var_os(CHILD).is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "ninja_api_integration_tests::test_install_survives_deleted_cwd",
+                    "--exact",
+                    "--test-threads=1",
+                ])
+                .env(CHILD, "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        write_daemon_shuriken(&source_root, "demo");
+        let meta = ArmoryMetadata {
+            id: "demo".into(),
+            name: "demo".into(),
+            version: "1.0.0".into(),
+            synopsis: None,
+            description: None,
+            authors: None,
+            license: None,
+            repository: None,
+            postinstall: None,
+            platform: std::env::consts::OS.into(),
+        };
+        let root = dir.path().join("installed");
+        manager_in(&source_root)
+            .await
+            .forge(meta, "demo".into(), Some(root.clone()))
+            .await
+            .unwrap();
+        let manager = manager_in(&root).await;
+
+        let doomed = tempdir().unwrap();
+        std::env::set_current_dir(doomed.path()).unwrap();
+        fs::remove_dir_all(doomed.path()).unwrap();
+        assert!(std::env::current_dir().is_err());
+
+        // A relative package path can't be resolved, so it isn't guessed at
+        let package = format!("demo-{}.shuriken", std::env::consts::OS);
+        let err = manager.install(&package, SilentReporter).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("the current directory is unavailable"),
+            "{err}"
+        );
+        assert!(!root.join("shurikens/demo").exists());
+
+        let package = root.join(&package);
+        manager
+            .install(package.to_str().unwrap(), SilentReporter)
+            .await
+            .unwrap();
+        assert!(root.join("shurikens/demo/.ninja/manifest.toml").exists());
+        manager.start("demo").await.unwrap();
+        manager.stop("demo").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_history_records_start_then_stop() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        assert!(manager.history("demo", 10).is_empty());
+        manager.start("demo").await.unwrap();
+        manager.stop("demo").await.unwrap();
+
+        let events = manager.history("demo", 10);
+        let kinds: Vec<_> = events.iter().map(|e| e.event).collect();
+        assert_eq!(
+            kinds,
+            vec![LifecycleEventKind::Started, LifecycleEventKind::Stopped]
+        );
+        assert!(events[0].timestamp <= events[1].timestamp);
+
+        let latest = manager.history("demo", 1);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].event, LifecycleEventKind::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_is_running_follows_start_and_stop() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        assert!(!manager.is_running("demo").await);
+        manager.start("demo").await.unwrap();
+        assert!(manager.is_running("demo").await);
+        assert!(manager.is_running("Demo").await);
+        manager.stop("demo").await.unwrap();
+        assert!(!manager.is_running("demo").await);
+        assert!(!manager.is_running("ghost").await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_cleans_stale_lockfile() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+
+        // A PID that was alive once but has exited since
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        let ninja_dir = shuriken_dir.join(".ninja");
+        fs::create_dir_all(&ninja_dir).unwrap();
+        fs::write(
+            ninja_dir.join("shuriken.lck"),
+            format!("{{\"pid\": {}}}", dead_pid),
+        )
+        .unwrap();
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        assert_eq!(manager.stale_lock("demo").await, Some(dead_pid));
+
+        // Without a terminal there's no prompt, the lock is cleaned silently
+        manager.start("demo").await.unwrap();
+        assert!(manager.is_running("demo").await);
+        assert_eq!(manager.stale_lock("demo").await, None);
+        manager.stop("demo").await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_adopt_running_process() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        write_daemon_shuriken(dir.path(), "other");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let mut dead = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = dead.id();
+        dead.wait().unwrap();
+        assert!(manager.adopt("other", dead_pid).await.is_err());
+        assert!(!manager.is_running("other").await);
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        manager.adopt("demo", child.id()).await.unwrap();
+
+        assert!(manager.is_running("demo").await);
+        let shuriken = manager.get("demo".to_string()).await.unwrap();
+        assert_eq!(shuriken.pid(&shuriken_dir).await, Some(child.id()));
+        let started = shuriken.start_time(&shuriken_dir).await.unwrap();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        assert!(started.timestamp() <= now);
+
+        // A second adoption would clobber the lockfile
+        let err = manager.adopt("demo", child.id()).await.unwrap_err();
+        assert_eq!(error_code(&err), Some("already_running"));
+
+        // Still running after reloading from disk
+        manager.refresh().await.unwrap();
+        assert!(manager.is_running("demo").await);
+
+        // Stopping terminates the adopted process instead of leaving it behind
+        manager.stop("demo").await.unwrap();
+        assert!(!manager.is_running("demo").await);
+        assert!(!shuriken_dir.join(".ninja").join("shuriken.lck").exists());
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exited_process_marks_shuriken_crashed_or_idle() {
+        let dir = tempdir().unwrap();
+        for (name, code) in [("crasher", 3), ("quitter", 0)] {
+            let shuriken_dir = write_daemon_shuriken(dir.path(), name);
+            fs::write(
+                shuriken_dir.join(format!(".ninja/{name}.ns")),
+                format!(
+                    "function start()\n\
+                         return proc.spawn({{ program = 'sh', args = {{ '-c', 'sleep 0.3; exit {code}' }} }})\n\
+                     end\n\
+                     function stop() end\n"
+                ),
+            )
+            .unwrap();
+        }
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("crasher").await.unwrap();
+        manager.start("quitter").await.unwrap();
+        assert!(manager.is_running("crasher").await);
+
+        let states = || async {
+            let mut states = manager.list(true).await.unwrap().left().unwrap();
+            states.sort_by(|a, b| a.0.cmp(&b.0));
+            states
+        };
+        let expected = vec![
+            ("crasher".to_string(), ShurikenState::Crashed(3)),
+            ("quitter".to_string(), ShurikenState::Idle),
+        ];
+        for _ in 0..50 {
+            if states().await == expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(states().await, expected);
+        assert!(
+            !dir.path()
+                .join("shurikens/crasher/.ninja/shuriken.lck")
+                .exists()
+        );
+
+        // A crashed Shuriken can be started again
+        manager.start("crasher").await.unwrap();
+        assert!(manager.is_running("crasher").await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_manager_checks_transitions_against_the_current_state() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "crasher");
+        fs::write(
+            shuriken_dir.join(".ninja/crasher.ns"),
+            "function start()\n\
+                 return proc.spawn({ program = 'sh', args = { '-c', 'sleep 0.3; exit 3' } })\n\
+             end\n\
+             function stop() end\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("crasher").await.unwrap();
+        let mut events = manager.subscribe_states();
+
+        // Something else moved it to Idle before the process died, so the
+        // watcher's Running -> Crashed is now an Idle -> Crashed
+        let shuriken = manager.get("crasher".to_string()).await.unwrap();
+        *shuriken.state.lock().await = ShurikenState::Idle;
+
+        // The watcher saw the exit but its transition was refused
+        let lock_path = shuriken_dir.join(".ninja/shuriken.lck");
+        for _ in 0..50 {
+            if !lock_path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(!lock_path.exists());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(*shuriken.state.lock().await, ShurikenState::Idle);
+        assert!(events.try_recv().is_err());
+
+        // Pausing reads the real state too
+        let err = manager.pause("crasher").await.unwrap_err();
+        assert_eq!(error_code(&err), Some("not_running"));
+    }
+
+    #[test]
+    fn test_restart_policy_backoff() {
+        let policy = RestartPolicy::OnFailure {
+            max_retries: 3,
+            backoff_secs: 2,
+        };
+        assert_eq!(policy.restart_delay(1, 1), Some(Duration::from_secs(2)));
+        assert_eq!(policy.restart_delay(1, 2), Some(Duration::from_secs(4)));
+        assert_eq!(policy.restart_delay(1, 3), Some(Duration::from_secs(8)));
+        assert_eq!(policy.restart_delay(1, 4), None);
+        assert_eq!(policy.restart_delay(0, 1), None);
+        assert_eq!(
+            RestartPolicy::Always.restart_delay(0, 1),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            RestartPolicy::Always.restart_delay(0, 3),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            RestartPolicy::Always.restart_delay(0, 100),
+            Some(MAX_ALWAYS_BACKOFF)
+        );
+        assert_eq!(RestartPolicy::default().restart_delay(1, 1), None);
+
+        // Only quick exits count as restarts in a row
+        assert_eq!(RestartPolicy::next_attempt(0, Duration::ZERO), 1);
+        assert_eq!(RestartPolicy::next_attempt(2, Duration::from_secs(1)), 3);
+        assert_eq!(RestartPolicy::next_attempt(2, STABLE_RUN), 1);
+        // A long-lived service that crashes now and then never runs out
+        let mut restarts = 0;
+        for _ in 0..10 {
+            restarts = RestartPolicy::next_attempt(restarts, STABLE_RUN * 2);
+            assert!(policy.restart_delay(1, restarts).is_some());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_on_failure_policy_restarts_until_retries_run_out() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "flaky");
+        append_manifest(
+            &shuriken_dir,
+            "\n[shuriken.restart.on-failure]\nmax-retries = 2\nbackoff-secs = 0\n",
+        );
+        fs::write(
+            shuriken_dir.join(".ninja/flaky.ns"),
+            "function start()\n\
+                 store.set('starts', (store.get('starts') or 0) + 1)\n\
+                 return proc.spawn({ program = 'sh', args = { '-c', 'sleep 0.2; exit 5' } })\n\
+             end\n\
+             function stop() end\n",
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        let shuriken = manager.get("flaky".to_string()).await.unwrap();
+        assert_eq!(
+            shuriken.metadata.restart,
+            Some(RestartPolicy::OnFailure {
+                max_retries: 2,
+                backoff_secs: 0
+            })
+        );
+
+        let starts = || {
+            let store = fs::read_to_string(shuriken_dir.join(".ninja/store.json"))
+                .unwrap_or_else(|_| "{}".into());
+            serde_json::from_str::<serde_json::Value>(&store).unwrap()["starts"]
+                .as_u64()
+                .unwrap_or(0)
+        };
+
+        manager.start("flaky").await.unwrap();
+        for _ in 0..100 {
+            if starts() == 3 && !manager.is_running("flaky").await {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        // The first start plus two restarts, then it stays down
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(starts(), 3);
+        assert_eq!(*shuriken.state.lock().await, ShurikenState::Crashed(5));
+        assert_eq!(manager.state_events.restarts("flaky"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_restart_stops_then_starts() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        // Not running yet, so this only starts it
+        manager.restart("demo").await.unwrap();
+        assert!(manager.is_running("demo").await);
+
+        let mut changes = manager.subscribe_states();
+        manager.restart("demo").await.unwrap();
+        assert!(manager.is_running("demo").await);
+        assert_eq!(changes.try_recv().unwrap().state, ShurikenState::Idle);
+        assert_eq!(changes.try_recv().unwrap().state, ShurikenState::Running);
+        assert!(changes.try_recv().is_err());
+        manager.stop("demo").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refresh_announces_removed_shurikens() {
+        let dir = tempdir().unwrap();
+        let gone_dir = write_daemon_shuriken(dir.path(), "gone");
+        write_daemon_shuriken(dir.path(), "kept");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        manager.start("gone").await.unwrap();
+        manager.stop("gone").await.unwrap();
+        assert_eq!(manager.history("gone", 10).len(), 2);
+
+        let mut changes = manager.subscribe_states();
+        fs::remove_dir_all(&gone_dir).unwrap();
+        manager.refresh().await.unwrap();
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.name, "gone");
+        assert_eq!(change.state, ShurikenState::Idle);
+        assert!(change.removed);
+        assert!(changes.try_recv().is_err());
+        assert!(manager.history("gone", 10).is_empty());
+
+        // Ordinary changes don't carry the flag
+        manager.start("kept").await.unwrap();
+        let change = changes.try_recv().unwrap();
+        assert!(!change.removed);
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({ "name": "kept", "state": "Running" })
+        );
+        manager.stop("kept").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_restart_reports_failed_start() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        // Refuses every start after the first one
+        fs::write(
+            shuriken_dir.join(".ninja/demo.ns"),
+            r#"function start()
+                local starts = (store.get("starts") or 0) + 1
+                store.set("starts", starts)
+                if starts > 1 then
+                    error("second start refused")
+                end
+            end
+
+            function stop()
+            end
+            "#,
+        )
+        .unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("demo").await.unwrap();
+        let err = manager.restart("demo").await.unwrap_err().to_string();
+        assert!(
+            err.contains("was stopped but failed to start again"),
+            "{err}"
+        );
+        assert!(err.contains("second start refused"), "{err}");
+        assert!(!manager.is_running("demo").await);
+    }
+
+    #[tokio::test]
+    async fn test_start_profile_runs_selected_script() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        let ninja_dir = shuriken_dir.join(".ninja");
+        for profile in ["dev", "prod"] {
+            append_manifest(
+                &shuriken_dir,
+                &format!("\n[shuriken.profiles.{profile}]\nscript-path = \"{profile}.ns\"\n"),
+            );
+            fs::write(
+                ninja_dir.join(format!("{profile}.ns")),
+                format!(
+                    r#"function start(ctx)
+                        fs.write(ctx.dir .. "/started", "{profile}")
+                    end
+
+                    function stop(ctx)
+                        fs.write(ctx.dir .. "/stopped", "{profile}")
+                    end
+                    "#
+                ),
+            )
+            .unwrap();
+        }
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        assert!(manager.start_profile("demo", "staging").await.is_err());
+        assert!(!manager.is_running("demo").await);
+
+        manager.start_profile("demo", "prod").await.unwrap();
+        assert!(manager.is_running("demo").await);
+        assert_eq!(
+            fs::read_to_string(shuriken_dir.join("started")).unwrap(),
+            "prod"
+        );
+
+        // Stopping runs the profile's script, not the base one
+        manager.stop("demo").await.unwrap();
+        assert_eq!(
+            fs::read_to_string(shuriken_dir.join("stopped")).unwrap(),
+            "prod"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_doctor_reports_missing_start_script() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let broken_dir = write_daemon_shuriken(dir.path(), "broken");
+        fs::remove_file(broken_dir.join(".ninja").join("broken.ns")).unwrap();
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let report = manager.doctor().await;
+        assert!(!report.ok);
+
+        let status = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.status)
+        };
+        assert_eq!(status("shurikens directory"), Some(CheckStatus::Pass));
+        assert_eq!(status("demo: start script"), Some(CheckStatus::Pass));
+        assert_eq!(status("broken: start script"), Some(CheckStatus::Fail));
+        assert_eq!(status("broken: lockfile"), Some(CheckStatus::Pass));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["ok"], false);
+        for check in json["checks"].as_array().unwrap() {
+            assert!(check["name"].is_string());
+            assert!(["pass", "warn", "fail"].contains(&check["status"].as_str().unwrap()));
+            assert!(check["detail"].is_string());
+        }
+
+        fs::write(broken_dir.join(".ninja").join("broken.ns"), "").unwrap();
+        assert!(manager.doctor().await.ok);
+    }
+
+    /// Serves fixed files over plain HTTP, answering 404 for other paths.
+    fn spawn_file_server(files: HashMap<&'static str, Vec<u8>>) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match files.get(path) {
+                    Some(body) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &b""[..]),
+                };
+                let head = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(body);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_self_update_downloads_and_verifies_release() {
+        const BINARY: &[u8] = b"new shurikenctl\n";
+        const SHA256: &str = "d6396e2692204cf0426517b8ca2b5eaa0436037e1bffc06d71ff6747ec0e59ca";
+        let dir = tempdir().unwrap();
+        let key_file = |name: &str, seed: u8| {
+            let path = dir.path().join(name);
+            fs::write(&path, signing::to_hex(&[seed; 32])).unwrap();
+            signing::load_signing_key(&path).unwrap()
+        };
+        let release_key = key_file("release.key", 7);
+        let rogue_key = key_file("rogue.key", 9);
+        let digest = (0..SHA256.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&SHA256[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+
+        let triple = self_update::target_triple();
+        let release = |url: &str, signature: &str| {
+            format!(
+                "name: mock\n\
+                 shurikens: []\n\
+                 cli:\n  version: 9.9.9\n  binaries:\n\
+                 \x20   {triple}:\n      url: {url}\n      sha256: {SHA256}\n{signature}"
+            )
+        };
+        let signed_for = |key, version| {
+            let message = signing::release_message(self_update::RELEASE_NAME, version, &digest);
+            format!("      signature: {}\n", signing::sign_digest(key, &message))
+        };
+        let signed = |key| signed_for(key, "9.9.9");
+        let base = spawn_file_server(HashMap::from([
+            (
+                "/good/registry.yml",
+                release("bin/good", &signed(&release_key)).into_bytes(),
+            ),
+            ("/good/bin/good", BINARY.to_vec()),
+            (
+                "/bad/registry.yml",
+                release("bin/tampered", &signed(&release_key)).into_bytes(),
+            ),
+            ("/bad/bin/tampered", b"evil shurikenctl\n".to_vec()),
+            (
+                "/rogue/registry.yml",
+                release("bin/good", &signed(&rogue_key)).into_bytes(),
+            ),
+            ("/rogue/bin/good", BINARY.to_vec()),
+            // An older release's signature, announced as the newest one
+            (
+                "/replayed/registry.yml",
+                release("bin/good", &signed_for(&release_key, "1.0.0")).into_bytes(),
+            ),
+            ("/replayed/bin/good", BINARY.to_vec()),
+            (
+                "/unsigned/registry.yml",
+                release("bin/good", "").into_bytes(),
+            ),
+            ("/unsigned/bin/good", BINARY.to_vec()),
+        ]));
+
+        let manager = manager_in(dir.path()).await;
+        {
+            let mut config = manager.config.write().await;
+            config.update_registry = "mock".into();
+            config.update_key = Some(signing::to_hex(release_key.verifying_key().as_bytes()));
+        }
+        let use_registry = |name: &str| {
+            HashMap::from([("mock".to_string(), format!("{base}/{name}/registry.yml"))])
+        };
+        let bin_dir = dir.path().join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        let exe = bin_dir.join("shurikenctl");
+        fs::write(&exe, "old shurikenctl\n").unwrap();
+
+        manager.config.write().await.registries = use_registry("good");
+        assert_eq!(
+            manager.self_update("9.9.9", &exe).await.unwrap(),
+            UpdateOutcome::UpToDate {
+                version: "9.9.9".into()
+            }
+        );
+        assert_eq!(
+            manager.self_update("1.15.6", &exe).await.unwrap(),
+            UpdateOutcome::Updated {
+                from: "1.15.6".into(),
+                to: "9.9.9".into()
+            }
+        );
+        assert_eq!(fs::read(&exe).unwrap(), BINARY);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(
+                fs::metadata(&exe).unwrap().permissions().mode() & 0o111,
+                0o111
+            );
+        }
+
+        // A binary that doesn't match its checksum never replaces the old one
+        fs::write(&exe, "old shurikenctl\n").unwrap();
+        manager.config.write().await.registries = use_registry("bad");
+        let err = manager.self_update("1.15.6", &exe).await.unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{err}");
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old shurikenctl\n");
+        assert_eq!(fs::read_dir(&bin_dir).unwrap().count(), 1);
+
+        // Nor does one that isn't signed with the pinned key for this version
+        for (name, expected) in [
+            ("unsigned", "is not signed"),
+            ("rogue", "Refusing"),
+            ("replayed", "Refusing"),
+        ] {
+            manager.config.write().await.registries = use_registry(name);
+            let err = manager.self_update("1.15.6", &exe).await.unwrap_err();
+            assert!(format!("{err:#}").contains(expected), "{err:#}");
+            assert_eq!(fs::read_to_string(&exe).unwrap(), "old shurikenctl\n");
+        }
+
+        // Other registries aren't asked, and nothing is trusted without a key
+        manager.config.write().await.registries =
+            HashMap::from([("elsewhere".to_string(), format!("{base}/good/registry.yml"))]);
+        let err = manager.self_update("1.15.6", &exe).await.unwrap_err();
+        assert!(
+            err.to_string().contains("'mock' is not configured"),
+            "{err}"
+        );
+        manager.config.write().await.registries = use_registry("good");
+        manager.config.write().await.update_key = None;
+        let err = manager.self_update("1.15.6", &exe).await.unwrap_err();
+        assert!(err.to_string().contains("No update_key"), "{err}");
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old shurikenctl\n");
+        manager.config.write().await.update_key =
+            Some(signing::to_hex(release_key.verifying_key().as_bytes()));
+
+        // Nothing is downloaded for a read-only executable
+        manager.config.write().await.registries = use_registry("good");
+        let mut permissions = fs::metadata(&exe).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&exe, permissions).unwrap();
+        let err = manager.self_update("1.15.6", &exe).await.unwrap_err();
+        assert!(err.to_string().contains("read-only"), "{err}");
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old shurikenctl\n");
+    }
+
+    #[tokio::test]
+    async fn test_http_download_streams_to_disk_and_verifies() {
+        const BINARY: &[u8] = b"new shurikenctl\n";
+        const SHA256: &str = "d6396e2692204cf0426517b8ca2b5eaa0436037e1bffc06d71ff6747ec0e59ca";
+        let big: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let base = spawn_file_server(HashMap::from([
+            ("/small", BINARY.to_vec()),
+            ("/big", big.clone()),
+        ]));
+
+        let dir = tempdir().unwrap();
+        let engine = NinjaEngine::new().await.unwrap();
+        engine
+            .execute(
+                &format!(
+                    "calls, last_done, last_total = 0, 0, 0\n\
+                     written = http.download('{base}/big', 'out/big.bin', function(done, total)\n\
+                       calls = calls + 1\n\
+                       last_done, last_total = done, total\n\
+                     end)\n\
+                     http.download('{base}/small', 'small.bin', {{ size = 16, sha256 = '{SHA256}' }})\n\
+                     local ok, err = pcall(http.download, '{base}/small', 'bad.bin', {{ sha256 = 'abcd' }})\n\
+                     bad_ok, bad_err = ok, tostring(err)\n\
+                     missing_ok = pcall(http.download, '{base}/missing', 'missing.bin')"
+                ),
+                Some(dir.path()),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(dir.path().join("out/big.bin")).unwrap(), big);
+        assert_eq!(fs::read(dir.path().join("small.bin")).unwrap(), BINARY);
+
+        let globals = engine.lua.globals();
+        assert_eq!(globals.get::<u64>("written").unwrap(), big.len() as u64);
+        assert!(globals.get::<u64>("calls").unwrap() >= 1);
+        assert_eq!(globals.get::<u64>("last_done").unwrap(), big.len() as u64);
+        assert_eq!(globals.get::<u64>("last_total").unwrap(), big.len() as u64);
+
+        assert!(!globals.get::<bool>("bad_ok").unwrap());
+        assert!(
+            globals
+                .get::<String>("bad_err")
+                .unwrap()
+                .contains("Checksum mismatch")
+        );
+        assert!(!dir.path().join("bad.bin").exists());
+        assert!(!dir.path().join("bad.bin.part").exists());
+        assert!(!globals.get::<bool>("missing_ok").unwrap());
+    }
+
+    #[test]
+    fn test_is_newer_version_compares_numerically() {
+        assert!(is_newer_version("1.10.0", "1.9.2"));
+        assert!(is_newer_version("v2.0", "1.99.99"));
+        assert!(is_newer_version("1.0.1", "1.0"));
+        assert!(!is_newer_version("1.0.0", "1.0"));
+        assert!(!is_newer_version("1.2.0-beta", "1.2.0"));
+    }
+
+    #[tokio::test]
+    async fn test_status_and_info_report_one_shuriken() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        assert_eq!(manager.status("Demo").await.unwrap(), ShurikenState::Idle);
+        let info = manager.info("demo").await.unwrap();
+        assert_eq!(info.shuriken_type, "daemon");
+        assert_eq!(info.pid, None);
+        let err = manager.status("ghost").await.unwrap_err();
+        assert_eq!(error_code(&err), Some("not_found"));
+
+        manager.start("demo").await.unwrap();
+        let info = manager.info("demo").await.unwrap();
+        assert_eq!(info.state, ShurikenState::Running);
+        assert!(info.start_time.is_some());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        tokio::spawn(ninja_http::serve(
+            ninja_http::router(Arc::new(manager), shutdown.clone(), None),
+            listener,
+            None,
+            shutdown,
+        ));
+
+        let body: serde_json::Value =
+            reqwest::get(format!("http://127.0.0.1:{port}/api/shurikens/info/demo"))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+        assert_eq!(body["data"]["state"], "Running");
+        assert_eq!(body["data"]["type"], "daemon");
+
+        let response = reqwest::get(format!(
+            "http://127.0.0.1:{port}/api/shurikens/status/ghost"
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_list_states_json_reuses_snapshot_until_state_changes() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let first = manager.list_states_json().await.unwrap();
+        assert_eq!(&*first, r#"{"demo":"Idle"}"#);
+        for _ in 0..100 {
+            let again = manager.list_states_json().await.unwrap();
+            assert!(Arc::ptr_eq(&first, &again), "snapshot was rebuilt");
+        }
+
+        manager.start("demo").await.unwrap();
+        let started = manager.list_states_json().await.unwrap();
+        assert!(!Arc::ptr_eq(&first, &started));
+        assert_eq!(&*started, r#"{"demo":"Running"}"#);
+
+        manager.refresh().await.unwrap();
+        let refreshed = manager.list_states_json().await.unwrap();
+        assert!(!Arc::ptr_eq(&started, &refreshed));
+    }
+
+    #[tokio::test]
+    async fn test_api_serves_https_with_self_signed_cert() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let tls = ninja_http::TlsPaths {
+            cert: dir.path().join("cert.pem"),
+            key: dir.path().join("key.pem"),
+        };
+        fs::write(&tls.cert, certified.cert.pem()).unwrap();
+        fs::write(&tls.key, certified.key_pair.serialize_pem()).unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(Arc::new(manager), shutdown.clone(), None);
+        tokio::spawn(ninja_http::serve(app, listener, Some(tls), shutdown));
+
+        let client = reqwest::Client::builder()
+            .add_root_certificate(
+                reqwest::Certificate::from_pem(certified.cert.pem().as_bytes()).unwrap(),
+            )
+            .build()
+            .unwrap();
+        let response = client
+            .get(format!("https://localhost:{port}/api/shurikens/list"))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["data"], serde_json::json!(["demo"]));
+
+        // Plain HTTP isn't accepted on the TLS port
+        assert!(
+            client
+                .get(format!("http://localhost:{port}/api/shurikens/list"))
+                .send()
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_stop_shuts_down_gracefully() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = Arc::new(manager_in(dir.path()).await);
+        manager.refresh().await.unwrap();
+        manager.start("demo").await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(manager.clone(), shutdown.clone(), None);
+        let server = tokio::spawn(ninja_http::serve(app, listener, None, shutdown));
+
+        let client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{port}/api/stop?shurikens=true");
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED);
+        let response = client.post(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let result = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server kept running after /api/stop")
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!(manager.status("demo").await.unwrap(), ShurikenState::Idle);
+        assert!(
+            reqwest::get(format!("http://127.0.0.1:{port}/api/shurikens/list"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_streams_state_changes_as_sse() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = Arc::new(manager_in(dir.path()).await);
+        manager.refresh().await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(manager.clone(), shutdown.clone(), None);
+        let server = tokio::spawn(ninja_http::serve(app, listener, None, shutdown.clone()));
+
+        let mut events = reqwest::get(format!("http://127.0.0.1:{port}/api/shurikens/events"))
+            .await
+            .unwrap();
+        assert_eq!(
+            events.headers()["content-type"].to_str().unwrap(),
+            "text/event-stream"
+        );
+
+        manager.start("demo").await.unwrap();
+        let mut received = String::new();
+        while !received.contains("\n\n") {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), events.chunk())
+                .await
+                .expect("no event after starting")
+                .unwrap()
+                .unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert_eq!(
+            received,
+            "data: {\"name\":\"demo\",\"state\":\"Running\"}\n\n"
+        );
+
+        // An open stream must not keep the server from shutting down
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("event stream held the server open")
+            .unwrap()
+            .unwrap();
+        drop(events);
+        assert_eq!(manager.state_events.subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_queries_and_mutations() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "demo");
+        append_manifest(&shuriken_dir, "\n[config]\nconfig-path = \"demo.conf\"\n");
+        fs::write(
+            shuriken_dir.join(".ninja/config.tmpl"),
+            "port = {{ port }}\n",
+        )
+        .unwrap();
+        write_daemon_shuriken(dir.path(), "other");
+        let manager = Arc::new(manager_in(dir.path()).await);
+        manager.refresh().await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(manager.clone(), shutdown.clone(), None);
+        tokio::spawn(ninja_http::serve(app, listener, None, shutdown));
+        let client = reqwest::Client::new();
+        let graphql = |query: &str| {
+            let request = client
+                .post(format!("http://127.0.0.1:{port}/graphql"))
+                .header("content-type", "application/json")
+                .body(serde_json::json!({ "query": query }).to_string());
+            async move {
+                let response = request.send().await.unwrap();
+                assert_eq!(response.status(), reqwest::StatusCode::OK);
+                response.json::<serde_json::Value>().await.unwrap()
+            }
+        };
+
+        let body = graphql("mutation { start(name: \"demo\") { name state pid } }").await;
+        assert_eq!(body["data"]["start"]["state"], "RUNNING");
+
+        let body = graphql("{ shurikens(state: RUNNING) { name type } }").await;
+        assert_eq!(
+            body["data"]["shurikens"],
+            serde_json::json!([{ "name": "demo", "type": "daemon" }])
+        );
+        let body = graphql("{ shurikens { name state } }").await;
+        assert_eq!(body["data"]["shurikens"].as_array().unwrap().len(), 2);
+
+        let body =
+            graphql("mutation { configure(name: \"demo\", options: { port: 8080 }) { name } }")
+                .await;
+        assert!(body["errors"].is_null(), "{body}");
+        assert_eq!(
+            fs::read_to_string(shuriken_dir.join("demo.conf")).unwrap(),
+            "port = 8080\n"
+        );
+
+        let body = graphql("{ shuriken(name: \"ghost\") { name } }").await;
+        assert!(body["data"].is_null());
+        assert_eq!(body["errors"][0]["extensions"]["code"], "not_found");
+
+        let body = graphql("mutation { stop(name: \"demo\") { state } }").await;
+        assert_eq!(body["data"]["stop"]["state"], "IDLE");
+
+        let page = reqwest::get(format!("http://127.0.0.1:{port}/graphiql"))
+            .await
+            .unwrap();
+        assert_eq!(page.status(), reqwest::StatusCode::OK);
+        assert!(page.text().await.unwrap().contains("graphiql"));
+    }
+
+    #[tokio::test]
+    async fn test_api_runs_tools_and_returns_printed_output() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "mechanic");
+        append_manifest(
+            &shuriken_dir,
+            "\n[[tools]]\nname = \"greet\"\nscript = \"greet.lua\"\n",
+        );
+        fs::write(
+            shuriken_dir.join("greet.lua"),
+            "print('working')\nreturn 'hello from ' .. shuriken.name",
+        )
+        .unwrap();
+        let manager = Arc::new(manager_in(dir.path()).await);
+        manager.refresh().await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(manager.clone(), shutdown.clone(), None);
+        tokio::spawn(ninja_http::serve(app, listener, None, shutdown));
+        let client = reqwest::Client::new();
+        let run = |path: &str| {
+            client
+                .post(format!(
+                    "http://127.0.0.1:{port}/api/shurikens/tools/{path}"
+                ))
+                .send()
+        };
+
+        let response = run("mechanic/greet").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["result"], "hello from mechanic");
+        assert_eq!(body["data"]["printed"], "working\n");
+
+        let response = run("mechanic/missing").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        let response = run("ghost/greet").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_api_installs_from_path_or_upload_and_starts_via_post() {
+        let dir = tempdir().unwrap();
+        let source_root = dir.path().join("source");
+        let output = dir.path().join("out");
+        for name in ["demo", "other"] {
+            let shuriken_dir = write_daemon_shuriken(&source_root, name);
+            fs::write(
+                shuriken_dir.join(".ninja/postinstall.lua"),
+                "error('postinstall ran')",
+            )
+            .unwrap();
+            // Only the uploaded package has a postinstall script, which fails
+            let postinstall = (name == "other").then(|| PathBuf::from(".ninja/postinstall.lua"));
+            let meta = ArmoryMetadata {
+                id: name.into(),
+                name: name.into(),
+                version: "1.0.0".into(),
+                synopsis: None,
+                description: None,
+                authors: None,
+                license: None,
+                repository: None,
+                postinstall,
+                platform: std::env::consts::OS.into(),
+            };
+            manager_in(&source_root)
+                .await
+                .forge(meta, name.into(), Some(output.clone()))
+                .await
+                .unwrap();
+        }
+        let package = |name: &str| output.join(format!("{name}-{}.shuriken", std::env::consts::OS));
+
+        let manager = Arc::new(manager_in(&dir.path().join("target")).await);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(manager.clone(), shutdown.clone(), None);
+        tokio::spawn(ninja_http::serve(app, listener, None, shutdown));
+        let base = format!("http://127.0.0.1:{port}/api/shurikens");
+        let client = reqwest::Client::new();
+
+        let install = serde_json::json!({ "path": package("demo") }).to_string();
+        let response = client
+            .post(format!("{base}/install"))
+            .header("content-type", "application/json")
+            .body(install.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(manager.get("demo".into()).await.is_ok());
+
+        let again = client
+            .post(format!("{base}/install"))
+            .header("content-type", "application/json")
+            .body(install)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(again.status(), reqwest::StatusCode::CONFLICT);
+        let body: serde_json::Value = again.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["code"], "already_installed");
+
+        let upload = |flags: &[(&str, &str)]| {
+            let mut upload = Vec::new();
+            for (name, value) in flags {
+                upload.extend(
+                    format!(
+                        "--XBOUNDARY\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+                    )
+                    .into_bytes(),
+                );
+            }
+            upload.extend(
+                b"--XBOUNDARY\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"other.shuriken\"\r\n\
+                Content-Type: application/octet-stream\r\n\r\n",
+            );
+            upload.extend(fs::read(package("other")).unwrap());
+            upload.extend(b"\r\n--XBOUNDARY--\r\n");
+            upload
+        };
+        let send_upload = |flags: &[(&str, &str)], origin: Option<&str>| {
+            let mut request = client
+                .post(format!("{base}/install"))
+                .header("content-type", "multipart/form-data; boundary=XBOUNDARY")
+                .body(upload(flags));
+            if let Some(origin) = origin {
+                request = request.header("origin", origin);
+            }
+            request.send()
+        };
+
+        // What any web page could send through the user's browser
+        let response = send_upload(&[], Some("http://evil.example")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "forbidden_origin");
+        assert!(manager.get("other".into()).await.is_err());
+
+        // The failing postinstall script isn't run unless asked for
+        let response = send_upload(&[], Some(&format!("http://127.0.0.1:{port}")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(manager.get("other".into()).await.is_ok());
+
+        let response = send_upload(&[("replace", "true"), ("run_postinstall", "true")], None)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR
+        );
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["error"].as_str().unwrap().contains("postinstall"));
+
+        // Mutating routes no longer answer GET, so a prefetch can't start anything
+        let response = client
+            .get(format!("{base}/start/demo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED);
+        let response = client
+            .post(format!("{base}/start/demo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            manager.status("demo").await.unwrap(),
+            ShurikenState::Running
+        );
+        let response = client
+            .post(format!("{base}/stop/demo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_api_token_guards_mutating_routes() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        let manager = Arc::new(manager);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(manager.clone(), shutdown.clone(), Some("s3cret".into()));
+        tokio::spawn(ninja_http::serve(app, listener, None, shutdown));
+        let base = format!("http://127.0.0.1:{port}/api/shurikens");
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{base}/start/demo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["code"], "unauthorized");
+
+        let response = client
+            .post(format!("{base}/start/demo"))
+            .bearer_auth("guess")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(manager.status("demo").await.unwrap(), ShurikenState::Idle);
+
+        // Reads stay open
+        let response = client
+            .get(format!("{base}/status/demo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .post(format!("{base}/start/demo"))
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            manager.status("demo").await.unwrap(),
+            ShurikenState::Running
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_rejects_unreadable_tls_files() {
+        let dir = tempdir().unwrap();
+        let manager = manager_in(dir.path()).await;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tls = ninja_http::TlsPaths {
+            cert: dir.path().join("missing.pem"),
+            key: dir.path().join("missing.key"),
+        };
+
+        let shutdown = ninja_http::Shutdown::default();
+        let app = ninja_http::router(Arc::new(manager), shutdown.clone(), None);
+        let err = ninja_http::serve(app, listener, Some(tls), shutdown)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Failed to load TLS certificate"));
+    }
+
+    #[tokio::test]
+    async fn test_openmetrics_per_shuriken() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "idle");
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "web");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("web").await.unwrap();
+        manager.stop("web").await.unwrap();
+        manager.start("web").await.unwrap();
+
+        // Pretend the current run started a minute ago
+        let lock_path = shuriken_dir.join(".ninja/shuriken.lck");
+        let mut lockfile: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&lock_path).unwrap()).unwrap();
+        let started = lockfile["start_time"].as_i64().unwrap();
+        lockfile["start_time"] = serde_json::json!(started - 60);
+        fs::write(&lock_path, lockfile.to_string()).unwrap();
+
+        let text = manager.export_openmetrics_per_shuriken().await;
+        assert!(text.contains("ninja_shuriken_up{name=\"web\"} 1\n"));
+        assert!(text.contains("ninja_shuriken_up{name=\"idle\"} 0\n"));
+        // Stopping and starting by hand isn't a restart
+        assert!(text.contains("ninja_shuriken_restarts_total{name=\"web\"} 0\n"));
+        assert!(text.contains("ninja_shuriken_restarts_total{name=\"idle\"} 0\n"));
+        assert!(text.contains("ninja_shuriken_uptime_seconds{name=\"idle\"} 0\n"));
+
+        let uptime: u64 = text
+            .lines()
+            .find_map(|line| line.strip_prefix("ninja_shuriken_uptime_seconds{name=\"web\"} "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((60..70).contains(&uptime), "uptime was {uptime}");
+        assert!(text.ends_with("# EOF\n"));
+
+        manager.stop("web").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_shuriken_stops_after_timeout() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "lazy");
+        append_manifest(&shuriken_dir, "idle-timeout = 1\n");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        manager.start("lazy").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        manager.touch("lazy").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        // Past the timeout since start, but not since the touch
+        assert!(manager.is_running("lazy").await);
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while manager.is_running("lazy").await && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(!manager.is_running("lazy").await);
+        let touch = manager.touch("lazy").await.unwrap_err();
+        assert_eq!(error_code(&touch), Some("not_running"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_stop_rechecks_activity_under_the_operation_lock() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "lazy");
+        append_manifest(&shuriken_dir, "idle-timeout = 1\n");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        manager.start("lazy").await.unwrap();
+
+        // Another operation holds the lock when the timer runs out, and the
+        // Shuriken is used again before it finishes
+        let guard = manager.operation_locks.acquire("lazy").await;
+        tokio::time::sleep(Duration::from_millis(1300)).await;
+        manager.touch("lazy").await.unwrap();
+        drop(guard);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(manager.is_running("lazy").await);
+        manager.stop("lazy").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_errors_carry_codes() {
+        let dir = tempdir().unwrap();
+        write_daemon_shuriken(dir.path(), "demo");
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let missing = manager.start("ghost").await.unwrap_err();
+        assert_eq!(error_code(&missing), Some("not_found"));
+        assert_eq!(
+            missing.downcast_ref::<ManagerError>(),
+            Some(&ManagerError::NotFound("ghost".to_string()))
+        );
+        assert_eq!(missing.to_string(), "No such shuriken: ghost");
+
+        manager.start("demo").await.unwrap();
+        let again = manager.start("demo").await.unwrap_err();
+        assert_eq!(error_code(&again), Some("already_running"));
+        manager.stop("demo").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_times_out_when_never_healthy() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "sleepy");
+
+        // Reserve a port and release it so nothing is listening there.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        append_manifest(&shuriken_dir, &format!("ports = [{port}]\n"));
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+
+        let result = manager
+            .start_with_timeout("sleepy", Some(Duration::from_secs(1)))
+            .await;
+        assert!(result.is_err());
+
+        let states = manager.list(true).await.unwrap().left().unwrap();
+        assert!(states.contains(&("sleepy".to_string(), ShurikenState::Idle)));
+    }
+
+    #[tokio::test]
+    async fn test_post_stop_script_receives_name() {
+        let dir = tempdir().unwrap();
+        let shuriken_dir = write_daemon_shuriken(dir.path(), "tidy");
+        let ninja_dir = shuriken_dir.join(".ninja");
+        fs::write(
+            ninja_dir.join("cleanup.lua"),
+            "fs.write('cleaned.txt', shuriken.name)",
+        )
+        .unwrap();
+        append_manifest(&shuriken_dir, "post-stop = \".ninja/cleanup.lua\"\n");
+
+        let manager = manager_in(dir.path()).await;
+        manager.refresh().await.unwrap();
+        manager.start("tidy").await.unwrap();
+        manager.stop("tidy").await.unwrap();
+
+        let cleaned = fs::read_to_string(shuriken_dir.join("cleaned.txt")).unwrap();
+        assert_eq!(cleaned, "tidy");
+    }
+
     #[tokio::test]
     async fn test_lockfile_written_for_script() {
         let dir = tempdir().unwrap();
@@ -161,6 +4694,15 @@ mod ninja_api_integration_tests {
                 check_ports: None,
                 script_path: Some(PathBuf::from("dummy.ns")),
                 shuriken_type: "daemon".into(),
+                start_timeout: None,
+                idle_timeout: None,
+                post_stop: None,
+                depends_on: None,
+                profiles: None,
+                restart: None,
+                log_buffer: None,
+                start_delay: None,
+                wait_for: None,
             },
             config: None,
             logs: None,
@@ -182,6 +4724,10 @@ mod ninja_api_integration_tests {
             root_path: dir.path().to_path_buf(),
             engine: Arc::new(Mutex::new(engine)),
             shurikens: Arc::new(RwLock::new(HashMap::new())),
+            state_events: Default::default(),
+            operation_locks: Default::default(),
+            idle_timers: Default::default(),
+            states_cache: Default::default(),
         };
 
         let list = manager.list(false).await.unwrap();
@@ -200,6 +4746,10 @@ mod ninja_api_integration_tests {
             root_path: dir.path().to_path_buf(),
             engine: Arc::new(Mutex::new(engine)),
             shurikens: Arc::new(RwLock::new(HashMap::new())),
+            state_events: Default::default(),
+            operation_locks: Default::default(),
+            idle_timers: Default::default(),
+            states_cache: Default::default(),
         };
 
         // Verify manager initialization
@@ -217,6 +4767,15 @@ mod ninja_api_integration_tests {
             check_ports: Some(true),
             script_path: Some(PathBuf::from("script.ns")),
             shuriken_type: "daemon".into(),
+            start_timeout: None,
+            idle_timeout: None,
+            post_stop: None,
+            depends_on: None,
+            profiles: None,
+            restart: None,
+            log_buffer: None,
+            start_delay: None,
+            wait_for: None,
         };
 
         assert_eq!(metadata.name, "test");